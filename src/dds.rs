@@ -2,20 +2,25 @@
 //! crate top level and modules [`no_key`](crate::no_key) and
 //! [`with_key`](crate::with_key).
 
-mod helpers;
+pub(crate) mod helpers;
 
 pub(crate) mod participant;
 pub use participant::DomainParticipant;
 
 pub(crate) mod ddsdata;
+pub(crate) mod ddssql;
+pub(crate) mod endpoint_registry;
+pub(crate) mod listener;
 pub(crate) mod pubsub;
 pub(crate) mod readcondition;
 pub(crate) mod topic;
 pub(crate) mod typedesc;
+pub(crate) mod waitset;
 
 pub mod result;
 pub use result::{
-  CreateError, CreateResult, ReadError, ReadResult, WaitError, WaitResult, WriteError, WriteResult,
+  CreateError, CreateResult, FilterError, ReadError, ReadResult, ShareError, ShareResult,
+  WaitError, WaitResult, WriteError, WriteResult,
 };
 
 // Public interface
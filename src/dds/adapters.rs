@@ -9,7 +9,7 @@
 pub mod no_key {
   use bytes::Bytes;
 
-  use crate::RepresentationIdentifier;
+  use crate::{dds::ddssql::FilterableSample, RepresentationIdentifier};
 
   /// trait for connecting a Deserializer implementation and DataReader
   /// together - no_key version.
@@ -51,6 +51,14 @@ pub mod no_key {
     fn output_encoding() -> RepresentationIdentifier;
 
     fn to_bytes(value: &D) -> Result<Bytes, Self::Error>;
+
+    /// Prepare the sample for evaluating content filters of remote
+    /// DataReaders at the writer side. The default implementation does not
+    /// support this, and then all samples are sent to all matched readers.
+    #[doc(hidden)]
+    fn to_filterable(_value: &D) -> Option<FilterableSample> {
+      None
+    }
   }
 }
 
@@ -12,7 +12,8 @@ use crate::{
 #[derive(Debug, PartialEq, Eq, Clone)]
 // Contents of a DATA submessage or several DATAFRAG submessages. This is either
 // a new sample, or key, or a key hash. The latter two are used to indicate
-// dispose or unregister.
+// dispose or unregister. CoherentSetEnd has no payload at all. It only ends a
+// coherent set, whose marking is in WriteOptions.
 pub enum DDSData {
   Data {
     serialized_payload: SerializedPayload,
@@ -25,6 +26,7 @@ pub enum DDSData {
     change_kind: ChangeKind,
     key_hash: KeyHash,
   },
+  CoherentSetEnd,
 }
 
 impl DDSData {
@@ -44,7 +46,7 @@ impl DDSData {
 
   pub fn change_kind(&self) -> ChangeKind {
     match self {
-      DDSData::Data {..} /*| DDSData::DataFrags {..}*/ => ChangeKind::Alive,
+      DDSData::Data {..} | DDSData::CoherentSetEnd /*| DDSData::DataFrags {..}*/ => ChangeKind::Alive,
       DDSData::DisposeByKey { change_kind, ..} | DDSData::DisposeByKeyHash { change_kind, .. }  => *change_kind,
     }
   }
@@ -57,6 +59,7 @@ impl DDSData {
       DDSData::DisposeByKeyHash { .. } => 16,
       // This is a fundamental constant of the RTPS
       // specification v2.5 Section 9.6.4.8 KeyHash (PID_KEY_HASH)
+      DDSData::CoherentSetEnd => 0,
     }
   }
 
@@ -64,7 +67,7 @@ impl DDSData {
     match self {
       DDSData::Data { serialized_payload } => serialized_payload.reallocate(),
       DDSData::DisposeByKey { key, .. } => key.reallocate(),
-      DDSData::DisposeByKeyHash { .. } => {}
+      DDSData::DisposeByKeyHash { .. } | DDSData::CoherentSetEnd => {}
     }
   }
 
@@ -79,6 +82,7 @@ impl DDSData {
       DDSData::Data { serialized_payload } => serialized_payload.value.clone(),
       DDSData::DisposeByKey { key, .. } => key.value.clone(),
       DDSData::DisposeByKeyHash { key_hash, .. } => Bytes::from(key_hash.to_vec()),
+      DDSData::CoherentSetEnd => Bytes::new(),
     }
   }
 
@@ -97,6 +101,7 @@ impl DDSData {
         let start = min(from, end);
         Bytes::from(hash_vec).slice(start..end)
       }
+      DDSData::CoherentSetEnd => Bytes::new(),
     }
   }
 }
@@ -0,0 +1,770 @@
+//! DDSSQL filter expressions
+//!
+//! This implements the subset of SQL that DDS spec v1.4 Annex B "Syntax for
+//! Queries and Filters" defines for ContentFilteredTopic filters and
+//! QueryCondition queries:
+//!
+//! * Comparisons `=` (also `==`), `<>` (also `!=`), `<`, `<=`, `>`, `>=` and
+//!   `LIKE` with `%` and `_` wildcards
+//! * `BETWEEN` and `NOT BETWEEN`
+//! * `AND`, `OR`, `NOT` and parentheses
+//! * Member names, also nested (`a.b.c`) and indexed (`a[2]`)
+//! * Integer, floating point, string (`'single quoted'`), boolean and
+//!   enumerated constants, and parameters `%0` .. `%99`
+//!
+//! Samples are evaluated via their serde serialization, see [`Value`].
+
+mod value;
+
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use serde::Serialize;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::dds::result::FilterError;
+pub(crate) use value::Value;
+use value::PathSegment;
+
+/// Name of the filter class that this module implements. RTPS spec v2.5
+/// Section 9.6.4.1 "Content filter info".
+pub(crate) const DDSSQL_FILTER_CLASS_NAME: &str = "DDSSQL";
+
+// DDS spec limits parameter numbers to two decimal digits.
+const MAX_PARAMETERS: usize = 100;
+
+/// Parsed filter expression together with its current parameter values.
+#[derive(Clone, Debug)]
+pub(crate) struct Filter {
+  expression: String,
+  condition: Condition,
+  parameter_count: usize, // 1 + highest parameter number referenced
+  parameters: Vec<String>,
+  parameter_values: Vec<Value>,
+}
+
+impl Filter {
+  pub fn new(expression: &str, parameters: Vec<String>) -> Result<Self, FilterError> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+      tokens,
+      pos: 0,
+      parameter_count: 0,
+    };
+    let condition = parser.parse_condition()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+      return Err(FilterError::Syntax {
+        position: token.position,
+        reason: format!("Unexpected {}", token.kind),
+      });
+    }
+    let mut filter = Filter {
+      expression: expression.to_string(),
+      condition,
+      parameter_count: parser.parameter_count,
+      parameters: Vec::new(),
+      parameter_values: Vec::new(),
+    };
+    filter.set_parameters(parameters)?;
+    Ok(filter)
+  }
+
+  pub fn expression(&self) -> &str {
+    &self.expression
+  }
+
+  pub fn parameters(&self) -> &[String] {
+    &self.parameters
+  }
+
+  /// Replaces the expression parameters. The expression itself is not
+  /// changed. There must be a value for each parameter referenced in the
+  /// expression.
+  pub fn set_parameters(&mut self, parameters: Vec<String>) -> Result<(), FilterError> {
+    if parameters.len() < self.parameter_count {
+      return Err(FilterError::BadParameter {
+        reason: format!(
+          "Expression \"{}\" needs {} parameters, but {} were given",
+          self.expression,
+          self.parameter_count,
+          parameters.len()
+        ),
+      });
+    }
+    if parameters.len() > MAX_PARAMETERS {
+      return Err(FilterError::BadParameter {
+        reason: format!("Too many parameters: {}", parameters.len()),
+      });
+    }
+    self.parameter_values = parameters
+      .iter()
+      .map(|p| parse_parameter(p))
+      .collect::<Result<Vec<_>, _>>()?;
+    self.parameters = parameters;
+    Ok(())
+  }
+
+  /// Does the sample pass the filter?
+  ///
+  /// A sample that cannot be evaluated, e.g. because it does not have a member
+  /// named in the expression, does not pass.
+  pub fn matches<D: Serialize + ?Sized>(&self, sample: &D) -> bool {
+    match Value::from_sample(sample) {
+      Ok(value) => self.matches_value(&value),
+      Err(e) => {
+        warn!(
+          "Cannot evaluate filter \"{}\" on sample: {}",
+          self.expression, e
+        );
+        false
+      }
+    }
+  }
+
+  pub fn matches_value(&self, sample: &Value) -> bool {
+    self.condition.evaluate(sample, &self.parameter_values)
+  }
+
+  pub fn matches_filterable(&self, sample: &FilterableSample) -> bool {
+    self.matches_value(&sample.0)
+  }
+}
+
+// Filters are the same, if they are written the same. Comparing the parsed
+// condition would not tell us anything more.
+impl PartialEq for Filter {
+  fn eq(&self, other: &Self) -> bool {
+    self.expression == other.expression && self.parameters == other.parameters
+  }
+}
+
+impl Eq for Filter {}
+
+/// A sample in a form where filters can be evaluated on it without knowing
+/// its type. This is how DataWriter passes samples to the RTPS Writer for
+/// writer-side content filtering.
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct FilterableSample(Value);
+
+impl FilterableSample {
+  pub(crate) fn new<D: Serialize + ?Sized>(sample: &D) -> Option<Self> {
+    Value::from_sample(sample)
+      .map_err(|e| warn!("Cannot prepare sample for content filtering: {}", e))
+      .ok()
+      .map(FilterableSample)
+  }
+}
+
+// ---------------------------------------------------------------------------
+// Syntax tree
+
+#[derive(Clone, Debug)]
+enum Condition {
+  And(Box<Condition>, Box<Condition>),
+  Or(Box<Condition>, Box<Condition>),
+  Not(Box<Condition>),
+  Comparison {
+    left: Operand,
+    op: RelOp,
+    right: Operand,
+  },
+  Between {
+    operand: Operand,
+    low: Operand,
+    high: Operand,
+  },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RelOp {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  Like,
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+  Member(Vec<PathSegment>),
+  Constant(Value),
+  Parameter(usize),
+}
+
+impl Condition {
+  fn evaluate(&self, sample: &Value, parameters: &[Value]) -> bool {
+    match self {
+      Condition::And(a, b) => a.evaluate(sample, parameters) && b.evaluate(sample, parameters),
+      Condition::Or(a, b) => a.evaluate(sample, parameters) || b.evaluate(sample, parameters),
+      Condition::Not(c) => !c.evaluate(sample, parameters),
+      Condition::Comparison { left, op, right } => {
+        match (
+          left.evaluate(sample, parameters),
+          right.evaluate(sample, parameters),
+        ) {
+          (Some(l), Some(r)) => op.apply(&l, &r),
+          _ => false,
+        }
+      }
+      Condition::Between { operand, low, high } => {
+        match (
+          operand.evaluate(sample, parameters),
+          low.evaluate(sample, parameters),
+          high.evaluate(sample, parameters),
+        ) {
+          (Some(v), Some(l), Some(h)) => RelOp::Ge.apply(&v, &l) && RelOp::Le.apply(&v, &h),
+          _ => false,
+        }
+      }
+    }
+  }
+}
+
+impl RelOp {
+  fn apply(self, left: &Value, right: &Value) -> bool {
+    if self == RelOp::Like {
+      return match (left.as_str(), right.as_str()) {
+        (Some(s), Some(pattern)) => like(s, pattern),
+        _ => false,
+      };
+    }
+    let ord = left.compare(right);
+    match self {
+      RelOp::Eq => ord == Some(Ordering::Equal),
+      RelOp::Ne => ord != Some(Ordering::Equal),
+      RelOp::Lt => ord == Some(Ordering::Less),
+      RelOp::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+      RelOp::Gt => ord == Some(Ordering::Greater),
+      RelOp::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+      RelOp::Like => unreachable!(),
+    }
+  }
+}
+
+impl Operand {
+  fn evaluate<'a>(&'a self, sample: &'a Value, parameters: &'a [Value]) -> Option<Cow<'a, Value>> {
+    match self {
+      Operand::Constant(v) => Some(Cow::Borrowed(v)),
+      Operand::Parameter(n) => parameters.get(*n).map(Cow::Borrowed),
+      Operand::Member(path) => match sample.member(path) {
+        Some(v) => Some(Cow::Borrowed(v)),
+        // A plain name that is not a member may be an enumerated constant.
+        None => match path.as_slice() {
+          [PathSegment::Member(name)] => Some(Cow::Owned(Value::Str(name.clone()))),
+          _ => {
+            debug!("Filter refers to {path:?}, but sample has no such member.");
+            None
+          }
+        },
+      },
+    }
+  }
+}
+
+// SQL LIKE: '%' matches any sequence of characters, '_' matches any single
+// character.
+fn like(s: &str, pattern: &str) -> bool {
+  let s: Vec<char> = s.chars().collect();
+  let p: Vec<char> = pattern.chars().collect();
+  // Classic wildcard matching with backtracking to the latest '%'
+  let (mut si, mut pi) = (0, 0);
+  let mut backtrack: Option<(usize, usize)> = None;
+  while si < s.len() {
+    if pi < p.len() && (p[pi] == '_' || p[pi] == s[si]) {
+      si += 1;
+      pi += 1;
+    } else if pi < p.len() && p[pi] == '%' {
+      backtrack = Some((pi, si));
+      pi += 1;
+    } else if let Some((bp, bs)) = backtrack {
+      pi = bp + 1;
+      si = bs + 1;
+      backtrack = Some((bp, bs + 1));
+    } else {
+      return false;
+    }
+  }
+  p[pi..].iter().all(|c| *c == '%')
+}
+
+// Expression parameters are given as strings. They are interpreted as
+// constants of the expression language, except that an unquoted string that
+// is not a number is taken as is.
+fn parse_parameter(parameter: &str) -> Result<Value, FilterError> {
+  let tokens = tokenize(parameter).unwrap_or_default();
+  match tokens.as_slice() {
+    [Token {
+      kind: TokenKind::Constant(v),
+      ..
+    }] => Ok(v.clone()),
+    [Token {
+      kind: TokenKind::Parameter(_),
+      ..
+    }] => Err(FilterError::BadParameter {
+      reason: format!("Parameter value cannot be a parameter reference: {parameter}"),
+    }),
+    _ => Ok(Value::Str(parameter.trim().to_string())),
+  }
+}
+
+// ---------------------------------------------------------------------------
+// Tokenizer
+
+#[derive(Clone, Debug)]
+struct Token {
+  kind: TokenKind,
+  position: usize,
+}
+
+#[derive(Clone, Debug)]
+enum TokenKind {
+  Name(Vec<PathSegment>),
+  Constant(Value),
+  Parameter(usize),
+  RelOp(RelOp),
+  And,
+  Or,
+  Not,
+  Between,
+  LeftParen,
+  RightParen,
+}
+
+impl fmt::Display for TokenKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TokenKind::Name(path) => write!(f, "name {path:?}"),
+      TokenKind::Constant(v) => write!(f, "constant {v:?}"),
+      TokenKind::Parameter(n) => write!(f, "parameter %{n}"),
+      TokenKind::RelOp(op) => write!(f, "operator {op:?}"),
+      TokenKind::And => write!(f, "AND"),
+      TokenKind::Or => write!(f, "OR"),
+      TokenKind::Not => write!(f, "NOT"),
+      TokenKind::Between => write!(f, "BETWEEN"),
+      TokenKind::LeftParen => write!(f, "'('"),
+      TokenKind::RightParen => write!(f, "')'"),
+    }
+  }
+}
+
+fn syntax_error<T>(position: usize, reason: impl Into<String>) -> Result<T, FilterError> {
+  Err(FilterError::Syntax {
+    position,
+    reason: reason.into(),
+  })
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FilterError> {
+  let chars: Vec<char> = input.chars().collect();
+  let mut tokens = Vec::new();
+  let mut i = 0;
+
+  while i < chars.len() {
+    let c = chars[i];
+    let start = i;
+    let next = chars.get(i + 1).copied();
+
+    let kind = if c.is_whitespace() {
+      i += 1;
+      continue;
+    } else if c == '(' {
+      i += 1;
+      TokenKind::LeftParen
+    } else if c == ')' {
+      i += 1;
+      TokenKind::RightParen
+    } else if c == '=' {
+      i += if next == Some('=') { 2 } else { 1 };
+      TokenKind::RelOp(RelOp::Eq)
+    } else if c == '!' && next == Some('=') {
+      i += 2;
+      TokenKind::RelOp(RelOp::Ne)
+    } else if c == '<' {
+      match next {
+        Some('=') => {
+          i += 2;
+          TokenKind::RelOp(RelOp::Le)
+        }
+        Some('>') => {
+          i += 2;
+          TokenKind::RelOp(RelOp::Ne)
+        }
+        _ => {
+          i += 1;
+          TokenKind::RelOp(RelOp::Lt)
+        }
+      }
+    } else if c == '>' {
+      if next == Some('=') {
+        i += 2;
+        TokenKind::RelOp(RelOp::Ge)
+      } else {
+        i += 1;
+        TokenKind::RelOp(RelOp::Gt)
+      }
+    } else if c == '%' {
+      i += 1;
+      let digits_start = i;
+      while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+      }
+      let digits: String = chars[digits_start..i].iter().collect();
+      match digits.parse::<usize>() {
+        Ok(n) if n < MAX_PARAMETERS => TokenKind::Parameter(n),
+        _ => return syntax_error(start, "Expected parameter number 0..99 after '%'"),
+      }
+    } else if c == '\'' {
+      i += 1;
+      let string_start = i;
+      while i < chars.len() && chars[i] != '\'' {
+        if chars[i] == '\n' {
+          return syntax_error(start, "Newline in string constant");
+        }
+        i += 1;
+      }
+      if i >= chars.len() {
+        return syntax_error(start, "Unterminated string constant");
+      }
+      let s: String = chars[string_start..i].iter().collect();
+      i += 1; // closing quote
+      TokenKind::Constant(Value::Str(s))
+    } else if c.is_ascii_digit()
+      || ((c == '-' || c == '+' || c == '.') && next.is_some_and(|n| n.is_ascii_digit()))
+    {
+      i += 1;
+      while i < chars.len()
+        && (chars[i].is_ascii_alphanumeric()
+          || chars[i] == '.'
+          || ((chars[i] == '-' || chars[i] == '+') && matches!(chars[i - 1], 'e' | 'E')))
+      {
+        i += 1;
+      }
+      let text: String = chars[start..i].iter().collect();
+      match parse_number(&text) {
+        Some(v) => TokenKind::Constant(v),
+        None => return syntax_error(start, format!("Malformed number \"{text}\"")),
+      }
+    } else if c.is_alphabetic() || c == '_' {
+      i += 1;
+      while i < chars.len()
+        && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '[' | ']'))
+      {
+        i += 1;
+      }
+      let word: String = chars[start..i].iter().collect();
+      match word.to_ascii_uppercase().as_str() {
+        "AND" => TokenKind::And,
+        "OR" => TokenKind::Or,
+        "NOT" => TokenKind::Not,
+        "BETWEEN" => TokenKind::Between,
+        "LIKE" => TokenKind::RelOp(RelOp::Like),
+        "TRUE" => TokenKind::Constant(Value::Bool(true)),
+        "FALSE" => TokenKind::Constant(Value::Bool(false)),
+        _ => TokenKind::Name(parse_path(&word, start)?),
+      }
+    } else {
+      return syntax_error(start, format!("Unexpected character '{c}'"));
+    };
+
+    tokens.push(Token {
+      kind,
+      position: start,
+    });
+  }
+  Ok(tokens)
+}
+
+fn parse_number(text: &str) -> Option<Value> {
+  let (negative, digits) = match text.strip_prefix('-') {
+    Some(rest) => (true, rest),
+    None => (false, text.strip_prefix('+').unwrap_or(text)),
+  };
+  let sign: i128 = if negative { -1 } else { 1 };
+  if let Some(hex) = digits
+    .strip_prefix("0x")
+    .or_else(|| digits.strip_prefix("0X"))
+  {
+    return i128::from_str_radix(hex, 16)
+      .ok()
+      .map(|i| Value::Int(sign * i));
+  }
+  if let Ok(i) = digits.parse::<i128>() {
+    return Some(Value::Int(sign * i));
+  }
+  digits
+    .parse::<f64>()
+    .ok()
+    .map(|f| Value::Float(if negative { -f } else { f }))
+}
+
+// "a.b[3].c" -> [Member(a), Member(b), Index(3), Member(c)]
+fn parse_path(word: &str, position: usize) -> Result<Vec<PathSegment>, FilterError> {
+  let mut path = Vec::new();
+  for part in word.split('.') {
+    let (name, mut indices) = match part.find('[') {
+      Some(bracket) => (&part[..bracket], &part[bracket..]),
+      None => (part, ""),
+    };
+    if name.is_empty() {
+      return syntax_error(position, format!("Malformed member name \"{word}\""));
+    }
+    path.push(PathSegment::Member(name.to_string()));
+    while !indices.is_empty() {
+      let close = match (indices.strip_prefix('['), indices.find(']')) {
+        (Some(_), Some(close)) => close,
+        _ => return syntax_error(position, format!("Malformed index in \"{word}\"")),
+      };
+      match indices[1..close].parse::<usize>() {
+        Ok(index) => path.push(PathSegment::Index(index)),
+        Err(_) => return syntax_error(position, format!("Malformed index in \"{word}\"")),
+      }
+      indices = &indices[close + 1..];
+    }
+  }
+  Ok(path)
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+//
+// Condition  ::= AndCond { OR AndCond }
+// AndCond    ::= NotCond { AND NotCond }
+// NotCond    ::= NOT NotCond | '(' Condition ')' | Predicate
+// Predicate  ::= Operand RelOp Operand
+//              | Operand [NOT] BETWEEN Operand AND Operand
+
+struct Parser {
+  tokens: Vec<Token>,
+  pos: usize,
+  parameter_count: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&TokenKind> {
+    self.tokens.get(self.pos).map(|t| &t.kind)
+  }
+
+  fn position(&self) -> usize {
+    self
+      .tokens
+      .get(self.pos)
+      .or(self.tokens.last())
+      .map_or(0, |t| t.position)
+  }
+
+  fn next(&mut self) -> Option<TokenKind> {
+    let t = self.tokens.get(self.pos).map(|t| t.kind.clone());
+    self.pos += 1;
+    t
+  }
+
+  fn parse_condition(&mut self) -> Result<Condition, FilterError> {
+    let mut left = self.parse_and()?;
+    while matches!(self.peek(), Some(TokenKind::Or)) {
+      self.pos += 1;
+      let right = self.parse_and()?;
+      left = Condition::Or(Box::new(left), Box::new(right));
+    }
+    Ok(left)
+  }
+
+  fn parse_and(&mut self) -> Result<Condition, FilterError> {
+    let mut left = self.parse_not()?;
+    while matches!(self.peek(), Some(TokenKind::And)) {
+      self.pos += 1;
+      let right = self.parse_not()?;
+      left = Condition::And(Box::new(left), Box::new(right));
+    }
+    Ok(left)
+  }
+
+  fn parse_not(&mut self) -> Result<Condition, FilterError> {
+    match self.peek() {
+      Some(TokenKind::Not) => {
+        self.pos += 1;
+        Ok(Condition::Not(Box::new(self.parse_not()?)))
+      }
+      Some(TokenKind::LeftParen) => {
+        self.pos += 1;
+        let c = self.parse_condition()?;
+        match self.next() {
+          Some(TokenKind::RightParen) => Ok(c),
+          _ => syntax_error(self.position(), "Expected ')'"),
+        }
+      }
+      _ => self.parse_predicate(),
+    }
+  }
+
+  fn parse_predicate(&mut self) -> Result<Condition, FilterError> {
+    let left = self.parse_operand()?;
+    let position = self.position();
+    match self.next() {
+      Some(TokenKind::RelOp(op)) => {
+        let right = self.parse_operand()?;
+        Ok(Condition::Comparison { left, op, right })
+      }
+      Some(TokenKind::Between) => self.parse_between(left),
+      Some(TokenKind::Not) => match self.next() {
+        Some(TokenKind::Between) => Ok(Condition::Not(Box::new(self.parse_between(left)?))),
+        _ => syntax_error(position, "Expected BETWEEN after NOT"),
+      },
+      Some(other) => syntax_error(position, format!("Expected operator, found {other}")),
+      None => syntax_error(position, "Expected operator, found end of expression"),
+    }
+  }
+
+  fn parse_between(&mut self, operand: Operand) -> Result<Condition, FilterError> {
+    let low = self.parse_operand()?;
+    match self.next() {
+      Some(TokenKind::And) => {}
+      _ => return syntax_error(self.position(), "Expected AND in BETWEEN range"),
+    }
+    let high = self.parse_operand()?;
+    Ok(Condition::Between { operand, low, high })
+  }
+
+  fn parse_operand(&mut self) -> Result<Operand, FilterError> {
+    let position = self.position();
+    match self.next() {
+      Some(TokenKind::Name(path)) => Ok(Operand::Member(path)),
+      Some(TokenKind::Constant(v)) => Ok(Operand::Constant(v)),
+      Some(TokenKind::Parameter(n)) => {
+        self.parameter_count = self.parameter_count.max(n + 1);
+        Ok(Operand::Parameter(n))
+      }
+      Some(other) => syntax_error(position, format!("Expected operand, found {other}")),
+      None => syntax_error(position, "Expected operand, found end of expression"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::Serialize;
+
+  use super::*;
+
+  #[derive(Serialize)]
+  #[allow(dead_code)]
+  enum Color {
+    Red,
+    Green,
+    Blue,
+  }
+
+  #[derive(Serialize)]
+  struct Position {
+    x: f64,
+    y: f64,
+  }
+
+  #[derive(Serialize)]
+  struct Shape {
+    id: i32,
+    name: String,
+    color: Color,
+    position: Position,
+    history: Vec<u16>,
+    visible: bool,
+  }
+
+  fn shape() -> Shape {
+    Shape {
+      id: 7,
+      name: "Square".to_string(),
+      color: Color::Green,
+      position: Position { x: 1.5, y: -3.0 },
+      history: vec![10, 20, 30],
+      visible: true,
+    }
+  }
+
+  fn check(expression: &str, parameters: &[&str]) -> bool {
+    Filter::new(
+      expression,
+      parameters.iter().map(|p| p.to_string()).collect(),
+    )
+    .unwrap()
+    .matches(&shape())
+  }
+
+  #[test]
+  fn ddssql_comparisons() {
+    assert!(check("id = 7", &[]));
+    assert!(check("id == 7", &[]));
+    assert!(!check("id <> 7", &[]));
+    assert!(check("id >= 7 AND id < 8", &[]));
+    assert!(check("position.x > 1", &[]));
+    assert!(check("position.y = -3", &[]));
+    assert!(check("name = 'Square'", &[]));
+    assert!(check("visible = TRUE", &[]));
+    assert!(check("history[1] = 20", &[]));
+    assert!(check("color = Green", &[]));
+    assert!(check("color <> Red", &[]));
+    assert!(check("color = 1", &[]));
+    assert!(check("0x07 = id", &[]));
+    // no such member
+    assert!(!check("foo.bar = 1", &[]));
+    assert!(check("NOT foo.bar = 1", &[]));
+  }
+
+  #[test]
+  fn ddssql_logic_and_ranges() {
+    assert!(check("(id < 5 OR id > 6) AND NOT name = 'Circle'", &[]));
+    assert!(!check("id < 5 OR id > 6 AND name = 'Circle'", &[]));
+    assert!(check("id BETWEEN 5 AND 10", &[]));
+    assert!(check("id NOT BETWEEN 8 AND 10", &[]));
+    assert!(check("name LIKE 'Sq%'", &[]));
+    assert!(check("name LIKE '_qua_e'", &[]));
+    assert!(!check("name LIKE 'Sq'", &[]));
+    assert!(check("name like '%a%e'", &[]));
+  }
+
+  #[test]
+  fn ddssql_parameters() {
+    assert!(check("id = %0 AND name = %1", &["7", "'Square'"]));
+    assert!(check("id > %1", &["unused", "6"]));
+    assert!(check("color = %0", &["Green"]));
+
+    let mut filter = Filter::new("position.x < %0", vec!["2.0".to_string()]).unwrap();
+    assert!(filter.matches(&shape()));
+    filter.set_parameters(vec!["1".to_string()]).unwrap();
+    assert!(!filter.matches(&shape()));
+    assert!(filter.set_parameters(vec![]).is_err());
+    assert_eq!(filter.parameters(), &["1".to_string()]);
+  }
+
+  #[test]
+  fn ddssql_syntax_errors() {
+    for bad in [
+      "",
+      "id =",
+      "id 7",
+      "(id = 7",
+      "id = 7)",
+      "name = 'Square",
+      "id BETWEEN 1 2",
+      "id = %100",
+      "a..b = 1",
+      "a[x] = 1",
+      "id = 7 AND",
+      "id # 7",
+    ] {
+      assert!(
+        matches!(
+          Filter::new(bad, vec!["0".to_string()]),
+          Err(FilterError::Syntax { .. })
+        ),
+        "{bad:?} should not parse"
+      );
+    }
+    assert!(matches!(
+      Filter::new("id = %2", vec![]),
+      Err(FilterError::BadParameter { .. })
+    ));
+  }
+}
@@ -0,0 +1,458 @@
+// Dynamic representation of a data sample, so that DDSSQL expressions can
+// refer to its members by name.
+//
+// The representation is produced by running the sample through a serde
+// Serializer. This way any type that can be written by RustDDS can also be
+// filtered, without a separate reflection mechanism.
+
+use std::{cmp::Ordering, fmt};
+
+use serde::{ser, Serialize};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+  // unit, unit structs, and Option::None
+  Null,
+  Bool(bool),
+  Int(i128),
+  Float(f64),
+  Str(String),
+  // Unit variant of an enum. DDSSQL can refer to these either by name or by
+  // ordinal.
+  Enum { variant: String, index: u32 },
+  Seq(Vec<Value>),
+  // Structs, maps and non-unit enum variants. Members are in declaration
+  // order.
+  Struct(Vec<(String, Value)>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum PathSegment {
+  Member(String),
+  Index(usize),
+}
+
+impl Value {
+  pub fn from_sample<D: Serialize + ?Sized>(sample: &D) -> Result<Value, ValueError> {
+    sample.serialize(ValueSerializer)
+  }
+
+  pub fn member(&self, path: &[PathSegment]) -> Option<&Value> {
+    let mut current = self;
+    for segment in path {
+      current = match (current, segment) {
+        (Value::Struct(members), PathSegment::Member(name)) => members
+          .iter()
+          .find_map(|(n, v)| if n == name { Some(v) } else { None })?,
+        (Value::Seq(elements), PathSegment::Index(i)) => elements.get(*i)?,
+        _ => return None,
+      };
+    }
+    Some(current)
+  }
+
+  // Comparison across the value kinds that DDSSQL considers comparable.
+  // Returns None for incomparable values, e.g. string vs. number.
+  pub fn compare(&self, other: &Value) -> Option<Ordering> {
+    match (self, other) {
+      (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+      (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+      (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+      (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+      (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+      (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+      (Value::Enum { index: a, .. }, Value::Enum { index: b, .. }) => Some(a.cmp(b)),
+      (Value::Enum { index, .. }, Value::Int(i)) => Some(i128::from(*index).cmp(i)),
+      (Value::Int(i), Value::Enum { index, .. }) => Some(i.cmp(&i128::from(*index))),
+      // Enumerated values given by name can only be tested for equality,
+      // because we do not know the ordinal of the name.
+      (Value::Enum { variant, .. }, Value::Str(s))
+      | (Value::Str(s), Value::Enum { variant, .. })
+        if variant == s =>
+      {
+        Some(Ordering::Equal)
+      }
+      _ => None,
+    }
+  }
+
+  // String form for LIKE matching
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Value::Str(s) => Some(s),
+      Value::Enum { variant, .. } => Some(variant),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub(crate) struct ValueError(String);
+
+impl fmt::Display for ValueError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl std::error::Error for ValueError {}
+
+impl ser::Error for ValueError {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    ValueError(msg.to_string())
+  }
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  type SerializeSeq = SeqSerializer;
+  type SerializeTuple = SeqSerializer;
+  type SerializeTupleStruct = SeqSerializer;
+  type SerializeTupleVariant = SeqSerializer;
+  type SerializeMap = StructSerializer;
+  type SerializeStruct = StructSerializer;
+  type SerializeStructVariant = StructSerializer;
+
+  fn serialize_bool(self, v: bool) -> Result<Value, ValueError> {
+    Ok(Value::Bool(v))
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_i16(self, v: i16) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_i32(self, v: i32) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_i64(self, v: i64) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_i128(self, v: i128) -> Result<Value, ValueError> {
+    Ok(Value::Int(v))
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_u16(self, v: u16) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_u32(self, v: u32) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_u64(self, v: u64) -> Result<Value, ValueError> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn serialize_u128(self, v: u128) -> Result<Value, ValueError> {
+    i128::try_from(v)
+      .map(Value::Int)
+      .map_err(|e| ValueError(e.to_string()))
+  }
+
+  fn serialize_f32(self, v: f32) -> Result<Value, ValueError> {
+    Ok(Value::Float(v.into()))
+  }
+
+  fn serialize_f64(self, v: f64) -> Result<Value, ValueError> {
+    Ok(Value::Float(v))
+  }
+
+  fn serialize_char(self, v: char) -> Result<Value, ValueError> {
+    Ok(Value::Str(v.to_string()))
+  }
+
+  fn serialize_str(self, v: &str) -> Result<Value, ValueError> {
+    Ok(Value::Str(v.to_string()))
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<Value, ValueError> {
+    Ok(Value::Seq(
+      v.iter().map(|b| Value::Int((*b).into())).collect(),
+    ))
+  }
+
+  fn serialize_none(self) -> Result<Value, ValueError> {
+    Ok(Value::Null)
+  }
+
+  fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, ValueError> {
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<Value, ValueError> {
+    Ok(Value::Null)
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ValueError> {
+    Ok(Value::Null)
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    variant: &'static str,
+  ) -> Result<Value, ValueError> {
+    Ok(Value::Enum {
+      variant: variant.to_string(),
+      index: variant_index,
+    })
+  }
+
+  fn serialize_newtype_struct<T: ?Sized + Serialize>(
+    self,
+    _name: &'static str,
+    value: &T,
+  ) -> Result<Value, ValueError> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: ?Sized + Serialize>(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+    value: &T,
+  ) -> Result<Value, ValueError> {
+    Ok(Value::Struct(vec![(
+      variant.to_string(),
+      value.serialize(self)?,
+    )]))
+  }
+
+  fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, ValueError> {
+    Ok(SeqSerializer::new(None, len.unwrap_or(0)))
+  }
+
+  fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, ValueError> {
+    Ok(SeqSerializer::new(None, len))
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    len: usize,
+  ) -> Result<SeqSerializer, ValueError> {
+    Ok(SeqSerializer::new(None, len))
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+    len: usize,
+  ) -> Result<SeqSerializer, ValueError> {
+    Ok(SeqSerializer::new(Some(variant), len))
+  }
+
+  fn serialize_map(self, len: Option<usize>) -> Result<StructSerializer, ValueError> {
+    Ok(StructSerializer::new(None, len.unwrap_or(0)))
+  }
+
+  fn serialize_struct(
+    self,
+    _name: &'static str,
+    len: usize,
+  ) -> Result<StructSerializer, ValueError> {
+    Ok(StructSerializer::new(None, len))
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+    len: usize,
+  ) -> Result<StructSerializer, ValueError> {
+    Ok(StructSerializer::new(Some(variant), len))
+  }
+}
+
+// Wraps the contents of a non-unit enum variant into a single-member struct,
+// so that the variant name becomes part of the member path.
+fn wrap_variant(variant: Option<&'static str>, value: Value) -> Value {
+  match variant {
+    Some(v) => Value::Struct(vec![(v.to_string(), value)]),
+    None => value,
+  }
+}
+
+struct SeqSerializer {
+  variant: Option<&'static str>,
+  elements: Vec<Value>,
+}
+
+impl SeqSerializer {
+  fn new(variant: Option<&'static str>, len: usize) -> Self {
+    Self {
+      variant,
+      elements: Vec::with_capacity(len),
+    }
+  }
+
+  fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueError> {
+    self.elements.push(value.serialize(ValueSerializer)?);
+    Ok(())
+  }
+
+  fn finish(self) -> Result<Value, ValueError> {
+    Ok(wrap_variant(self.variant, Value::Seq(self.elements)))
+  }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueError> {
+    self.push(value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueError> {
+    self.push(value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueError> {
+    self.push(value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueError> {
+    self.push(value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
+
+struct StructSerializer {
+  variant: Option<&'static str>,
+  members: Vec<(String, Value)>,
+  next_key: Option<String>,
+}
+
+impl StructSerializer {
+  fn new(variant: Option<&'static str>, len: usize) -> Self {
+    Self {
+      variant,
+      members: Vec::with_capacity(len),
+      next_key: None,
+    }
+  }
+
+  fn push<T: ?Sized + Serialize>(&mut self, name: String, value: &T) -> Result<(), ValueError> {
+    self.members.push((name, value.serialize(ValueSerializer)?));
+    Ok(())
+  }
+
+  fn finish(self) -> Result<Value, ValueError> {
+    Ok(wrap_variant(self.variant, Value::Struct(self.members)))
+  }
+}
+
+impl ser::SerializeMap for StructSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  // Map keys become member names, so that map entries can be referred to
+  // like struct members.
+  fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), ValueError> {
+    let name = match key.serialize(ValueSerializer)? {
+      Value::Str(s) => s,
+      Value::Int(i) => i.to_string(),
+      Value::Bool(b) => b.to_string(),
+      Value::Enum { variant, .. } => variant,
+      other => return Err(ValueError(format!("Unsupported map key {other:?}"))),
+    };
+    self.next_key = Some(name);
+    Ok(())
+  }
+
+  fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ValueError> {
+    let name = self
+      .next_key
+      .take()
+      .ok_or_else(|| ValueError("Map value without key".to_string()))?;
+    self.push(name, value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  fn serialize_field<T: ?Sized + Serialize>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), ValueError> {
+    self.push(key.to_string(), value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+  type Ok = Value;
+  type Error = ValueError;
+
+  fn serialize_field<T: ?Sized + Serialize>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), ValueError> {
+    self.push(key.to_string(), value)
+  }
+
+  fn end(self) -> Result<Value, ValueError> {
+    self.finish()
+  }
+}
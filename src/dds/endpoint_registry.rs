@@ -0,0 +1,151 @@
+use std::{
+  any::Any,
+  collections::BTreeMap,
+  sync::{Arc, Mutex, MutexGuard, Weak},
+};
+
+use chrono::Utc;
+
+use crate::{
+  dds::{
+    qos::QosPolicies,
+    result::{CreateError, ShareError, ShareResult},
+    statusevents::EndpointDescription,
+  },
+  structure::guid::GUID,
+};
+
+// Bookkeeping of the DataReaders of a Subscriber, or DataWriters of a Publisher.
+//
+// Every endpoint is described here from creation until it is dropped. An
+// endpoint can also be shared, so that it can be looked up by Topic name and
+// type from elsewhere in the application. The registry stores a type-erased
+// Weak pointer to the shared endpoint, so it does not keep the endpoint alive.
+#[derive(Default)]
+pub(crate) struct EndpointRegistry {
+  endpoints: Mutex<BTreeMap<GUID, RegisteredEndpoint>>,
+}
+
+struct RegisteredEndpoint {
+  description: EndpointDescription,
+  // Weak<Mutex<E>>, where E is the concrete DataReader or DataWriter type
+  shared: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl EndpointRegistry {
+  fn lock(&self) -> MutexGuard<'_, BTreeMap<GUID, RegisteredEndpoint>> {
+    // The map is always left in a consistent state, so poisoning is harmless.
+    self
+      .endpoints
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  pub fn add(&self, guid: GUID, topic_name: String, type_name: String, qos: QosPolicies) {
+    self.lock().insert(
+      guid,
+      RegisteredEndpoint {
+        description: EndpointDescription {
+          updated_time: Utc::now(),
+          guid,
+          topic_name,
+          type_name,
+          qos,
+        },
+        shared: None,
+      },
+    );
+  }
+
+  pub fn update_qos(&self, guid: GUID, qos: &QosPolicies) {
+    if let Some(registered) = self.lock().get_mut(&guid) {
+      registered.description.qos = qos.clone();
+      registered.description.updated_time = Utc::now();
+    }
+  }
+
+  pub fn remove(&self, guid: GUID) {
+    self.lock().remove(&guid);
+  }
+
+  pub fn descriptions(&self) -> Vec<EndpointDescription> {
+    self
+      .lock()
+      .values()
+      .map(|e| e.description.clone())
+      .collect()
+  }
+
+  // Wraps the endpoint for sharing and makes it available to lookup. An
+  // endpoint of someone else is given back.
+  pub fn share<E>(&self, guid: GUID, endpoint: E) -> ShareResult<E>
+  where
+    E: Send + 'static,
+  {
+    let mut endpoints = self.lock();
+    let Some(registered) = endpoints.get_mut(&guid) else {
+      return Err(ShareError {
+        error: CreateError::BadParameter {
+          reason: format!("Endpoint {guid:?} does not belong here."),
+        },
+        endpoint: Box::new(endpoint),
+      });
+    };
+    let shared = Arc::new(Mutex::new(endpoint));
+    registered.shared = Some(Box::new(Arc::downgrade(&shared)));
+    Ok(shared)
+  }
+
+  // Finds a shared endpoint of type E in the given Topic. If there are several,
+  // the one with the smallest GUID is returned.
+  pub fn lookup<E>(&self, topic_name: &str) -> Option<Arc<Mutex<E>>>
+  where
+    E: Send + 'static,
+  {
+    self
+      .lock()
+      .values()
+      .filter(|e| e.description.topic_name == topic_name)
+      .filter_map(|e| e.shared.as_ref()?.downcast_ref::<Weak<Mutex<E>>>())
+      .find_map(Weak::upgrade)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::EntityKind;
+
+  #[test]
+  fn registry_lookup_is_type_checked() {
+    let registry = EndpointRegistry::default();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    registry.add(
+      guid,
+      "Square".to_string(),
+      "ShapeType".to_string(),
+      QosPolicies::qos_none(),
+    );
+    assert!(registry.lookup::<String>("Square").is_none());
+
+    let shared = registry.share(guid, "endpoint".to_string()).unwrap();
+    assert!(registry.lookup::<u32>("Square").is_none());
+    assert!(registry.lookup::<String>("Circle").is_none());
+    let found = registry.lookup::<String>("Square").unwrap();
+    assert!(Arc::ptr_eq(&shared, &found));
+
+    // Registry does not keep the endpoint alive
+    drop(found);
+    drop(shared);
+    assert!(registry.lookup::<String>("Square").is_none());
+    assert_eq!(registry.descriptions().len(), 1);
+
+    registry.remove(guid);
+    assert!(registry.descriptions().is_empty());
+
+    let unknown = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let error = registry.share(unknown, 1_u32).unwrap_err();
+    assert!(matches!(error.error, CreateError::BadParameter { .. }));
+    assert_eq!(*error.endpoint, 1);
+  }
+}
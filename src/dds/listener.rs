@@ -0,0 +1,687 @@
+// DDS-style Listeners
+//
+// See DDS Specification v1.4 Section 2.2.4.3 Listener Interface and
+// 2.2.4.4 Conditions and Listeners for the propagation rules.
+//
+// Status events are tapped from the status channels of the entities, and sent
+// to a listener thread, which is started when the first listener is set. The
+// thread looks up the listener responsible for each event, and calls it.
+//
+// The event queue is bounded, so that slow listeners cannot make it grow
+// without limit. Events are dropped when it is full. The statuses carry
+// cumulative counts, so the next delivered event of a kind still tells the
+// listener the correct totals.
+use std::{
+  collections::HashMap,
+  panic::{self, AssertUnwindSafe},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc, Arc, Mutex, Weak,
+  },
+  thread,
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use enumflags2::BitFlags;
+
+use crate::{
+  dds::statusevents::{
+    DataReaderStatus, DataWriterStatus, DomainParticipantStatusEvent, StatusKind,
+  },
+  structure::guid::GUID,
+};
+
+/// Callbacks for status changes of a DataReader.
+///
+/// All methods have an empty default implementation, so implement only the
+/// ones you need. The methods are called from a listener thread managed by
+/// RustDDS, so they should return quickly.
+///
+/// Set with e.g.
+/// [`DataReader::set_listener`](crate::with_key::DataReader::set_listener).
+pub trait DataReaderListener: Send + Sync {
+  /// New data has arrived to the DataReader `reader`.
+  fn on_data_available(&self, _reader: GUID) {}
+  /// Called with [`DataReaderStatus::RequestedDeadlineMissed`]
+  fn on_requested_deadline_missed(&self, _reader: GUID, _status: &DataReaderStatus) {}
+  /// Called with [`DataReaderStatus::RequestedIncompatibleQos`]
+  fn on_requested_incompatible_qos(&self, _reader: GUID, _status: &DataReaderStatus) {}
+  /// Called with [`DataReaderStatus::SampleRejected`]
+  fn on_sample_rejected(&self, _reader: GUID, _status: &DataReaderStatus) {}
+  /// Called with [`DataReaderStatus::LivelinessChanged`]
+  fn on_liveliness_changed(&self, _reader: GUID, _status: &DataReaderStatus) {}
+  /// Called with [`DataReaderStatus::SubscriptionMatched`]
+  fn on_subscription_matched(&self, _reader: GUID, _status: &DataReaderStatus) {}
+  /// Called with [`DataReaderStatus::SampleLost`]
+  fn on_sample_lost(&self, _reader: GUID, _status: &DataReaderStatus) {}
+}
+
+/// Callbacks for status changes of a DataWriter.
+///
+/// See [`DataReaderListener`] for general notes.
+pub trait DataWriterListener: Send + Sync {
+  /// Called with [`DataWriterStatus::LivelinessLost`]
+  fn on_liveliness_lost(&self, _writer: GUID, _status: &DataWriterStatus) {}
+  /// Called with [`DataWriterStatus::OfferedDeadlineMissed`]
+  fn on_offered_deadline_missed(&self, _writer: GUID, _status: &DataWriterStatus) {}
+  /// Called with [`DataWriterStatus::OfferedIncompatibleQos`]
+  fn on_offered_incompatible_qos(&self, _writer: GUID, _status: &DataWriterStatus) {}
+  /// Called with [`DataWriterStatus::PublicationMatched`]
+  fn on_publication_matched(&self, _writer: GUID, _status: &DataWriterStatus) {}
+}
+
+/// Callbacks for a Subscriber. These also receive the statuses of those
+/// DataReaders of the Subscriber that do not have a listener of their own.
+pub trait SubscriberListener: DataReaderListener {
+  /// New data has arrived to the DataReader `reader` of this Subscriber.
+  /// If this is enabled, `on_data_available` is not called.
+  fn on_data_on_readers(&self, _reader: GUID) {}
+}
+
+/// Callbacks for a Publisher. These receive the statuses of those DataWriters
+/// of the Publisher that do not have a listener of their own.
+pub trait PublisherListener: DataWriterListener {}
+
+/// Callbacks for a DomainParticipant. These receive the statuses of those
+/// endpoints whose Publisher or Subscriber does not have a listener for them,
+/// either.
+pub trait DomainParticipantListener: SubscriberListener + PublisherListener {
+  /// Called with every [`DomainParticipantStatusEvent`], regardless of the
+  /// status mask.
+  fn on_participant_event(&self, _event: &DomainParticipantStatusEvent) {}
+}
+
+// An entity that has a listener, and statuses that the listener handles.
+struct Registration<L: ?Sized> {
+  listener: Arc<L>,
+  mask: BitFlags<StatusKind>,
+}
+
+impl<L: ?Sized> Registration<L> {
+  // Listener, if it handles status `kind`
+  fn handling(registration: Option<&Self>, kind: StatusKind) -> Option<Arc<L>> {
+    registration
+      .filter(|r| r.mask.contains(kind))
+      .map(|r| Arc::clone(&r.listener))
+  }
+}
+
+#[derive(Default)]
+struct Listeners {
+  participant: Option<Registration<dyn DomainParticipantListener>>,
+  publishers: HashMap<u64, Registration<dyn PublisherListener>>,
+  subscribers: HashMap<u64, Registration<dyn SubscriberListener>>,
+  writers: HashMap<GUID, Registration<dyn DataWriterListener>>,
+  readers: HashMap<GUID, Registration<dyn DataReaderListener>>,
+}
+
+pub(crate) enum ListenerEvent {
+  DataReader {
+    reader: GUID,
+    subscriber: u64,
+    status: DataReaderStatus,
+  },
+  DataWriter {
+    writer: GUID,
+    publisher: u64,
+    status: DataWriterStatus,
+  },
+  Participant(DomainParticipantStatusEvent),
+}
+
+// How many events may wait for the listener thread
+const EVENT_QUEUE_CAPACITY: usize = 128;
+
+// Listeners of one DomainParticipant and its contained entities.
+// Publishers and Subscribers are identified by keys given out by this hub.
+#[derive(Default)]
+pub(crate) struct ListenerHub {
+  listeners: Mutex<Listeners>,
+  // None until the listener thread is started
+  event_sender: Mutex<Option<mpsc::SyncSender<ListenerEvent>>>,
+  // Events are being dropped, because the queue is full
+  dropping_events: AtomicBool,
+  next_key: AtomicU64,
+}
+
+impl ListenerHub {
+  pub(crate) fn new_key(&self) -> u64 {
+    self.next_key.fetch_add(1, Ordering::Relaxed)
+  }
+
+  // Sends the event to the listener thread, if there is one. The event is
+  // built only if needed.
+  pub(crate) fn send(hub: &Weak<Self>, make_event: impl FnOnce() -> ListenerEvent) {
+    if let Some(hub) = hub.upgrade() {
+      if let Some(sender) = hub.event_sender.lock().unwrap().as_ref() {
+        // This is called from the event loop, so it must not block.
+        match sender.try_send(make_event()) {
+          Ok(()) => hub.dropping_events.store(false, Ordering::Relaxed),
+          Err(mpsc::TrySendError::Full(_)) => {
+            if !hub.dropping_events.swap(true, Ordering::Relaxed) {
+              warn!("Listeners are too slow. Dropping status events.");
+            }
+          }
+          // The thread has stopped, and then there is no one to tell.
+          Err(mpsc::TrySendError::Disconnected(_)) => {}
+        }
+      }
+    }
+  }
+
+  pub(crate) fn set_participant_listener(
+    self: &Arc<Self>,
+    listener: Option<Arc<dyn DomainParticipantListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    let start_thread = listener.is_some();
+    self.listeners.lock().unwrap().participant =
+      listener.map(|listener| Registration { listener, mask });
+    if start_thread {
+      self.ensure_thread();
+    }
+  }
+
+  pub(crate) fn set_publisher_listener(
+    self: &Arc<Self>,
+    key: u64,
+    listener: Option<Arc<dyn PublisherListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    let start_thread = listener.is_some();
+    Self::register(
+      &mut self.listeners.lock().unwrap().publishers,
+      key,
+      listener,
+      mask,
+    );
+    if start_thread {
+      self.ensure_thread();
+    }
+  }
+
+  pub(crate) fn set_subscriber_listener(
+    self: &Arc<Self>,
+    key: u64,
+    listener: Option<Arc<dyn SubscriberListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    let start_thread = listener.is_some();
+    Self::register(
+      &mut self.listeners.lock().unwrap().subscribers,
+      key,
+      listener,
+      mask,
+    );
+    if start_thread {
+      self.ensure_thread();
+    }
+  }
+
+  pub(crate) fn set_writer_listener(
+    self: &Arc<Self>,
+    writer: GUID,
+    listener: Option<Arc<dyn DataWriterListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    let start_thread = listener.is_some();
+    Self::register(
+      &mut self.listeners.lock().unwrap().writers,
+      writer,
+      listener,
+      mask,
+    );
+    if start_thread {
+      self.ensure_thread();
+    }
+  }
+
+  pub(crate) fn set_reader_listener(
+    self: &Arc<Self>,
+    reader: GUID,
+    listener: Option<Arc<dyn DataReaderListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    let start_thread = listener.is_some();
+    Self::register(
+      &mut self.listeners.lock().unwrap().readers,
+      reader,
+      listener,
+      mask,
+    );
+    if start_thread {
+      self.ensure_thread();
+    }
+  }
+
+  fn register<K: std::hash::Hash + Eq, L: ?Sized>(
+    registrations: &mut HashMap<K, Registration<L>>,
+    key: K,
+    listener: Option<Arc<L>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    match listener {
+      Some(listener) => {
+        registrations.insert(key, Registration { listener, mask });
+      }
+      None => {
+        registrations.remove(&key);
+      }
+    }
+  }
+
+  fn ensure_thread(self: &Arc<Self>) {
+    let mut event_sender = self.event_sender.lock().unwrap();
+    if event_sender.is_some() {
+      return;
+    }
+    let (sender, receiver) = mpsc::sync_channel(EVENT_QUEUE_CAPACITY);
+    // The thread must not keep the hub alive, or it would never stop.
+    let hub = Arc::downgrade(self);
+    let spawned = thread::Builder::new()
+      .name("RustDDS listener thread".to_string())
+      .spawn(move || {
+        // Stops when the hub, and with it the sender, is dropped.
+        while let Ok(event) = receiver.recv() {
+          match hub.upgrade() {
+            Some(hub) => {
+              if panic::catch_unwind(AssertUnwindSafe(|| hub.dispatch(event))).is_err() {
+                error!("A listener callback panicked.");
+              }
+            }
+            None => break,
+          }
+        }
+        debug!("Listener thread stopped.");
+      });
+    match spawned {
+      Ok(_join_handle) => *event_sender = Some(sender),
+      Err(e) => error!("Cannot start listener thread: {e}"),
+    }
+  }
+
+  fn dispatch(&self, event: ListenerEvent) {
+    match event {
+      ListenerEvent::DataReader {
+        reader,
+        subscriber,
+        status,
+      } => self.dispatch_reader_status(reader, subscriber, &status),
+      ListenerEvent::DataWriter {
+        writer,
+        publisher,
+        status,
+      } => self.dispatch_writer_status(writer, publisher, &status),
+      ListenerEvent::Participant(event) => {
+        let participant = self
+          .listeners
+          .lock()
+          .unwrap()
+          .participant
+          .as_ref()
+          .map(|r| Arc::clone(&r.listener));
+        if let Some(participant) = participant {
+          participant.on_participant_event(&event);
+        }
+      }
+    }
+  }
+
+  // The listener lock must not be held while calling the listeners, because
+  // they may want to e.g. set listeners.
+  fn dispatch_reader_status(&self, reader: GUID, subscriber: u64, status: &DataReaderStatus) {
+    let kind = status.kind();
+    let listeners = self.listeners.lock().unwrap();
+    let subscriber_reg = listeners.subscribers.get(&subscriber);
+
+    // DATA_ON_READERS takes precedence over DATA_AVAILABLE
+    if kind == StatusKind::DataAvailable {
+      if let Some(sl) = Registration::handling(subscriber_reg, StatusKind::DataOnReaders) {
+        drop(listeners);
+        sl.on_data_on_readers(reader);
+        return;
+      }
+      if let Some(pl) =
+        Registration::handling(listeners.participant.as_ref(), StatusKind::DataOnReaders)
+      {
+        drop(listeners);
+        pl.on_data_on_readers(reader);
+        return;
+      }
+    }
+
+    if let Some(rl) = Registration::handling(listeners.readers.get(&reader), kind) {
+      drop(listeners);
+      call_reader_listener(&*rl, reader, status);
+    } else if let Some(sl) = Registration::handling(subscriber_reg, kind) {
+      drop(listeners);
+      call_reader_listener(&*sl, reader, status);
+    } else if let Some(pl) = Registration::handling(listeners.participant.as_ref(), kind) {
+      drop(listeners);
+      call_reader_listener(&*pl, reader, status);
+    }
+  }
+
+  fn dispatch_writer_status(&self, writer: GUID, publisher: u64, status: &DataWriterStatus) {
+    let kind = status.kind();
+    let listeners = self.listeners.lock().unwrap();
+    if let Some(wl) = Registration::handling(listeners.writers.get(&writer), kind) {
+      drop(listeners);
+      call_writer_listener(&*wl, writer, status);
+    } else if let Some(pl) = Registration::handling(listeners.publishers.get(&publisher), kind) {
+      drop(listeners);
+      call_writer_listener(&*pl, writer, status);
+    } else if let Some(dpl) = Registration::handling(listeners.participant.as_ref(), kind) {
+      drop(listeners);
+      call_writer_listener(&*dpl, writer, status);
+    }
+  }
+}
+
+// Identifies a Publisher or Subscriber in the ListenerHub. Dropping this
+// removes the listener of the entity.
+pub(crate) struct ListenerKey {
+  key: u64,
+  hub: Weak<ListenerHub>,
+}
+
+impl ListenerKey {
+  pub(crate) fn new(hub: &Arc<ListenerHub>) -> Self {
+    Self {
+      key: hub.new_key(),
+      hub: Arc::downgrade(hub),
+    }
+  }
+
+  pub(crate) fn key(&self) -> u64 {
+    self.key
+  }
+
+  pub(crate) fn hub(&self) -> Weak<ListenerHub> {
+    Weak::clone(&self.hub)
+  }
+}
+
+impl Drop for ListenerKey {
+  fn drop(&mut self) {
+    if let Some(hub) = self.hub.upgrade() {
+      let mut listeners = hub.listeners.lock().unwrap();
+      // A key is either a Publisher or a Subscriber.
+      listeners.publishers.remove(&self.key);
+      listeners.subscribers.remove(&self.key);
+    }
+  }
+}
+
+fn call_reader_listener<L: DataReaderListener + ?Sized>(
+  listener: &L,
+  reader: GUID,
+  status: &DataReaderStatus,
+) {
+  match status {
+    DataReaderStatus::DataAvailable => listener.on_data_available(reader),
+    DataReaderStatus::RequestedDeadlineMissed { .. } => {
+      listener.on_requested_deadline_missed(reader, status);
+    }
+    DataReaderStatus::RequestedIncompatibleQos { .. } => {
+      listener.on_requested_incompatible_qos(reader, status);
+    }
+    DataReaderStatus::SampleRejected { .. } => listener.on_sample_rejected(reader, status),
+    DataReaderStatus::LivelinessChanged { .. } => listener.on_liveliness_changed(reader, status),
+    DataReaderStatus::SubscriptionMatched { .. } => {
+      listener.on_subscription_matched(reader, status);
+    }
+    DataReaderStatus::SampleLost { .. } => listener.on_sample_lost(reader, status),
+  }
+}
+
+fn call_writer_listener<L: DataWriterListener + ?Sized>(
+  listener: &L,
+  writer: GUID,
+  status: &DataWriterStatus,
+) {
+  match status {
+    DataWriterStatus::LivelinessLost { .. } => listener.on_liveliness_lost(writer, status),
+    DataWriterStatus::OfferedDeadlineMissed { .. } => {
+      listener.on_offered_deadline_missed(writer, status);
+    }
+    DataWriterStatus::OfferedIncompatibleQos { .. } => {
+      listener.on_offered_incompatible_qos(writer, status);
+    }
+    DataWriterStatus::PublicationMatched { .. } => listener.on_publication_matched(writer, status),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{dds::statusevents::CountWithChange, structure::guid::EntityKind};
+
+  // Records which listener got which callback
+  struct Recorder {
+    name: &'static str,
+    calls: Arc<Mutex<Vec<String>>>,
+  }
+
+  impl Recorder {
+    fn new(name: &'static str, calls: &Arc<Mutex<Vec<String>>>) -> Arc<Self> {
+      Arc::new(Self {
+        name,
+        calls: Arc::clone(calls),
+      })
+    }
+
+    fn record(&self, call: &str) {
+      self
+        .calls
+        .lock()
+        .unwrap()
+        .push(format!("{}:{call}", self.name));
+    }
+  }
+
+  impl DataReaderListener for Recorder {
+    fn on_data_available(&self, _reader: GUID) {
+      self.record("data_available");
+    }
+    fn on_subscription_matched(&self, _reader: GUID, _status: &DataReaderStatus) {
+      self.record("subscription_matched");
+    }
+  }
+  impl DataWriterListener for Recorder {
+    fn on_publication_matched(&self, _writer: GUID, _status: &DataWriterStatus) {
+      self.record("publication_matched");
+    }
+  }
+  impl SubscriberListener for Recorder {
+    fn on_data_on_readers(&self, _reader: GUID) {
+      self.record("data_on_readers");
+    }
+  }
+  impl PublisherListener for Recorder {}
+  impl DomainParticipantListener for Recorder {}
+
+  fn subscription_matched() -> DataReaderStatus {
+    DataReaderStatus::SubscriptionMatched {
+      total: CountWithChange::new(1, 1),
+      current: CountWithChange::new(1, 1),
+      writer: GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+    }
+  }
+
+  fn taken(calls: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+    std::mem::take(&mut *calls.lock().unwrap())
+  }
+
+  #[test]
+  fn reader_status_propagates_to_parents() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let hub = Arc::new(ListenerHub::default());
+    let subscriber = hub.new_key();
+    let reader = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+
+    hub.set_participant_listener(
+      Some(Recorder::new("participant", &calls)),
+      StatusKind::SubscriptionMatched.into(),
+    );
+    hub.dispatch_reader_status(reader, subscriber, &subscription_matched());
+    assert_eq!(taken(&calls), vec!["participant:subscription_matched"]);
+
+    hub.set_subscriber_listener(
+      subscriber,
+      Some(Recorder::new("subscriber", &calls)),
+      StatusKind::SubscriptionMatched.into(),
+    );
+    hub.dispatch_reader_status(reader, subscriber, &subscription_matched());
+    assert_eq!(taken(&calls), vec!["subscriber:subscription_matched"]);
+
+    // Reader listener does not handle the status, so it goes to Subscriber.
+    hub.set_reader_listener(
+      reader,
+      Some(Recorder::new("reader", &calls)),
+      StatusKind::DataAvailable.into(),
+    );
+    hub.dispatch_reader_status(reader, subscriber, &subscription_matched());
+    assert_eq!(taken(&calls), vec!["subscriber:subscription_matched"]);
+
+    hub.dispatch_reader_status(reader, subscriber, &DataReaderStatus::DataAvailable);
+    assert_eq!(taken(&calls), vec!["reader:data_available"]);
+
+    // DataOnReaders takes precedence over DataAvailable
+    hub.set_participant_listener(
+      Some(Recorder::new("participant", &calls)),
+      StatusKind::DataOnReaders.into(),
+    );
+    hub.dispatch_reader_status(reader, subscriber, &DataReaderStatus::DataAvailable);
+    assert_eq!(taken(&calls), vec!["participant:data_on_readers"]);
+
+    // Removing the Subscriber listener
+    hub.set_subscriber_listener(subscriber, None, BitFlags::empty());
+    hub.set_reader_listener(reader, None, BitFlags::empty());
+    hub.dispatch_reader_status(reader, subscriber, &subscription_matched());
+    assert!(taken(&calls).is_empty());
+  }
+
+  #[test]
+  fn writer_status_propagates_to_parents() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let hub = Arc::new(ListenerHub::default());
+    let publisher = ListenerKey::new(&hub);
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let status = DataWriterStatus::PublicationMatched {
+      total: CountWithChange::new(1, 1),
+      current: CountWithChange::new(1, 1),
+      reader: GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED),
+    };
+
+    hub.set_publisher_listener(
+      publisher.key(),
+      Some(Recorder::new("publisher", &calls)),
+      StatusKind::PublicationMatched.into(),
+    );
+    hub.dispatch_writer_status(writer, publisher.key(), &status);
+    assert_eq!(taken(&calls), vec!["publisher:publication_matched"]);
+
+    hub.set_writer_listener(
+      writer,
+      Some(Recorder::new("writer", &calls)),
+      StatusKind::PublicationMatched.into(),
+    );
+    hub.dispatch_writer_status(writer, publisher.key(), &status);
+    assert_eq!(taken(&calls), vec!["writer:publication_matched"]);
+
+    // Dropping the key removes the Publisher listener.
+    let publisher_key = publisher.key();
+    drop(publisher);
+    hub.set_writer_listener(writer, None, BitFlags::empty());
+    hub.dispatch_writer_status(writer, publisher_key, &status);
+    assert!(taken(&calls).is_empty());
+  }
+
+  #[test]
+  fn listener_thread_delivers_events() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let hub = Arc::new(ListenerHub::default());
+    let weak_hub = Arc::downgrade(&hub);
+    let reader = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+
+    // Nothing is sent before there are listeners.
+    ListenerHub::send(&weak_hub, || unreachable!());
+
+    hub.set_reader_listener(
+      reader,
+      Some(Recorder::new("reader", &calls)),
+      StatusKind::DataAvailable.into(),
+    );
+    ListenerHub::send(&weak_hub, || ListenerEvent::DataReader {
+      reader,
+      subscriber: 0,
+      status: DataReaderStatus::DataAvailable,
+    });
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while calls.lock().unwrap().is_empty() && std::time::Instant::now() < deadline {
+      thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(taken(&calls), vec!["reader:data_available"]);
+  }
+
+  #[test]
+  fn slow_listener_does_not_block_sending() {
+    // Blocks in the first callback until released
+    struct Slow {
+      release: Mutex<mpsc::Receiver<()>>,
+      calls: AtomicU64,
+    }
+    impl DataReaderListener for Slow {
+      fn on_data_available(&self, _reader: GUID) {
+        if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+          self.release.lock().unwrap().recv().unwrap();
+        }
+      }
+    }
+
+    let hub = Arc::new(ListenerHub::default());
+    let weak_hub = Arc::downgrade(&hub);
+    let reader = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let (release_sender, release_receiver) = mpsc::channel();
+    let slow = Arc::new(Slow {
+      release: Mutex::new(release_receiver),
+      calls: AtomicU64::new(0),
+    });
+    hub.set_reader_listener(
+      reader,
+      Some(Arc::clone(&slow) as Arc<dyn DataReaderListener>),
+      StatusKind::DataAvailable.into(),
+    );
+
+    let send = || {
+      ListenerHub::send(&weak_hub, || ListenerEvent::DataReader {
+        reader,
+        subscriber: 0,
+        status: DataReaderStatus::DataAvailable,
+      });
+    };
+    send();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while slow.calls.load(Ordering::SeqCst) == 0 && std::time::Instant::now() < deadline {
+      thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(slow.calls.load(Ordering::SeqCst), 1);
+
+    // The listener is stuck. The queue fills up, and the rest are dropped.
+    for _ in 0..2 * EVENT_QUEUE_CAPACITY {
+      send();
+    }
+    assert!(hub.dropping_events.load(Ordering::Relaxed));
+
+    release_sender.send(()).unwrap();
+    let expected = 1 + EVENT_QUEUE_CAPACITY as u64;
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while slow.calls.load(Ordering::SeqCst) < expected && std::time::Instant::now() < deadline {
+      thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(slow.calls.load(Ordering::SeqCst), expected);
+  }
+}
@@ -1,19 +1,23 @@
 use std::{
   io,
   pin::Pin,
+  sync::Arc,
   task::{Context, Poll},
 };
 
 use futures::stream::{FusedStream, Stream};
+use enumflags2::BitFlags;
 
 use crate::{
   dds::{
     adapters::no_key::DeserializerAdapter,
+    listener::DataReaderListener,
     no_key::datasample::DataSample,
-    qos::{HasQoSPolicy, QosPolicies},
-    readcondition::ReadCondition,
-    result::ReadResult,
-    statusevents::DataReaderStatus,
+    qos::{HasQoSPolicy, MutQosPolicy, QosPolicies},
+    readcondition::{ReadCondition, SampleCondition},
+    result::{QosError, ReadResult},
+    statusevents::{CountWithChange, DataReaderStatus, StatusKind},
+    waitset::{DataReaderReadCondition, StatusCondition},
     with_key::{
       datareader as datareader_with_key,
       datasample::{DataSample as WithKeyDataSample, Sample},
@@ -21,11 +25,12 @@ use crate::{
       DataReaderStream as WithKeyDataReaderStream,
     },
   },
+  discovery::sedp_messages::PublicationBuiltinTopicData,
   serialization::CDRDeserializerAdapter,
-  structure::entity::RTPSEntity,
+  structure::{duration::Duration, entity::RTPSEntity},
   StatusEvented, GUID,
 };
-use super::wrappers::{DAWrapper, NoKeyWrapper};
+use super::wrappers::{DAWrapper, NoKeyCondition, NoKeyWrapper};
 
 /// Simplified type for CDR encoding
 pub type DataReaderCdr<D> = DataReader<D, CDRDeserializerAdapter<D>>;
@@ -77,7 +82,9 @@ where
   /// # Arguments
   ///
   /// * `max_samples` - Limits maximum amount of samples read
-  /// * `read_condition` - Limits results by condition
+  /// * `read_condition` - Limits results by condition. This is either a
+  ///   [`ReadCondition`] or a reference to a
+  ///   [`QueryCondition`](crate::QueryCondition).
   ///
   /// # Examples
   ///
@@ -102,10 +109,11 @@ where
   pub fn read(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<Vec<DataSample<&D>>> {
-    let values: Vec<WithKeyDataSample<&NoKeyWrapper<D>>> =
-      self.keyed_datareader.read(max_samples, read_condition)?;
+    let values: Vec<WithKeyDataSample<&NoKeyWrapper<D>>> = self
+      .keyed_datareader
+      .read(max_samples, NoKeyCondition(read_condition))?;
     let mut result = Vec::with_capacity(values.len());
     for ks in values {
       if let Some(s) = DataSample::<D>::from_with_key_ref(ks) {
@@ -121,7 +129,9 @@ where
   /// # Arguments
   ///
   /// * `max_samples` - Limits maximum amount of samples read
-  /// * `read_condition` - Limits results by condition
+  /// * `read_condition` - Limits results by condition. This is either a
+  ///   [`ReadCondition`] or a reference to a
+  ///   [`QueryCondition`](crate::QueryCondition).
   ///
   /// # Examples
   ///
@@ -147,10 +157,11 @@ where
   pub fn take(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<Vec<DataSample<D>>> {
-    let values: Vec<WithKeyDataSample<NoKeyWrapper<D>>> =
-      self.keyed_datareader.take(max_samples, read_condition)?;
+    let values: Vec<WithKeyDataSample<NoKeyWrapper<D>>> = self
+      .keyed_datareader
+      .take(max_samples, NoKeyCondition(read_condition))?;
     let mut result = Vec::with_capacity(values.len());
     for ks in values {
       if let Some(s) = DataSample::<D>::from_with_key(ks) {
@@ -288,7 +299,7 @@ where
   /// ```
   pub fn conditional_iterator(
     &mut self,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<impl Iterator<Item = &D>> {
     // TODO: We could come up with a more efficient implementation than wrapping a
     // read call
@@ -370,7 +381,7 @@ where
   /// ```
   pub fn into_conditional_iterator(
     &mut self,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<impl Iterator<Item = D>> {
     // TODO: We could come up with a more efficient implementation than wrapping a
     // read call
@@ -381,6 +392,77 @@ where
         .map(|ds| ds.value),
     )
   }
+  /// Gets the number of samples discarded by the
+  /// [`TimeBasedFilter`](crate::policy::TimeBasedFilter) QoS policy.
+  ///
+  /// See [`get_time_based_filter_status`](WithKeyDataReader::get_time_based_filter_status)
+  /// of the keyed DataReader.
+  pub fn get_time_based_filter_status(&mut self) -> ReadResult<CountWithChange> {
+    self.keyed_datareader.get_time_based_filter_status()
+  }
+
+  /// Waits until the historical data of all matched DataWriters has been
+  /// received, or until `max_wait` has passed.
+  ///
+  /// See [`wait_for_historical_data`](WithKeyDataReader::wait_for_historical_data)
+  /// of the keyed DataReader.
+  pub fn wait_for_historical_data(&mut self, max_wait: Duration) -> ReadResult<bool> {
+    self.keyed_datareader.wait_for_historical_data(max_wait)
+  }
+
+  /// Like the synchronous version.
+  /// But there is no timeout. Use asyncs to bring your own timeout.
+  pub async fn async_wait_for_historical_data(&mut self) -> ReadResult<bool> {
+    self.keyed_datareader.async_wait_for_historical_data().await
+  }
+
+  /// Gets the discovery data of the DataWriters currently matched with this
+  /// DataReader.
+  ///
+  /// See [`get_matched_publications`](WithKeyDataReader::get_matched_publications)
+  /// of the keyed DataReader.
+  pub fn get_matched_publications(&self) -> impl Iterator<Item = PublicationBuiltinTopicData> {
+    self.keyed_datareader.get_matched_publications()
+  }
+
+  /// Enables or disables [`DataReaderStatus::DataAvailable`] events in the
+  /// status stream of this DataReader.
+  ///
+  /// See [`set_data_available_status`](WithKeyDataReader::set_data_available_status)
+  /// of the keyed DataReader.
+  pub fn set_data_available_status(&self, enabled: bool) {
+    self.keyed_datareader.set_data_available_status(enabled);
+  }
+
+  /// Gets the StatusCondition of this DataReader.
+  ///
+  /// See [`get_statuscondition`](WithKeyDataReader::get_statuscondition)
+  /// of the keyed DataReader.
+  pub fn get_statuscondition(&self) -> StatusCondition {
+    self.keyed_datareader.get_statuscondition()
+  }
+
+  /// Sets a listener for status changes of this DataReader.
+  ///
+  /// See [`set_listener`](WithKeyDataReader::set_listener)
+  /// of the keyed DataReader.
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn DataReaderListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self.keyed_datareader.set_listener(listener, mask);
+  }
+
+  /// Creates a Condition that triggers when this DataReader has samples
+  /// matching `read_condition`.
+  ///
+  /// See [`create_readcondition`](WithKeyDataReader::create_readcondition)
+  /// of the keyed DataReader.
+  pub fn create_readcondition(&mut self, read_condition: ReadCondition) -> DataReaderReadCondition {
+    self.keyed_datareader.create_readcondition(read_condition)
+  }
+
   /*
   /// Gets latest RequestedDeadlineMissed status
   ///
@@ -548,6 +630,18 @@ where
   }
 }
 
+/// See [`set_qos`](crate::with_key::DataReader::set_qos) of the keyed
+/// DataReader.
+impl<D, DA> MutQosPolicy for DataReader<D, DA>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn set_qos(&mut self, new_qos: &QosPolicies) -> Result<(), QosError> {
+    self.keyed_datareader.set_qos(new_qos)
+  }
+}
+
 impl<D, DA> RTPSEntity for DataReader<D, DA>
 where
   D: 'static,
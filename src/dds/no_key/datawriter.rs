@@ -1,15 +1,21 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use mio_06::Evented;
+use enumflags2::BitFlags;
 
 use crate::{
   dds::{
     adapters::no_key::SerializerAdapter,
+    listener::DataWriterListener,
     pubsub::Publisher,
-    qos::{HasQoSPolicy, QosPolicies},
-    result::{unwrap_no_key_write_error, WriteResult},
-    statusevents::{DataWriterStatus, StatusReceiverStream},
+    qos::{HasQoSPolicy, MutQosPolicy, QosPolicies},
+    result::{unwrap_no_key_write_error, QosError, WriteResult},
+    statusevents::{
+      DataWriterStatus, LivelinessLostStatus, OfferedDeadlineMissedStatus,
+      OfferedIncompatibleQosStatus, PublicationMatchedStatus, StatusKind, StatusReceiverStream,
+    },
     topic::Topic,
+    waitset::StatusCondition,
     with_key::datawriter as datawriter_with_key,
   },
   discovery::sedp_messages::SubscriptionBuiltinTopicData,
@@ -128,14 +134,13 @@ where
   pub fn wait_for_acknowledgments(&self, max_wait: Duration) -> WriteResult<bool, ()> {
     self.keyed_datawriter.wait_for_acknowledgments(max_wait)
   }
-  /*
+
   // status queries
-  /// Unimplemented. <b>Do not use</b>.
+  /// Gets the LIVELINESS_LOST status. See the keyed DataWriter.
   ///
   /// # Examples
   ///
-  // TODO: enable run when implemented
-  /// ```no_run
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::no_key::DataWriter;
@@ -150,23 +155,21 @@ where
   /// #
   /// // NoKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(status) = data_writer.get_liveliness_lost_status() {
-  ///   // Do something
-  /// }
+  /// let status = data_writer.get_liveliness_lost_status();
+  /// assert_eq!(status.count.count(), 0);
   /// ```
-  pub fn get_liveliness_lost_status(&self) -> Result<LivelinessLostStatus> {
+  pub fn get_liveliness_lost_status(&self) -> LivelinessLostStatus {
     self.keyed_datawriter.get_liveliness_lost_status()
   }
 
-  /// Should get latest offered deadline missed status. <b>Do not use yet</b> use `get_status_lister` instead for the moment.
+  /// Gets the OFFERED_DEADLINE_MISSED status. See the keyed DataWriter.
   ///
   /// # Examples
   ///
   /// ```
   /// # use serde::{Serialize, Deserialize};
-  /// # use std::time::Duration;
   /// # use rustdds::*;
   /// # use rustdds::no_key::DataWriter;
   /// # use rustdds::serialization::CDRSerializerAdapter;
@@ -180,22 +183,20 @@ where
   /// #
   /// // NoKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(odl_status) = data_writer.get_offered_deadline_missed_status() {
-  ///   // Do something
-  /// }
+  /// let status = data_writer.get_offered_deadline_missed_status();
+  /// assert_eq!(status.count.count(), 0);
   /// ```
-  pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
+  pub fn get_offered_deadline_missed_status(&self) -> OfferedDeadlineMissedStatus {
     self.keyed_datawriter.get_offered_deadline_missed_status()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// Gets the OFFERED_INCOMPATIBLE_QOS status. See the keyed DataWriter.
   ///
   /// # Examples
   ///
-  // TODO: enable run when implemented
-  /// ```no_run
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::no_key::DataWriter;
@@ -210,22 +211,20 @@ where
   /// #
   /// // NoKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(status) = data_writer.get_offered_incompatible_qos_status() {
-  ///   // Do something
-  /// }
+  /// let status = data_writer.get_offered_incompatible_qos_status();
+  /// assert_eq!(status.last_policy_id, None);
   /// ```
-  pub fn get_offered_incompatible_qos_status(&self) -> Result<OfferedIncompatibleQosStatus> {
+  pub fn get_offered_incompatible_qos_status(&self) -> OfferedIncompatibleQosStatus {
     self.keyed_datawriter.get_offered_incompatible_qos_status()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// Gets the PUBLICATION_MATCHED status. See the keyed DataWriter.
   ///
   /// # Examples
   ///
-  // TODO: enable run when implemented
-  /// ```no_run
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::no_key::DataWriter;
@@ -240,16 +239,30 @@ where
   /// #
   /// // NoKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(status) = data_writer.get_publication_matched_status() {
-  ///   // Do something
-  /// }
+  /// let status = data_writer.get_publication_matched_status();
+  /// println!("Currently matched with {} readers", status.current.count());
   /// ```
-  pub fn get_publication_matched_status(&self) -> Result<PublicationMatchedStatus> {
+  pub fn get_publication_matched_status(&self) -> PublicationMatchedStatus {
     self.keyed_datawriter.get_publication_matched_status()
   }
-  */
+
+  /// Gets the StatusCondition of this DataWriter. See the keyed DataWriter.
+  pub fn get_statuscondition(&self) -> StatusCondition {
+    self.keyed_datawriter.get_statuscondition()
+  }
+
+  /// Sets a listener for status changes of this DataWriter. See the keyed
+  /// DataWriter.
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn DataWriterListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self.keyed_datawriter.set_listener(listener, mask);
+  }
+
   /// Topic this DataWriter is connected to.
   ///
   /// # Examples
@@ -428,6 +441,14 @@ impl<D, SA: SerializerAdapter<D>> HasQoSPolicy for DataWriter<D, SA> {
   }
 }
 
+/// See [`set_qos`](crate::with_key::DataWriter::set_qos) of the keyed
+/// DataWriter.
+impl<D, SA: SerializerAdapter<D>> MutQosPolicy for DataWriter<D, SA> {
+  fn set_qos(&mut self, new_qos: &QosPolicies) -> Result<(), QosError> {
+    self.keyed_datawriter.set_qos(new_qos)
+  }
+}
+
 //-------------------------------------------------------------------------------
 // async writing implementation
 //
@@ -3,7 +3,13 @@ use std::{marker::PhantomData, ops::Deref};
 use bytes::Bytes;
 
 use crate::{
-  dds::adapters::*, messages::submessages::submessages::RepresentationIdentifier, Keyed,
+  dds::{
+    adapters::*,
+    ddssql::FilterableSample,
+    readcondition::{ReadCondition, SampleCondition},
+  },
+  messages::submessages::submessages::RepresentationIdentifier,
+  Keyed,
 };
 
 // This wrapper is used to convert NO_KEY types to WITH_KEY
@@ -34,6 +40,21 @@ impl<D> Keyed for NoKeyWrapper<D> {
   fn key(&self) {}
 }
 
+// wrapper for SampleCondition
+// * inside evaluates NO_KEY samples
+// * outside evaluates wrapped samples
+pub(crate) struct NoKeyCondition<C>(pub C);
+
+impl<D, C: SampleCondition<D>> SampleCondition<NoKeyWrapper<D>> for NoKeyCondition<C> {
+  fn read_condition(&self) -> ReadCondition {
+    self.0.read_condition()
+  }
+
+  fn content_matches(&self, sample: Option<&NoKeyWrapper<D>>) -> bool {
+    self.0.content_matches(sample.map(|w| &w.d))
+  }
+}
+
 // wrapper for SerializerAdapter
 // * inside is NO_KEY
 // * outside of wrapper is WITH_KEY
@@ -55,6 +76,10 @@ where
   fn to_bytes(value: &NoKeyWrapper<D>) -> Result<Bytes, SA::Error> {
     SA::to_bytes(&value.d)
   }
+
+  fn to_filterable(value: &NoKeyWrapper<D>) -> Option<FilterableSample> {
+    SA::to_filterable(&value.d)
+  }
 }
 
 // This is the point of wrapping. Implement dummy key serialization
@@ -16,24 +16,28 @@ use mio_extras::channel as mio_channel;
 use mio_06::{self, Evented};
 use mio_08::{Interest, Registry};
 use futures::stream::{FusedStream, Stream};
+use enumflags2::BitFlags;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_out_of_resources, create_error_poisoned,
+  create_error_bad_parameter, create_error_out_of_resources, create_error_poisoned,
   dds::{
     pubsub::*,
     qos::*,
     result::*,
+    listener::{DomainParticipantListener, ListenerEvent, ListenerHub},
     statusevents::{
-      sync_status_channel, DomainParticipantStatusEvent, StatusChannelReceiver, StatusChannelSender,
+      sync_status_channel, DomainParticipantStatusEvent, StatusChannelReceiver,
+      StatusChannelSender, StatusKind,
     },
     topic::*,
     typedesc::TypeDesc,
   },
   discovery::{
+    content_filter_property::ContentFilterProperty,
     discovery::{Discovery, DiscoveryCommand},
-    discovery_db::DiscoveryDB,
+    discovery_db::{discovery_db_write, DiscoveryDB},
     sedp_messages::DiscoveredTopicData,
   },
   network::{constant::*, udp_listener::UDPListener},
@@ -223,6 +227,14 @@ impl DomainParticipantBuilder {
     // Channel used to report noteworthy events to DomainParticipant
     let (status_sender, status_receiver) = sync_status_channel(16)?;
 
+    // Listeners get a copy of the participant status events
+    let listener_hub = Arc::new(ListenerHub::default());
+    let hub = Arc::downgrade(&listener_hub);
+    let status_sender =
+      status_sender.with_listener_tap(move |event: &DomainParticipantStatusEvent| {
+        ListenerHub::send(&hub, || ListenerEvent::Participant(event.clone()));
+      });
+
     #[cfg(not(feature = "security"))]
     let security_plugins_handle = None;
     #[cfg(feature = "security")]
@@ -240,6 +252,7 @@ impl DomainParticipantBuilder {
       status_sender.clone(),
       status_receiver,
       security_plugins_handle.clone(),
+      listener_hub,
     )?;
     let self_locators = dp.self_locators();
 
@@ -401,6 +414,51 @@ impl DomainParticipant {
     self.dpi.lock()?.find_topic(&w, name, timeout)
   }
 
+  /// Creates a ContentFilteredTopic, which can be used to create DataReaders
+  /// that receive only samples matching the filter.
+  ///
+  /// # Arguments
+  ///
+  /// * `name` - Name of the ContentFilteredTopic.
+  /// * `related_topic` - Topic whose samples are to be filtered. Must be
+  ///   created by this DomainParticipant.
+  /// * `filter_expression` - DDSSQL expression, as defined in DDS spec Annex
+  ///   B.
+  /// * `expression_parameters` - Values for the parameters `%0`, `%1`, ... in
+  ///   the expression.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let filtered = domain_participant.create_content_filtered_topic("filtered".to_string(), &topic, "x > %0", vec!["1".to_string()]);
+  /// assert!(filtered.is_ok());
+  /// ```
+  pub fn create_content_filtered_topic(
+    &self,
+    name: String,
+    related_topic: &Topic,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<ContentFilteredTopic> {
+    if related_topic.participant().as_ref() != Some(self) {
+      return create_error_bad_parameter!(
+        "Topic {} does not belong to this DomainParticipant",
+        related_topic.name()
+      );
+    }
+    ContentFilteredTopic::new(
+      name,
+      related_topic,
+      filter_expression,
+      expression_parameters,
+    )
+    .or_else(|e| create_error_bad_parameter!("Cannot create ContentFilteredTopic: {e}"))
+  }
+
   /// # Examples
   ///
   /// ```
@@ -458,6 +516,70 @@ impl DomainParticipant {
     self.dpi.lock()?.assert_liveliness()
   }
 
+  /// Ignores a remote DomainParticipant, identified by its GUID. Its
+  /// messages are dropped, and its DataReaders, DataWriters, and Topics are
+  /// no longer discovered, matched, or reported. Those already discovered are
+  /// removed.
+  ///
+  /// This cannot be undone, unless the DomainParticipant is deleted.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  ///
+  /// // e.g. from DomainParticipantStatusEvent::ParticipantDiscovered
+  /// let noisy = GUID::new_participant_guid();
+  /// domain_participant.ignore_participant(noisy).unwrap();
+  /// ```
+  pub fn ignore_participant(&self, participant: GUID) -> CreateResult<()> {
+    if participant.prefix == self.guid().prefix {
+      return create_error_bad_parameter!("Cannot ignore self.");
+    }
+    self
+      .dpi
+      .lock()?
+      .ignore(|db| db.ignore_participant(participant.prefix))
+  }
+
+  /// Ignores all remote DataReaders, DataWriters, and Topic definitions of
+  /// the Topic `topic_name`. Local endpoints of the Topic are not affected,
+  /// but they will not match any remote ones.
+  ///
+  /// See [`ignore_participant`](Self::ignore_participant).
+  pub fn ignore_topic(&self, topic_name: &str) -> CreateResult<()> {
+    let topic_name = topic_name.to_string();
+    self.dpi.lock()?.ignore(|db| db.ignore_topic(topic_name))
+  }
+
+  /// Ignores a remote DataWriter, identified by its GUID.
+  ///
+  /// See [`ignore_participant`](Self::ignore_participant).
+  pub fn ignore_publication(&self, writer: GUID) -> CreateResult<()> {
+    if !writer.entity_id.entity_kind.is_writer() {
+      return create_error_bad_parameter!("{writer:?} is not a DataWriter.");
+    }
+    self.ignore_endpoint(writer)
+  }
+
+  /// Ignores a remote DataReader, identified by its GUID.
+  ///
+  /// See [`ignore_participant`](Self::ignore_participant).
+  pub fn ignore_subscription(&self, reader: GUID) -> CreateResult<()> {
+    if !reader.entity_id.entity_kind.is_reader() {
+      return create_error_bad_parameter!("{reader:?} is not a DataReader.");
+    }
+    self.ignore_endpoint(reader)
+  }
+
+  fn ignore_endpoint(&self, endpoint: GUID) -> CreateResult<()> {
+    if endpoint.prefix == self.guid().prefix {
+      return create_error_bad_parameter!("Cannot ignore own endpoint {endpoint:?}.");
+    }
+    self.dpi.lock()?.ignore(|db| db.ignore_endpoint(endpoint))
+  }
+
   /// Get a `DomainDomainParticipantStatusListener` that can be used
   /// to get `DomainParticipantStatusEvent`s for this DomainParticipant.
   pub fn status_listener(&self) -> DomainParticipantStatusListener {
@@ -466,6 +588,47 @@ impl DomainParticipant {
     }
   }
 
+  /// Sets a listener, which is called when the statuses in `mask` change in
+  /// this DomainParticipant, or in any of its contained entities that do not
+  /// handle the status with a listener of their own.
+  ///
+  /// [`DomainParticipantStatusEvent`]s are passed to the listener regardless
+  /// of `mask`. The listener is called from a thread managed by RustDDS.
+  ///
+  /// Setting `None` removes the listener.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use std::sync::Arc;
+  /// # use rustdds::*;
+  /// struct MatchLogger;
+  ///
+  /// impl DataReaderListener for MatchLogger {
+  ///   fn on_subscription_matched(&self, reader: GUID, status: &DataReaderStatus) {
+  ///     println!("{reader:?}: {status:?}");
+  ///   }
+  /// }
+  /// impl DataWriterListener for MatchLogger {}
+  /// impl SubscriberListener for MatchLogger {}
+  /// impl PublisherListener for MatchLogger {}
+  /// impl DomainParticipantListener for MatchLogger {}
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// domain_participant.set_listener(
+  ///   Some(Arc::new(MatchLogger)),
+  ///   StatusKind::SubscriptionMatched.into(),
+  /// );
+  /// ```
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn DomainParticipantListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    let listener_hub = self.dpi.lock().unwrap().listener_hub();
+    listener_hub.set_participant_listener(listener, mask);
+  }
+
   pub(crate) fn weak_clone(&self) -> DomainParticipantWeak {
     DomainParticipantWeak::new(self)
   }
@@ -483,6 +646,20 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().dpi.discovery_db.clone()
   }
 
+  // Updates the content filter of local readers, and asks Discovery to
+  // re-announce them.
+  pub(crate) fn update_local_reader_content_filter(&self, content_filter: &ContentFilterProperty) {
+    let updated_readers =
+      discovery_db_write(&self.discovery_db()).update_local_reader_content_filter(content_filter);
+    let dpd = self.dpi.lock().unwrap();
+    for guid in updated_readers {
+      dpd
+        .discovery_command_sender
+        .try_send(DiscoveryCommand::AddLocalReader { guid })
+        .unwrap_or_else(|e| error!("Cannot re-announce reader {guid:?}: {e:?}"));
+    }
+  }
+
   pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
     self.dpi.lock().unwrap().new_entity_id(entity_kind)
   }
@@ -665,6 +842,7 @@ pub struct DomainParticipantWeak {
   guid: GUID,
   #[cfg(feature = "security")] // just to avoid warning
   qos: QosPolicies,
+  listener_hub: Arc<ListenerHub>,
 }
 
 impl DomainParticipantWeak {
@@ -676,6 +854,7 @@ impl DomainParticipantWeak {
       guid: dp.guid(),
       #[cfg(feature="security")] // just to avoid warning
       qos: dp.qos(),
+      listener_hub: dp.dpi.lock().unwrap().listener_hub(),
     }
   }
 
@@ -729,6 +908,10 @@ impl DomainParticipantWeak {
       })
   }
 
+  pub(crate) fn listener_hub(&self) -> Arc<ListenerHub> {
+    Arc::clone(&self.listener_hub)
+  }
+
   pub fn upgrade(self) -> Option<DomainParticipant> {
     self.dpi.upgrade().map(|d| DomainParticipant { dpi: d })
   }
@@ -749,6 +932,7 @@ pub(crate) struct DomainParticipantDisc {
   discovery_join_handle: mio_channel::Receiver<JoinHandle<()>>,
   // This allows deterministic generation of EntityIds for DataReader, DataWriter, etc.
   entity_id_generator: atomic::AtomicU32,
+  listener_hub: Arc<ListenerHub>,
 }
 
 impl DomainParticipantDisc {
@@ -764,6 +948,7 @@ impl DomainParticipantDisc {
     status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    listener_hub: Arc<ListenerHub>,
   ) -> CreateResult<Self> {
     let dpi = DomainParticipantInner::new(
       domain_id,
@@ -782,9 +967,14 @@ impl DomainParticipantDisc {
       discovery_command_sender,
       discovery_join_handle,
       entity_id_generator: atomic::AtomicU32::new(0),
+      listener_hub,
     })
   }
 
+  pub(crate) fn listener_hub(&self) -> Arc<ListenerHub> {
+    Arc::clone(&self.listener_hub)
+  }
+
   // This generates identifiers that consist of given EntityKind and arbitrary,
   // unique identifier.
   pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
@@ -861,6 +1051,16 @@ impl DomainParticipantDisc {
   //   self.dpi.lock().unwrap().discovery_db.clone()
   // }
 
+  // Marks entities ignored, and asks Discovery to remove those that are
+  // already known.
+  pub(crate) fn ignore(&self, mark_ignored: impl FnOnce(&mut DiscoveryDB)) -> CreateResult<()> {
+    mark_ignored(&mut discovery_db_write(&self.dpi.discovery_db));
+    self
+      .discovery_command_sender
+      .send(DiscoveryCommand::RemoveIgnored)
+      .or_else(|e| create_error_poisoned!("Cannot send RemoveIgnored to Discovery: {e:?}"))
+  }
+
   pub(crate) fn assert_liveliness(&self) -> WriteResult<(), ()> {
     // No point in checking for the LIVELINESS QoS of MANUAL_BY_PARTICIPANT,
     // the discovery command mutates a field which is only read
@@ -1361,8 +1561,6 @@ impl DomainParticipantInner {
   }
   // get_builtin_subscriber (why would we need this?)
 
-  // ignore_* operations. TODO: Do we need any of those?
-
   // delete_contained_entities is not needed. Data structures should be designed
   // so that lifetime of all created objects is within the lifetime of
   // DomainParticipant. Then such deletion is implicit.
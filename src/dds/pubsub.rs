@@ -1,12 +1,20 @@
 use std::{
+  collections::BTreeMap,
   fmt::Debug,
-  sync::{Arc, Mutex, MutexGuard, RwLock},
-  time::Duration,
+  ops::Deref,
+  sync::{
+    atomic::{AtomicBool, AtomicI64},
+    Arc, Mutex, MutexGuard, RwLock,
+  },
+  task::Poll,
+  time::{Duration, Instant},
 };
 
 use serde::{Deserialize, Serialize};
-use mio_extras::channel as mio_channel;
+use mio_extras::channel::{self as mio_channel, TrySendError};
+use futures::{future, StreamExt};
 use byteorder::LittleEndian;
+use enumflags2::BitFlags;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
@@ -14,6 +22,7 @@ use crate::{
   create_error_dropped, create_error_internal, create_error_poisoned,
   dds::{
     adapters,
+    endpoint_registry::EndpointRegistry,
     key::Keyed,
     no_key,
     no_key::{
@@ -21,8 +30,16 @@ use crate::{
     },
     participant::*,
     qos::*,
-    result::{CreateError, CreateResult, WaitResult},
-    statusevents::{sync_status_channel, DataReaderStatus},
+    result::{CreateError, CreateResult, ShareResult, WriteError, WriteResult},
+    listener::{
+      DataReaderListener, DataWriterListener, ListenerEvent, ListenerHub, ListenerKey,
+      PublisherListener, SubscriberListener,
+    },
+    statusevents::{
+      sync_status_channel, sync_status_channel_tracked, DataReaderStatus, DataWriterStatus,
+      DataWriterStatusRecord, EndpointDescription, StatusChanges, StatusChannelReceiver,
+      StatusEvented, StatusKind, SubscriberStatus,
+    },
     topic::*,
     with_key,
     with_key::{
@@ -34,13 +51,23 @@ use crate::{
   },
   mio_source,
   rtps::{
-    reader::ReaderIngredients,
+    coherent_set::{
+      CoherentChanges, CoherentSet, CoherentSetTracker, CoherentSets, PublisherCoherentChanges,
+    },
+    command_channel_wakers::CommandChannelWakers,
+    history_reservations::HistoryReservations,
+    reader::{
+      DataAvailability, MatchedWriterGuids, ReaderIngredients, SubscriberStatusSender,
+      WriterStrengths,
+    },
     writer::{WriterCommand, WriterIngredients},
   },
   serialization::{cdr_deserializer::CDRDeserializerAdapter, cdr_serializer::CDRSerializerAdapter},
   structure::{
+    duration::Duration as DdsDuration,
     entity::RTPSEntity,
     guid::{EntityId, EntityKind, GUID},
+    sequence_number::SequenceNumber,
   },
 };
 use super::{
@@ -89,6 +116,8 @@ use crate::no_security::{security_plugins::SecurityPluginsHandle, EndpointSecuri
 #[derive(Clone)]
 pub struct Publisher {
   inner: Arc<Mutex<InnerPublisher>>,
+  // Present if Presentation QoS has coherent_access
+  coherent_changes: Option<PublisherCoherentChanges>,
 }
 
 impl Publisher {
@@ -103,7 +132,11 @@ impl Publisher {
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
   ) -> Self {
+    let coherent_changes = qos.presentation().filter(|p| p.coherent_access).map(|p| {
+      CoherentChanges::new_shared(p.access_scope == policy::PresentationAccessScope::Group)
+    });
     Self {
+      coherent_changes,
       inner: Arc::new(Mutex::new(InnerPublisher::new(
         dp,
         discovery_db,
@@ -276,43 +309,346 @@ impl Publisher {
   // delete_datawriter should not be needed. The DataWriter object itself should
   // be deleted to accomplish this.
 
-  // lookup datawriter: maybe not necessary? App should remember datawriters it
-  // has created.
+  /// Makes a DataWriter of this Publisher available to
+  /// [`lookup_datawriter`](Self::lookup_datawriter), so that other parts of the
+  /// application can find it without having a reference to it.
+  ///
+  /// The DataWriter is moved into the returned shared handle. The Publisher
+  /// does not keep it alive: the DataWriter is deleted when the last handle is
+  /// dropped.
+  ///
+  /// Returns `BadParameter` error if the DataWriter does not belong to this
+  /// Publisher. The DataWriter is then given back in the
+  /// [`ShareError`](crate::dds::result::ShareError).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// use serde::Serialize;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter_cdr::<SomeType>(&topic, None).unwrap();
+  /// let shared_writer = publisher.share_datawriter(data_writer).unwrap();
+  ///
+  /// // Somewhere else
+  /// let found = publisher
+  ///   .lookup_datawriter::<SomeType, CDRSerializerAdapter<SomeType>>("some_topic")
+  ///   .unwrap();
+  /// found.lock().unwrap().write(SomeType { a: 1 }, None).unwrap();
+  /// ```
+  pub fn share_datawriter<D, SA>(
+    &self,
+    data_writer: WithKeyDataWriter<D, SA>,
+  ) -> ShareResult<WithKeyDataWriter<D, SA>>
+  where
+    D: 'static + Keyed + Send,
+    <D as Keyed>::K: Send,
+    SA: 'static + adapters::with_key::SerializerAdapter<D> + Send,
+  {
+    // Do not hold the inner lock, because a dropped DataWriter would need it.
+    let endpoints = self.endpoints();
+    endpoints.share(data_writer.guid(), data_writer)
+  }
+
+  /// Same as [`share_datawriter`](Self::share_datawriter), but for NoKey
+  /// DataWriters. Look it up with
+  /// [`lookup_datawriter_no_key`](Self::lookup_datawriter_no_key).
+  pub fn share_datawriter_no_key<D, SA>(
+    &self,
+    data_writer: NoKeyDataWriter<D, SA>,
+  ) -> ShareResult<NoKeyDataWriter<D, SA>>
+  where
+    D: 'static + Send,
+    SA: 'static + adapters::no_key::SerializerAdapter<D> + Send,
+  {
+    let endpoints = self.endpoints();
+    endpoints.share(data_writer.guid(), data_writer)
+  }
+
+  /// Retrieves a shared DataWriter of this Publisher by Topic name. The data
+  /// type `D` and serializer adapter `SA` must be the same as the DataWriter
+  /// was created with, otherwise it is not found.
+  ///
+  /// Only DataWriters made available with
+  /// [`share_datawriter`](Self::share_datawriter) and still alive can be found.
+  /// If there are several, any one of them is returned.
+  pub fn lookup_datawriter<D, SA>(
+    &self,
+    topic_name: &str,
+  ) -> Option<Arc<Mutex<WithKeyDataWriter<D, SA>>>>
+  where
+    D: 'static + Keyed + Send,
+    <D as Keyed>::K: Send,
+    SA: 'static + adapters::with_key::SerializerAdapter<D> + Send,
+  {
+    self.endpoints().lookup(topic_name)
+  }
+
+  /// Same as [`lookup_datawriter`](Self::lookup_datawriter), but for NoKey
+  /// DataWriters shared with
+  /// [`share_datawriter_no_key`](Self::share_datawriter_no_key).
+  pub fn lookup_datawriter_no_key<D, SA>(
+    &self,
+    topic_name: &str,
+  ) -> Option<Arc<Mutex<NoKeyDataWriter<D, SA>>>>
+  where
+    D: 'static + Send,
+    SA: 'static + adapters::no_key::SerializerAdapter<D> + Send,
+  {
+    self.endpoints().lookup(topic_name)
+  }
+
+  /// Describes all the DataWriters currently contained in this Publisher,
+  /// including the ones that have not been shared.
+  pub fn datawriters(&self) -> Vec<EndpointDescription> {
+    self.endpoints().descriptions()
+  }
+
+  fn endpoints(&self) -> Arc<EndpointRegistry> {
+    Arc::clone(&self.inner_lock().endpoints)
+  }
+
+  /// Suspends sending of the samples written by the DataWriters of this
+  /// Publisher, including DataWriters created while suspended. Writing
+  /// continues to work as usual, but the samples are held back until
+  /// [`resume_publications`](Self::resume_publications), which sends them in
+  /// as few RTPS messages as possible.
+  ///
+  /// This is a performance optimization for writing many samples at once. It
+  /// does not affect what is delivered to DataReaders. Calls may be nested,
+  /// and only the outermost pair counts.
+  ///
+  /// See DDS spec v1.4 Section 2.2.2.4.1.8 suspend_publications.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  ///
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// publisher.suspend_publications().unwrap();
+  /// // ... write with DataWriters of publisher ...
+  /// publisher.resume_publications().unwrap();
+  /// ```
+  pub fn suspend_publications(&self) -> WriteResult<(), ()> {
+    self.inner_lock().suspend_publications()
+  }
+
+  /// Resumes publications suspended by
+  /// [`suspend_publications`](Self::suspend_publications), and sends the
+  /// samples written in the meantime.
+  ///
+  /// See DDS spec v1.4 Section 2.2.2.4.1.9 resume_publications.
+  pub fn resume_publications(&self) -> WriteResult<(), ()> {
+    self.inner_lock().resume_publications()
+  }
+
+  /// Begins a coherent change set. The samples written by the DataWriters of
+  /// this Publisher until [`end_coherent_changes`](Self::end_coherent_changes)
+  /// are delivered to matching DataReaders all together, or not at all.
+  ///
+  /// With access scope `Topic`, the set of each DataWriter is delivered
+  /// separately. With `Group`, the sets of all DataWriters of this Publisher
+  /// are delivered together to a Subscriber that also has `Group` access
+  /// scope.
+  ///
+  /// This has an effect only if the Publisher has
+  /// [`Presentation`](policy::Presentation) QoS with `coherent_access` set.
+  /// Otherwise it does nothing. Calls may be nested, and only the outermost
+  /// pair counts.
+  ///
+  /// See DDS spec v1.4 Section 2.2.2.4.1.10 begin_coherent_changes.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// # use rustdds::policy::{Presentation, PresentationAccessScope};
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new()
+  ///   .presentation(Presentation {
+  ///     access_scope: PresentationAccessScope::Group,
+  ///     coherent_access: true,
+  ///     ordered_access: false,
+  ///   })
+  ///   .build();
+  ///
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// publisher.begin_coherent_changes().unwrap();
+  /// // ... write with DataWriters of publisher ...
+  /// publisher.end_coherent_changes().unwrap();
+  /// ```
+  pub fn begin_coherent_changes(&self) -> WriteResult<(), ()> {
+    if let Some(coherent_changes) = &self.coherent_changes {
+      coherent_changes.lock()?.begin();
+    }
+    Ok(())
+  }
 
-  // Suspend and resume publications are performance optimization methods.
-  // The minimal correct implementation is to do nothing. See DDS spec 2.2.2.4.1.8
-  // and .9
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn suspend_publications(&self) {
-    unimplemented!();
+  /// Ends a coherent change set started by
+  /// [`begin_coherent_changes`](Self::begin_coherent_changes).
+  pub fn end_coherent_changes(&self) -> WriteResult<(), ()> {
+    match &self.coherent_changes {
+      Some(coherent_changes) => coherent_changes.lock()?.end(),
+      None => Ok(()),
+    }
   }
 
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn resume_publications(&self) {
-    unimplemented!();
+  // A DataWriter is writing a sample. Returns the coherent set marking for it,
+  // if a set is in progress.
+  pub(crate) fn coherent_set_for(
+    &self,
+    writer: GUID,
+    sequence_number: SequenceNumber,
+    cc_upload: &mio_channel::SyncSender<WriterCommand>,
+    available_sequence_number: &Arc<AtomicI64>,
+    max_blocking_time: Option<DdsDuration>,
+  ) -> Option<CoherentSet> {
+    self.coherent_changes.as_ref().and_then(|coherent_changes| {
+      coherent_changes
+        .lock()
+        // A panic in another thread must not make DataWriter::write panic too.
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .sample_written(
+          writer,
+          sequence_number,
+          cc_upload,
+          available_sequence_number,
+          max_blocking_time,
+        )
+    })
   }
 
-  // coherent change set
-  // In case such QoS is not supported, these should be no-ops.
-  // TODO: Implement these when coherent change-sets are supported.
-  // Coherent set not implemented and currently does nothing
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn begin_coherent_changes(&self) {}
+  /// Waits until all matched reliable DataReaders have acknowledged the data
+  /// written so far by all DataWriters of this Publisher, or until `max_wait`
+  /// has passed.
+  ///
+  /// Returns `Ok(true)` if everything was acknowledged, and `Ok(false)` on
+  /// timeout. Best-effort DataWriters have nothing to wait for.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.4.1.12 wait_for_acknowledgments.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// # use std::time::Duration;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  ///
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// // ... write with DataWriters of publisher ...
+  /// publisher.wait_for_acknowledgments(Duration::from_millis(100));
+  /// ```
+  pub fn wait_for_acknowledgments(&self, max_wait: Duration) -> WriteResult<bool, ()> {
+    let deadline = Instant::now() + max_wait;
+    let writers = self.inner_lock().writer_command_senders();
+    let (acked_sender, mut acked_receiver) = sync_status_channel::<()>(writers.len().max(1))?;
+    let poll = mio_06::Poll::new()?;
+    poll.register(
+      acked_receiver.as_status_evented(),
+      mio_06::Token(0),
+      mio_06::Ready::readable(),
+      mio_06::PollOpt::edge(),
+    )?;
 
-  // Coherent set not implemented and currently does nothing
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn end_coherent_changes(&self) {}
+    let mut pending = 0;
+    for (guid, writer, _) in writers {
+      let time_left = deadline.saturating_duration_since(Instant::now());
+      let command = WriterCommand::WaitForAcknowledgments {
+        all_acked: acked_sender.clone(),
+      };
+      match try_send_timeout(&writer, command, Some(time_left.into())) {
+        Ok(()) => pending += 1,
+        Err(TrySendError::Full(_)) => {
+          warn!("wait_for_acknowledgments: Writer {guid:?} is not responding. Timing out.");
+          return Ok(false);
+        }
+        // The DataWriter was just dropped. Nothing to wait for.
+        Err(e) => debug!("wait_for_acknowledgments: Cannot reach Writer {guid:?}: {e}"),
+      }
+    }
 
-  // Wait for all matched reliable DataReaders acknowledge data written so far,
-  // or timeout.
-  /// **NOT IMPLEMENTED. DO NOT USE**
-  #[deprecated(note = "unimplemented")]
-  pub fn wait_for_acknowledgments(&self, _max_wait: Duration) -> WaitResult<()> {
-    unimplemented!();
+    let mut events = mio_06::Events::with_capacity(1);
+    loop {
+      while pending > 0 && acked_receiver.try_recv().is_ok() {
+        pending -= 1;
+      }
+      if pending == 0 {
+        return Ok(true);
+      }
+      let now = Instant::now();
+      if now >= deadline {
+        return Ok(false);
+      }
+      poll.poll(&mut events, Some(deadline - now))?;
+    }
+  }
+
+  /// Like the synchronous version.
+  /// But there is no timeout. Use asyncs to bring your own timeout.
+  pub async fn async_wait_for_acknowledgments(&self) -> WriteResult<bool, ()> {
+    let writers = self.inner_lock().writer_command_senders();
+    let (acked_sender, acked_receiver) = sync_status_channel::<()>(writers.len().max(1))?;
+
+    let mut pending = 0;
+    for (guid, writer, wakers) in writers {
+      let mut command = Some(WriterCommand::WaitForAcknowledgments {
+        all_acked: acked_sender.clone(),
+      });
+      let sent = future::poll_fn(|cx| {
+        // Registered before trying, so that the wakeup is not missed, if the
+        // channel gets room right after a failed try.
+        wakers.register(cx.waker());
+        match writer.try_send(command.take().unwrap()) {
+          Ok(()) => Poll::Ready(true),
+          Err(TrySendError::Full(c)) => {
+            // The Writer is busy. It wakes us up when it has taken commands.
+            command = Some(c);
+            Poll::Pending
+          }
+          Err(e) => {
+            // The DataWriter was just dropped. Nothing to wait for.
+            debug!("async_wait_for_acknowledgments: Cannot reach Writer {guid:?}: {e}");
+            Poll::Ready(false)
+          }
+        }
+      })
+      .await;
+      if sent {
+        pending += 1;
+      }
+    }
+    drop(acked_sender);
+
+    let mut acks = acked_receiver.as_async_status_stream();
+    while pending > 0 {
+      match acks.next().await {
+        Some(()) => pending -= 1,
+        None => return Ok(false), // All the Writers are gone
+      }
+    }
+    Ok(true)
   }
 
   // What is the use case for this? (is it useful in Rust style of programming?
@@ -377,6 +713,32 @@ impl Publisher {
     self.inner_lock().set_default_datawriter_qos(q);
   }
 
+  /// Sets a listener, which is called when the statuses in `mask` change in
+  /// those DataWriters of this Publisher that do not handle the status with
+  /// a listener of their own. Setting `None` removes the listener.
+  ///
+  /// See [`DomainParticipant::set_listener`] for an example.
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn PublisherListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self.inner_lock().set_listener(listener, mask);
+  }
+
+  pub(crate) fn set_writer_listener(
+    &self,
+    guid: GUID,
+    listener: Option<Arc<dyn DataWriterListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self.inner_lock().set_writer_listener(guid, listener, mask);
+  }
+
+  pub(crate) fn update_writer_qos(&self, guid: GUID, qos: &QosPolicies) {
+    self.endpoints().update_qos(guid, qos);
+  }
+
   // This is used on DataWriter .drop()
   pub(crate) fn remove_writer(&self, guid: GUID) {
     self.inner_lock().remove_writer(guid);
@@ -410,6 +772,19 @@ struct InnerPublisher {
   remove_writer_sender: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  // Command channels to the RTPS Writers of our DataWriters, and the tasks
+  // waiting for room in them
+  writers: BTreeMap<
+    GUID,
+    (
+      mio_channel::SyncSender<WriterCommand>,
+      Arc<CommandChannelWakers>,
+    ),
+  >,
+  // Nesting depth of suspend_publications calls. Zero = not suspended.
+  suspend_depth: u32,
+  listener_key: Arc<ListenerKey>,
+  endpoints: Arc<EndpointRegistry>,
 }
 
 // public interface for Publisher
@@ -429,6 +804,7 @@ impl InnerPublisher {
     // other. EntityKind is just some value, since we do not show it to anyone.
     let id = EntityId::MAX;
     // dp.clone().upgrade().unwrap().new_entity_id(EntityKind::UNKNOWN_BUILT_IN);
+    let listener_key = Arc::new(ListenerKey::new(&dp.listener_hub()));
 
     Self {
       id,
@@ -440,11 +816,15 @@ impl InnerPublisher {
       remove_writer_sender,
       discovery_command,
       security_plugins_handle,
+      writers: BTreeMap::new(),
+      suspend_depth: 0,
+      listener_key,
+      endpoints: Arc::default(),
     }
   }
 
   pub fn create_datawriter<D, SA>(
-    &self,
+    &mut self,
     outer: &Publisher,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
@@ -457,9 +837,16 @@ impl InnerPublisher {
   {
     // Data samples from DataWriter to HistoryCache
     let (dwcc_upload, hccc_download) = mio_channel::sync_channel::<WriterCommand>(16);
-    let writer_waker = Arc::new(Mutex::new(None));
+    let writer_wakers = Arc::new(CommandChannelWakers::default());
+    // Writer tells DataWriter if content filtering is needed.
+    let has_filtering_readers = Arc::new(AtomicBool::new(false));
     // Status reports back from Writer to DataWriter.
-    let (status_sender, status_receiver) = sync_status_channel(4)?;
+    let (status_sender, status_receiver) = sync_status_channel_tracked(
+      4,
+      Arc::new(StatusChanges::default()),
+      DataWriterStatus::kind,
+    )?;
+    let status_record = Arc::new(Mutex::new(DataWriterStatusRecord::default()));
 
     // DDS Spec 2.2.2.4.1.5 create_datawriter:
     // If no QoS is specified, we should take the Publisher default
@@ -542,15 +929,31 @@ impl InnerPublisher {
       }
     }
 
+    let hub = self.listener_key.hub();
+    let publisher = self.listener_key.key();
+    let status_sender = status_sender.with_listener_tap(move |status| {
+      ListenerHub::send(&hub, || ListenerEvent::DataWriter {
+        writer: guid,
+        publisher,
+        status: status.clone(),
+      });
+    });
+
+    // DataWriter reserves room in Writer history. Writer releases it.
+    let history_reservations = Arc::new(HistoryReservations::new(&writer_qos));
+
     let new_writer = WriterIngredients {
       guid,
       writer_command_receiver: hccc_download,
-      writer_command_receiver_waker: Arc::clone(&writer_waker),
+      writer_command_receiver_wakers: Arc::clone(&writer_wakers),
       topic_name: topic.name(),
       topic_cache_handle,
       like_stateless: writer_like_stateless,
       qos_policies: writer_qos.clone(),
       status_sender,
+      has_filtering_readers: Arc::clone(&has_filtering_readers),
+      status_record: Arc::clone(&status_record),
+      history_reservations: Arc::clone(&history_reservations),
       security_plugins: self.security_plugins_handle.clone(),
     };
 
@@ -561,15 +964,34 @@ impl InnerPublisher {
       .send(new_writer)
       .or_else(|e| create_error_poisoned!("Adding a new writer failed: {}", e))?;
 
+    // The new Writer must hold back its samples, if we are suspended.
+    if self.suspend_depth > 0 {
+      dwcc_upload
+        .send(WriterCommand::SuspendPublications)
+        .or_else(|e| create_error_poisoned!("Cannot suspend the new writer: {}", e))?;
+    }
+    self
+      .writers
+      .insert(guid, (dwcc_upload.clone(), Arc::clone(&writer_wakers)));
+    self.endpoints.add(
+      guid,
+      topic.name(),
+      topic.get_type().name().to_string(),
+      writer_qos.clone(),
+    );
+
     let data_writer = WithKeyDataWriter::<D, SA>::new(
       outer.clone(),
       topic.clone(),
       writer_qos,
       guid,
       dwcc_upload,
-      writer_waker,
+      writer_wakers,
       self.discovery_command.clone(),
       status_receiver,
+      has_filtering_readers,
+      status_record,
+      history_reservations,
     )?;
 
     // notify Discovery DB
@@ -641,7 +1063,7 @@ impl InnerPublisher {
   }
 
   pub fn create_datawriter_no_key<D, SA>(
-    &self,
+    &mut self,
     outer: &Publisher,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
@@ -685,7 +1107,70 @@ impl InnerPublisher {
     entity_id_opt.unwrap_or_else(|| self.participant().unwrap().new_entity_id(entity_kind))
   }
 
-  pub(crate) fn remove_writer(&self, guid: GUID) {
+  pub fn suspend_publications(&mut self) -> WriteResult<(), ()> {
+    self.suspend_depth += 1;
+    if self.suspend_depth > 1 {
+      return Ok(());
+    }
+    self.command_writers(|| WriterCommand::SuspendPublications)
+  }
+
+  pub fn resume_publications(&mut self) -> WriteResult<(), ()> {
+    if self.suspend_depth == 0 {
+      warn!("resume_publications() called without matching suspend_publications()");
+      return Ok(());
+    }
+    self.suspend_depth -= 1;
+    if self.suspend_depth > 0 {
+      return Ok(());
+    }
+    self.command_writers(|| WriterCommand::ResumePublications)
+  }
+
+  // Send a command to all of our Writers. The command goes through the same
+  // channel as the samples, so it takes effect in order with them. We are
+  // holding the Publisher lock, so do not wait for a full channel longer than
+  // max_blocking_time.
+  fn command_writers(&self, command: impl Fn() -> WriterCommand) -> WriteResult<(), ()> {
+    let timeout = self.my_qos_policies.reliable_max_blocking_time();
+    let mut result = Ok(());
+    for (guid, (writer, _)) in &self.writers {
+      match try_send_timeout(writer, command(), timeout) {
+        Ok(()) => (),
+        Err(TrySendError::Full(_)) => {
+          warn!("Command to Writer {guid:?} timed out: timeout={timeout:?}");
+          result = Err(WriteError::WouldBlock { data: () });
+        }
+        Err(e) => {
+          error!("Cannot send command to Writer {guid:?}: {e}");
+          result = Err(WriteError::Poisoned {
+            reason: format!("Cannot send command to Writer {guid:?}: {e}"),
+            data: (),
+          });
+        }
+      }
+    }
+    result
+  }
+
+  fn writer_command_senders(
+    &self,
+  ) -> Vec<(
+    GUID,
+    mio_channel::SyncSender<WriterCommand>,
+    Arc<CommandChannelWakers>,
+  )> {
+    self
+      .writers
+      .iter()
+      .map(|(guid, (sender, wakers))| (*guid, sender.clone(), Arc::clone(wakers)))
+      .collect()
+  }
+
+  pub(crate) fn remove_writer(&mut self, guid: GUID) {
+    self.writers.remove(&guid);
+    self.endpoints.remove(guid);
+    self.set_writer_listener(guid, None, BitFlags::empty());
     try_send_timeout(&self.remove_writer_sender, guid, None)
       .unwrap_or_else(|e| error!("Cannot remove Writer {:?} : {:?}", guid, e));
   }
@@ -693,6 +1178,27 @@ impl InnerPublisher {
   pub(crate) fn identity(&self) -> EntityId {
     self.id
   }
+
+  pub(crate) fn set_listener(
+    &self,
+    listener: Option<Arc<dyn PublisherListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    if let Some(hub) = self.listener_key.hub().upgrade() {
+      hub.set_publisher_listener(self.listener_key.key(), listener, mask);
+    }
+  }
+
+  pub(crate) fn set_writer_listener(
+    &self,
+    guid: GUID,
+    listener: Option<Arc<dyn DataWriterListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    if let Some(hub) = self.listener_key.hub().upgrade() {
+      hub.set_writer_listener(guid, listener, mask);
+    }
+  }
 }
 
 impl Debug for InnerPublisher {
@@ -763,7 +1269,8 @@ impl Subscriber {
   ///
   /// # Arguments
   ///
-  /// * `topic` - Reference to the DDS [Topic](struct.Topic.html) this reader
+  /// * `topic` - Reference to the DDS [Topic](struct.Topic.html) or
+  ///   [ContentFilteredTopic](struct.ContentFilteredTopic.html) this reader
   ///   reads from
   /// * `entity_id` - Optional [EntityId](data_types/struct.EntityId.html) if
   ///   necessary for DDS communication (random if None)
@@ -797,19 +1304,21 @@ impl Subscriber {
   /// ```
   pub fn create_datareader<D, SA>(
     &self,
-    topic: &Topic,
+    topic: &impl ReaderTopicDescription<D>,
     qos: Option<QosPolicies>,
   ) -> CreateResult<WithKeyDataReader<D, SA>>
   where
     D: 'static + Keyed,
     SA: adapters::with_key::DeserializerAdapter<D>,
   {
-    self.inner.create_datareader(self, topic, None, qos, false)
+    self
+      .inner
+      .create_datareader(self, topic.topic(), topic.sample_filter(), None, qos, false)
   }
 
   pub fn create_datareader_cdr<D>(
     &self,
-    topic: &Topic,
+    topic: &impl ReaderTopicDescription<D>,
     qos: Option<QosPolicies>,
   ) -> CreateResult<WithKeyDataReader<D, CDRDeserializerAdapter<D>>>
   where
@@ -823,7 +1332,8 @@ impl Subscriber {
   ///
   /// # Arguments
   ///
-  /// * `topic` - Reference to the DDS [Topic](struct.Topic.html) this reader
+  /// * `topic` - Reference to the DDS [Topic](struct.Topic.html) or
+  ///   [ContentFilteredTopic](struct.ContentFilteredTopic.html) this reader
   ///   reads from
   /// * `entity_id` - Optional [EntityId](data_types/struct.EntityId.html) if
   ///   necessary for DDS communication (random if None)
@@ -850,35 +1360,44 @@ impl Subscriber {
   /// ```
   pub fn create_datareader_no_key<D, SA>(
     &self,
-    topic: &Topic,
+    topic: &impl ReaderTopicDescription<D>,
     qos: Option<QosPolicies>,
   ) -> CreateResult<NoKeyDataReader<D, SA>>
   where
     D: 'static,
     SA: adapters::no_key::DeserializerAdapter<D>,
   {
-    self
-      .inner
-      .create_datareader_no_key(self, topic, None, qos, false)
+    self.inner.create_datareader_no_key(
+      self,
+      topic.topic(),
+      topic.sample_filter(),
+      None,
+      qos,
+      false,
+    )
   }
 
   pub fn create_simple_datareader_no_key<D, DA>(
     &self,
-    topic: &Topic,
+    topic: &impl ReaderTopicDescription<D>,
     qos: Option<QosPolicies>,
   ) -> CreateResult<no_key::SimpleDataReader<D, DA>>
   where
     D: 'static,
     DA: 'static + adapters::no_key::DeserializerAdapter<D>,
   {
-    self
-      .inner
-      .create_simple_datareader_no_key(self, topic, None, qos)
+    self.inner.create_simple_datareader_no_key(
+      self,
+      topic.topic(),
+      topic.sample_filter(),
+      None,
+      qos,
+    )
   }
 
   pub fn create_datareader_no_key_cdr<D>(
     &self,
-    topic: &Topic,
+    topic: &impl ReaderTopicDescription<D>,
     qos: Option<QosPolicies>,
   ) -> CreateResult<NoKeyDataReader<D, CDRDeserializerAdapter<D>>>
   where
@@ -900,9 +1419,14 @@ impl Subscriber {
     D: 'static + Keyed,
     SA: adapters::with_key::DeserializerAdapter<D>,
   {
-    self
-      .inner
-      .create_datareader(self, topic, Some(entity_id), qos, reader_like_stateless)
+    self.inner.create_datareader(
+      self,
+      topic,
+      None,
+      Some(entity_id),
+      qos,
+      reader_like_stateless,
+    )
   }
 
   #[cfg(feature = "security")] // to avoid "never used" warning
@@ -917,28 +1441,122 @@ impl Subscriber {
     D: 'static,
     SA: adapters::no_key::DeserializerAdapter<D>,
   {
-    self
-      .inner
-      .create_datareader_no_key(self, topic, Some(entity_id), qos, reader_like_stateless)
+    self.inner.create_datareader_no_key(
+      self,
+      topic,
+      None,
+      Some(entity_id),
+      qos,
+      reader_like_stateless,
+    )
+  }
+
+  /// Makes a DataReader of this Subscriber available to
+  /// [`lookup_datareader`](Self::lookup_datareader), so that other parts of the
+  /// application can find it without having a reference to it.
+  ///
+  /// The DataReader is moved into the returned shared handle. The Subscriber
+  /// does not keep it alive: the DataReader is deleted when the last handle is
+  /// dropped.
+  ///
+  /// Returns `BadParameter` error if the DataReader does not belong to this
+  /// Subscriber. The DataReader is then given back in the
+  /// [`ShareError`](crate::dds::result::ShareError).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// use serde::Deserialize;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Deserialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader_cdr::<SomeType>(&topic, None).unwrap();
+  /// let shared_reader = subscriber.share_datareader(data_reader).unwrap();
+  ///
+  /// // Somewhere else
+  /// let found = subscriber
+  ///   .lookup_datareader::<SomeType, CDRDeserializerAdapter<SomeType>>("some_topic")
+  ///   .unwrap();
+  /// assert!(std::sync::Arc::ptr_eq(&shared_reader, &found));
+  /// ```
+  pub fn share_datareader<D, SA>(
+    &self,
+    data_reader: WithKeyDataReader<D, SA>,
+  ) -> ShareResult<WithKeyDataReader<D, SA>>
+  where
+    D: 'static + Keyed + Send,
+    <D as Keyed>::K: Send,
+    SA: 'static + adapters::with_key::DeserializerAdapter<D> + Send,
+  {
+    self.inner.endpoints.share(data_reader.guid(), data_reader)
+  }
+
+  /// Same as [`share_datareader`](Self::share_datareader), but for NoKey
+  /// DataReaders. Look it up with
+  /// [`lookup_datareader_no_key`](Self::lookup_datareader_no_key).
+  pub fn share_datareader_no_key<D, SA>(
+    &self,
+    data_reader: NoKeyDataReader<D, SA>,
+  ) -> ShareResult<NoKeyDataReader<D, SA>>
+  where
+    D: 'static + Send,
+    SA: 'static + adapters::no_key::DeserializerAdapter<D> + Send,
+  {
+    self.inner.endpoints.share(data_reader.guid(), data_reader)
+  }
+
+  /// Retrieves a shared DataReader of this Subscriber by Topic name. The data
+  /// type `D` and deserializer adapter `SA` must be the same as the DataReader
+  /// was created with, otherwise it is not found.
+  ///
+  /// Only DataReaders made available with
+  /// [`share_datareader`](Self::share_datareader) and still alive can be found.
+  /// If there are several, any one of them is returned.
+  pub fn lookup_datareader<D, SA>(
+    &self,
+    topic_name: &str,
+  ) -> Option<Arc<Mutex<WithKeyDataReader<D, SA>>>>
+  where
+    D: 'static + Keyed + Send,
+    <D as Keyed>::K: Send,
+    SA: 'static + adapters::with_key::DeserializerAdapter<D> + Send,
+  {
+    self.inner.endpoints.lookup(topic_name)
   }
 
-  // Retrieves a previously created DataReader belonging to the Subscriber.
-  // TODO: Is this even possible. Would probably need to return reference and
-  // store references on creation
-  /*
-  pub(crate) fn lookup_datareader<D, SA>(
+  /// Same as [`lookup_datareader`](Self::lookup_datareader), but for NoKey
+  /// DataReaders shared with
+  /// [`share_datareader_no_key`](Self::share_datareader_no_key).
+  pub fn lookup_datareader_no_key<D, SA>(
     &self,
-    _topic_name: &str,
-  ) -> Option<WithKeyDataReader<D, SA>>
+    topic_name: &str,
+  ) -> Option<Arc<Mutex<NoKeyDataReader<D, SA>>>>
   where
-    D: Keyed + DeserializeOwned,
-    SA: DeserializerAdapter<D>,
+    D: 'static + Send,
+    SA: 'static + adapters::no_key::DeserializerAdapter<D> + Send,
   {
-    todo!()
-  // TO think: Is this really necessary? Because the caller would have to know
-    // types D and SA. Should we just trust whoever creates DataReaders to also remember them?
+    self.inner.endpoints.lookup(topic_name)
+  }
+
+  /// Describes all the DataReaders currently contained in this Subscriber,
+  /// including the ones that have not been shared.
+  pub fn datareaders(&self) -> Vec<EndpointDescription> {
+    self.inner.endpoints.descriptions()
   }
-  */
 
   /// Returns [DomainParticipant](struct.DomainParticipant.html) if it is sill
   /// alive.
@@ -958,6 +1576,94 @@ impl Subscriber {
     self.inner.participant()
   }
 
+  /// Begins coherent access. Until the matching
+  /// [`end_access`](Self::end_access), no new coherent sets are made
+  /// available to the DataReaders of this Subscriber. This way the application
+  /// can read a group coherent set from several DataReaders, without any of
+  /// them changing in between.
+  ///
+  /// This has an effect only if the Subscriber has
+  /// [`Presentation`](policy::Presentation) QoS with `coherent_access` set
+  /// and access scope `Group`. Calls may be nested.
+  ///
+  /// Coherent sets that are left incomplete, e.g. because their DataWriter
+  /// was lost, are never made available.
+  ///
+  /// See DDS spec v1.4 Section 2.2.2.5.2.8 begin_access.
+  pub fn begin_access(&self) {
+    if let Some(coherent_sets) = &self.inner.coherent_sets {
+      coherent_sets.lock().unwrap().begin_access();
+    }
+  }
+
+  /// Ends coherent access started by [`begin_access`](Self::begin_access).
+  pub fn end_access(&self) {
+    if let Some(coherent_sets) = &self.inner.coherent_sets {
+      coherent_sets.lock().unwrap().end_access();
+    }
+  }
+
+  /// Gets a status listener, which tells when new data arrives to any
+  /// DataReader of this Subscriber. A single listener can then be polled
+  /// instead of every DataReader.
+  ///
+  /// Events are sent only while the listener exists. Getting a new listener
+  /// disconnects the previous one.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// let status_listener = subscriber.status_listener().unwrap();
+  /// while let Some(SubscriberStatus::DataOnReaders { reader }) = status_listener.try_recv_status() {
+  ///   println!("New data in DataReader {reader:?}");
+  /// }
+  /// ```
+  pub fn status_listener(&self) -> CreateResult<StatusChannelReceiver<SubscriberStatus>> {
+    // There is at most one event per DataReader in the channel, unless the
+    // DataReaders are read without consuming the events.
+    let (status_sender, status_receiver) = sync_status_channel(64)?;
+    *self.inner.status_sender.lock().unwrap() = Some(status_sender);
+    Ok(status_receiver)
+  }
+
+  /// Sets a listener, which is called when the statuses in `mask` change in
+  /// those DataReaders of this Subscriber that do not handle the status with
+  /// a listener of their own. Setting `None` removes the listener.
+  ///
+  /// If the mask contains [`StatusKind::DataOnReaders`], the listener is
+  /// called with `on_data_on_readers` instead of calling any
+  /// `on_data_available`.
+  ///
+  /// See [`DomainParticipant::set_listener`] for an example.
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn SubscriberListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    if let Some(hub) = self.inner.listener_key.hub().upgrade() {
+      hub.set_subscriber_listener(self.inner.listener_key.key(), listener, mask);
+    }
+  }
+
+  pub(crate) fn set_reader_listener(
+    &self,
+    guid: GUID,
+    listener: Option<Arc<dyn DataReaderListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self.inner.set_reader_listener(guid, listener, mask);
+  }
+
+  pub(crate) fn update_reader_qos(&self, guid: GUID, qos: &QosPolicies) {
+    self.inner.endpoints.update_qos(guid, qos);
+  }
+
   pub(crate) fn remove_reader(&self, guid: GUID) {
     self.inner.remove_reader(guid);
   }
@@ -972,6 +1678,11 @@ pub struct InnerSubscriber {
   sender_remove_reader: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  // Shared by all DataReaders if access scope is GROUP
+  coherent_sets: Option<CoherentSets>,
+  status_sender: SubscriberStatusSender,
+  listener_key: Arc<ListenerKey>,
+  endpoints: Arc<EndpointRegistry>,
 }
 
 impl InnerSubscriber {
@@ -984,10 +1695,19 @@ impl InnerSubscriber {
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
   ) -> Self {
+    let coherent_sets = qos
+      .presentation()
+      .filter(|p| p.coherent_access && p.access_scope == policy::PresentationAccessScope::Group)
+      .map(|_| CoherentSetTracker::new_shared());
+    let listener_key = Arc::new(ListenerKey::new(&domain_participant.listener_hub()));
     Self {
       domain_participant,
       discovery_db,
       qos,
+      coherent_sets,
+      status_sender: SubscriberStatusSender::default(),
+      listener_key,
+      endpoints: Arc::default(),
       sender_add_reader,
       sender_remove_reader,
       discovery_command,
@@ -1000,6 +1720,7 @@ impl InnerSubscriber {
     outer: &Subscriber,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
+    sample_filter: Option<SampleFilter<D>>,
     optional_qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
   ) -> CreateResult<WithKeyDataReader<D, SA>>
@@ -1011,6 +1732,7 @@ impl InnerSubscriber {
       outer,
       entity_id_opt,
       topic,
+      sample_filter,
       optional_qos,
       reader_like_stateless,
     )?;
@@ -1024,6 +1746,7 @@ impl InnerSubscriber {
     outer: &Subscriber,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
+    sample_filter: Option<SampleFilter<D>>,
     optional_qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
   ) -> CreateResult<with_key::SimpleDataReader<D, SA>>
@@ -1034,15 +1757,21 @@ impl InnerSubscriber {
     // incoming data notification channel from Reader to DataReader
     let (send, rec) = mio_channel::sync_channel::<()>(4);
     // status change channel from Reader to DataReader
-    let (status_sender, status_receiver) = sync_status_channel::<DataReaderStatus>(4)?;
+    let status_changes = Arc::new(StatusChanges::default());
+    let (status_sender, status_receiver) =
+      sync_status_channel_tracked(4, Arc::clone(&status_changes), DataReaderStatus::kind)?;
 
     // reader command channel from Datareader to Reader
     let (reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(0);
-    // The buffer length is zero, i.e. sender and receiver must rendezvous at
-    // send/receive. This is needed to synchronize sending of wakers from
-    // DataReader to Reader. If the capacity is increased, then some data
-    // available for reading notifications may be missed.
+      mio_channel::sync_channel::<ReaderCommand>(4);
+    // Commands are sent only now and then, so a small buffer suffices. The
+    // buffer used to be zero-length, so that DataReader and Reader rendezvous
+    // at each command. Wakers are no longer sent as commands, but shared
+    // through data_reader_waker, so nothing depends on the rendezvous anymore.
+    // A zero-length mio channel would not even work for the current commands:
+    // its send() blocks until the Reader receives, but the event loop is
+    // signaled only after that, so the Reader would never wake up to receive.
+    let reader_command_wakers = Arc::new(CommandChannelWakers::default());
 
     // Use subscriber QoS as basis, modify by Topic settings, and modify by
     // specified QoS.
@@ -1127,6 +1856,32 @@ impl InnerSubscriber {
     let data_reader_waker = Arc::new(Mutex::new(None));
 
     let (poll_event_source, poll_event_sender) = mio_source::make_poll_channel()?;
+    let writer_strengths = WriterStrengths::default();
+    let matched_writer_guids = MatchedWriterGuids::default();
+    let data_availability = Arc::new(DataAvailability::new(status_changes));
+    // Coherent sets are tracked per DataReader, unless access scope is GROUP.
+    let coherent_sets = match self.qos.presentation() {
+      Some(p) if p.coherent_access => Some(
+        self
+          .coherent_sets
+          .clone()
+          .unwrap_or_else(CoherentSetTracker::new_shared),
+      ),
+      _ => None,
+    };
+
+    let hub = self.listener_key.hub();
+    let subscriber = self.listener_key.key();
+    let status_sender = status_sender.with_listener_tap(move |status| {
+      ListenerHub::send(&hub, || ListenerEvent::DataReader {
+        reader: reader_guid,
+        subscriber,
+        status: status.clone(),
+      });
+    });
+
+    // DataReader reports SampleRejected by itself
+    let status_sender_for_datareader = status_sender.clone();
 
     let new_reader = ReaderIngredients {
       guid: reader_guid,
@@ -1137,8 +1892,14 @@ impl InnerSubscriber {
       like_stateless: reader_like_stateless,
       qos_policy: qos.clone(),
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::clone(&reader_command_wakers),
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender,
+      writer_strengths: Arc::clone(&writer_strengths),
+      matched_writer_guids: Arc::clone(&matched_writer_guids),
+      coherent_sets: coherent_sets.clone(),
+      data_availability: Arc::clone(&data_availability),
+      subscriber_status_sender: Arc::clone(&self.status_sender),
       security_plugins: self.security_plugins_handle.clone(),
     };
 
@@ -1175,7 +1936,15 @@ impl InnerSubscriber {
         .discovery_db
         .write()
         .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
-      db.update_local_topic_reader(&dp, topic, &new_reader, security_info);
+      db.update_local_topic_reader(
+        &dp,
+        topic,
+        &new_reader,
+        sample_filter
+          .as_ref()
+          .map(SampleFilter::content_filter_property),
+        security_info,
+      );
       db.update_topic_data_p(topic);
 
       if let Err(e) = self.discovery_command.try_send(DiscoveryCommand::AddTopic {
@@ -1191,6 +1960,13 @@ impl InnerSubscriber {
       }
     }
 
+    self.endpoints.add(
+      reader_guid,
+      topic.name(),
+      topic.get_type().name().to_string(),
+      qos.clone(),
+    );
+
     let datareader = with_key::SimpleDataReader::<D, SA>::new(
       outer.clone(),
       entity_id,
@@ -1200,9 +1976,16 @@ impl InnerSubscriber {
       topic_cache_handle,
       self.discovery_command.clone(),
       status_receiver,
+      status_sender_for_datareader,
       reader_command_sender,
+      reader_command_wakers,
       data_reader_waker,
       poll_event_source,
+      sample_filter,
+      writer_strengths,
+      matched_writer_guids,
+      coherent_sets,
+      data_availability,
     )?;
 
     // Send reader ingredients to DP event loop, where the actual reader will be
@@ -1232,6 +2015,7 @@ impl InnerSubscriber {
     &self,
     outer: &Subscriber,
     topic: &Topic,
+    sample_filter: Option<SampleFilter<D>>,
     entity_id: Option<EntityId>,
     qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
@@ -1243,13 +2027,21 @@ impl InnerSubscriber {
     if topic.kind() != TopicKind::WithKey {
       return Err(CreateError::TopicKind(TopicKind::WithKey));
     }
-    self.create_datareader_internal(outer, entity_id, topic, qos, reader_like_stateless)
+    self.create_datareader_internal(
+      outer,
+      entity_id,
+      topic,
+      sample_filter,
+      qos,
+      reader_like_stateless,
+    )
   }
 
   pub fn create_datareader_no_key<D: 'static, SA>(
     &self,
     outer: &Subscriber,
     topic: &Topic,
+    sample_filter: Option<SampleFilter<D>>,
     entity_id_opt: Option<EntityId>,
     qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
@@ -1268,6 +2060,7 @@ impl InnerSubscriber {
       outer,
       Some(entity_id),
       topic,
+      sample_filter.map(|f| f.map(NoKeyWrapper::deref)),
       qos,
       reader_like_stateless,
     )?;
@@ -1279,6 +2072,7 @@ impl InnerSubscriber {
     &self,
     outer: &Subscriber,
     topic: &Topic,
+    sample_filter: Option<SampleFilter<D>>,
     entity_id_opt: Option<EntityId>,
     qos: Option<QosPolicies>,
   ) -> CreateResult<no_key::SimpleDataReader<D, SA>>
@@ -1296,6 +2090,7 @@ impl InnerSubscriber {
       outer,
       Some(entity_id),
       topic,
+      sample_filter.map(|f| f.map(NoKeyWrapper::deref)),
       qos,
       false,
     )?;
@@ -1307,7 +2102,20 @@ impl InnerSubscriber {
     self.domain_participant.clone().upgrade()
   }
 
+  fn set_reader_listener(
+    &self,
+    guid: GUID,
+    listener: Option<Arc<dyn DataReaderListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    if let Some(hub) = self.listener_key.hub().upgrade() {
+      hub.set_reader_listener(guid, listener, mask);
+    }
+  }
+
   pub(crate) fn remove_reader(&self, guid: GUID) {
+    self.endpoints.remove(guid);
+    self.set_reader_listener(guid, None, BitFlags::empty());
     try_send_timeout(&self.sender_remove_reader, guid, None)
       .unwrap_or_else(|e| error!("Cannot remove Reader {:?} : {:?}", guid, e));
   }
@@ -1326,4 +2134,131 @@ impl InnerSubscriber {
 // -------------------------------------------------------------------
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+  use byteorder::LittleEndian;
+
+  use super::*;
+  use crate::{dds::qos::policy::Reliability, structure::topic_kind::TopicKind, test::random_data::*};
+
+  #[test]
+  fn publisher_wait_for_acknowledgments() {
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "publisher_ack_topic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    let reliable_writer: WithKeyDataWriter<
+      RandomData,
+      CDRSerializerAdapter<RandomData, LittleEndian>,
+    > = publisher
+      .create_datawriter(&topic, Some(reliable_qos))
+      .expect("Failed to create datawriter");
+    let best_effort_writer: WithKeyDataWriter<
+      RandomData,
+      CDRSerializerAdapter<RandomData, LittleEndian>,
+    > = publisher
+      .create_datawriter(&topic, None)
+      .expect("Failed to create datawriter");
+
+    for (a, writer) in [&reliable_writer, &best_effort_writer].iter().enumerate() {
+      let data = RandomData {
+        a: a as i64,
+        b: "Fobar".to_string(),
+      };
+      writer.write(data, None).expect("Unable to write data");
+    }
+
+    // No matched readers, so there is nobody to wait for.
+    assert!(publisher
+      .wait_for_acknowledgments(Duration::from_secs(2))
+      .unwrap());
+    assert!(futures::executor::block_on(publisher.async_wait_for_acknowledgments()).unwrap());
+
+    // Dropped DataWriters are not waited for.
+    drop(reliable_writer);
+    assert!(publisher
+      .wait_for_acknowledgments(Duration::from_secs(2))
+      .unwrap());
+  }
+
+  #[test]
+  fn subscriber_lookup_datareader() {
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let subscriber = domain_participant
+      .create_subscriber(&qos)
+      .expect("Failed to create subscriber");
+    let other_subscriber = domain_participant
+      .create_subscriber(&qos)
+      .expect("Failed to create subscriber");
+    let topic = domain_participant
+      .create_topic(
+        "subscriber_lookup_topic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let reader = subscriber
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .expect("Failed to create datareader");
+    let guid = reader.guid();
+    let descriptions = subscriber.datareaders();
+    assert_eq!(descriptions.len(), 1);
+    assert_eq!(descriptions[0].guid, guid);
+    assert_eq!(descriptions[0].topic_name, "subscriber_lookup_topic");
+    assert_eq!(descriptions[0].type_name, "RandomData");
+    assert!(other_subscriber.datareaders().is_empty());
+
+    // Only shared readers can be found
+    let lookup = |subscriber: &Subscriber| {
+      subscriber.lookup_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(
+        "subscriber_lookup_topic",
+      )
+    };
+    assert!(lookup(&subscriber).is_none());
+    let shared = subscriber.share_datareader(reader).unwrap();
+    assert_eq!(lookup(&subscriber).unwrap().lock().unwrap().guid(), guid);
+    assert!(lookup(&other_subscriber).is_none());
+    // Wrong type
+    assert!(subscriber
+      .lookup_datareader_no_key::<RandomData, CDRDeserializerAdapter<RandomData>>(
+        "subscriber_lookup_topic"
+      )
+      .is_none());
+
+    // Reader belongs to another Subscriber
+    let reader = subscriber
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .expect("Failed to create datareader");
+    let reader_guid = reader.guid();
+    let Err(error) = other_subscriber.share_datareader(reader) else {
+      panic!("Shared a DataReader of another Subscriber");
+    };
+    assert!(matches!(error.error, CreateError::BadParameter { .. }));
+    // ... and is given back.
+    assert_eq!(error.endpoint.guid(), reader_guid);
+    assert_eq!(subscriber.datareaders().len(), 2);
+    let shared_2 = subscriber.share_datareader(*error.endpoint).unwrap();
+
+    drop(shared);
+    drop(shared_2);
+    assert!(lookup(&subscriber).is_none());
+    assert!(subscriber.datareaders().is_empty());
+  }
+}
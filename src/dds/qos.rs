@@ -389,8 +389,9 @@ impl QosPolicies {
 
     // check Ownership:
     // offered kind == requested kind
+    // Strength does not affect matching.
     if let (Some(off), Some(req)) = (self.ownership, other.ownership) {
-      if off != req {
+      if std::mem::discriminant(&off) != std::mem::discriminant(&req) {
         return Some(QosPolicyId::Ownership);
       }
     }
@@ -429,6 +430,48 @@ impl QosPolicies {
     None
   }
 
+  /// Check if an existing DataWriter or DataReader can change its QoS from
+  /// `self` to `new`.
+  ///
+  /// * None => The change is allowed
+  /// * Some(policyId) => One of the policies that would change, but cannot be
+  ///   changed after the entity has been created.
+  ///
+  /// Changeable policies are Deadline, LatencyBudget, Ownership strength (but
+  /// not kind), TimeBasedFilter, and Lifespan. See the "Changeable" column in
+  /// DDS spec v1.4 Section "2.2.3 Supported QoS".
+  pub fn immutable_policy_change(&self, new: &Self) -> Option<QosPolicyId> {
+    let ownership_kind = |qos: &Self| {
+      qos
+        .ownership
+        .map(|o| matches!(o, policy::Ownership::Exclusive { .. }))
+    };
+
+    if self.durability != new.durability {
+      Some(QosPolicyId::Durability)
+    } else if self.presentation != new.presentation {
+      Some(QosPolicyId::Presentation)
+    } else if ownership_kind(self) != ownership_kind(new) {
+      Some(QosPolicyId::Ownership)
+    } else if self.liveliness != new.liveliness {
+      Some(QosPolicyId::Liveliness)
+    } else if self.reliability != new.reliability {
+      Some(QosPolicyId::Reliability)
+    } else if self.destination_order != new.destination_order {
+      Some(QosPolicyId::DestinationOrder)
+    } else if self.history != new.history {
+      Some(QosPolicyId::History)
+    } else if self.resource_limits != new.resource_limits {
+      Some(QosPolicyId::ResourceLimits)
+    } else {
+      #[cfg(feature = "security")]
+      if self.property != new.property {
+        return Some(QosPolicyId::Property);
+      }
+      None
+    }
+  }
+
   // serialization
   pub fn to_parameter_list(
     &self,
@@ -905,6 +948,26 @@ pub mod policy {
     pub max_samples_per_instance: i32,
   }
 
+  impl ResourceLimits {
+    // Limits as counts. None means unlimited, i.e. LENGTH_UNLIMITED or some other
+    // non-positive value.
+    fn limit(value: i32) -> Option<usize> {
+      usize::try_from(value).ok().filter(|&v| v > 0)
+    }
+
+    pub(crate) fn sample_limit(&self) -> Option<usize> {
+      Self::limit(self.max_samples)
+    }
+
+    pub(crate) fn instance_limit(&self) -> Option<usize> {
+      Self::limit(self.max_instances)
+    }
+
+    pub(crate) fn samples_per_instance_limit(&self) -> Option<usize> {
+      Self::limit(self.max_samples_per_instance)
+    }
+  }
+
   #[cfg(feature = "security")]
   use crate::security;
   // DDS Security spec v1.1
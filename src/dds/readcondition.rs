@@ -1,6 +1,7 @@
 use enumflags2::BitFlags;
+use serde::Serialize;
 
-use crate::dds::sampleinfo::*;
+use crate::dds::{ddssql::Filter, result::FilterError, sampleinfo::*};
 
 /// This is used to specify which samples are to be read or taken from
 /// a [`Datareader`](crate::with_key::DataReader)
@@ -13,8 +14,6 @@ pub struct ReadCondition {
   sample_state_mask: BitFlags<SampleState>,
   view_state_mask: BitFlags<ViewState>,
   instance_state_mask: BitFlags<InstanceState>,
-  // Extension idea: Add a query string and a list of query parameters to upgrade this
-  // to QueryCondition. But that would be a lot of work, especially in DataReader.
 }
 
 impl ReadCondition {
@@ -48,3 +47,102 @@ impl ReadCondition {
     &self.instance_state_mask
   }
 }
+
+/// Selects samples to be read or taken from a DataReader by their sample,
+/// view, and instance states, and possibly also by their contents.
+///
+/// This is implemented by [`ReadCondition`] and [`QueryCondition`].
+pub trait SampleCondition<D> {
+  /// The states of the samples to select
+  fn read_condition(&self) -> ReadCondition;
+
+  /// Does the sample content match? Samples that carry only a key, i.e.
+  /// disposes and unregisters, are given as `None`.
+  fn content_matches(&self, sample: Option<&D>) -> bool;
+}
+
+impl<D> SampleCondition<D> for ReadCondition {
+  fn read_condition(&self) -> ReadCondition {
+    *self
+  }
+
+  fn content_matches(&self, _sample: Option<&D>) -> bool {
+    true
+  }
+}
+
+impl<D, C: SampleCondition<D>> SampleCondition<D> for &C {
+  fn read_condition(&self) -> ReadCondition {
+    (*self).read_condition()
+  }
+
+  fn content_matches(&self, sample: Option<&D>) -> bool {
+    (*self).content_matches(sample)
+  }
+}
+
+/// A [`ReadCondition`] that additionally evaluates a query on the contents of
+/// the samples.
+///
+/// The query is a DDSSQL expression, in the same syntax as the filter of a
+/// [`ContentFilteredTopic`](crate::ContentFilteredTopic), and it may refer to
+/// parameters `%0`, `%1`, ... Samples that carry only a key (disposes and
+/// unregisters) do not match any query.
+///
+/// See DDS Specification 1.4 Section "2.2.2.5.9 QueryCondition"
+///
+/// # Examples
+///
+/// ```
+/// use rustdds::*;
+///
+/// let alarms = QueryCondition::new(
+///   ReadCondition::not_read(),
+///   "severity > %0 AND source LIKE 'engine%'",
+///   vec!["3".to_string()],
+/// )
+/// .unwrap();
+/// // Use e.g. as data_reader.take(10, &alarms)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCondition {
+  read_condition: ReadCondition,
+  query: Filter,
+}
+
+impl QueryCondition {
+  pub fn new(
+    read_condition: ReadCondition,
+    query_expression: &str,
+    query_parameters: Vec<String>,
+  ) -> Result<Self, FilterError> {
+    Ok(Self {
+      read_condition,
+      query: Filter::new(query_expression, query_parameters)?,
+    })
+  }
+
+  pub fn query_expression(&self) -> &str {
+    self.query.expression()
+  }
+
+  pub fn query_parameters(&self) -> &[String] {
+    self.query.parameters()
+  }
+
+  /// Replaces the query parameters. There must be a value for each parameter
+  /// referenced in the query expression.
+  pub fn set_query_parameters(&mut self, query_parameters: Vec<String>) -> Result<(), FilterError> {
+    self.query.set_parameters(query_parameters)
+  }
+}
+
+impl<D: Serialize> SampleCondition<D> for QueryCondition {
+  fn read_condition(&self) -> ReadCondition {
+    self.read_condition
+  }
+
+  fn content_matches(&self, sample: Option<&D>) -> bool {
+    sample.is_some_and(|d| self.query.matches(d))
+  }
+}
@@ -5,9 +5,13 @@
 //! Using specialized error types makes the description of possible failures
 //! more precise.
 
-use std::sync::PoisonError;
+use std::{
+  fmt,
+  sync::{Arc, Mutex, PoisonError},
+};
 
 use crate::{
+  dds::qos::QosPolicyId,
   no_key::wrappers::NoKeyWrapper,
   serialization::{cdr_deserializer, cdr_serializer},
   TopicKind,
@@ -102,6 +106,7 @@ pub type ReadResult<T> = std::result::Result<T, ReadError>;
 /// Note: This type contains payload data type `D`. This means that `WriteError`
 /// implements `Debug` only if `D` does.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum WriteError<D> {
   /// Data serializer (`SerializerAdapter`) reported an error when called.
   /// Reason field gives more details on what went wrong.
@@ -123,6 +128,16 @@ pub enum WriteError<D> {
   #[error("Write operation timed out while blocking")]
   WouldBlock { data: D },
 
+  /// The operation is not possible in the current state, e.g. unregistering
+  /// an instance that is not registered.
+  #[error("Precondition not met: {reason}")]
+  PreconditionNotMet { reason: String },
+
+  /// A limit of the RESOURCE_LIMITS QoS policy would be exceeded, e.g.
+  /// writing a new instance when `max_instances` instances are registered.
+  #[error("Out of resources: {reason}")]
+  OutOfResources { reason: String, data: D },
+
   /// Something that should not go wrong went wrong anyway.
   /// This is usually a bug in RustDDS
   #[error("Internal error: {reason}")]
@@ -159,6 +174,10 @@ impl<D> WriteError<D> {
       WriteError::Poisoned { reason, data: _ } => WriteError::Poisoned { reason, data: () },
       WriteError::Io(e) => WriteError::Io(e),
       WriteError::WouldBlock { data: _ } => WriteError::WouldBlock { data: () },
+      WriteError::PreconditionNotMet { reason } => WriteError::PreconditionNotMet { reason },
+      WriteError::OutOfResources { reason, data: _ } => {
+        WriteError::OutOfResources { reason, data: () }
+      }
       WriteError::Internal { reason } => WriteError::Internal { reason },
     }
   }
@@ -180,6 +199,11 @@ pub(crate) fn unwrap_no_key_write_error<D>(
       data: data.d,
     },
     WriteError::WouldBlock { data } => WriteError::WouldBlock { data: data.d },
+    WriteError::PreconditionNotMet { reason } => WriteError::PreconditionNotMet { reason },
+    WriteError::OutOfResources { reason, data } => WriteError::OutOfResources {
+      reason,
+      data: data.d,
+    },
     WriteError::Internal { reason } => WriteError::Internal { reason },
     WriteError::Io(io) => WriteError::Io(io),
   }
@@ -216,6 +240,26 @@ pub enum CreateError {
   NotAllowedBySecurity { reason: String },
 }
 
+/// Error type for sharing a DataReader or DataWriter. The endpoint that could
+/// not be shared is given back. It is boxed to keep the error small.
+#[derive(thiserror::Error)]
+#[error("Cannot share endpoint: {error}")]
+pub struct ShareError<E> {
+  pub error: CreateError,
+  pub endpoint: Box<E>,
+}
+
+// Endpoints do not implement Debug, so it is left out.
+impl<E> fmt::Debug for ShareError<E> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ShareError")
+      .field("error", &self.error)
+      .finish_non_exhaustive()
+  }
+}
+
+pub type ShareResult<E> = std::result::Result<Arc<Mutex<E>>, ShareError<E>>;
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! create_error_dropped {
@@ -301,6 +345,9 @@ pub type CreateResult<T> = std::result::Result<T, CreateError>;
 pub enum WaitError {
   #[error("Waiting timed out")]
   Timeout,
+
+  #[error("std:io:Error {0}")]
+  Io(#[from] std::io::Error),
 }
 
 pub type WaitResult<T> = std::result::Result<T, WaitError>;
@@ -309,4 +356,22 @@ pub type WaitResult<T> = std::result::Result<T, WaitError>;
 pub enum QosError {
   #[error("Parameter value or combination of values was bad. Details: {details}")]
   BadParameter { details: String },
+
+  /// Attempted to change a QoS policy that cannot be changed after the entity
+  /// has been created.
+  #[error("QoS policy {policy:?} cannot be changed after creation")]
+  ImmutablePolicy { policy: QosPolicyId },
+}
+
+/// Error type for content filter and query expressions, e.g. in
+/// [`ContentFilteredTopic`](crate::ContentFilteredTopic).
+#[derive(Debug, thiserror::Error)]
+pub enum FilterError {
+  /// The expression does not conform to the DDSSQL grammar.
+  #[error("Filter expression syntax error at position {position}: {reason}")]
+  Syntax { position: usize, reason: String },
+
+  /// Expression parameters do not fit the expression.
+  #[error("Bad filter expression parameter: {reason}")]
+  BadParameter { reason: String },
 }
@@ -6,6 +6,7 @@
 // Communication statues are detailed in Figure 2.13 and tables in Section
 // 2.2.4.1 in DDS Specification v1.4
 use std::{
+  collections::BTreeMap,
   io,
   pin::Pin,
   sync::{Arc, Mutex},
@@ -19,9 +20,10 @@ use mio_06::Evented;
 use mio_extras::channel as mio_channel;
 use mio_08::{event, Interest, Registry, Token};
 use chrono::Utc;
+use enumflags2::{bitflags, BitFlags};
 
 use crate::{
-  dds::{qos::QosPolicyId, topic::TopicData},
+  dds::{key::KeyHash, qos::QosPolicyId, topic::TopicData, waitset::ConditionWakers},
   discovery::SpdpDiscoveredParticipantData,
   messages::{protocol_version::ProtocolVersion, vendor_id::VendorId},
   mio_source::*,
@@ -59,6 +61,29 @@ where
 
 pub(crate) fn sync_status_channel<T>(
   capacity: usize,
+) -> io::Result<(StatusChannelSender<T>, StatusChannelReceiver<T>)> {
+  make_status_channel(capacity, None)
+}
+
+// Like sync_status_channel, but the statuses in transit are also recorded in
+// `status_changes`, so that StatusConditions can see them.
+pub(crate) fn sync_status_channel_tracked<T>(
+  capacity: usize,
+  status_changes: Arc<StatusChanges>,
+  kind_of: fn(&T) -> StatusKind,
+) -> io::Result<(StatusChannelSender<T>, StatusChannelReceiver<T>)> {
+  make_status_channel(
+    capacity,
+    Some(StatusTracker {
+      status_changes,
+      kind_of,
+    }),
+  )
+}
+
+fn make_status_channel<T>(
+  capacity: usize,
+  tracker: Option<StatusTracker<T>>,
 ) -> io::Result<(StatusChannelSender<T>, StatusChannelReceiver<T>)> {
   let (signal_receiver, signal_sender) = make_poll_channel()?;
   let (actual_sender, actual_receiver) = mio_channel::sync_channel(capacity);
@@ -68,35 +93,78 @@ pub(crate) fn sync_status_channel<T>(
       actual_sender,
       signal_sender,
       waker: Arc::clone(&waker),
+      tracker: tracker.clone(),
+      listener_tap: None,
     },
     StatusChannelReceiver {
       actual_receiver: Mutex::new(actual_receiver),
       signal_receiver,
       waker,
+      tracker,
     },
   ))
 }
 
+struct StatusTracker<T> {
+  status_changes: Arc<StatusChanges>,
+  kind_of: fn(&T) -> StatusKind,
+}
+
+// derive(Clone) would require T: Clone
+impl<T> Clone for StatusTracker<T> {
+  fn clone(&self) -> Self {
+    Self {
+      status_changes: Arc::clone(&self.status_changes),
+      kind_of: self.kind_of,
+    }
+  }
+}
+
 // TODO: try to make this (and the Receiver) private types
 #[derive(Clone)]
 pub struct StatusChannelSender<T> {
   actual_sender: mio_channel::SyncSender<T>,
   signal_sender: PollEventSender,
   waker: Arc<Mutex<Option<Waker>>>,
+  tracker: Option<StatusTracker<T>>,
+  listener_tap: Option<ListenerTap<T>>,
 }
 
+// Passes a copy of each status to listeners, see dds::listener
+type ListenerTap<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
 pub struct StatusChannelReceiver<T> {
   actual_receiver: Mutex<mio_channel::Receiver<T>>,
   signal_receiver: PollEventSource,
   waker: Arc<Mutex<Option<Waker>>>,
+  tracker: Option<StatusTracker<T>>,
 }
 
 impl<T> StatusChannelSender<T> {
+  pub(crate) fn with_listener_tap(self, tap: impl Fn(&T) + Send + Sync + 'static) -> Self {
+    Self {
+      listener_tap: Some(Arc::new(tap)),
+      ..self
+    }
+  }
+
+  /// Passes the status to listeners only, not to the channel.
+  pub(crate) fn send_to_listener(&self, t: &T) {
+    if let Some(tap) = &self.listener_tap {
+      tap(t);
+    }
+  }
+
   /// Best-effort send. If there is no receiver, this will fail silently.
   pub fn try_send(&self, t: T) -> Result<(), mio_channel::TrySendError<T>> {
+    self.send_to_listener(&t);
     let mut w = self.waker.lock().unwrap(); // lock already at the beginning
+    let kind = self.tracker.as_ref().map(|tr| (tr.kind_of)(&t));
     match self.actual_sender.try_send(t) {
       Ok(()) => {
+        if let (Some(tracker), Some(kind)) = (&self.tracker, kind) {
+          tracker.status_changes.status_sent(kind);
+        }
         self.signal_sender.send();
         w.as_ref().map(|w| w.wake_by_ref());
         *w = None;
@@ -123,7 +191,15 @@ impl<T> StatusChannelReceiver<T> {
     // We do not manipulate waker here, because the
     // synchronous and asynchronous receiving are not supposed to be mixed.
     self.signal_receiver.drain();
-    self.actual_receiver.lock().unwrap().try_recv()
+    let result = self.actual_receiver.lock().unwrap().try_recv();
+    if let (Some(tracker), Ok(t)) = (&self.tracker, &result) {
+      tracker.status_changes.status_received((tracker.kind_of)(t));
+    }
+    result
+  }
+
+  pub(crate) fn status_changes(&self) -> Option<&Arc<StatusChanges>> {
+    self.tracker.as_ref().map(|tr| &tr.status_changes)
   }
 
   pub(crate) fn get_waker_update_lock(&self) -> std::sync::MutexGuard<'_, Option<Waker>> {
@@ -406,9 +482,12 @@ pub enum DataReaderStatus {
   },
   /// Remote Writer has become active or inactive.
   LivelinessChanged {
+    /// Number of matched Writers that are currently alive
     alive_total: CountWithChange,
+    /// Number of matched Writers that are not alive
     not_alive_total: CountWithChange,
-    // last_publication_key:
+    /// The Writer that caused this change
+    writer: GUID,
   },
   /// Deadline requested by this DataReader was missed.
   RequestedDeadlineMissed {
@@ -426,8 +505,10 @@ pub enum DataReaderStatus {
     //policies: Vec<QosPolicyCount>, // Not implemented
   },
 
-  // DataAvailable variant is not implemented, as it seems to bring little additional value,
-  // because the normal data waiting mechanism already uses the same mio::poll structure.
+  /// New data has arrived to this DataReader. This is sent only if enabled with
+  /// `set_data_available_status`, and only once until the DataReader reads
+  /// again.
+  DataAvailable,
   /// A sample has been lost (never received).
   /// TODO: Implement this.
   /// * Check that the following interpretation is correct:
@@ -450,14 +531,24 @@ pub enum DataReaderStatus {
   },
 }
 
+/// Status events of a Subscriber
+#[derive(Debug, Clone)]
+pub enum SubscriberStatus {
+  /// New data has arrived to a DataReader of this Subscriber. This is sent
+  /// only once until the DataReader reads again.
+  DataOnReaders { reader: GUID },
+}
+
 #[derive(Debug, Clone)]
 pub enum DataWriterStatus {
   LivelinessLost {
     count: CountWithChange,
   },
+  /// Deadline offered by this DataWriter was missed for an instance.
   OfferedDeadlineMissed {
     count: CountWithChange,
-    // last_instance_key:
+    /// Key hash of the instance that was not updated in time
+    last_instance_key: KeyHash,
   },
   OfferedIncompatibleQos {
     count: CountWithChange,
@@ -475,6 +566,239 @@ pub enum DataWriterStatus {
   },
 }
 
+impl DataReaderStatus {
+  /// The kind of status this event is about
+  pub fn kind(&self) -> StatusKind {
+    match self {
+      Self::SampleRejected { .. } => StatusKind::SampleRejected,
+      Self::LivelinessChanged { .. } => StatusKind::LivelinessChanged,
+      Self::RequestedDeadlineMissed { .. } => StatusKind::RequestedDeadlineMissed,
+      Self::RequestedIncompatibleQos { .. } => StatusKind::RequestedIncompatibleQos,
+      Self::DataAvailable => StatusKind::DataAvailable,
+      Self::SampleLost { .. } => StatusKind::SampleLost,
+      Self::SubscriptionMatched { .. } => StatusKind::SubscriptionMatched,
+    }
+  }
+}
+
+impl DataWriterStatus {
+  /// The kind of status this event is about
+  pub fn kind(&self) -> StatusKind {
+    match self {
+      Self::LivelinessLost { .. } => StatusKind::LivelinessLost,
+      Self::OfferedDeadlineMissed { .. } => StatusKind::OfferedDeadlineMissed,
+      Self::OfferedIncompatibleQos { .. } => StatusKind::OfferedIncompatibleQos,
+      Self::PublicationMatched { .. } => StatusKind::PublicationMatched,
+    }
+  }
+}
+
+/// Kinds of communication statuses, as bitflags. These are used to select
+/// statuses in a [`StatusCondition`](crate::StatusCondition).
+///
+/// The values are the same as in DDS Specification v1.4 Section 2.3.3 DCPS
+/// PSM : IDL.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[bitflags]
+#[repr(u32)]
+pub enum StatusKind {
+  InconsistentTopic = 1 << 0,
+  OfferedDeadlineMissed = 1 << 1,
+  RequestedDeadlineMissed = 1 << 2,
+  OfferedIncompatibleQos = 1 << 5,
+  RequestedIncompatibleQos = 1 << 6,
+  SampleLost = 1 << 7,
+  SampleRejected = 1 << 8,
+  DataOnReaders = 1 << 9,
+  DataAvailable = 1 << 10,
+  LivelinessLost = 1 << 11,
+  LivelinessChanged = 1 << 12,
+  PublicationMatched = 1 << 13,
+  SubscriptionMatched = 1 << 14,
+}
+
+impl StatusKind {
+  /// Set that contains all status kinds
+  pub fn any() -> BitFlags<Self> {
+    BitFlags::<Self>::all()
+  }
+}
+
+/// DDS INCONSISTENT_TOPIC status of a Topic
+///
+/// The count is the number of remote Topic definitions, i.e. remote
+/// DataReaders, DataWriters, or Topics, that have the same name as the local
+/// Topic, but a different type name or inconsistent QoS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InconsistentTopicStatus {
+  pub count: CountWithChange,
+}
+
+/// DDS LIVELINESS_LOST status of a DataWriter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivelinessLostStatus {
+  pub count: CountWithChange,
+}
+
+/// DDS OFFERED_DEADLINE_MISSED status of a DataWriter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OfferedDeadlineMissedStatus {
+  pub count: CountWithChange,
+  /// Key hash of the instance that missed its deadline last, if any.
+  pub last_instance_key: Option<KeyHash>,
+}
+
+/// DDS OFFERED_INCOMPATIBLE_QOS status of a DataWriter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OfferedIncompatibleQosStatus {
+  pub count: CountWithChange,
+  /// Policy that was incompatible in the latest mismatch, if any.
+  pub last_policy_id: Option<QosPolicyId>,
+  //policies: Vec<QosPolicyCount>,  // Not implemented
+}
+
+/// DDS PUBLICATION_MATCHED status of a DataWriter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicationMatchedStatus {
+  pub total: CountWithChange,
+  pub current: CountWithChange,
+  /// Reader that was matched or unmatched last, if any.
+  pub last_reader: Option<GUID>,
+}
+
+// A cumulative count, and its value when the application last read it.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct CountRecord {
+  count: i32,
+  count_read: i32,
+}
+
+impl CountRecord {
+  pub(crate) fn set(&mut self, count: i32) {
+    self.count = count;
+  }
+
+  pub(crate) fn read(&mut self) -> CountWithChange {
+    let count_change = self.count - self.count_read;
+    self.count_read = self.count;
+    CountWithChange::new(self.count, count_change)
+  }
+}
+
+// Latest DataWriter statuses, so that they can be read without draining the
+// status channel. Shared between RTPS Writer and DataWriter. The Writer
+// records each DataWriterStatus it sends, so that both views agree.
+#[derive(Debug, Default)]
+pub(crate) struct DataWriterStatusRecord {
+  liveliness_lost: CountRecord,
+  offered_deadline_missed: CountRecord,
+  last_instance_key: Option<KeyHash>,
+  offered_incompatible_qos: CountRecord,
+  last_policy_id: Option<QosPolicyId>,
+  publication_matched_total: CountRecord,
+  publication_matched_current: CountRecord,
+  last_reader: Option<GUID>,
+}
+
+impl DataWriterStatusRecord {
+  pub(crate) fn record(&mut self, status: &DataWriterStatus) {
+    match status {
+      DataWriterStatus::LivelinessLost { count } => self.liveliness_lost.count = count.count(),
+      DataWriterStatus::OfferedDeadlineMissed {
+        count,
+        last_instance_key,
+      } => {
+        self.offered_deadline_missed.count = count.count();
+        self.last_instance_key = Some(*last_instance_key);
+      }
+      DataWriterStatus::OfferedIncompatibleQos {
+        count,
+        last_policy_id,
+        ..
+      } => {
+        self.offered_incompatible_qos.count = count.count();
+        self.last_policy_id = Some(*last_policy_id);
+      }
+      DataWriterStatus::PublicationMatched {
+        total,
+        current,
+        reader,
+      } => {
+        self.publication_matched_total.count = total.count();
+        self.publication_matched_current.count = current.count();
+        self.last_reader = Some(*reader);
+      }
+    }
+  }
+
+  pub(crate) fn liveliness_lost(&mut self) -> LivelinessLostStatus {
+    LivelinessLostStatus {
+      count: self.liveliness_lost.read(),
+    }
+  }
+
+  pub(crate) fn offered_deadline_missed(&mut self) -> OfferedDeadlineMissedStatus {
+    OfferedDeadlineMissedStatus {
+      count: self.offered_deadline_missed.read(),
+      last_instance_key: self.last_instance_key,
+    }
+  }
+
+  pub(crate) fn offered_incompatible_qos(&mut self) -> OfferedIncompatibleQosStatus {
+    OfferedIncompatibleQosStatus {
+      count: self.offered_incompatible_qos.read(),
+      last_policy_id: self.last_policy_id,
+    }
+  }
+
+  pub(crate) fn publication_matched(&mut self) -> PublicationMatchedStatus {
+    PublicationMatchedStatus {
+      total: self.publication_matched_total.read(),
+      current: self.publication_matched_current.read(),
+      last_reader: self.last_reader,
+    }
+  }
+}
+
+// Status changes of an entity that the application has not yet seen, i.e.
+// statuses in transit in the status channel, or not read with a status getter.
+// Shared between the status channel and the StatusCondition of the entity.
+#[derive(Default)]
+pub(crate) struct StatusChanges {
+  pending: Mutex<BTreeMap<StatusKind, usize>>,
+  wakers: ConditionWakers,
+}
+
+impl StatusChanges {
+  pub(crate) fn status_sent(&self, kind: StatusKind) {
+    *self.pending.lock().unwrap().entry(kind).or_default() += 1;
+    self.wakers.wake();
+  }
+
+  pub(crate) fn status_received(&self, kind: StatusKind) {
+    let mut pending = self.pending.lock().unwrap();
+    if let Some(count) = pending.get_mut(&kind) {
+      *count -= 1;
+      if *count == 0 {
+        pending.remove(&kind);
+      }
+    }
+  }
+
+  // The application has read the status with a getter.
+  pub(crate) fn clear(&self, kind: StatusKind) {
+    self.pending.lock().unwrap().remove(&kind);
+  }
+
+  pub(crate) fn changed(&self) -> BitFlags<StatusKind> {
+    self.pending.lock().unwrap().keys().copied().collect()
+  }
+
+  pub(crate) fn wakers(&self) -> &ConditionWakers {
+    &self.wakers
+  }
+}
+
 /// Helper to contain same count actions across statuses
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct CountWithChange {
@@ -1,25 +1,58 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+  fmt::Debug,
+  sync::{Arc, Mutex, RwLock},
+};
+
+use serde::Serialize;
 
 use crate::{
   dds::{
+    ddssql::{Filter, DDSSQL_FILTER_CLASS_NAME},
     participant::{DomainParticipant, DomainParticipantWeak},
     qos::{HasQoSPolicy, QosPolicies},
+    result::FilterError,
+    statusevents::{CountRecord, InconsistentTopicStatus},
     typedesc::TypeDesc,
   },
-  discovery::sedp_messages::TopicBuiltinTopicData,
+  discovery::{
+    content_filter_property::ContentFilterProperty, discovery_db::discovery_db_read,
+    sedp_messages::TopicBuiltinTopicData,
+  },
 };
 pub use crate::structure::topic_kind::TopicKind;
 
 /// Trait approximation of DDS 2.2.2.3.1 TopicDescription Class
 ///
-/// Now it is utterly useless, but if we ever add ContentFilteredTopic or
-/// MultiTopic, then it may turn out to be useful.
+/// Implemented by [`Topic`] and [`ContentFilteredTopic`].
 pub trait TopicDescription {
   fn participant(&self) -> Option<DomainParticipant>;
   fn get_type(&self) -> TypeDesc; // This replaces type_name() from spec
   fn name(&self) -> String;
 }
 
+/// TopicDescriptions that a DataReader can be created for.
+///
+/// This is implemented by [`Topic`] for any sample type `D`, and by
+/// [`ContentFilteredTopic`] for sample types that implement `Serialize`,
+/// because the filter is evaluated on the serialized structure of the sample.
+pub trait ReaderTopicDescription<D>: TopicDescription {
+  /// The Topic that the DataReader actually subscribes to.
+  fn topic(&self) -> &Topic;
+
+  #[doc(hidden)]
+  fn sample_filter(&self) -> Option<SampleFilter<D>>;
+}
+
+impl<D> ReaderTopicDescription<D> for Topic {
+  fn topic(&self) -> &Topic {
+    self
+  }
+
+  fn sample_filter(&self) -> Option<SampleFilter<D>> {
+    None
+  }
+}
+
 /// This is a more usable version of TopicBuiltinTopicData from Discovery.
 ///
 /// It is used for describing discovered topics.
@@ -120,14 +153,34 @@ impl Topic {
   pub fn kind(&self) -> TopicKind {
     self.inner.kind()
   }
-  /*
+
+  /// Gets the INCONSISTENT_TOPIC status, i.e. how many remote definitions of
+  /// this Topic have been discovered with a different type name or
+  /// inconsistent QoS. The change is counted from the previous call.
+  ///
+  /// Each inconsistency is also reported as
+  /// [`DomainParticipantStatusEvent::InconsistentTopic`](crate::DomainParticipantStatusEvent::InconsistentTopic).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::*;
+  ///
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// let topic = domain_participant
+  ///     .create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey)
+  ///     .unwrap();
+  /// let status = topic.get_inconsistent_topic_status();
+  /// if status.count.count_change() > 0 {
+  ///   println!("Someone disagrees about the type of {}", topic.name());
+  /// }
+  /// ```
   // DDS spec 2.2.2.3.2 Topic Class
   // specifies only method get_inconsistent_topic_status
-  // TODO: implement
-  pub(crate) fn get_inconsistent_topic_status() -> Result<InconsistentTopicStatus> {
-    unimplemented!()
+  pub fn get_inconsistent_topic_status(&self) -> InconsistentTopicStatus {
+    self.inner.get_inconsistent_topic_status()
   }
-  */
 }
 
 impl PartialEq for Topic {
@@ -169,6 +222,176 @@ impl HasQoSPolicy for Topic {
 
 // impl DDSEntity for Topic {}
 
+// -------------------------------- ContentFilteredTopic ----------------------
+
+/// DDS ContentFilteredTopic
+///
+/// DDS Specification, Section 2.2.2.3.3 ContentFilteredTopic Class:
+/// > ContentFilteredTopic is a specialization of TopicDescription that allows
+/// > for content-based subscriptions.
+///
+/// A DataReader created for a ContentFilteredTopic receives only those samples
+/// of the related Topic that pass the filter. The filter expression uses the
+/// DDSSQL syntax specified in DDS spec Annex B. The filter is also announced in
+/// Discovery, so that remote DataWriters may filter samples before sending.
+///
+/// ContentFilteredTopics are created using
+/// [`DomainParticipant::create_content_filtered_topic`].
+///
+/// # Examples
+///
+/// ```
+/// use rustdds::*;
+///
+/// let domain_participant = DomainParticipant::new(0).unwrap();
+/// let qos = QosPolicyBuilder::new().build();
+/// let topic = domain_participant
+///       .create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey)
+///       .unwrap();
+/// let filtered_topic = domain_participant
+///       .create_content_filtered_topic(
+///         "some_filtered_topic".to_string(),
+///         &topic,
+///         "a > %0 AND b LIKE 'foo%'",
+///         vec!["5".to_string()],
+///       )
+///       .unwrap();
+/// ```
+#[derive(Clone)]
+pub struct ContentFilteredTopic {
+  name: String,
+  related_topic: Topic,
+  filter: Arc<RwLock<Filter>>,
+}
+
+impl ContentFilteredTopic {
+  pub(crate) fn new(
+    name: String,
+    related_topic: &Topic,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> Result<Self, FilterError> {
+    let filter = Filter::new(filter_expression, expression_parameters)?;
+    Ok(Self {
+      name,
+      related_topic: related_topic.clone(),
+      filter: Arc::new(RwLock::new(filter)),
+    })
+  }
+
+  /// The Topic whose samples are filtered
+  pub fn related_topic(&self) -> &Topic {
+    &self.related_topic
+  }
+
+  pub fn filter_expression(&self) -> String {
+    self.filter.read().unwrap().expression().to_string()
+  }
+
+  pub fn expression_parameters(&self) -> Vec<String> {
+    self.filter.read().unwrap().parameters().to_vec()
+  }
+
+  /// Changes the expression parameters of the filter.
+  ///
+  /// The new parameters take effect immediately in all DataReaders created
+  /// for this ContentFilteredTopic. The changed filter is also announced to
+  /// remote DataWriters.
+  pub fn set_expression_parameters(&self, parameters: Vec<String>) -> Result<(), FilterError> {
+    self.filter.write().unwrap().set_parameters(parameters)?;
+    if let Some(dp) = self.related_topic.participant() {
+      dp.update_local_reader_content_filter(&self.content_filter_property());
+    }
+    Ok(())
+  }
+
+  pub(crate) fn content_filter_property(&self) -> ContentFilterProperty {
+    let filter = self.filter.read().unwrap();
+    ContentFilterProperty {
+      content_filtered_topic_name: self.name.clone(),
+      related_topic_name: self.related_topic.name(),
+      filter_class_name: DDSSQL_FILTER_CLASS_NAME.to_string(),
+      filter_expression: filter.expression().to_string(),
+      expression_parameters: filter.parameters().to_vec(),
+    }
+  }
+}
+
+impl Debug for ContentFilteredTopic {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ContentFilteredTopic")
+      .field("name", &self.name)
+      .field("related_topic", &self.related_topic.name())
+      .field("filter", &self.filter.read().unwrap())
+      .finish()
+  }
+}
+
+impl TopicDescription for ContentFilteredTopic {
+  /// Gets [DomainParticipant](struct.DomainParticipant.html) of the related
+  /// Topic, if it is still alive.
+  fn participant(&self) -> Option<DomainParticipant> {
+    self.related_topic.participant()
+  }
+
+  /// Gets type description of the related Topic
+  fn get_type(&self) -> TypeDesc {
+    self.related_topic.get_type()
+  }
+
+  /// Gets name of this ContentFilteredTopic
+  fn name(&self) -> String {
+    self.name.clone()
+  }
+}
+
+impl<D: Serialize> ReaderTopicDescription<D> for ContentFilteredTopic {
+  fn topic(&self) -> &Topic {
+    &self.related_topic
+  }
+
+  fn sample_filter(&self) -> Option<SampleFilter<D>> {
+    Some(SampleFilter {
+      content_filtered_topic: self.clone(),
+      matches: Arc::new(|filter: &Filter, sample: &D| filter.matches(sample)),
+    })
+  }
+}
+
+/// Reader-side evaluation of a ContentFilteredTopic filter on samples of
+/// type `D`.
+///
+/// This is not needed by applications. It is public only because it is part
+/// of [`ReaderTopicDescription`].
+pub struct SampleFilter<D> {
+  content_filtered_topic: ContentFilteredTopic,
+  #[allow(clippy::type_complexity)]
+  matches: Arc<dyn Fn(&Filter, &D) -> bool + Send + Sync>,
+}
+
+impl<D> SampleFilter<D> {
+  pub(crate) fn matches(&self, sample: &D) -> bool {
+    let filter = self.content_filtered_topic.filter.read().unwrap();
+    (self.matches)(&filter, sample)
+  }
+
+  pub(crate) fn content_filter_property(&self) -> ContentFilterProperty {
+    self.content_filtered_topic.content_filter_property()
+  }
+
+  // Evaluate the same filter on a wrapper type, e.g. NoKeyWrapper<D>
+  pub(crate) fn map<W: 'static>(self, unwrap: fn(&W) -> &D) -> SampleFilter<W>
+  where
+    D: 'static,
+  {
+    let matches = self.matches;
+    SampleFilter {
+      content_filtered_topic: self.content_filtered_topic,
+      matches: Arc::new(move |filter: &Filter, sample: &W| matches(filter, unwrap(sample))),
+    }
+  }
+}
+
 // -------------------------------- InnerTopic -----------------------------
 
 #[derive(Clone)]
@@ -178,6 +401,7 @@ pub struct InnerTopic {
   my_typedesc: TypeDesc,
   my_qos_policies: QosPolicies,
   topic_kind: TopicKind, // WITH_KEY or NO_KEY
+  inconsistent_topic: Arc<Mutex<CountRecord>>,
 }
 
 impl InnerTopic {
@@ -196,6 +420,7 @@ impl InnerTopic {
       my_typedesc,
       my_qos_policies: my_qos_policies.clone(),
       topic_kind,
+      inconsistent_topic: Arc::new(Mutex::new(CountRecord::default())),
     }
   }
 
@@ -214,10 +439,17 @@ impl InnerTopic {
   pub fn kind(&self) -> TopicKind {
     self.topic_kind
   }
-  /*
-  pub(crate) fn get_inconsistent_topic_status() -> Result<TopicStatus> {
-    unimplemented!()
-  } */
+
+  fn get_inconsistent_topic_status(&self) -> InconsistentTopicStatus {
+    let mut record = self.inconsistent_topic.lock().unwrap();
+    // If the DomainParticipant is gone, there is nothing new to count.
+    if let Some(dp) = self.participant() {
+      record.set(discovery_db_read(&dp.discovery_db()).inconsistent_topic_count(&self.my_name));
+    }
+    InconsistentTopicStatus {
+      count: record.read(),
+    }
+  }
 }
 
 impl PartialEq for InnerTopic {
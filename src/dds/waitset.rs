@@ -0,0 +1,561 @@
+// WaitSet and Conditions
+//
+// See DDS Specification v1.4 Sections 2.2.2.1.6 WaitSet Class to
+// 2.2.2.1.9 StatusCondition Class.
+//
+// Conditions do not know which WaitSets they are attached to. Instead, each
+// WaitSet gives a waker to the Conditions attached to it, and the entity
+// behind a Condition calls the wakers whenever the trigger value may have
+// become true. The WaitSet then re-evaluates the trigger values. Wakeups may
+// be spurious, but they are never missed.
+use std::{
+  io,
+  sync::{
+    atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+  task::{Poll, Waker},
+  time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use enumflags2::BitFlags;
+use mio_08::{Events, Interest, Token};
+
+use crate::{
+  dds::{
+    readcondition::ReadCondition,
+    sampleinfo::SampleState,
+    result::{CreateResult, WaitError, WaitResult},
+    statusevents::{StatusChanges, StatusKind},
+  },
+  mio_source::*,
+  rtps::reader::DataAvailability,
+};
+
+static NEXT_WAITSET_ID: AtomicU64 = AtomicU64::new(0);
+
+// Wakes up one WaitSet, whether it is blocked in wait() or awaited in
+// wait_async().
+#[derive(Clone)]
+struct WaitSetWaker {
+  waitset_id: u64,
+  poll_sender: PollEventSender,
+  task_waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl WaitSetWaker {
+  fn wake(&self) {
+    self.poll_sender.send();
+    if let Some(w) = self.task_waker.lock().unwrap().take() {
+      w.wake();
+    }
+  }
+}
+
+/// Wakers of the WaitSets that some Conditions are attached to.
+///
+/// This is an implementation detail of [`Condition`].
+#[doc(hidden)]
+#[derive(Default)]
+pub struct ConditionWakers {
+  // One entry per attached Condition. Several Conditions may share the same
+  // ConditionWakers, so the same WaitSet may be here more than once.
+  wakers: Mutex<Vec<WaitSetWaker>>,
+}
+
+impl ConditionWakers {
+  pub(crate) fn wake(&self) {
+    for w in self.wakers.lock().unwrap().iter() {
+      w.wake();
+    }
+  }
+
+  fn add(&self, waker: WaitSetWaker) {
+    self.wakers.lock().unwrap().push(waker);
+  }
+
+  fn remove(&self, waitset_id: u64) {
+    let mut wakers = self.wakers.lock().unwrap();
+    if let Some(pos) = wakers.iter().position(|w| w.waitset_id == waitset_id) {
+      wakers.remove(pos);
+    }
+  }
+}
+
+/// A Condition can be attached to a [`WaitSet`], which then waits until the
+/// trigger value of some of its Conditions becomes true.
+///
+/// See DDS Specification v1.4 Section 2.2.2.1.7 Condition Class.
+pub trait Condition: Send + Sync {
+  /// Current trigger value of the Condition
+  fn get_trigger_value(&self) -> bool;
+
+  #[doc(hidden)]
+  fn wakers(&self) -> &ConditionWakers;
+
+  // Identity of the Condition. Clones of a Condition have the same identity.
+  #[doc(hidden)]
+  fn condition_ptr(&self) -> *const ();
+
+  #[doc(hidden)]
+  fn box_clone(&self) -> Box<dyn Condition>;
+}
+
+impl dyn Condition + '_ {
+  /// Checks if this is the same Condition (or a clone of it) as `other`.
+  ///
+  /// This is useful for finding out which Conditions were returned by
+  /// [`WaitSet::wait`].
+  pub fn is<C: Condition>(&self, other: &C) -> bool {
+    self.condition_ptr() == other.condition_ptr()
+  }
+}
+
+/// A Condition whose trigger value is completely under the control of the
+/// application.
+///
+/// See DDS Specification v1.4 Section 2.2.2.1.8 GuardCondition Class.
+///
+/// Cloning a GuardCondition produces another handle to the same Condition.
+#[derive(Clone, Default)]
+pub struct GuardCondition {
+  inner: Arc<GuardConditionInner>,
+}
+
+#[derive(Default)]
+struct GuardConditionInner {
+  trigger_value: AtomicBool,
+  wakers: ConditionWakers,
+}
+
+impl GuardCondition {
+  /// Creates a new GuardCondition, whose trigger value is false.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the trigger value. Setting it to `true` wakes up the WaitSets that
+  /// this Condition is attached to.
+  pub fn set_trigger_value(&self, value: bool) {
+    self.inner.trigger_value.store(value, Ordering::SeqCst);
+    if value {
+      self.inner.wakers.wake();
+    }
+  }
+}
+
+impl Condition for GuardCondition {
+  fn get_trigger_value(&self) -> bool {
+    self.inner.trigger_value.load(Ordering::SeqCst)
+  }
+
+  fn wakers(&self) -> &ConditionWakers {
+    &self.inner.wakers
+  }
+
+  fn condition_ptr(&self) -> *const () {
+    Arc::as_ptr(&self.inner) as *const ()
+  }
+
+  fn box_clone(&self) -> Box<dyn Condition> {
+    Box::new(self.clone())
+  }
+}
+
+/// A Condition that is triggered by communication status changes of an
+/// entity, e.g. a DataReader or a DataWriter.
+///
+/// The trigger value is true when some of the enabled statuses have changed.
+/// A status change is pending until the corresponding status event is received
+/// from the status channel of the entity, or, for statuses that have a getter
+/// method, until the getter is called. DATA_AVAILABLE is pending until the
+/// DataReader reads or takes.
+///
+/// Get one from e.g. [`DataReader::get_statuscondition`](crate::with_key::DataReader::get_statuscondition).
+/// Each entity has exactly one StatusCondition, so all the returned handles
+/// share the same enabled statuses.
+///
+/// See DDS Specification v1.4 Section 2.2.2.1.9 StatusCondition Class.
+#[derive(Clone)]
+pub struct StatusCondition {
+  inner: Arc<StatusConditionInner>,
+}
+
+struct StatusConditionInner {
+  status_changes: Arc<StatusChanges>,
+  data_availability: Option<Arc<DataAvailability>>, // Only for DataReaders
+  enabled_statuses: AtomicU32,
+}
+
+impl StatusCondition {
+  pub(crate) fn new(
+    status_changes: Arc<StatusChanges>,
+    data_availability: Option<Arc<DataAvailability>>,
+  ) -> Self {
+    Self {
+      inner: Arc::new(StatusConditionInner {
+        status_changes,
+        data_availability,
+        enabled_statuses: AtomicU32::new(BitFlags::<StatusKind>::all().bits()),
+      }),
+    }
+  }
+
+  /// Statuses that can trigger this Condition. By default, all statuses are
+  /// enabled.
+  pub fn get_enabled_statuses(&self) -> BitFlags<StatusKind> {
+    BitFlags::from_bits_truncate(self.inner.enabled_statuses.load(Ordering::SeqCst))
+  }
+
+  pub fn set_enabled_statuses(&self, statuses: BitFlags<StatusKind>) {
+    self
+      .inner
+      .enabled_statuses
+      .store(statuses.bits(), Ordering::SeqCst);
+    // Some of the newly enabled statuses may already be pending.
+    self.inner.status_changes.wakers().wake();
+  }
+
+  /// Statuses of the entity that have changed and have not yet been
+  /// read by the application, regardless of which statuses are enabled.
+  pub fn get_status_changes(&self) -> BitFlags<StatusKind> {
+    let mut changes = self.inner.status_changes.changed();
+    if let Some(data_availability) = &self.inner.data_availability {
+      // For DataReaders, the unread flag is the authoritative source, because
+      // DataAvailable events are sent only if explicitly enabled.
+      changes.set(StatusKind::DataAvailable, data_availability.is_unread());
+    }
+    changes
+  }
+}
+
+impl Condition for StatusCondition {
+  fn get_trigger_value(&self) -> bool {
+    self
+      .get_status_changes()
+      .intersects(self.get_enabled_statuses())
+  }
+
+  fn wakers(&self) -> &ConditionWakers {
+    self.inner.status_changes.wakers()
+  }
+
+  fn condition_ptr(&self) -> *const () {
+    Arc::as_ptr(&self.inner) as *const ()
+  }
+
+  fn box_clone(&self) -> Box<dyn Condition> {
+    Box::new(self.clone())
+  }
+}
+
+// Part of a DataReaderReadCondition that the DataReader updates after
+// accessing its samples.
+pub(crate) struct ReadConditionState {
+  read_condition: ReadCondition,
+  // Are there samples matching the ReadCondition in the DataReader?
+  has_samples: AtomicBool,
+  data_availability: Arc<DataAvailability>,
+}
+
+impl ReadConditionState {
+  pub(crate) fn read_condition(&self) -> &ReadCondition {
+    &self.read_condition
+  }
+
+  pub(crate) fn set_has_samples(&self, has_samples: bool) {
+    let had_samples = self.has_samples.swap(has_samples, Ordering::SeqCst);
+    if has_samples && !had_samples {
+      self.data_availability.status_changes().wakers().wake();
+    }
+  }
+}
+
+/// A [`ReadCondition`] attached to a specific DataReader, so that it can be
+/// used in a [`WaitSet`].
+///
+/// The trigger value is true when the DataReader has samples that match the
+/// ReadCondition. Samples that have arrived, but have not yet been read or
+/// taken, are assumed to match if the ReadCondition selects not-read samples.
+///
+/// Get one from
+/// [`DataReader::create_readcondition`](crate::with_key::DataReader::create_readcondition).
+///
+/// See DDS Specification v1.4 Section 2.2.2.5.8 ReadCondition Class.
+#[derive(Clone)]
+pub struct DataReaderReadCondition {
+  state: Arc<ReadConditionState>,
+}
+
+impl DataReaderReadCondition {
+  pub(crate) fn new(
+    read_condition: ReadCondition,
+    data_availability: Arc<DataAvailability>,
+  ) -> Self {
+    Self {
+      state: Arc::new(ReadConditionState {
+        read_condition,
+        has_samples: AtomicBool::new(false),
+        data_availability,
+      }),
+    }
+  }
+
+  pub(crate) fn state(&self) -> &Arc<ReadConditionState> {
+    &self.state
+  }
+
+  /// The sample, view, and instance state masks of this Condition
+  pub fn read_condition(&self) -> ReadCondition {
+    self.state.read_condition
+  }
+}
+
+impl Condition for DataReaderReadCondition {
+  fn get_trigger_value(&self) -> bool {
+    self.state.has_samples.load(Ordering::SeqCst)
+      || (self.state.data_availability.is_unread()
+        && self
+          .state
+          .read_condition
+          .sample_state_mask()
+          .contains(SampleState::NotRead))
+  }
+
+  fn wakers(&self) -> &ConditionWakers {
+    self.state.data_availability.status_changes().wakers()
+  }
+
+  fn condition_ptr(&self) -> *const () {
+    Arc::as_ptr(&self.state) as *const ()
+  }
+
+  fn box_clone(&self) -> Box<dyn Condition> {
+    Box::new(self.clone())
+  }
+}
+
+/// Waits for any of the attached [`Condition`]s to trigger.
+///
+/// This is an alternative to polling the entities with mio or using async
+/// streams, meant mostly for porting code from other DDS implementations.
+///
+/// See DDS Specification v1.4 Section 2.2.2.1.6 WaitSet Class.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use rustdds::*;
+///
+/// let mut waitset = WaitSet::new().unwrap();
+/// let guard = GuardCondition::new();
+/// waitset.attach_condition(&guard);
+///
+/// // Nothing has triggered yet
+/// assert!(waitset.wait(Duration::from_millis(10)).is_err());
+///
+/// guard.set_trigger_value(true);
+/// let active = waitset.wait(Duration::from_millis(10)).unwrap();
+/// assert!(active[0].is(&guard));
+/// ```
+pub struct WaitSet {
+  id: u64,
+  conditions: Vec<Box<dyn Condition>>,
+  poll: mio_08::Poll,
+  events: Events,
+  poll_source: PollEventSource,
+  waker: WaitSetWaker,
+}
+
+impl WaitSet {
+  pub fn new() -> CreateResult<Self> {
+    let (mut poll_source, poll_sender) = make_poll_channel()?;
+    let poll = mio_08::Poll::new()?;
+    poll
+      .registry()
+      .register(&mut poll_source, Token(0), Interest::READABLE)?;
+    let id = NEXT_WAITSET_ID.fetch_add(1, Ordering::Relaxed);
+    Ok(Self {
+      id,
+      conditions: Vec::new(),
+      poll,
+      events: Events::with_capacity(4),
+      poll_source,
+      waker: WaitSetWaker {
+        waitset_id: id,
+        poll_sender,
+        task_waker: Arc::new(Mutex::new(None)),
+      },
+    })
+  }
+
+  /// Attaches a Condition. Attaching an already attached Condition does
+  /// nothing. A Condition may be attached to several WaitSets.
+  pub fn attach_condition<C: Condition + 'static>(&mut self, condition: &C) {
+    if self.position(condition).is_none() {
+      condition.wakers().add(self.waker.clone());
+      self.conditions.push(condition.box_clone());
+    }
+  }
+
+  /// Detaches a Condition. Returns `false` if the Condition was not attached.
+  pub fn detach_condition<C: Condition>(&mut self, condition: &C) -> bool {
+    match self.position(condition) {
+      Some(pos) => {
+        let removed = self.conditions.remove(pos);
+        removed.wakers().remove(self.id);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// The attached Conditions
+  pub fn get_conditions(&self) -> &[Box<dyn Condition>] {
+    &self.conditions
+  }
+
+  /// Blocks until at least one of the attached Conditions has triggered, or
+  /// `timeout` has elapsed.
+  ///
+  /// Returns the triggered Conditions, or [`WaitError::Timeout`].
+  pub fn wait(&mut self, timeout: Duration) -> WaitResult<Vec<&dyn Condition>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+      // Drain before checking, so that any trigger after the check produces
+      // a new poll event.
+      self.poll_source.drain();
+      if self.conditions.iter().any(|c| c.get_trigger_value()) {
+        return Ok(self.triggered());
+      }
+      let now = Instant::now();
+      if now >= deadline {
+        return Err(WaitError::Timeout);
+      }
+      match self.poll.poll(&mut self.events, Some(deadline - now)) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+        Err(e) => return Err(WaitError::Io(e)),
+      }
+    }
+  }
+
+  /// Async version of [`wait`](Self::wait). There is no timeout, but the
+  /// returned future can be raced against a timer of the async runtime.
+  pub async fn wait_async(&mut self) -> Vec<&dyn Condition> {
+    let task_waker = Arc::clone(&self.waker.task_waker);
+    let conditions = &self.conditions;
+    futures::future::poll_fn(|cx| {
+      // Store the waker before checking, so that no trigger is missed.
+      *task_waker.lock().unwrap() = Some(cx.waker().clone());
+      if conditions.iter().any(|c| c.get_trigger_value()) {
+        Poll::Ready(())
+      } else {
+        Poll::Pending
+      }
+    })
+    .await;
+    self.triggered()
+  }
+
+  fn triggered(&self) -> Vec<&dyn Condition> {
+    self
+      .conditions
+      .iter()
+      .filter(|c| c.get_trigger_value())
+      .map(|c| c.as_ref())
+      .collect()
+  }
+
+  fn position<C: Condition>(&self, condition: &C) -> Option<usize> {
+    self
+      .conditions
+      .iter()
+      .position(|c| c.condition_ptr() == condition.condition_ptr())
+  }
+}
+
+impl Drop for WaitSet {
+  fn drop(&mut self) {
+    for c in &self.conditions {
+      c.wakers().remove(self.id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::thread;
+
+  use super::*;
+
+  #[test]
+  fn waitset_wakes_up_on_guard_condition() {
+    let mut waitset = WaitSet::new().unwrap();
+    let guard = GuardCondition::new();
+    let other_guard = GuardCondition::new();
+    waitset.attach_condition(&guard);
+    waitset.attach_condition(&other_guard);
+    waitset.attach_condition(&guard); // no effect
+    assert_eq!(waitset.get_conditions().len(), 2);
+
+    assert!(matches!(
+      waitset.wait(Duration::from_millis(10)),
+      Err(WaitError::Timeout)
+    ));
+
+    let guard_clone = guard.clone();
+    let setter = thread::spawn(move || {
+      thread::sleep(Duration::from_millis(50));
+      guard_clone.set_trigger_value(true);
+    });
+    let active = waitset.wait(Duration::from_secs(5)).unwrap();
+    assert_eq!(active.len(), 1);
+    assert!(active[0].is(&guard));
+    assert!(!active[0].is(&other_guard));
+    setter.join().unwrap();
+
+    assert!(waitset.detach_condition(&guard));
+    assert!(!waitset.detach_condition(&guard));
+    assert!(guard.wakers().wakers.lock().unwrap().is_empty());
+    assert!(waitset.wait(Duration::from_millis(10)).is_err());
+  }
+
+  #[test]
+  fn waitset_wait_async() {
+    let mut waitset = WaitSet::new().unwrap();
+    let guard = GuardCondition::new();
+    waitset.attach_condition(&guard);
+
+    let guard_clone = guard.clone();
+    let setter = thread::spawn(move || {
+      thread::sleep(Duration::from_millis(50));
+      guard_clone.set_trigger_value(true);
+    });
+    let active = futures::executor::block_on(waitset.wait_async());
+    assert!(active[0].is(&guard));
+    setter.join().unwrap();
+  }
+
+  #[test]
+  fn status_condition_uses_enabled_statuses() {
+    let status_changes = Arc::new(StatusChanges::default());
+    let condition = StatusCondition::new(Arc::clone(&status_changes), None);
+    assert!(!condition.get_trigger_value());
+
+    status_changes.status_sent(StatusKind::LivelinessLost);
+    assert_eq!(condition.get_status_changes(), StatusKind::LivelinessLost);
+    assert!(condition.get_trigger_value());
+
+    condition.set_enabled_statuses(StatusKind::PublicationMatched.into());
+    assert!(!condition.get_trigger_value());
+    condition.set_enabled_statuses(StatusKind::LivelinessLost | StatusKind::PublicationMatched);
+    assert!(condition.get_trigger_value());
+
+    status_changes.status_received(StatusKind::LivelinessLost);
+    assert!(!condition.get_trigger_value());
+  }
+}
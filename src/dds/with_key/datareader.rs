@@ -8,16 +8,19 @@ use std::{
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 use futures::stream::{FusedStream, Stream};
+use enumflags2::BitFlags;
 
 use super::datasample_cache::DataSampleCache;
 use crate::{
   dds::{
     adapters::with_key::*,
     key::*,
+    listener::DataReaderListener,
     qos::*,
     readcondition::*,
-    result::ReadResult,
+    result::{QosError, ReadResult},
     statusevents::*,
+    waitset::{DataReaderReadCondition, StatusCondition},
     with_key::{datasample::*, simpledatareader::*},
   },
   discovery::sedp_messages::PublicationBuiltinTopicData,
@@ -79,7 +82,10 @@ where
   DA: DeserializerAdapter<D>,
 {
   pub(crate) fn from_simple_data_reader(simple_data_reader: SimpleDataReader<D, DA>) -> Self {
-    let dsc = DataSampleCache::new(simple_data_reader.qos().clone());
+    let dsc = DataSampleCache::new(
+      simple_data_reader.qos().clone(),
+      simple_data_reader.writer_strengths(),
+    );
 
     Self {
       simple_data_reader,
@@ -96,6 +102,17 @@ where
         .datasample_cache
         .fill_from_deserialized_cache_change(dcc);
     }
+    let now = Timestamp::now();
+    self.datasample_cache.remove_expired_samples(now);
+    self.datasample_cache.release_filtered_samples(now);
+    if let Some(release_time) = self.datasample_cache.next_filtered_release() {
+      self
+        .simple_data_reader
+        .notify_data_available_at(release_time);
+    }
+    if let Some(status) = self.datasample_cache.sample_rejected_status() {
+      self.simple_data_reader.send_status(status);
+    }
     Ok(())
   }
 
@@ -103,7 +120,10 @@ where
     self.simple_data_reader.drain_read_notifications();
   }
 
-  fn select_keys_for_access(&self, read_condition: ReadCondition) -> Vec<(Timestamp, D::K)> {
+  fn select_keys_for_access(
+    &self,
+    read_condition: impl SampleCondition<D>,
+  ) -> Vec<(Timestamp, D::K)> {
     self.datasample_cache.select_keys_for_access(read_condition)
   }
 
@@ -118,7 +138,7 @@ where
   fn select_instance_keys_for_access(
     &self,
     instance: &D::K,
-    rc: ReadCondition,
+    rc: impl SampleCondition<D>,
   ) -> Vec<(Timestamp, D::K)> {
     self
       .datasample_cache
@@ -131,7 +151,8 @@ where
   /// # Arguments
   ///
   /// * `max_samples` - Limits maximum amount of samples read
-  /// * `read_condition` - Limits results by condition
+  /// * `read_condition` - Limits results by condition. This is either a
+  ///   [`ReadCondition`] or a reference to a [`QueryCondition`].
   ///
   /// # Examples
   ///
@@ -171,7 +192,7 @@ where
   pub fn read(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<Vec<DataSample<&D>>> {
     // Clear notification buffer. This must be done first to avoid race conditions.
     self.drain_read_notifications();
@@ -191,7 +212,8 @@ where
   /// # Arguments
   ///
   /// * `max_samples` - Limits maximum amount of samples read
-  /// * `read_condition` - Limits results by condition
+  /// * `read_condition` - Limits results by condition. This is either a
+  ///   [`ReadCondition`] or a reference to a [`QueryCondition`].
   ///
   /// # Examples
   ///
@@ -230,7 +252,7 @@ where
   pub fn take(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<Vec<DataSample<D>>> {
     // Clear notification buffer. This must be done first to avoid race conditions.
     self.drain_read_notifications();
@@ -329,7 +351,7 @@ where
   fn read_bare(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<Vec<Sample<&D, D::K>>> {
     self.drain_read_notifications();
     self.fill_and_lock_local_datasample_cache()?;
@@ -345,7 +367,7 @@ where
   fn take_bare(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<Vec<Sample<D, D::K>>> {
     // Clear notification buffer. This must be done first to avoid race conditions.
     self.drain_read_notifications();
@@ -444,7 +466,7 @@ where
   /// ```
   pub fn conditional_iterator(
     &mut self,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<impl Iterator<Item = Sample<&D, D::K>>> {
     // TODO: We could come up with a more efficient implementation than wrapping a
     // read call
@@ -539,7 +561,7 @@ where
   /// ```
   pub fn into_conditional_iterator(
     &mut self,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
   ) -> ReadResult<impl Iterator<Item = Sample<D, D::K>>> {
     // TODO: We could come up with a more efficient implementation than wrapping a
     // take call
@@ -610,7 +632,7 @@ where
   pub fn read_instance(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
     // Select only samples from instance specified by key. In case of None, select the
     // "smallest" instance as specified by the key type Ord trait.
     instance_key: Option<<D as Keyed>::K>,
@@ -677,7 +699,7 @@ where
   pub fn take_instance(
     &mut self,
     max_samples: usize,
-    read_condition: ReadCondition,
+    read_condition: impl SampleCondition<D>,
     // Select only samples from instance specified by key. In case of None, select the
     // "smallest" instance as specified by the key type Ord trait.
     instance_key: Option<<D as Keyed>::K>,
@@ -703,11 +725,272 @@ where
     Ok(result)
   }
 
+  /// Gets the number of samples discarded by the
+  /// [`TimeBasedFilter`](crate::policy::TimeBasedFilter) QoS policy.
+  ///
+  /// When samples of an instance arrive faster than `minimum_separation`
+  /// allows, only the latest one is kept and delivered when the separation has
+  /// elapsed. The others are discarded. The change count is relative to the
+  /// previous call of this method.
+  ///
+  /// This is not a standard DDS status.
+  pub fn get_time_based_filter_status(&mut self) -> ReadResult<CountWithChange> {
+    self.fill_and_lock_local_datasample_cache()?;
+    Ok(self.datasample_cache.filtered_sample_count())
+  }
+
+  /// Waits until the historical data of all matched DataWriters has been
+  /// received, or until `max_wait` has passed. Historical data of a DataWriter
+  /// is what it had written before it was matched with this DataReader.
+  ///
+  /// This applies to Reliable DataReaders with `TransientLocal` or stronger
+  /// [`Durability`](crate::policy::Durability). Others return `Ok(true)`
+  /// immediately.
+  ///
   /// Return values:
   /// true - got all historical data
   /// false - timeout before all historical data was received
-  pub fn wait_for_historical_data(&mut self, _max_wait: Duration) -> bool {
-    todo!()
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.5.2.26 wait_for_historical_data.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new()
+  ///   .reliability(policy::Reliability::Reliable {
+  ///     max_blocking_time: rustdds::Duration::ZERO,
+  ///   })
+  ///   .durability(policy::Durability::TransientLocal)
+  ///   .build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// // No DataWriters are matched, so there is nothing to wait for.
+  /// assert!(data_reader.wait_for_historical_data(rustdds::Duration::from_secs(1)).unwrap());
+  /// ```
+  pub fn wait_for_historical_data(&mut self, max_wait: Duration) -> ReadResult<bool> {
+    self.simple_data_reader.wait_for_historical_data(max_wait)
+  }
+
+  /// Like the synchronous version.
+  /// But there is no timeout. Use asyncs to bring your own timeout.
+  pub async fn async_wait_for_historical_data(&mut self) -> ReadResult<bool> {
+    self
+      .simple_data_reader
+      .async_wait_for_historical_data()
+      .await
+  }
+
+  /// Enables or disables [`DataReaderStatus::DataAvailable`] events in the
+  /// status stream of this DataReader. They are disabled by default.
+  ///
+  /// An event is sent when new data arrives, but only once until the
+  /// DataReader reads again. See also
+  /// [`Subscriber::status_listener`](crate::Subscriber::status_listener).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_reader.set_data_available_status(true);
+  /// ```
+  pub fn set_data_available_status(&self, enabled: bool) {
+    self.simple_data_reader.set_data_available_status(enabled);
+  }
+
+  /// Gets the StatusCondition of this DataReader, for waiting on status
+  /// changes in a [`WaitSet`](crate::WaitSet).
+  ///
+  /// DATA_AVAILABLE is a pending status change from the arrival of new data
+  /// until the next read or take, regardless of
+  /// [`set_data_available_status`](Self::set_data_available_status).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status_condition = data_reader.get_statuscondition();
+  /// status_condition.set_enabled_statuses(StatusKind::DataAvailable | StatusKind::SubscriptionMatched);
+  ///
+  /// let mut waitset = WaitSet::new().unwrap();
+  /// waitset.attach_condition(&status_condition);
+  /// // Waits until something happens, or times out
+  /// let _ = waitset.wait(std::time::Duration::from_millis(10));
+  /// ```
+  pub fn get_statuscondition(&self) -> StatusCondition {
+    self.simple_data_reader.status_condition().clone()
+  }
+
+  /// Sets a listener, which is called when the statuses in `mask` change in
+  /// this DataReader. Statuses not in `mask` are passed on to the listener of
+  /// the Subscriber, and then the DomainParticipant. Setting `None` removes
+  /// the listener.
+  ///
+  /// [`on_data_available`](DataReaderListener::on_data_available) is called
+  /// when new data arrives after the application has read all previous data.
+  /// It is not called if the Subscriber or DomainParticipant listener
+  /// handles [`StatusKind::DataOnReaders`].
+  ///
+  /// The listener is called from a thread managed by RustDDS, so it cannot
+  /// read this DataReader directly. Use e.g. a channel to signal the thread
+  /// that owns the DataReader.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use std::sync::{mpsc, Arc, Mutex};
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// struct DataSignal(Mutex<mpsc::Sender<()>>);
+  /// impl DataReaderListener for DataSignal {
+  ///   fn on_data_available(&self, _reader: GUID) {
+  ///     let _ = self.0.lock().unwrap().send(());
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let (sender, receiver) = mpsc::channel();
+  /// data_reader.set_listener(
+  ///   Some(Arc::new(DataSignal(Mutex::new(sender)))),
+  ///   StatusKind::DataAvailable.into(),
+  /// );
+  /// // Reads when there is new data, or times out
+  /// if receiver.recv_timeout(std::time::Duration::from_millis(10)).is_ok() {
+  ///   // read data_reader
+  /// }
+  /// ```
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn DataReaderListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self.simple_data_reader.set_listener(listener, mask);
+  }
+
+  /// Creates a Condition that triggers when this DataReader has samples
+  /// matching `read_condition`, for use in a [`WaitSet`](crate::WaitSet).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let not_read = data_reader.create_readcondition(ReadCondition::not_read());
+  /// let mut waitset = WaitSet::new().unwrap();
+  /// waitset.attach_condition(&not_read);
+  ///
+  /// if let Ok(active) = waitset.wait(std::time::Duration::from_millis(10)) {
+  ///   if active.iter().any(|c| c.is(&not_read)) {
+  ///     let samples = data_reader.take(10, ReadCondition::not_read());
+  ///   }
+  /// }
+  /// ```
+  pub fn create_readcondition(&mut self, read_condition: ReadCondition) -> DataReaderReadCondition {
+    let condition = DataReaderReadCondition::new(
+      read_condition,
+      Arc::clone(self.simple_data_reader.data_availability()),
+    );
+    self.datasample_cache.add_read_condition(condition.state());
+    condition
   }
 
   // Spec calls for two separate functions:
@@ -718,9 +1001,43 @@ where
   // only thing that could be done with the handles would be counting how many
   // we got.
 
+  /// Gets the discovery data of the DataWriters currently matched with this
+  /// DataReader.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.5.3.33 get_matched_publications and
+  /// 2.2.2.5.3.34 get_matched_publication_data.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// for publication in data_reader.get_matched_publications() {
+  ///   println!("Matched writer {:?} of participant {:?}", publication.key, publication.participant_key);
+  /// }
+  /// ```
   pub fn get_matched_publications(&self) -> impl Iterator<Item = PublicationBuiltinTopicData> {
-    // TODO: Obviously not implemented
-    vec![].into_iter()
+    self.simple_data_reader.matched_publications().into_iter()
   }
 
   /// An async stream for reading the (bare) data samples.
@@ -846,6 +1163,61 @@ where
   }
 }
 
+/// Changes the QoS of the DataReader. The policies defined in `new_qos` replace
+/// the current ones, and the rest are left unchanged.
+///
+/// Only Deadline, LatencyBudget, Ownership strength, TimeBasedFilter, and
+/// Lifespan can be changed. Attempting to change other policies results in
+/// [`QosError::ImmutablePolicy`](crate::dds::result::QosError::ImmutablePolicy),
+/// and nothing is changed.
+///
+/// The new QoS is announced to remote DataWriters via Discovery, and matches
+/// to DataWriters are re-evaluated, because they may have become compatible or
+/// incompatible.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::{Serialize, Deserialize};
+/// # use rustdds::*;
+/// use rustdds::qos::{policy::TimeBasedFilter, MutQosPolicy};
+///
+/// let domain_participant = DomainParticipant::new(0).unwrap();
+/// let qos = QosPolicyBuilder::new().build();
+/// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct SomeType { a: i32 }
+/// impl Keyed for SomeType {
+///   type K = i32;
+///
+///   fn key(&self) -> Self::K {
+///     self.a
+///   }
+/// }
+///
+/// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+/// let mut data_reader = subscriber.create_datareader_cdr::<SomeType>(&topic, None).unwrap();
+///
+/// let filter = QosPolicyBuilder::new()
+///   .time_based_filter(TimeBasedFilter { minimum_separation: Duration::from_millis(100) })
+///   .build();
+/// data_reader.set_qos(&filter).unwrap();
+/// ```
+impl<D, DA> MutQosPolicy for DataReader<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn set_qos(&mut self, new_qos: &QosPolicies) -> Result<(), QosError> {
+    self.simple_data_reader.set_qos(new_qos)?;
+    self
+      .datasample_cache
+      .set_qos(self.simple_data_reader.qos().clone());
+    Ok(())
+  }
+}
+
 impl<D, DA> RTPSEntity for DataReader<D, DA>
 where
   D: Keyed + 'static,
@@ -1000,6 +1372,10 @@ mod tests {
     messages::submessages::{
       elements::serialized_payload::SerializedPayload, submessage_flag::*, submessages::Data,
     },
+    discovery::{
+      discovery_db::{discovery_db_read, discovery_db_write},
+      sedp_messages::DiscoveredWriterData,
+    },
     mio_source,
     network::udp_sender::UDPSender,
     rtps::{
@@ -1008,13 +1384,73 @@ mod tests {
     },
     serialization::cdr_serializer::to_bytes,
     structure::{
+      dds_cache::TopicCache,
       guid::{EntityId, EntityKind, GuidPrefix},
       sequence_number::SequenceNumber,
     },
-    test::random_data::*,
+    test::{random_data::*, test_data::*},
     RepresentationIdentifier,
   };
 
+  // The ends of a test Reader's channels, which would belong to the DataReader.
+  struct ReaderHandles {
+    status_receiver: StatusChannelReceiver<DataReaderStatus>,
+    _command_sender: mio_channel::SyncSender<ReaderCommand>,
+    _notification_receiver: mio_channel::Receiver<()>,
+    _notification_event_source: mio_source::PollEventSource,
+    _participant_status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
+  }
+
+  // An RTPS Reader that feeds the DataReader. It shares the ownership strengths,
+  // matched writers and data availability of the DataReader.
+  fn test_reader(
+    topic_cache: Arc<Mutex<TopicCache>>,
+    datareader: &DataReader<RandomData, CDRDeserializerAdapter<RandomData>>,
+    qos_policy: QosPolicies,
+  ) -> (Reader, ReaderHandles) {
+    let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let (status_sender, status_receiver) = sync_status_channel::<DataReaderStatus>(16).unwrap();
+    let (participant_status_sender, participant_status_receiver) = sync_status_channel(16).unwrap();
+    let (command_sender, reader_command_receiver) = mio_channel::sync_channel::<ReaderCommand>(10);
+
+    let topic_name = topic_cache.lock().unwrap().topic_name();
+    let reader_ing = ReaderIngredients {
+      guid: datareader.guid(),
+      notification_sender,
+      status_sender,
+      topic_name,
+      topic_cache_handle: topic_cache,
+      like_stateless: false,
+      qos_policy,
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender: notification_event_sender,
+      writer_strengths: datareader.simple_data_reader.writer_strengths(),
+      matched_writer_guids: datareader.simple_data_reader.matched_writer_guids(),
+      coherent_sets: None,
+      data_availability: Arc::clone(datareader.simple_data_reader.data_availability()),
+      subscriber_status_sender: Arc::default(),
+      security_plugins: None,
+    };
+    let reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    let handles = ReaderHandles {
+      status_receiver,
+      _command_sender: command_sender,
+      _notification_receiver: notification_receiver,
+      _notification_event_source: notification_event_source,
+      _participant_status_receiver: participant_status_receiver,
+    };
+    (reader, handles)
+  }
+
   #[test]
   fn read_and_take() {
     // Test the read and take methods of the DataReader
@@ -1065,8 +1501,14 @@ mod tests {
       like_stateless: false,
       qos_policy: QosPolicies::qos_none(),
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
 
@@ -1230,8 +1672,14 @@ mod tests {
       like_stateless: false,
       qos_policy: QosPolicies::qos_none(),
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
 
@@ -1400,4 +1848,401 @@ mod tests {
     assert!(results.is_ok());
     assert!(results.unwrap().is_empty());
   }
+
+  #[test]
+  fn read_content_filtered() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr filtered".to_string(),
+        "filter test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let filtered_topic = dp
+      .create_content_filtered_topic(
+        "dr filtered cft".to_string(),
+        &topic,
+        "a > %0 AND b LIKE '%)'",
+        vec!["10".to_string()],
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&filtered_topic, None)
+      .unwrap();
+    let (mut reader, _handles) = test_reader(topic_cache, &datareader, QosPolicies::qos_none());
+
+    // The filter is announced in Discovery
+    let content_filter = |dp: &DomainParticipant, guid| {
+      discovery_db_read(&dp.discovery_db())
+        .get_local_topic_reader(guid)
+        .and_then(|drd| drd.content_filter.clone())
+        .unwrap()
+    };
+    let announced = content_filter(&dp, datareader.guid());
+    assert_eq!(announced.related_topic_name, topic.name());
+    assert_eq!(announced.expression_parameters, vec!["10".to_string()]);
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &QosPolicies::qos_none(),
+    );
+
+    let mut feed = |sn: i64, data: &RandomData| {
+      let data_msg = Data {
+        reader_id: reader.entity_id(),
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::from(sn),
+        serialized_payload: Some(
+          SerializedPayload {
+            representation_identifier: RepresentationIdentifier::CDR_LE,
+            representation_options: [0, 0],
+            value: Bytes::from(to_bytes::<RandomData, LittleEndian>(data).unwrap()),
+          }
+          .into(),
+        ),
+        ..Data::default()
+      };
+      reader.handle_data_msg(
+        data_msg,
+        DATA_Flags::Endianness | DATA_Flags::Data,
+        &mr_state,
+      );
+    };
+
+    let data = |a, b: &str| RandomData {
+      a,
+      b: b.to_string(),
+    };
+    feed(1, &data(10, ":)"));
+    feed(2, &data(11, ":("));
+    feed(3, &data(12, ":)"));
+
+    let values: Vec<RandomData> = datareader
+      .take(100, ReadCondition::any())
+      .unwrap()
+      .into_iter()
+      .filter_map(|s| s.into_value().value())
+      .collect();
+    assert_eq!(values, vec![data(12, ":)")]);
+
+    // Parameter change applies to subsequent samples
+    filtered_topic
+      .set_expression_parameters(vec!["0".to_string()])
+      .unwrap();
+    assert!(filtered_topic.set_expression_parameters(vec![]).is_err());
+    feed(4, &data(5, ":)"));
+
+    let values: Vec<RandomData> = datareader
+      .take(100, ReadCondition::any())
+      .unwrap()
+      .into_iter()
+      .filter_map(|s| s.into_value().value())
+      .collect();
+    assert_eq!(values, vec![data(5, ":)")]);
+
+    let announced = content_filter(&dp, datareader.guid());
+    assert_eq!(announced.expression_parameters, vec!["0".to_string()]);
+  }
+
+  #[test]
+  fn matched_publications() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr matched".to_string(),
+        "matched test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    assert_eq!(datareader.get_matched_publications().count(), 0);
+
+    // An RTPS Reader sharing the matched writer bookkeeping with the DataReader
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+    let (mut reader, _handles) = test_reader(topic_cache, &datareader, QosPolicies::qos_none());
+
+    // A remote writer is discovered and matched.
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mut writer_data = DiscoveredWriterData {
+      last_updated: std::time::Instant::now(),
+      writer_proxy: writer_proxy_data().unwrap(),
+      publication_topic_data: publication_builtin_topic_data().unwrap(),
+    };
+    writer_data.writer_proxy.remote_writer_guid = writer_guid;
+    writer_data.publication_topic_data.key = writer_guid;
+    writer_data.publication_topic_data.topic_name = topic.name();
+    discovery_db_write(&dp.discovery_db()).update_publication(&writer_data);
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &qos);
+
+    let matched: Vec<_> = datareader.get_matched_publications().collect();
+    assert_eq!(matched, vec![writer_data.publication_topic_data.clone()]);
+
+    // A writer that no longer competes for ownership, e.g. because its
+    // liveliness was lost, is still matched.
+    datareader
+      .simple_data_reader
+      .writer_strengths()
+      .lock()
+      .unwrap()
+      .remove(&writer_guid);
+    assert_eq!(datareader.get_matched_publications().count(), 1);
+
+    // ... and lost.
+    reader.remove_writer_proxy(writer_guid);
+    assert_eq!(datareader.get_matched_publications().count(), 0);
+  }
+
+  #[test]
+  fn exclusive_owner_loses_liveliness() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicyBuilder::new()
+      .ownership(policy::Ownership::Exclusive { strength: 0 })
+      .history(policy::History::KeepAll)
+      .build();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "exclusive_liveliness".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(
+        &topic,
+        Some(qos.clone()),
+      )
+      .unwrap();
+
+    let (mut reader, _handles) = test_reader(topic_cache, &datareader, qos.clone());
+
+    // A strong writer with a lease and a weak writer, both writing instance 1
+    let lease_duration = Duration::from_secs(1);
+    let writer = |n: u8, strength: i32| {
+      let guid = GUID {
+        prefix: GuidPrefix::new(&[n; 12]),
+        entity_id: EntityId::create_custom_entity_id(
+          [n; 3],
+          EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+        ),
+      };
+      let offered_qos = QosPolicyBuilder::new()
+        .ownership(policy::Ownership::Exclusive { strength })
+        .liveliness(policy::Liveliness::ManualByTopic { lease_duration })
+        .build();
+      (guid, offered_qos)
+    };
+    let (strong, strong_qos) = writer(1, 10);
+    let (weak, weak_qos) = writer(2, 1);
+    reader.matched_writer_add(strong, EntityId::UNKNOWN, vec![], vec![], &strong_qos);
+    reader.matched_writer_add(weak, EntityId::UNKNOWN, vec![], vec![], &weak_qos);
+
+    let write = |reader: &mut Reader, writer_guid: GUID, sn: i64, b: &str| {
+      let data_msg = Data {
+        reader_id: reader.entity_id(),
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::from(sn),
+        serialized_payload: Some(
+          SerializedPayload {
+            representation_identifier: RepresentationIdentifier::CDR_LE,
+            representation_options: [0, 0],
+            value: Bytes::from(
+              to_bytes::<RandomData, LittleEndian>(&RandomData {
+                a: 1,
+                b: b.to_string(),
+              })
+              .unwrap(),
+            ),
+          }
+          .into(),
+        ),
+        ..Data::default()
+      };
+      let mr_state = MessageReceiverState {
+        source_guid_prefix: writer_guid.prefix,
+        ..Default::default()
+      };
+      reader.handle_data_msg(
+        data_msg,
+        DATA_Flags::Endianness | DATA_Flags::Data,
+        &mr_state,
+      );
+    };
+    let taken = |datareader: &mut DataReader<RandomData, CDRDeserializerAdapter<RandomData>>| {
+      datareader
+        .take(100, ReadCondition::any())
+        .unwrap()
+        .into_iter()
+        .map(|sample| sample.into_value().unwrap().b)
+        .collect::<Vec<String>>()
+    };
+
+    write(&mut reader, strong, 1, "strong");
+    write(&mut reader, weak, 1, "weak");
+    assert_eq!(taken(&mut datareader), vec!["strong".to_string()]);
+
+    // The lease of the owner expires. Its strength is no longer available for
+    // arbitration, so the weaker writer takes over.
+    reader.handle_liveliness_check(Timestamp::now() + Duration::from_secs(2));
+    assert!(!datareader
+      .simple_data_reader
+      .writer_strengths()
+      .lock()
+      .unwrap()
+      .contains_key(&strong));
+    write(&mut reader, weak, 2, "weak owner");
+    assert_eq!(taken(&mut datareader), vec!["weak owner".to_string()]);
+
+    // Writing asserts the liveliness of the strong writer, which gets the
+    // instance back.
+    write(&mut reader, strong, 2, "strong again");
+    write(&mut reader, weak, 3, "weak again");
+    assert_eq!(taken(&mut datareader), vec!["strong again".to_string()]);
+  }
+
+  #[test]
+  fn sample_stream_resets_data_available() {
+    use futures::StreamExt;
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "stream_data_available".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+    let datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    datareader
+      .simple_data_reader
+      .data_availability()
+      .set_status_enabled(true);
+    let (mut reader, handles) = test_reader(topic_cache, &datareader, qos.clone());
+    let status_receiver = &handles.status_receiver;
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &qos);
+
+    let mut write = |sn: i64| {
+      let data_msg = Data {
+        reader_id: reader.entity_id(),
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::from(sn),
+        serialized_payload: Some(
+          SerializedPayload {
+            representation_identifier: RepresentationIdentifier::CDR_LE,
+            representation_options: [0, 0],
+            value: Bytes::from(
+              to_bytes::<RandomData, LittleEndian>(&RandomData {
+                a: sn,
+                b: "stream".to_string(),
+              })
+              .unwrap(),
+            ),
+          }
+          .into(),
+        ),
+        ..Data::default()
+      };
+      reader.handle_data_msg(
+        data_msg,
+        DATA_Flags::Endianness | DATA_Flags::Data,
+        &mr_state,
+      );
+    };
+    let data_available_count = || {
+      std::iter::from_fn(|| status_receiver.try_recv().ok())
+        .filter(|status| matches!(status, DataReaderStatus::DataAvailable))
+        .count()
+    };
+
+    let mut stream = datareader.simple_data_reader.as_async_stream();
+    let mut take_value = || match futures::executor::block_on(stream.next()) {
+      Some(Ok(DeserializedCacheChange {
+        sample: Sample::Value(data),
+        ..
+      })) => data.a,
+      _ => panic!("Expected a sample from the stream"),
+    };
+
+    write(1);
+    assert_eq!(data_available_count(), 1);
+    assert_eq!(take_value(), 1);
+
+    // Taking from the stream has reset the status, so new data is reported
+    // again.
+    write(2);
+    assert_eq!(data_available_count(), 1);
+    assert_eq!(take_value(), 2);
+  }
 }
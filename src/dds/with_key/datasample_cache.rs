@@ -1,20 +1,28 @@
 use std::{
   collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
   ops::Bound,
+  sync::{Arc, Weak},
 };
 
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
+use enumflags2::BitFlags;
 
 use crate::{
   dds::{
     key::Keyed,
     qos::{policy, QosPolicies},
-    readcondition::ReadCondition,
+    readcondition::{ReadCondition, SampleCondition},
     sampleinfo::*,
+    statusevents::{CountWithChange, DataReaderStatus, SampleRejectedStatusKind},
+    waitset::ReadConditionState,
     with_key::datasample::{DataSample, DeserializedCacheChange, Sample},
   },
-  structure::{guid::GUID, sequence_number::SequenceNumber, time::Timestamp},
+  rtps::reader::WriterStrengths,
+  structure::{
+    cache_change::ChangeKind, duration::Duration, guid::GUID, sequence_number::SequenceNumber,
+    time::Timestamp,
+  },
   with_key::WriteOptions,
 };
 
@@ -32,6 +40,27 @@ pub struct DataSampleCache<D: Keyed> {
   datasamples: BTreeMap<Timestamp, SampleWithMetaData<D>>, /* ordered storage for deserialized
                                                             * samples */
   pub(crate) instance_map: BTreeMap<D::K, InstanceMetaData>, // ordered storage for instances
+  // Number of instances in instance_map that take up resources. See
+  // InstanceMetaData::takes_resources.
+  live_instance_count: usize,
+  writer_strengths: WriterStrengths, // for EXCLUSIVE ownership
+  // Latest value of each instance held back by TIME_BASED_FILTER, waiting to be
+  // delivered when minimum_separation has elapsed.
+  filtered_samples: BTreeMap<D::K, FilteredSample<D>>,
+  filtered_sample_count: i32, // samples discarded by TIME_BASED_FILTER
+  filtered_sample_count_reported: i32,
+  // Release time of held back samples that the DataReader has been notified
+  // about.
+  filtered_release_notified: Option<Timestamp>,
+  // Samples rejected due to RESOURCE_LIMITS. The RTPS Reader has acknowledged
+  // these already, so a rejection is final: a reliable Writer will not resend
+  // them.
+  rejected_sample_count: i32,
+  rejected_sample_count_reported: i32,
+  last_rejected_reason: SampleRejectedStatusKind,
+  // ReadConditions created for the DataReader. These are updated whenever the
+  // samples or their states change.
+  read_conditions: Vec<Weak<ReadConditionState>>,
 }
 
 pub(crate) struct InstanceMetaData {
@@ -39,8 +68,79 @@ pub(crate) struct InstanceMetaData {
   instance_state: InstanceState,         // latest known alive/not_alive state for this instance
   latest_generation_available: NotAliveGenerationCounts, // in this instance
   last_generation_accessed: NotAliveGenerationCounts, // in this instance
+  owner: Option<InstanceOwner>,          // with EXCLUSIVE ownership only
+  // Source timestamp and writer of the latest accepted sample. Only with
+  // BY_SOURCE_TIMESTAMP destination order.
+  latest_source_timestamp: Option<(Timestamp, GUID)>,
+  // Receive time of the latest value delivered to the application. Only with
+  // TIME_BASED_FILTER.
+  latest_delivery: Option<Timestamp>,
+}
+
+struct InstanceOwner {
+  writer_guid: GUID,
+  latest_sample: Timestamp, // receive time of owner's latest sample to the instance
+}
+
+impl InstanceMetaData {
+  // An instance takes up resources while it is alive or has samples.
+  fn takes_resources(&self) -> bool {
+    self.instance_state == InstanceState::Alive || !self.instance_samples.is_empty()
+  }
+
+  // EXCLUSIVE ownership arbitration. Decides if the writer may update this
+  // instance, and updates the owner. The owner is the strongest live writer.
+  // Ownership changes if a stronger writer appears, or if the owner loses
+  // liveliness or misses its deadline on this instance.
+  // Ties in strength are broken by GUID, so that all Readers pick the same owner.
+  fn arbitrate_ownership(
+    &mut self,
+    writer_guid: GUID,
+    receive_timestamp: Timestamp,
+    writer_strengths: &BTreeMap<GUID, i32>,
+    deadline: Option<Duration>,
+  ) -> bool {
+    let is_owner = match &self.owner {
+      None => true,
+      Some(owner) if owner.writer_guid == writer_guid => true,
+      Some(owner) => match writer_strengths.get(&owner.writer_guid) {
+        // Owner has lost its liveliness or is no longer matched.
+        None => true,
+        Some(_)
+          if deadline
+            .is_some_and(|d| receive_timestamp.duration_since(owner.latest_sample) > d) =>
+        {
+          true
+        }
+        // An unknown writer cannot take ownership from a live owner.
+        Some(owner_strength) => writer_strengths
+          .get(&writer_guid)
+          .is_some_and(|strength| (strength, owner.writer_guid) > (owner_strength, writer_guid)),
+      },
+    };
+    if is_owner {
+      self.owner = Some(InstanceOwner {
+        writer_guid,
+        latest_sample: receive_timestamp,
+      });
+    }
+    is_owner
+  }
+}
+
+// Keeps count of instances that take up resources, when an instance that
+// did (or did not) take resources changes.
+fn update_live_instance_count(live_instance_count: &mut usize, was_live: bool, is_live: bool) {
+  match (was_live, is_live) {
+    (false, true) => *live_instance_count += 1,
+    (true, false) => *live_instance_count -= 1,
+    _ => (),
+  }
 }
 
+// See DataSampleCache::present_states
+type PresentStates = [[BitFlags<InstanceState>; 2]; 2];
+
 struct SampleWithMetaData<D: Keyed> {
   // a snapshot of the instance-wide counts
   // at the time this sample was received.
@@ -66,17 +166,41 @@ where
   }
 }
 
+// A sample that has passed all other checks, but is held back by
+// TIME_BASED_FILTER.
+struct FilteredSample<D: Keyed> {
+  sample: Sample<D, D::K>,
+  writer_guid: GUID,
+  sequence_number: SequenceNumber,
+  receive_timestamp: Timestamp,
+  write_options: WriteOptions,
+}
+
 impl<D> DataSampleCache<D>
 where
   D: Keyed,
 {
-  pub fn new(qos: QosPolicies) -> Self {
+  pub fn new(qos: QosPolicies, writer_strengths: WriterStrengths) -> Self {
     Self {
       qos,
       datasamples: BTreeMap::new(),
       instance_map: BTreeMap::new(),
+      live_instance_count: 0,
+      writer_strengths,
+      filtered_samples: BTreeMap::new(),
+      filtered_sample_count: 0,
+      filtered_sample_count_reported: 0,
+      filtered_release_notified: None,
+      rejected_sample_count: 0,
+      rejected_sample_count_reported: 0,
+      last_rejected_reason: SampleRejectedStatusKind::NotRejected,
+      read_conditions: Vec::new(),
     }
   }
+  // The DataReader QoS has been changed by the application.
+  pub fn set_qos(&mut self, qos: QosPolicies) {
+    self.qos = qos;
+  }
 
   pub(crate) fn fill_from_deserialized_cache_change(
     &mut self,
@@ -90,6 +214,7 @@ where
       deserialized_cc.sequence_number,
       deserialized_cc.receive_instant,
       deserialized_cc.write_options,
+      deserialized_cc.change_kind,
     );
   }
 
@@ -100,29 +225,35 @@ where
     sequence_number: SequenceNumber,
     receive_timestamp: Timestamp,
     write_options: WriteOptions,
+    change_kind: ChangeKind,
   ) {
     let instance_key = match &new_sample {
       Sample::Value(d) => d.key(),
       Sample::Dispose(k) => k.clone(),
     };
 
-    let new_instance_state = match new_sample {
-      Sample::Value(_) => InstanceState::Alive,
-      Sample::Dispose(_) => InstanceState::NotAliveDisposed,
-    };
+    // A held back sample of this instance may have become deliverable before
+    // this one arrived.
+    self.release_filtered_sample(&instance_key, receive_timestamp);
+    let minimum_separation = self.minimum_separation();
 
     // find or create metadata record
+    let new_instance = !self.instance_map.contains_key(&instance_key);
     let instance_metadata = if let Some(imd) = self.instance_map.get_mut(&instance_key) {
       imd
     } else {
       // not found, create new one.
       let imd = InstanceMetaData {
         instance_samples: BTreeSet::new(),
-        instance_state: new_instance_state,
+        instance_state: instance_state_after(&new_sample, change_kind),
         latest_generation_available: NotAliveGenerationCounts::zero(), /* this is new instance,
                                                                         * so start from zero */
         last_generation_accessed: NotAliveGenerationCounts::sub_zero(), // never accessed
+        owner: None,
+        latest_source_timestamp: None,
+        latest_delivery: None,
       };
+      update_live_instance_count(&mut self.live_instance_count, false, imd.takes_resources());
       self.instance_map.insert(instance_key.clone(), imd);
       self
         .instance_map
@@ -131,6 +262,137 @@ where
         .unwrap()
     };
 
+    // With BY_SOURCE_TIMESTAMP destination order, samples older than the latest
+    // one in the instance are dropped. DDS spec v1.4 Section 2.2.3.17. Then
+    // all Readers end up in the same instance state, no matter in which order
+    // the samples arrived. Same timestamps are ordered by writer GUID.
+    let by_source_timestamp =
+      self.qos.destination_order() == Some(policy::DestinationOrder::BySourceTimeStamp);
+    let source_timestamp = (
+      write_options
+        .source_timestamp()
+        .unwrap_or(receive_timestamp),
+      writer_guid,
+    );
+    if by_source_timestamp
+      && instance_metadata
+        .latest_source_timestamp
+        .is_some_and(|latest| source_timestamp < latest)
+    {
+      debug!(
+        "Dropping sample {:?} from {:?}, because it is older than the latest in instance.",
+        sequence_number, writer_guid
+      );
+      return;
+    }
+
+    // With EXCLUSIVE ownership, only the owner of the instance is heard.
+    if let Some(policy::Ownership::Exclusive { .. }) = self.qos.ownership() {
+      let deadline = self.qos.deadline().map(|policy::Deadline(d)| d);
+      let writer_strengths = self.writer_strengths.lock().unwrap();
+      if !instance_metadata.arbitrate_ownership(
+        writer_guid,
+        receive_timestamp,
+        &writer_strengths,
+        deadline,
+      ) {
+        debug!(
+          "Dropping sample {:?} from {:?}, because it does not own the instance.",
+          sequence_number, writer_guid
+        );
+        return;
+      }
+    }
+
+    // With TIME_BASED_FILTER, a value is delivered only if minimum_separation
+    // has elapsed since the previous delivered value of the instance. Otherwise,
+    // it is held back, replacing any previously held back value, and delivered
+    // when the separation has elapsed. Disposes are never filtered, but they
+    // supersede any held back value.
+    let is_value = matches!(new_sample, Sample::Value(_));
+    if let Some(minimum_separation) = minimum_separation {
+      let too_soon = instance_metadata
+        .latest_delivery
+        .is_some_and(|latest| receive_timestamp < latest + minimum_separation);
+      if is_value && too_soon {
+        let filtered_sample = FilteredSample {
+          sample: new_sample,
+          writer_guid,
+          sequence_number,
+          receive_timestamp,
+          write_options,
+        };
+        if self
+          .filtered_samples
+          .insert(instance_key, filtered_sample)
+          .is_some()
+        {
+          self.filtered_sample_count += 1;
+        }
+        return;
+      }
+      if self.filtered_samples.remove(&instance_key).is_some() {
+        self.filtered_sample_count += 1;
+      }
+    }
+
+    // Only samples that would be stored count against RESOURCE_LIMITS. Disposes
+    // are never rejected, so that the instance state is always up to date.
+    if is_value && self.reject_over_limits(&instance_key, new_instance, sequence_number) {
+      if new_instance {
+        if let Some(imd) = self.instance_map.remove(&instance_key) {
+          update_live_instance_count(&mut self.live_instance_count, imd.takes_resources(), false);
+        }
+      }
+      return;
+    }
+
+    let instance_metadata = self
+      .instance_map
+      .get_mut(&instance_key)
+      // created above, if it did not exist
+      .unwrap();
+    if by_source_timestamp {
+      instance_metadata.latest_source_timestamp = Some(source_timestamp);
+    }
+    if is_value && minimum_separation.is_some() {
+      instance_metadata.latest_delivery = Some(receive_timestamp);
+    }
+
+    self.insert_sample(
+      &instance_key,
+      new_sample,
+      writer_guid,
+      sequence_number,
+      receive_timestamp,
+      write_options,
+      change_kind,
+    );
+  }
+
+  // Stores an accepted sample and updates the instance state accordingly.
+  #[allow(clippy::too_many_arguments)]
+  fn insert_sample(
+    &mut self,
+    instance_key: &D::K,
+    new_sample: Sample<D, D::K>,
+    writer_guid: GUID,
+    sequence_number: SequenceNumber,
+    receive_timestamp: Timestamp,
+    write_options: WriteOptions,
+    change_kind: ChangeKind,
+  ) {
+    let mut new_instance_state = instance_state_after(&new_sample, change_kind);
+    let keep_last_depth = self.keep_last_depth();
+    let instance_metadata = self
+      .instance_map
+      .get_mut(instance_key)
+      // Instances are never removed, and samples are inserted only after the
+      // instance has been created.
+      .unwrap();
+
+    let was_live = instance_metadata.takes_resources();
+
     // update instance metadata
     instance_metadata.instance_samples.insert(receive_timestamp);
 
@@ -145,6 +407,11 @@ where
           .disposed_generation_count += 1;
       }
 
+      // Unregistering a disposed instance does not bring it back to life
+      (InstanceState::NotAliveDisposed, InstanceState::NotAliveNoWriters) => {
+        new_instance_state = InstanceState::NotAliveDisposed;
+      }
+
       (InstanceState::NotAliveDisposed, _) => (), // you can only die once
 
       (InstanceState::NotAliveNoWriters, InstanceState::Alive) =>
@@ -188,67 +455,290 @@ where
         },
       );
 
-    // garbage collect
-    let sample_keep_history_limit: Option<i32> = match self.qos.history() {
-      Some(policy::History::KeepAll) => None, // no limit
-      Some(policy::History::KeepLast { depth }) => Some(depth),
-      None => Some(1), // default history policy
+    // garbage collect: KEEP_LAST replaces the oldest samples of the instance.
+    // With KEEP_ALL, samples over RESOURCE_LIMITS have been rejected already.
+    if let Some(instance_keep_count) = keep_last_depth {
+      let remove_count = instance_metadata
+        .instance_samples
+        .len()
+        .saturating_sub(instance_keep_count);
+      let keys_to_remove: Vec<_> = instance_metadata
+        .instance_samples
+        .iter()
+        .take(remove_count)
+        .copied()
+        .collect();
+      for k in keys_to_remove {
+        instance_metadata.instance_samples.remove(&k);
+        self.datasamples.remove(&k);
+      }
+    }
+    update_live_instance_count(
+      &mut self.live_instance_count,
+      was_live,
+      instance_metadata.takes_resources(),
+    );
+  }
+
+  // How many samples are kept per instance with KEEP_LAST history. This is the
+  // history depth, unless RESOURCE_LIMITS max_samples_per_instance is smaller.
+  // None means KEEP_ALL.
+  fn keep_last_depth(&self) -> Option<usize> {
+    let depth = match self.qos.history() {
+      Some(policy::History::KeepAll) => return None,
+      Some(policy::History::KeepLast { depth }) => usize::try_from(depth).unwrap_or(1).max(1),
+      None => 1, // default history policy
     };
-    let sample_keep_resource_limit = if let Some(policy::ResourceLimits {
-      max_samples: _,
-      max_instances: _,
-      max_samples_per_instance,
-    }) = self.qos.resource_limits
+    let max_samples_per_instance = self
+      .qos
+      .resource_limits()
+      .and_then(|limits| limits.samples_per_instance_limit());
+    Some(max_samples_per_instance.map_or(depth, |max| max.min(depth)))
+  }
+
+  // Checks if a new sample to the instance would exceed RESOURCE_LIMITS. DDS
+  // spec v1.4 Section 2.2.3.19. A sample that replaces an older one in a
+  // KEEP_LAST instance needs no more room. A new instance is counted in
+  // live_instance_count already, because its metadata record exists, but it
+  // has no resources yet.
+  fn rejection_reason(
+    &self,
+    instance_key: &D::K,
+    new_instance: bool,
+  ) -> Option<SampleRejectedStatusKind> {
+    let limits = self.qos.resource_limits()?;
+    let instance = self.instance_map.get(instance_key);
+    let instance_samples = instance.map_or(0, |imd| imd.instance_samples.len());
+    let counted = instance.is_some_and(InstanceMetaData::takes_resources);
+
+    if new_instance || !counted {
+      let other_instances = self.live_instance_count - usize::from(counted);
+      if limits
+        .instance_limit()
+        .is_some_and(|max_instances| other_instances >= max_instances)
+      {
+        return Some(SampleRejectedStatusKind::ByInstancesLimit);
+      }
+    }
+
+    if self
+      .keep_last_depth()
+      .is_some_and(|depth| instance_samples >= depth)
     {
-      Some(max_samples_per_instance)
-    } else {
-      None
-    };
+      return None; // replaces the oldest sample of the instance
+    }
+    if limits
+      .samples_per_instance_limit()
+      .is_some_and(|max_samples_per_instance| instance_samples >= max_samples_per_instance)
+    {
+      return Some(SampleRejectedStatusKind::BySamplesPerInstanceLimit);
+    }
+    if limits
+      .sample_limit()
+      .is_some_and(|max_samples| self.datasamples.len() >= max_samples)
+    {
+      return Some(SampleRejectedStatusKind::BySamplesLimit);
+    }
+    None
+  }
 
-    if let Some(instance_keep_count) = sample_keep_history_limit.or(sample_keep_resource_limit) {
-      let remove_count = instance_metadata.instance_samples.len() as i32 - instance_keep_count;
-      if remove_count > 0 {
-        let keys_to_remove: Vec<_> = instance_metadata
-          .instance_samples
-          .iter()
-          .take(remove_count as usize)
-          .copied()
-          .collect();
-        for k in keys_to_remove {
-          instance_metadata.instance_samples.remove(&k);
-          self.datasamples.remove(&k);
-        }
+  // Counts a sample rejected due to RESOURCE_LIMITS. Returns true, if the
+  // sample was rejected.
+  fn reject_over_limits(
+    &mut self,
+    instance_key: &D::K,
+    new_instance: bool,
+    sequence_number: SequenceNumber,
+  ) -> bool {
+    match self.rejection_reason(instance_key, new_instance) {
+      None => false,
+      Some(reason) => {
+        debug!("Rejecting sample {sequence_number:?}: {reason:?}");
+        self.rejected_sample_count += 1;
+        self.last_rejected_reason = reason;
+        true
       }
     }
+  }
 
-    // TODO: Implement other resource_limit settings than max_instances_per
-    // sample, i.e.
+  fn minimum_separation(&self) -> Option<Duration> {
+    match self.qos.time_based_filter() {
+      Some(policy::TimeBasedFilter { minimum_separation })
+        if minimum_separation > Duration::ZERO =>
+      {
+        Some(minimum_separation)
+      }
+      _ => None,
+    }
+  }
+
+  // Delivers the held back sample of an instance, if minimum_separation has
+  // elapsed by time `now`.
+  fn release_filtered_sample(&mut self, instance_key: &D::K, now: Timestamp) {
+    let Some(minimum_separation) = self.minimum_separation() else {
+      return;
+    };
+    let Some(instance_metadata) = self.instance_map.get_mut(instance_key) else {
+      return;
+    };
+    let Some(latest_delivery) = instance_metadata.latest_delivery else {
+      return;
+    };
+    let release_time = latest_delivery + minimum_separation;
+    if now < release_time {
+      return;
+    }
+    if let Some(filtered) = self.filtered_samples.remove(instance_key) {
+      if self.reject_over_limits(instance_key, false, filtered.sequence_number) {
+        return;
+      }
+      // The next separation period starts when this one ends, so that the
+      // delivery rate does not depend on how often the application reads.
+      if let Some(instance_metadata) = self.instance_map.get_mut(instance_key) {
+        instance_metadata.latest_delivery = Some(release_time);
+      }
+      self.insert_sample(
+        instance_key,
+        filtered.sample,
+        filtered.writer_guid,
+        filtered.sequence_number,
+        filtered.receive_timestamp,
+        filtered.write_options,
+        ChangeKind::Alive, // only values are held back
+      );
+    }
+  }
+
+  /// Delivers all held back samples whose TIME_BASED_FILTER minimum_separation
+  /// has elapsed by time `now`.
+  pub(crate) fn release_filtered_samples(&mut self, now: Timestamp) {
+    let instance_keys: Vec<D::K> = self.filtered_samples.keys().cloned().collect();
+    for instance_key in instance_keys {
+      self.release_filtered_sample(&instance_key, now);
+    }
+    self.update_read_conditions();
+  }
+
+  /// When the next held back sample becomes deliverable, unless this was
+  /// already returned by the previous call. The DataReader must be notified
+  /// then, because no new data may arrive to wake it up.
+  pub(crate) fn next_filtered_release(&mut self) -> Option<Timestamp> {
+    let minimum_separation = self.minimum_separation()?;
+    let next_release = self
+      .filtered_samples
+      .keys()
+      .filter_map(|key| self.instance_map.get(key)?.latest_delivery)
+      .min()
+      .map(|latest_delivery| latest_delivery + minimum_separation);
+    if next_release == self.filtered_release_notified {
+      return None;
+    }
+    self.filtered_release_notified = next_release;
+    next_release
+  }
+
+  /// How many samples TIME_BASED_FILTER has discarded, and how many of those
+  /// since the previous call.
+  pub(crate) fn filtered_sample_count(&mut self) -> CountWithChange {
+    let change = self.filtered_sample_count - self.filtered_sample_count_reported;
+    self.filtered_sample_count_reported = self.filtered_sample_count;
+    CountWithChange::new(self.filtered_sample_count, change)
+  }
+
+  /// SampleRejected status, if samples have been rejected since the previous
+  /// call.
+  pub(crate) fn sample_rejected_status(&mut self) -> Option<DataReaderStatus> {
+    let change = self.rejected_sample_count - self.rejected_sample_count_reported;
+    if change == 0 {
+      return None;
+    }
+    self.rejected_sample_count_reported = self.rejected_sample_count;
+    Some(DataReaderStatus::SampleRejected {
+      count: CountWithChange::new(self.rejected_sample_count, change),
+      last_reason: self.last_rejected_reason,
+    })
+  }
+
+  /// Removes samples whose LIFESPAN has expired by time `now`, so that they
+  /// are never delivered to the application. A sample expires at its source
+  /// timestamp plus the lifespan duration. Samples without a source timestamp
+  /// are timed from their reception.
+  pub(crate) fn remove_expired_samples(&mut self, now: Timestamp) {
+    let Some(policy::Lifespan { duration }) = self.qos.lifespan() else {
+      return;
+    };
+    let is_expired = |receive_timestamp: Timestamp, write_options: &WriteOptions| {
+      write_options
+        .source_timestamp()
+        .unwrap_or(receive_timestamp)
+        + duration
+        < now
+    };
+
+    self
+      .filtered_samples
+      .retain(|_key, filtered| !is_expired(filtered.receive_timestamp, &filtered.write_options));
+
+    let expired: Vec<(Timestamp, D::K)> = self
+      .datasamples
+      .iter()
+      .filter(|(ts, dsm)| is_expired(**ts, &dsm.write_options))
+      .map(|(ts, dsm)| (*ts, dsm.key()))
+      .collect();
+    if expired.is_empty() {
+      return;
+    }
+    for (ts, key) in expired {
+      self.datasamples.remove(&ts);
+      self.remove_from_instance(&key, ts);
+    }
+    self.update_read_conditions();
   }
 
   // Calling select_(instance)_keys_for access does not constitute access, i.e.
   // it does not change any state of the cache.
   // Samples are marked read or viewed only when "read" or "take" methods (below)
   // are called.
-  pub fn select_keys_for_access(&self, rc: ReadCondition) -> Vec<(Timestamp, D::K)> {
-    self
+  pub fn select_keys_for_access(
+    &self,
+    condition: impl SampleCondition<D>,
+  ) -> Vec<(Timestamp, D::K)> {
+    let rc = condition.read_condition();
+    let mut keys: Vec<_> = self
       .datasamples
       .iter()
       .filter_map(|(ts, dsm)| {
         let key = dsm.key();
-        if self.sample_selector(&rc, self.instance_map.get(&key).unwrap(), dsm) {
+        if self.sample_selector(&rc, self.instance_map.get(&key).unwrap(), dsm)
+          && Self::content_selector(&condition, dsm)
+        {
           Some((*ts, key))
         } else {
           None
         }
       })
-      .collect()
+      .collect();
+
+    // Samples are stored in reception order. Within an instance, that is also
+    // the source timestamp order, because older samples were dropped, but
+    // across instances we must sort.
+    if self.qos.destination_order() == Some(policy::DestinationOrder::BySourceTimeStamp) {
+      keys.sort_by_key(|(ts, _key)| {
+        self.datasamples[ts]
+          .write_options
+          .source_timestamp()
+          .unwrap_or(*ts)
+      });
+    }
+    keys
   }
 
   pub fn select_instance_keys_for_access(
     &self,
     instance: &D::K,
-    rc: ReadCondition,
+    condition: impl SampleCondition<D>,
   ) -> Vec<(Timestamp, D::K)> {
+    let rc = condition.read_condition();
     match self.instance_map.get(instance) {
       None => Vec::new(),
       Some(imd) => imd
@@ -256,7 +746,7 @@ where
         .iter()
         .filter_map(|ts| {
           if let Some(ds) = self.datasamples.get(ts) {
-            if self.sample_selector(&rc, imd, ds) {
+            if self.sample_selector(&rc, imd, ds) && Self::content_selector(&condition, ds) {
               Some((*ts, instance.clone()))
             } else {
               None
@@ -269,6 +759,14 @@ where
     }
   }
 
+  // select helper for QueryConditions
+  fn content_selector(condition: &impl SampleCondition<D>, d: &SampleWithMetaData<D>) -> bool {
+    match &d.sample {
+      Sample::Value(value) => condition.content_matches(Some(value)),
+      Sample::Dispose(_key) => condition.content_matches(None),
+    }
+  }
+
   // select helper
   fn sample_selector(
     &self,
@@ -354,6 +852,50 @@ where
         panic!("Instance disappeared!?!!1!");
       }
     }
+    self.update_read_conditions();
+  }
+
+  pub(crate) fn add_read_condition(&mut self, state: &Arc<ReadConditionState>) {
+    let present = self.present_states();
+    state.set_has_samples(Self::has_samples(state.read_condition(), &present));
+    self.read_conditions.push(Arc::downgrade(state));
+  }
+
+  fn update_read_conditions(&mut self) {
+    self.read_conditions.retain(|rc| rc.strong_count() > 0);
+    if self.read_conditions.is_empty() {
+      return;
+    }
+    // One pass over the samples, regardless of the number of conditions
+    let present = self.present_states();
+    for state in self.read_conditions.iter().filter_map(Weak::upgrade) {
+      state.set_has_samples(Self::has_samples(state.read_condition(), &present));
+    }
+  }
+
+  // Instance states of the samples in the cache, indexed by whether the sample
+  // has been read and whether its view state is New.
+  fn present_states(&self) -> PresentStates {
+    let mut present = PresentStates::default();
+    for dsm in self.datasamples.values() {
+      let imd = self.instance_map.get(&dsm.key()).unwrap();
+      let is_new = dsm.generation_counts.total() > imd.last_generation_accessed.total();
+      present[usize::from(dsm.sample_has_been_read)][usize::from(is_new)] |= imd.instance_state;
+    }
+    present
+  }
+
+  // Are there any samples that the ReadCondition would select
+  fn has_samples(rc: &ReadCondition, present: &PresentStates) -> bool {
+    let sample_states = [SampleState::NotRead, SampleState::Read];
+    let view_states = [ViewState::NotNew, ViewState::New];
+    (0..2)
+      .filter(|&read| rc.sample_state_mask().contains(sample_states[read]))
+      .any(|read| {
+        (0..2)
+          .filter(|&new| rc.view_state_mask().contains(view_states[new]))
+          .any(|new| present[read][new].intersects(*rc.instance_state_mask()))
+      })
   }
 
   // read methods perform actual read or take. They must be called with key
@@ -453,6 +995,7 @@ where
       let dswm = self.datasamples.remove(ts).unwrap();
       let imd = self.instance_map.get(key).unwrap();
       let sample_info = Self::make_sample_info(&dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      self.remove_from_instance(key, *ts);
       // dwsm.sample_has_been_read = true; // no need to mark read, as the dswm is
       // about to be destroyed
       Self::record_instance_generation_viewed(
@@ -514,6 +1057,7 @@ where
 
     for (ts, key) in keys.iter() {
       let dswm = self.datasamples.remove(ts).unwrap();
+      self.remove_from_instance(key, *ts);
       // dwsm.sample_has_been_read = true; // no need to mark read, as the dswm is
       // about to be destroyed
       Self::record_instance_generation_viewed(
@@ -528,6 +1072,20 @@ where
     result
   }
 
+  // Forgets a sample, which has been removed from datasamples, from its
+  // instance.
+  fn remove_from_instance(&mut self, instance_key: &D::K, sample: Timestamp) {
+    if let Some(instance_metadata) = self.instance_map.get_mut(instance_key) {
+      let was_live = instance_metadata.takes_resources();
+      instance_metadata.instance_samples.remove(&sample);
+      update_live_instance_count(
+        &mut self.live_instance_count,
+        was_live,
+        instance_metadata.takes_resources(),
+      );
+    }
+  }
+
   pub fn next_key(&self, key: &D::K) -> Option<D::K> {
     self
       .instance_map
@@ -539,6 +1097,16 @@ where
 
 // helper function
 // somewhat like result.as_ref() , but one-sided only
+// Instance state after receiving a sample. A key-only sample either disposes
+// or unregisters the instance, as told by the change kind.
+fn instance_state_after<D, K>(sample: &Sample<D, K>, change_kind: ChangeKind) -> InstanceState {
+  match (sample, change_kind) {
+    (Sample::Value(_), _) => InstanceState::Alive,
+    (Sample::Dispose(_), ChangeKind::NotAliveUnregistered) => InstanceState::NotAliveNoWriters,
+    (Sample::Dispose(_), _) => InstanceState::NotAliveDisposed,
+  }
+}
+
 pub(crate) fn result_ok_as_ref_err_clone<T, E: Clone>(r: &Sample<T, E>) -> Sample<&T, E> {
   match *r {
     Sample::Value(ref x) => Sample::Value(x),
@@ -548,13 +1116,19 @@ pub(crate) fn result_ok_as_ref_err_clone<T, E: Clone>(r: &Sample<T, E>) -> Sampl
 
 #[cfg(test)]
 mod tests {
-  // use super::*;
-  // use crate::{
-  //   structure::{time::Timestamp},
-  // };
+  use super::*;
+  use crate::{
+    dds::{
+      qos::QosPolicyBuilder,
+      readcondition::QueryCondition,
+      waitset::{Condition, DataReaderReadCondition},
+      with_key::datawriter::WriteOptionsBuilder,
+    },
+    rtps::reader::DataAvailability,
+    structure::guid::{EntityId, GuidPrefix},
+    test::random_data::*,
+  };
   // use crate::dds::ddsdata::DDSData;
-  // use crate::dds::traits::key::Keyed;
-  // use crate::test::random_data::*;
 
   #[test]
   fn dsc_empty_qos() {
@@ -585,4 +1159,639 @@ mod tests {
     }
     */
   }
+
+  fn writer(n: u8) -> GUID {
+    GUID::new_with_prefix_and_id(GuidPrefix::new(&[n; 12]), EntityId::UNKNOWN)
+  }
+
+  // Adds a sample of instance 1 and tells if it was accepted
+  fn write(
+    cache: &mut DataSampleCache<RandomData>,
+    writer_guid: GUID,
+    b: &str,
+    receive_timestamp: Timestamp,
+  ) -> bool {
+    write_with_options(
+      cache,
+      writer_guid,
+      b,
+      receive_timestamp,
+      WriteOptions::default(),
+    )
+  }
+
+  fn write_with_options(
+    cache: &mut DataSampleCache<RandomData>,
+    writer_guid: GUID,
+    b: &str,
+    receive_timestamp: Timestamp,
+    write_options: WriteOptions,
+  ) -> bool {
+    let sample_count = cache.datasamples.len();
+    cache.add_sample(
+      Sample::Value(RandomData {
+        a: 1,
+        b: b.to_string(),
+      }),
+      writer_guid,
+      SequenceNumber::new(1),
+      receive_timestamp,
+      write_options,
+      ChangeKind::Alive,
+    );
+    cache.datasamples.len() > sample_count
+  }
+
+  #[test]
+  fn dsc_exclusive_ownership_strongest_writer_wins() {
+    let qos = QosPolicyBuilder::new()
+      .ownership(policy::Ownership::Exclusive { strength: 0 })
+      .history(policy::History::KeepAll)
+      .build();
+    let writer_strengths = WriterStrengths::default();
+    writer_strengths
+      .lock()
+      .unwrap()
+      .extend([(writer(1), 10), (writer(2), 20), (writer(3), 20)]);
+    let mut cache = DataSampleCache::<RandomData>::new(qos, writer_strengths.clone());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write(&mut cache, writer(1), "first", t(1)));
+    assert!(write(&mut cache, writer(2), "stronger", t(2)));
+    assert!(!write(&mut cache, writer(1), "weaker", t(3)));
+    // Equal strength: lower GUID wins
+    assert!(!write(&mut cache, writer(3), "equal", t(4)));
+    // Writer that is not matched cannot take over
+    assert!(!write(&mut cache, writer(4), "unknown", t(5)));
+    assert!(write(&mut cache, writer(2), "owner", t(6)));
+
+    // Owner loses liveliness
+    writer_strengths.lock().unwrap().remove(&writer(2));
+    assert!(write(&mut cache, writer(1), "new owner", t(7)));
+    assert!(write(&mut cache, writer(3), "stronger again", t(8)));
+  }
+
+  #[test]
+  fn dsc_exclusive_ownership_transfers_on_missed_deadline() {
+    let qos = QosPolicyBuilder::new()
+      .ownership(policy::Ownership::Exclusive { strength: 0 })
+      .deadline(policy::Deadline(Duration::from_millis(100)))
+      .history(policy::History::KeepAll)
+      .build();
+    let writer_strengths = WriterStrengths::default();
+    writer_strengths
+      .lock()
+      .unwrap()
+      .extend([(writer(1), 10), (writer(2), 20)]);
+    let mut cache = DataSampleCache::<RandomData>::new(qos, writer_strengths);
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write(&mut cache, writer(2), "owner", t(0)));
+    assert!(!write(&mut cache, writer(1), "within deadline", t(50)));
+    assert!(write(&mut cache, writer(1), "deadline missed", t(200)));
+    // The stronger writer gets the instance back when it resumes.
+    assert!(write(&mut cache, writer(2), "back", t(250)));
+    assert!(!write(&mut cache, writer(1), "weaker again", t(260)));
+  }
+
+  #[test]
+  fn dsc_shared_ownership_accepts_all_writers() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+
+    assert!(write(&mut cache, writer(2), "a", t0));
+    assert!(write(
+      &mut cache,
+      writer(1),
+      "b",
+      t0 + Duration::from_millis(1)
+    ));
+  }
+
+  fn source_timestamp(timestamp: Timestamp) -> WriteOptions {
+    WriteOptionsBuilder::new()
+      .source_timestamp(timestamp)
+      .build()
+  }
+
+  #[test]
+  fn dsc_by_source_timestamp_drops_older_samples() {
+    let qos = QosPolicyBuilder::new()
+      .destination_order(policy::DestinationOrder::BySourceTimeStamp)
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    // Sample written later by writer 2 arrives first
+    assert!(write_with_options(
+      &mut cache,
+      writer(2),
+      "b",
+      t(10),
+      source_timestamp(t(2))
+    ));
+    // Earlier sample from writer 1 is older than the latest one
+    assert!(!write_with_options(
+      &mut cache,
+      writer(1),
+      "a",
+      t(11),
+      source_timestamp(t(1))
+    ));
+    // Newer samples are accepted
+    assert!(write_with_options(
+      &mut cache,
+      writer(1),
+      "c",
+      t(12),
+      source_timestamp(t(3))
+    ));
+    // Samples without source timestamp are stamped with reception time
+    assert!(write(&mut cache, writer(2), "d", t(13)));
+    assert!(!write_with_options(
+      &mut cache,
+      writer(1),
+      "e",
+      t(14),
+      source_timestamp(t(4))
+    ));
+
+    let values: Vec<String> = cache
+      .datasamples
+      .values()
+      .filter_map(|s| s.sample.clone().value().map(|d| d.b))
+      .collect();
+    assert_eq!(values, vec!["b", "c", "d"]);
+  }
+
+  #[test]
+  fn dsc_by_reception_timestamp_accepts_older_samples() {
+    let qos = QosPolicyBuilder::new()
+      .destination_order(policy::DestinationOrder::ByReceptionTimestamp)
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write_with_options(
+      &mut cache,
+      writer(2),
+      "b",
+      t(10),
+      source_timestamp(t(2))
+    ));
+    assert!(write_with_options(
+      &mut cache,
+      writer(1),
+      "a",
+      t(11),
+      source_timestamp(t(1))
+    ));
+  }
+
+  fn values(cache: &DataSampleCache<RandomData>) -> Vec<String> {
+    cache
+      .datasamples
+      .values()
+      .filter_map(|s| s.sample.clone().value().map(|d| d.b))
+      .collect()
+  }
+
+  #[test]
+  fn dsc_time_based_filter_keeps_latest_until_separation() {
+    let qos = QosPolicyBuilder::new()
+      .time_based_filter(policy::TimeBasedFilter {
+        minimum_separation: Duration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write(&mut cache, writer(1), "a", t(0)));
+    // Within separation: held back, each replacing the previous one
+    assert!(!write(&mut cache, writer(1), "b", t(10)));
+    assert!(!write(&mut cache, writer(1), "c", t(20)));
+    cache.release_filtered_samples(t(50));
+    assert_eq!(values(&cache), vec!["a"]);
+    // The DataReader is told once when to look again
+    assert_eq!(cache.next_filtered_release(), Some(t(100)));
+    assert_eq!(cache.next_filtered_release(), None);
+
+    // Separation has elapsed, so latest held back sample is delivered
+    cache.release_filtered_samples(t(100));
+    assert_eq!(values(&cache), vec!["a", "c"]);
+    assert_eq!(cache.filtered_sample_count(), CountWithChange::new(1, 1));
+    assert_eq!(cache.next_filtered_release(), None);
+
+    // Next period started at 100 ms
+    assert!(!write(&mut cache, writer(1), "d", t(150)));
+    // Arrives after the period ended at 200 ms, so the held back sample is
+    // delivered, but this one starts waiting for the next period.
+    write(&mut cache, writer(1), "e", t(210));
+    assert_eq!(values(&cache), vec!["a", "c", "d"]);
+    assert_eq!(cache.filtered_sample_count(), CountWithChange::new(1, 0));
+  }
+
+  #[test]
+  fn dsc_unregister_makes_instance_not_alive_no_writers() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let instance_state =
+      |cache: &DataSampleCache<RandomData>| cache.instance_map[&1].instance_state;
+
+    assert!(write(&mut cache, writer(1), "a", t0));
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(2),
+      t0 + Duration::from_millis(10),
+      WriteOptions::default(),
+      ChangeKind::NotAliveUnregistered,
+    );
+    assert_eq!(instance_state(&cache), InstanceState::NotAliveNoWriters);
+
+    // Written again, and then disposed. Unregistering keeps it disposed.
+    assert!(write(
+      &mut cache,
+      writer(1),
+      "b",
+      t0 + Duration::from_millis(20)
+    ));
+    assert_eq!(instance_state(&cache), InstanceState::Alive);
+    assert_eq!(
+      cache.instance_map[&1]
+        .latest_generation_available
+        .no_writers_generation_count,
+      1
+    );
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(4),
+      t0 + Duration::from_millis(30),
+      WriteOptions::default(),
+      ChangeKind::NotAliveDisposed,
+    );
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(5),
+      t0 + Duration::from_millis(40),
+      WriteOptions::default(),
+      ChangeKind::NotAliveUnregistered,
+    );
+    assert_eq!(instance_state(&cache), InstanceState::NotAliveDisposed);
+  }
+
+  #[test]
+  fn dsc_time_based_filter_does_not_hold_back_dispose() {
+    let qos = QosPolicyBuilder::new()
+      .time_based_filter(policy::TimeBasedFilter {
+        minimum_separation: Duration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+
+    assert!(write(&mut cache, writer(1), "a", t0));
+    assert!(!write(
+      &mut cache,
+      writer(1),
+      "b",
+      t0 + Duration::from_millis(10)
+    ));
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(3),
+      t0 + Duration::from_millis(20),
+      WriteOptions::default(),
+      ChangeKind::NotAliveDisposed,
+    );
+    assert_eq!(cache.datasamples.len(), 2);
+    assert_eq!(cache.filtered_sample_count(), CountWithChange::new(1, 1));
+
+    // Nothing left to deliver
+    cache.release_filtered_samples(t0 + Duration::from_millis(200));
+    assert_eq!(cache.datasamples.len(), 2);
+  }
+
+  #[test]
+  fn dsc_updates_read_conditions() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let data_availability = Arc::new(DataAvailability::default());
+    let not_read =
+      DataReaderReadCondition::new(ReadCondition::not_read(), Arc::clone(&data_availability));
+    let any = DataReaderReadCondition::new(ReadCondition::any(), data_availability);
+    cache.add_read_condition(not_read.state());
+    cache.add_read_condition(any.state());
+    assert!(!not_read.get_trigger_value());
+    assert!(!any.get_trigger_value());
+
+    let t0 = Timestamp::now();
+    assert!(write(&mut cache, writer(1), "a", t0));
+    cache.release_filtered_samples(t0);
+    assert!(not_read.get_trigger_value());
+    assert!(any.get_trigger_value());
+
+    let keys = cache.select_keys_for_access(ReadCondition::any());
+    cache.read_by_keys(&keys);
+    assert!(!not_read.get_trigger_value());
+    assert!(any.get_trigger_value());
+
+    cache.take_by_keys(&keys);
+    assert!(!any.get_trigger_value());
+
+    // Dropped conditions are forgotten
+    drop(any);
+    cache.release_filtered_samples(t0);
+    assert_eq!(cache.read_conditions.len(), 1);
+  }
+
+  #[test]
+  fn dsc_selects_by_query_condition() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    for (i, b) in ["a", "b", "c"].iter().enumerate() {
+      assert!(write(
+        &mut cache,
+        writer(1),
+        b,
+        t0 + Duration::from_millis(i as i64)
+      ));
+    }
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(4),
+      t0 + Duration::from_millis(3),
+      WriteOptions::default(),
+      ChangeKind::NotAliveDisposed,
+    );
+
+    let mut query = QueryCondition::new(
+      ReadCondition::not_read(),
+      "b <> %0",
+      vec!["'b'".to_string()],
+    )
+    .unwrap();
+    let keys = cache.select_keys_for_access(&query);
+    assert_eq!(keys.len(), 2);
+    let values: Vec<String> = cache
+      .read_by_keys(&keys)
+      .into_iter()
+      .map(|ds| ds.into_value().unwrap().b.clone())
+      .collect();
+    assert_eq!(values, vec!["a", "c"]);
+
+    // Already read
+    assert!(cache.select_keys_for_access(&query).is_empty());
+    // The dispose is still not read, but does not match the query
+    assert_eq!(
+      cache
+        .select_keys_for_access(ReadCondition::not_read())
+        .len(),
+      2
+    );
+
+    query.set_query_parameters(vec!["'a'".to_string()]).unwrap();
+    let any_query = QueryCondition::new(
+      ReadCondition::any(),
+      query.query_expression(),
+      query.query_parameters().to_vec(),
+    )
+    .unwrap();
+    assert_eq!(cache.select_keys_for_access(&any_query).len(), 2);
+    assert_eq!(
+      cache.select_instance_keys_for_access(&1, &any_query).len(),
+      2
+    );
+  }
+
+  #[test]
+  fn dsc_lifespan_expires_samples() {
+    let qos = QosPolicyBuilder::new()
+      .lifespan(policy::Lifespan {
+        duration: Duration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    write(&mut cache, writer(1), "a", t(0));
+    // Source timestamp takes precedence over reception time
+    write_with_options(&mut cache, writer(1), "b", t(10), source_timestamp(t(50)));
+    write(&mut cache, writer(1), "c", t(60));
+
+    cache.remove_expired_samples(t(100));
+    assert_eq!(values(&cache), vec!["a", "b", "c"]);
+    cache.remove_expired_samples(t(120));
+    assert_eq!(values(&cache), vec!["b", "c"]);
+    cache.remove_expired_samples(t(155));
+    assert_eq!(values(&cache), vec!["c"]);
+    assert_eq!(
+      cache
+        .select_instance_keys_for_access(&1, ReadCondition::any())
+        .len(),
+      1
+    );
+  }
+
+  // Adds a sample of the given instance and tells if it was not rejected
+  fn write_instance(
+    cache: &mut DataSampleCache<RandomData>,
+    a: i64,
+    receive_timestamp: Timestamp,
+  ) -> bool {
+    let rejected_count = cache.rejected_sample_count;
+    cache.add_sample(
+      Sample::Value(RandomData {
+        a,
+        b: "x".to_string(),
+      }),
+      writer(1),
+      SequenceNumber::new(1),
+      receive_timestamp,
+      WriteOptions::default(),
+      ChangeKind::Alive,
+    );
+    cache.rejected_sample_count == rejected_count
+  }
+
+  fn rejected(cache: &mut DataSampleCache<RandomData>) -> Option<(i32, SampleRejectedStatusKind)> {
+    match cache.sample_rejected_status() {
+      Some(DataReaderStatus::SampleRejected { count, last_reason }) => {
+        Some((count.count(), last_reason))
+      }
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn dsc_keep_all_rejects_over_resource_limits() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .resource_limits(policy::ResourceLimits {
+        max_samples: -1,
+        max_instances: 2,
+        max_samples_per_instance: 2,
+      })
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write_instance(&mut cache, 1, t(0)));
+    assert!(write_instance(&mut cache, 1, t(1)));
+    assert_eq!(rejected(&mut cache), None);
+    assert!(!write_instance(&mut cache, 1, t(2)));
+    assert_eq!(
+      rejected(&mut cache),
+      Some((1, SampleRejectedStatusKind::BySamplesPerInstanceLimit))
+    );
+
+    assert!(write_instance(&mut cache, 2, t(3)));
+    assert!(!write_instance(&mut cache, 3, t(4)));
+    assert_eq!(
+      rejected(&mut cache),
+      Some((2, SampleRejectedStatusKind::ByInstancesLimit))
+    );
+    assert_eq!(rejected(&mut cache), None);
+  }
+
+  #[test]
+  fn dsc_keep_last_is_per_instance_within_max_samples() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepLast { depth: 2 })
+      .resource_limits(policy::ResourceLimits {
+        max_samples: 3,
+        max_instances: -1,
+        max_samples_per_instance: -1,
+      })
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    // Older samples of an instance are replaced
+    for ms in 0..3 {
+      assert!(write_instance(&mut cache, 1, t(ms)));
+    }
+    assert_eq!(cache.instance_map[&1].instance_samples.len(), 2);
+    assert!(write_instance(&mut cache, 2, t(3)));
+    // Cache is full
+    assert!(!write_instance(&mut cache, 2, t(4)));
+    assert_eq!(
+      rejected(&mut cache),
+      Some((1, SampleRejectedStatusKind::BySamplesLimit))
+    );
+    // but replacing is still possible
+    assert!(write_instance(&mut cache, 1, t(5)));
+    assert_eq!(cache.datasamples.len(), 3);
+  }
+
+  #[test]
+  fn dsc_rejects_only_samples_to_be_stored() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .time_based_filter(policy::TimeBasedFilter {
+        minimum_separation: Duration::from_millis(100),
+      })
+      .resource_limits(policy::ResourceLimits {
+        max_samples: 1,
+        max_instances: 1,
+        max_samples_per_instance: -1,
+      })
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write_instance(&mut cache, 1, t(0)));
+    // Held back by TIME_BASED_FILTER, not rejected
+    assert!(write_instance(&mut cache, 1, t(10)));
+    assert_eq!(rejected(&mut cache), None);
+    // A new instance is rejected, and leaves nothing behind
+    assert!(!write_instance(&mut cache, 2, t(20)));
+    assert_eq!(
+      rejected(&mut cache),
+      Some((1, SampleRejectedStatusKind::ByInstancesLimit))
+    );
+    assert!(!cache.instance_map.contains_key(&2));
+
+    // A dispose is stored even though the cache is full
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(2),
+      t(30),
+      WriteOptions::default(),
+      ChangeKind::NotAliveDisposed,
+    );
+    assert_eq!(rejected(&mut cache), None);
+    assert_eq!(
+      cache.instance_map[&1].instance_state,
+      InstanceState::NotAliveDisposed
+    );
+    assert_eq!(cache.datasamples.len(), 2);
+  }
+
+  #[test]
+  fn dsc_taken_samples_free_resources() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .resource_limits(policy::ResourceLimits {
+        max_samples: -1,
+        max_instances: 1,
+        max_samples_per_instance: 1,
+      })
+      .build();
+    let mut cache = DataSampleCache::<RandomData>::new(qos, WriterStrengths::default());
+    let t0 = Timestamp::now();
+    let t = |ms| t0 + Duration::from_millis(ms);
+
+    assert!(write_instance(&mut cache, 1, t(0)));
+    let keys = cache.select_keys_for_access(ReadCondition::any());
+    cache.take_by_keys(&keys);
+    // Taken sample no longer counts against the instance
+    assert!(write_instance(&mut cache, 1, t(10)));
+
+    cache.add_sample(
+      Sample::Dispose(1),
+      writer(1),
+      SequenceNumber::new(2),
+      t(20),
+      WriteOptions::default(),
+      ChangeKind::NotAliveDisposed,
+    );
+    // Disposed instance still has samples
+    assert!(!write_instance(&mut cache, 2, t(30)));
+    let keys = cache.select_keys_for_access(ReadCondition::any());
+    cache.take_bare_by_keys(&keys);
+    assert_eq!(cache.live_instance_count, 0);
+    assert!(write_instance(&mut cache, 2, t(40)));
+    assert_eq!(cache.live_instance_count, 1);
+  }
 }
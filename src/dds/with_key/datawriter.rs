@@ -1,15 +1,17 @@
 use std::{
+  collections::BTreeSet,
   marker::PhantomData,
   pin::Pin,
   sync::{
-    atomic::{AtomicI64, Ordering},
-    Arc, Mutex,
+    atomic::{AtomicBool, AtomicI64, Ordering},
+    Arc, Mutex, MutexGuard,
   },
-  task::{Context, Poll, Waker},
+  task::{Context, Poll},
   time::{Duration, Instant},
 };
 
 use futures::{Future, Stream};
+use enumflags2::BitFlags;
 use mio_06::{Events, PollOpt, Ready, Token};
 use mio_extras::channel::{self as mio_channel, SendError, TrySendError};
 #[allow(unused_imports)]
@@ -19,19 +21,26 @@ use crate::{
   dds::{
     adapters::with_key::SerializerAdapter,
     ddsdata::DDSData,
+    ddssql::FilterableSample,
     helpers::*,
+    key::{Key, KeyHash},
+    listener::DataWriterListener,
     pubsub::Publisher,
     qos::{
-      policy::{Liveliness, Reliability},
-      HasQoSPolicy, QosPolicies,
+      policy::{Durability, Liveliness, Reliability},
+      HasQoSPolicy, MutQosPolicy, QosPolicies,
     },
-    result::{CreateResult, WriteError, WriteResult},
+    result::{CreateResult, QosError, WriteError, WriteResult},
     statusevents::*,
     topic::Topic,
+    waitset::StatusCondition,
   },
   discovery::{discovery::DiscoveryCommand, sedp_messages::SubscriptionBuiltinTopicData},
   messages::submessages::elements::serialized_payload::SerializedPayload,
-  rtps::writer::WriterCommand,
+  rtps::{
+    coherent_set::CoherentSet, command_channel_wakers::CommandChannelWakers,
+    history_reservations::HistoryReservations, writer::WriterCommand,
+  },
   serialization::CDRSerializerAdapter,
   structure::{
     cache_change::ChangeKind, duration, entity::RTPSEntity, guid::GUID, rpc::SampleIdentity,
@@ -47,6 +56,7 @@ pub struct WriteOptionsBuilder {
   related_sample_identity: Option<SampleIdentity>,
   source_timestamp: Option<Timestamp>,
   to_single_reader: Option<GUID>,
+  coherent_set: Option<CoherentSet>,
 }
 
 impl WriteOptionsBuilder {
@@ -59,6 +69,7 @@ impl WriteOptionsBuilder {
       related_sample_identity: self.related_sample_identity,
       source_timestamp: self.source_timestamp,
       to_single_reader: self.to_single_reader,
+      coherent_set: self.coherent_set,
     }
   }
 
@@ -88,6 +99,12 @@ impl WriteOptionsBuilder {
     self.to_single_reader = Some(reader);
     self
   }
+
+  #[must_use]
+  pub(crate) fn coherent_set(mut self, coherent_set: CoherentSet) -> Self {
+    self.coherent_set = Some(coherent_set);
+    self
+  }
 }
 
 /// Type to be used with write_with_options.
@@ -98,6 +115,7 @@ pub struct WriteOptions {
   source_timestamp: Option<Timestamp>,             // from DDS spec
   to_single_reader: Option<GUID>,                  /* try to send to one Reader only
                                                     * future extension room fo other fields. */
+  pub(crate) coherent_set: Option<CoherentSet>, // set by Publisher
 }
 
 impl WriteOptions {
@@ -112,6 +130,10 @@ impl WriteOptions {
   pub fn to_single_reader(&self) -> Option<GUID> {
     self.to_single_reader
   }
+
+  pub(crate) fn coherent_set(&self) -> Option<&CoherentSet> {
+    self.coherent_set.as_ref()
+  }
 }
 
 impl From<Option<Timestamp>> for WriteOptions {
@@ -120,6 +142,7 @@ impl From<Option<Timestamp>> for WriteOptions {
       related_sample_identity: None,
       source_timestamp,
       to_single_reader: None,
+      coherent_set: None,
     }
   }
 }
@@ -163,10 +186,15 @@ pub struct DataWriter<D: Keyed, SA: SerializerAdapter<D> = CDRSerializerAdapter<
   qos_policy: QosPolicies,
   my_guid: GUID,
   cc_upload: mio_channel::SyncSender<WriterCommand>,
-  cc_upload_waker: Arc<Mutex<Option<Waker>>>,
+  cc_upload_wakers: Arc<CommandChannelWakers>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusChannelReceiver<DataWriterStatus>,
-  available_sequence_number: AtomicI64,
+  available_sequence_number: Arc<AtomicI64>, // shared with Publisher coherent changes
+  has_filtering_readers: Arc<AtomicBool>,    // shared with RTPS Writer
+  registered_instances: Mutex<BTreeSet<D::K>>, // written or registered, not unregistered
+  status_record: Arc<Mutex<DataWriterStatusRecord>>, // shared with RTPS Writer
+  history_reservations: Arc<HistoryReservations>, // shared with RTPS Writer
+  status_condition: StatusCondition,
 }
 
 impl<D, SA> Drop for DataWriter<D, SA>
@@ -210,9 +238,12 @@ where
     qos: QosPolicies,
     guid: GUID,
     cc_upload: mio_channel::SyncSender<WriterCommand>,
-    cc_upload_waker: Arc<Mutex<Option<Waker>>>,
+    cc_upload_wakers: Arc<CommandChannelWakers>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_receiver: StatusChannelReceiver<DataWriterStatus>,
+    has_filtering_readers: Arc<AtomicBool>,
+    status_record: Arc<Mutex<DataWriterStatusRecord>>,
+    history_reservations: Arc<HistoryReservations>,
   ) -> CreateResult<Self> {
     if let Some(lv) = qos.liveliness {
       match lv {
@@ -224,6 +255,10 @@ where
         }
       }
     };
+    let status_changes = status_receiver
+      .status_changes()
+      .cloned()
+      .unwrap_or_default();
     Ok(Self {
       data_phantom: PhantomData,
       ser_phantom: PhantomData,
@@ -232,13 +267,43 @@ where
       qos_policy: qos,
       my_guid: guid,
       cc_upload,
-      cc_upload_waker,
+      cc_upload_wakers,
       discovery_command,
       status_receiver,
-      available_sequence_number: AtomicI64::new(1), // valid numbering starts from 1
+      available_sequence_number: Arc::new(AtomicI64::new(1)), // valid numbering starts from 1
+      has_filtering_readers,
+      registered_instances: Mutex::new(BTreeSet::new()),
+      status_record,
+      history_reservations,
+      status_condition: StatusCondition::new(status_changes, None),
     })
   }
 
+  // Content filters of remote readers are evaluated in the RTPS Writer, which
+  // does not know the data type. Convert the sample to filterable form here,
+  // but only if some matched reader has a filter, or the Writer explicitly
+  // keeps historical data for late joining readers, which may have filters.
+  fn filterable_sample(&self, data: &D) -> Option<FilterableSample> {
+    let keeps_history = matches!(
+      self.qos_policy.durability(),
+      Some(Durability::TransientLocal | Durability::Transient | Durability::Persistent)
+    );
+    if keeps_history || self.has_filtering_readers.load(Ordering::Relaxed) {
+      SA::to_filterable(data)
+    } else {
+      None
+    }
+  }
+
+  // The RTPS Writer tracks DEADLINE per instance, but it does not know the
+  // data type. Identify the instance by key hash, but only if needed.
+  fn deadline_instance(&self, key: &D::K) -> Option<KeyHash> {
+    self
+      .qos_policy
+      .deadline()
+      .map(|_deadline| key.hash_key(false))
+  }
+
   fn next_sequence_number(&self) -> SequenceNumber {
     SequenceNumber::from(
       self
@@ -247,6 +312,58 @@ where
     )
   }
 
+  // Marks the sample to belong to a coherent set, if the Publisher has one in
+  // progress.
+  fn with_coherent_set(
+    &self,
+    mut write_options: WriteOptions,
+    sequence_number: SequenceNumber,
+  ) -> WriteOptions {
+    write_options.coherent_set = self.my_publisher.coherent_set_for(
+      self.my_guid,
+      sequence_number,
+      &self.cc_upload,
+      &self.available_sequence_number,
+      self.qos().reliable_max_blocking_time(),
+    );
+    write_options
+  }
+
+  fn data_command(
+    &self,
+    ddsdata: DDSData,
+    write_options: WriteOptions,
+    sequence_number: SequenceNumber,
+    data: &D,
+  ) -> WriterCommand {
+    WriterCommand::DDSData {
+      ddsdata,
+      write_options: self.with_coherent_set(write_options, sequence_number),
+      sequence_number,
+      filterable_sample: self.filterable_sample(data),
+      instance: self.deadline_instance(&data.key()),
+    }
+  }
+
+  fn register_key(&self, key: D::K) {
+    self.registered_instances.lock().unwrap().insert(key);
+  }
+
+  // Registering a new instance must not exceed ResourceLimits max_instances.
+  fn check_instance_limit(&self, key: &D::K) -> Result<(), String> {
+    let registered = self.registered_instances.lock().unwrap();
+    match self
+      .qos_policy
+      .resource_limits()
+      .and_then(|limits| limits.instance_limit())
+    {
+      Some(max_instances) if !registered.contains(key) && registered.len() >= max_instances => Err(
+        format!("{max_instances} instances are registered already (max_instances)"),
+      ),
+      _ => Ok(()),
+    }
+  }
+
   fn undo_sequence_number(&self) {
     self
       .available_sequence_number
@@ -358,21 +475,45 @@ where
       }
     };
 
+    if let Err(reason) = self.check_instance_limit(&data.key()) {
+      return Err(WriteError::OutOfResources { reason, data });
+    }
+
     let ddsdata = DDSData::new(SerializedPayload::new_from_bytes(
       SA::output_encoding(),
       send_buffer,
     ));
-    let sequence_number = self.next_sequence_number();
-    let writer_command = WriterCommand::DDSData {
-      ddsdata,
-      write_options,
-      sequence_number,
-    };
 
     let timeout = self.qos().reliable_max_blocking_time();
 
+    // With reliable KEEP_ALL, wait for room in history before taking a sequence
+    // number.
+    let sequence_number = if self.history_reservations.is_limited() {
+      let instance = data.key().hash_key(false);
+      let max_blocking_time = timeout.unwrap_or(TIMEOUT_FALLBACK).to_std();
+      match self.history_reservations.reserve(
+        instance,
+        || self.next_sequence_number(),
+        max_blocking_time,
+      ) {
+        Some(sequence_number) => sequence_number,
+        None => {
+          warn!(
+            "Write timed out: history is full. topic={:?}  timeout={:?}",
+            self.my_topic.name(),
+            timeout,
+          );
+          return Err(WriteError::WouldBlock { data });
+        }
+      }
+    } else {
+      self.next_sequence_number()
+    };
+    let writer_command = self.data_command(ddsdata, write_options, sequence_number, &data);
+
     match try_send_timeout(&self.cc_upload, writer_command, timeout) {
       Ok(_) => {
+        self.register_key(data.key());
         self.refresh_manual_liveliness();
         Ok(SampleIdentity {
           writer_guid: self.my_guid,
@@ -385,10 +526,12 @@ where
           self.my_topic.name(),
           timeout,
         );
+        self.history_reservations.cancel(sequence_number);
         self.undo_sequence_number();
         Err(WriteError::WouldBlock { data })
       }
       Err(TrySendError::Disconnected(_)) => {
+        self.history_reservations.cancel(sequence_number);
         self.undo_sequence_number();
         Err(WriteError::Poisoned {
           reason: "Cannot send to Writer".to_string(),
@@ -396,6 +539,7 @@ where
         })
       }
       Err(TrySendError::Io(e)) => {
+        self.history_reservations.cancel(sequence_number);
         self.undo_sequence_number();
         Err(e.into())
       }
@@ -484,14 +628,18 @@ where
     } // match
   }
 
-  /*
-
-  /// Unimplemented. <b>Do not use</b>.
+  /// Gets the LIVELINESS_LOST status, i.e. how many times this DataWriter has
+  /// failed to assert its liveliness in time.
+  ///
+  /// The count is cumulative, and the change is relative to the previous call
+  /// of this method. The same changes are also reported as
+  /// [`DataWriterStatus`] events, and reading either does not affect the
+  /// other. Calling this method resets the status change in the
+  /// [`StatusCondition`] of this DataWriter.
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::with_key::DataWriter;
@@ -513,19 +661,22 @@ where
   ///
   /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(lls) = data_writer.get_liveliness_lost_status() {
-  ///   // do something
-  /// }
+  /// let status = data_writer.get_liveliness_lost_status();
+  /// assert_eq!(status.count.count(), 0);
   /// ```
-  pub fn get_liveliness_lost_status(&self) -> Result<LivelinessLostStatus> {
-    todo!()
+  pub fn get_liveliness_lost_status(&self) -> LivelinessLostStatus {
+    let status = self.status_record().liveliness_lost();
+    self.status_read(StatusKind::LivelinessLost);
+    status
   }
 
-  /// Should get latest offered deadline missed status. <b>Do not use yet</b> use `get_status_lister` instead for the moment.
+  /// Gets the OFFERED_DEADLINE_MISSED status, i.e. how many times this
+  /// DataWriter has failed to update an instance within the Deadline period.
+  ///
+  /// Counts behave as in
+  /// [`get_liveliness_lost_status`](Self::get_liveliness_lost_status).
   ///
   /// # Examples
   ///
@@ -551,42 +702,26 @@ where
   ///
   /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Deadline missed status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(odms) = data_writer.get_offered_deadline_missed_status() {
-  ///   // do something
-  /// }
+  /// let status = data_writer.get_offered_deadline_missed_status();
+  /// assert_eq!(status.count.count(), 0);
   /// ```
-  pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
-    let mut fstatus = OfferedDeadlineMissedStatus::new();
-    while let Ok(status) = self.status_receiver.try_recv() {
-      match status {
-        StatusChange::OfferedDeadlineMissedStatus(status) => fstatus = status,
-  // TODO: possibly save old statuses
-        _ => (),
-      }
-    }
-
-    match self
-      .cc_upload
-      .try_send(WriterCommand::ResetOfferedDeadlineMissedStatus {
-        writer_guid: self.guid(),
-      }) {
-      Ok(_) => (),
-      Err(e) => error!("Unable to send ResetOfferedDeadlineMissedStatus. {e:?}"),
-    };
-
-    Ok(fstatus)
+  pub fn get_offered_deadline_missed_status(&self) -> OfferedDeadlineMissedStatus {
+    let status = self.status_record().offered_deadline_missed();
+    self.status_read(StatusKind::OfferedDeadlineMissed);
+    status
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// Gets the OFFERED_INCOMPATIBLE_QOS status, i.e. how many DataReaders have
+  /// been found that requested QoS this DataWriter does not offer.
+  ///
+  /// Counts behave as in
+  /// [`get_liveliness_lost_status`](Self::get_liveliness_lost_status).
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::with_key::DataWriter;
@@ -608,24 +743,66 @@ where
   ///
   /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status = data_writer.get_offered_incompatible_qos_status();
+  /// assert_eq!(status.last_policy_id, None);
+  /// ```
+  pub fn get_offered_incompatible_qos_status(&self) -> OfferedIncompatibleQosStatus {
+    let status = self.status_record().offered_incompatible_qos();
+    self.status_read(StatusKind::OfferedIncompatibleQos);
+    status
+  }
+
+  /// Gets the PUBLICATION_MATCHED status, i.e. how many DataReaders this
+  /// DataWriter has been matched with in total, and how many are currently
+  /// matched.
   ///
-  /// // Liveliness lost status has changed
+  /// Counts behave as in
+  /// [`get_liveliness_lost_status`](Self::get_liveliness_lost_status). The
+  /// change of the current count may also be negative.
   ///
-  /// if let Ok(oiqs) = data_writer.get_offered_incompatible_qos_status() {
-  ///   // do something
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
   /// }
+  ///
+  /// // WithKey is important
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status = data_writer.get_publication_matched_status();
+  /// println!("Currently matched with {} readers", status.current.count());
   /// ```
-  pub fn get_offered_incompatible_qos_status(&self) -> Result<OfferedIncompatibleQosStatus> {
-    todo!()
+  pub fn get_publication_matched_status(&self) -> PublicationMatchedStatus {
+    let status = self.status_record().publication_matched();
+    self.status_read(StatusKind::PublicationMatched);
+    status
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// Gets the StatusCondition of this DataWriter, for waiting on status
+  /// changes in a [`WaitSet`](crate::WaitSet).
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::with_key::DataWriter;
@@ -645,21 +822,91 @@ where
   ///   }
   /// }
   ///
-  /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// // Liveliness lost status has changed
+  /// let status_condition = data_writer.get_statuscondition();
+  /// status_condition.set_enabled_statuses(StatusKind::PublicationMatched.into());
   ///
-  /// if let Ok(pms) = data_writer.get_publication_matched_status() {
-  ///   // do something
+  /// let mut waitset = WaitSet::new().unwrap();
+  /// waitset.attach_condition(&status_condition);
+  /// // Waits until a DataReader is matched, or times out
+  /// let _ = waitset.wait(std::time::Duration::from_millis(10));
+  /// ```
+  pub fn get_statuscondition(&self) -> StatusCondition {
+    self.status_condition.clone()
+  }
+
+  /// Sets a listener, which is called when the statuses in `mask` change in
+  /// this DataWriter. Statuses not in `mask` are passed on to the listener of
+  /// the Publisher, and then the DomainParticipant. Setting `None` removes
+  /// the listener.
+  ///
+  /// The listener is called from a thread managed by RustDDS.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use std::sync::Arc;
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
   /// }
+  ///
+  /// struct MatchCounter;
+  /// impl DataWriterListener for MatchCounter {
+  ///   fn on_publication_matched(&self, _writer: GUID, status: &DataWriterStatus) {
+  ///     println!("{status:?}");
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_writer.set_listener(
+  ///   Some(Arc::new(MatchCounter)),
+  ///   StatusKind::PublicationMatched.into(),
+  /// );
   /// ```
-  pub fn get_publication_matched_status(&self) -> Result<PublicationMatchedStatus> {
-    todo!()
+  pub fn set_listener(
+    &self,
+    listener: Option<Arc<dyn DataWriterListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self
+      .my_publisher
+      .set_writer_listener(self.my_guid, listener, mask);
+  }
+
+  // The application has seen the current value of a status
+  // The record only holds counters, so it is usable even if a panic poisoned
+  // the lock.
+  fn status_record(&self) -> MutexGuard<'_, DataWriterStatusRecord> {
+    self
+      .status_record
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
   }
 
-  */
+  fn status_read(&self, kind: StatusKind) {
+    if let Some(status_changes) = self.status_receiver.status_changes() {
+      status_changes.clear(kind);
+    }
+  }
 
   /// Topic assigned to this DataWriter
   ///
@@ -870,6 +1117,142 @@ where
     &self,
     key: &<D as Keyed>::K,
     source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    self.send_key_change(key, ChangeKind::NotAliveDisposed, source_timestamp)
+  }
+
+  /// Tells DDS that this DataWriter is going to write the instance, and returns
+  /// its key.
+  ///
+  /// Writing a sample registers its instance automatically, so calling this is
+  /// optional. Registering sends nothing to Readers.
+  ///
+  /// Fails with [`WriteError::OutOfResources`] if registering the instance
+  /// would exceed `max_instances` of the ResourceLimits QoS policy.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.4.2.5 register_instance.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32, val: usize }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// // WithKey is important
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let key = data_writer.register_instance(&SomeType { a: 1, val: 3 }).unwrap();
+  /// assert_eq!(key, 1);
+  /// assert_eq!(data_writer.lookup_instance(&SomeType { a: 1, val: 4 }), Some(1));
+  /// ```
+  pub fn register_instance(&self, instance: &D) -> WriteResult<D::K, ()> {
+    let key = instance.key();
+    self
+      .check_instance_limit(&key)
+      .map_err(|reason| WriteError::OutOfResources { reason, data: () })?;
+    self.register_key(key.clone());
+    Ok(key)
+  }
+
+  /// Tells DDS that this DataWriter is no longer going to write the instance.
+  ///
+  /// Unlike [`dispose`](Self::dispose), this does not say that the instance
+  /// is gone, only that this writer no longer updates it. Readers see the
+  /// instance as `NotAliveNoWriters`.
+  ///
+  /// Fails with [`WriteError::PreconditionNotMet`] if the instance has not
+  /// been written or registered by this DataWriter.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.4.2.7 unregister_instance.
+  ///
+  /// # Arguments
+  ///
+  /// * `key` - Key of the instance
+  /// * `source_timestamp` - DDS source timestamp (None uses now as time as
+  ///   specified in DDS spec)
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32, val: usize }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// // WithKey is important
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_writer.write(SomeType { a: 1, val: 3 }, None).unwrap();
+  /// data_writer.unregister_instance(&1, None).unwrap();
+  /// assert_eq!(data_writer.lookup_instance(&SomeType { a: 1, val: 3 }), None);
+  /// ```
+  pub fn unregister_instance(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    if !self.registered_instances.lock()?.contains(key) {
+      return Err(WriteError::PreconditionNotMet {
+        reason: "Instance is not registered".to_string(),
+      });
+    }
+    self.send_key_change(key, ChangeKind::NotAliveUnregistered, source_timestamp)?;
+    self.registered_instances.lock()?.remove(key);
+    Ok(())
+  }
+
+  /// Returns the key of the instance, if this DataWriter has registered it,
+  /// either explicitly or by writing.
+  ///
+  /// See DDS Spec 1.4 Section 2.2.2.4.2.10 lookup_instance.
+  pub fn lookup_instance(&self, instance: &D) -> Option<D::K> {
+    let key = instance.key();
+    self
+      .registered_instances
+      .lock()
+      .unwrap()
+      .contains(&key)
+      .then_some(key)
+  }
+
+  // Sends a key-only change, i.e. dispose or unregister.
+  fn send_key_change(
+    &self,
+    key: &<D as Keyed>::K,
+    change_kind: ChangeKind,
+    source_timestamp: Option<Timestamp>,
   ) -> WriteResult<(), ()> {
     let send_buffer = SA::key_to_bytes(key).map_err(|e| WriteError::Serialization {
       reason: format!("{e}"),
@@ -877,15 +1260,19 @@ where
     })?; // serialize key
 
     let ddsdata = DDSData::new_disposed_by_key(
-      ChangeKind::NotAliveDisposed,
+      change_kind,
       SerializedPayload::new_from_bytes(SA::output_encoding(), send_buffer),
     );
+    let sequence_number = self.next_sequence_number();
     self
       .cc_upload
       .send(WriterCommand::DDSData {
         ddsdata,
-        write_options: WriteOptions::from(source_timestamp),
-        sequence_number: self.next_sequence_number(),
+        write_options: self
+          .with_coherent_set(WriteOptions::from(source_timestamp), sequence_number),
+        sequence_number,
+        filterable_sample: None, // dispose or unregister is not filtered
+        instance: self.deadline_instance(key),
       })
       .map_err(|e| {
         self.undo_sequence_number();
@@ -943,6 +1330,74 @@ where
   }
 }
 
+/// Changes the QoS of the DataWriter. The policies defined in `new_qos` replace
+/// the current ones, and the rest are left unchanged.
+///
+/// Only Deadline, LatencyBudget, Ownership strength, TimeBasedFilter, and
+/// Lifespan can be changed. Attempting to change other policies results in
+/// [`QosError::ImmutablePolicy`], and nothing is changed.
+///
+/// The new QoS is announced to remote DataReaders via Discovery, and matches to
+/// DataReaders are re-evaluated, because they may have become compatible or
+/// incompatible.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::{Serialize, Deserialize};
+/// # use rustdds::*;
+/// use rustdds::qos::{policy::Deadline, MutQosPolicy};
+///
+/// let domain_participant = DomainParticipant::new(0).unwrap();
+/// let qos = QosPolicyBuilder::new().build();
+/// let publisher = domain_participant.create_publisher(&qos).unwrap();
+///
+/// #[derive(Serialize, Deserialize, Debug)]
+/// struct SomeType { a: i32 }
+/// impl Keyed for SomeType {
+///   type K = i32;
+///
+///   fn key(&self) -> Self::K {
+///     self.a
+///   }
+/// }
+///
+/// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+/// let mut data_writer = publisher.create_datawriter_cdr::<SomeType>(&topic, None).unwrap();
+///
+/// let deadline = QosPolicyBuilder::new()
+///   .deadline(Deadline(Duration::from_millis(500)))
+///   .build();
+/// data_writer.set_qos(&deadline).unwrap();
+///
+/// let reliable = QosPolicyBuilder::new()
+///   .reliable(Duration::from_millis(100))
+///   .build();
+/// assert!(data_writer.set_qos(&reliable).is_err());
+/// ```
+impl<D, SA> MutQosPolicy for DataWriter<D, SA>
+where
+  D: Keyed,
+  SA: SerializerAdapter<D>,
+{
+  fn set_qos(&mut self, new_qos: &QosPolicies) -> Result<(), QosError> {
+    let qos = self.qos_policy.modify_by(new_qos);
+    if let Some(policy) = self.qos_policy.immutable_policy_change(&qos) {
+      return Err(QosError::ImmutablePolicy { policy });
+    }
+    self.qos_policy = qos.clone();
+    self.my_publisher.update_writer_qos(self.my_guid, &qos);
+    self
+      .discovery_command
+      .try_send(DiscoveryCommand::UpdateLocalWriterQos {
+        guid: self.my_guid,
+        qos,
+      })
+      .unwrap_or_else(|e| error!("Cannot announce new QoS of {:?}: {e:?}", self.my_guid));
+    Ok(())
+  }
+}
+
 //-------------------------------------------------------------------------------
 // async writing implementation
 //
@@ -955,10 +1410,14 @@ where
 {
   writer: &'a DataWriter<D, SA>,
   writer_command: Option<WriterCommand>,
-  sequence_number: SequenceNumber,
+  sequence_number: SequenceNumber, // zero until room in history is reserved
   timeout: Option<duration::Duration>,
   timeout_instant: Instant,
   sample: Option<D>,
+  // Instance and contents of the sample, while room in history is not yet
+  // reserved for it. The WriterCommand is made once it is. Only with reliable
+  // KEEP_ALL.
+  unreserved: Option<(KeyHash, DDSData, WriteOptions)>,
 }
 
 // This is required, because AsyncWrite contains "D".
@@ -978,10 +1437,35 @@ where
   type Output = WriteResult<SampleIdentity, D>;
 
   fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    if let Some((instance, ..)) = self.unreserved {
+      let writer = self.writer;
+      match writer.history_reservations.try_reserve_or_wake(
+        instance,
+        || writer.next_sequence_number(),
+        cx.waker(),
+      ) {
+        Some(sequence_number) => {
+          let (_instance, ddsdata, write_options) = self.unreserved.take().unwrap();
+          let data = self.sample.as_ref().unwrap();
+          let writer_command = writer.data_command(ddsdata, write_options, sequence_number, data);
+          self.writer_command = Some(writer_command);
+          self.sequence_number = sequence_number;
+        }
+        None if Instant::now() < self.timeout_instant => return Poll::Pending,
+        None => {
+          return Poll::Ready(Err(WriteError::WouldBlock {
+            data: self.sample.take().unwrap(),
+          }));
+        }
+      }
+    }
     match self.writer_command.take() {
       Some(wc) => {
         match self.writer.cc_upload.try_send(wc) {
           Ok(()) => {
+            if let Some(data) = &self.sample {
+              self.writer.register_key(data.key());
+            }
             self.writer.refresh_manual_liveliness();
             Poll::Ready(Ok(SampleIdentity {
               writer_guid: self.writer.my_guid,
@@ -989,12 +1473,16 @@ where
             }))
           }
           Err(TrySendError::Full(wc)) => {
-            *self.writer.cc_upload_waker.lock().unwrap() = Some(cx.waker().clone());
+            self.writer.cc_upload_wakers.register(cx.waker());
             if Instant::now() < self.timeout_instant {
               // Put our command back
               self.writer_command = Some(wc);
               Poll::Pending
             } else {
+              self
+                .writer
+                .history_reservations
+                .cancel(self.sequence_number);
               // TODO: unwrap
               Poll::Ready(Err(WriteError::WouldBlock {
                 data: self.sample.take().unwrap(),
@@ -1010,6 +1498,10 @@ where
             );
             // TODO: Is this (undo) the right thing to do, if there are
             // several futures in progress? (Can this result in confused numbering?)
+            self
+              .writer
+              .history_reservations
+              .cancel(self.sequence_number);
             self.writer.undo_sequence_number();
             Poll::Ready(Err(WriteError::Poisoned {
               reason: format!("{other_err}"),
@@ -1097,6 +1589,9 @@ where
           _ => unreachable!(),
         };
 
+        // Registered before trying, so that the wakeup is not missed, if the
+        // channel gets room right after a failed try.
+        writer.cc_upload_wakers.register(cx.waker());
         match writer
           .cc_upload
           .try_send(WriterCommand::WaitForAcknowledgments {
@@ -1169,28 +1664,42 @@ where
       }
     };
 
+    if let Err(reason) = self.check_instance_limit(&data.key()) {
+      return Err(WriteError::OutOfResources { reason, data });
+    }
+
     let dds_data = DDSData::new(SerializedPayload::new_from_bytes(
       SA::output_encoding(),
       send_buffer,
     ));
-    let sequence_number = self.next_sequence_number();
-    let writer_command = WriterCommand::DDSData {
-      ddsdata: dds_data,
-      write_options,
-      sequence_number,
+
+    // With reliable KEEP_ALL, the sequence number is taken only once there is
+    // room in history.
+    let (writer_command, sequence_number, unreserved) = if self.history_reservations.is_limited() {
+      let instance = data.key().hash_key(false);
+      (
+        None,
+        SequenceNumber::zero(),
+        Some((instance, dds_data, write_options)),
+      )
+    } else {
+      let sequence_number = self.next_sequence_number();
+      let writer_command = self.data_command(dds_data, write_options, sequence_number, &data);
+      (Some(writer_command), sequence_number, None)
     };
 
     let timeout = self.qos().reliable_max_blocking_time();
 
     let write_future = AsyncWrite {
       writer: self,
-      writer_command: Some(writer_command),
+      writer_command,
       sequence_number,
       timeout,
       timeout_instant: std::time::Instant::now()
         + timeout
           .map(|t| t.to_std())
           .unwrap_or(crate::dds::helpers::TIMEOUT_FALLBACK.to_std()),
+      unreserved,
       sample: Some(data),
     };
     write_future.await
@@ -1315,6 +1824,126 @@ mod tests {
     // TODO: verify that dispose is sent correctly
   }
 
+  #[test]
+  fn dw_register_and_unregister_test() {
+    let domain_participant = DomainParticipant::new(0).expect("Publisher creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "Aasii".to_string(),
+        "Huh?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher
+        .create_datawriter(&topic, None)
+        .expect("Failed to create datawriter");
+
+    let data_1 = RandomData {
+      a: 1,
+      b: "Fobar".to_string(),
+    };
+    let data_2 = RandomData {
+      a: 2,
+      b: "Fobar".to_string(),
+    };
+
+    assert_eq!(data_writer.lookup_instance(&data_1), None);
+    assert_eq!(
+      data_writer.register_instance(&data_1).unwrap(),
+      data_1.key()
+    );
+    assert_eq!(data_writer.lookup_instance(&data_1), Some(data_1.key()));
+
+    // Writing registers too
+    data_writer
+      .write(data_2.clone(), None)
+      .expect("Unable to write data");
+    assert_eq!(data_writer.lookup_instance(&data_2), Some(data_2.key()));
+
+    data_writer
+      .unregister_instance(&data_2.key(), None)
+      .expect("Unable to unregister instance");
+    assert_eq!(data_writer.lookup_instance(&data_2), None);
+    assert!(matches!(
+      data_writer.unregister_instance(&data_2.key(), None),
+      Err(WriteError::PreconditionNotMet { .. })
+    ));
+    assert_eq!(data_writer.lookup_instance(&data_1), Some(data_1.key()));
+  }
+
+  #[test]
+  fn dw_max_instances_test() {
+    use crate::dds::qos::{policy, QosPolicyBuilder};
+
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "max_instances_topic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let writer_qos = QosPolicyBuilder::new()
+      .resource_limits(policy::ResourceLimits {
+        max_samples: 10,
+        max_instances: 1,
+        max_samples_per_instance: 10,
+      })
+      .build();
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher
+        .create_datawriter(&topic, Some(writer_qos))
+        .expect("Failed to create datawriter");
+
+    let data_1 = RandomData {
+      a: 1,
+      b: "Fobar".to_string(),
+    };
+    let data_2 = RandomData {
+      a: 2,
+      b: "Fobar".to_string(),
+    };
+
+    data_writer
+      .write(data_1.clone(), None)
+      .expect("Unable to write data");
+    // The same instance can be written again.
+    data_writer
+      .write(data_1.clone(), None)
+      .expect("Unable to write data");
+
+    assert!(matches!(
+      data_writer.write(data_2.clone(), None),
+      Err(WriteError::OutOfResources { .. })
+    ));
+    assert!(matches!(
+      data_writer.register_instance(&data_2),
+      Err(WriteError::OutOfResources { .. })
+    ));
+    assert_eq!(data_writer.lookup_instance(&data_2), None);
+
+    data_writer
+      .unregister_instance(&data_1.key(), None)
+      .expect("Unable to unregister instance");
+    data_writer
+      .write(data_2.clone(), None)
+      .expect("Unable to write data");
+    assert_eq!(data_writer.lookup_instance(&data_2), Some(data_2.key()));
+  }
+
   #[test]
   fn dw_wait_for_ack_test() {
     let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
@@ -1349,4 +1978,76 @@ mod tests {
     assert!(res); // we should get "true" immediately, because we have
                   // no Reliable QoS
   }
+
+  #[test]
+  fn dw_set_qos_test() {
+    use crate::dds::qos::{policy, QosPolicyBuilder, QosPolicyId};
+
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "set_qos_topic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let writer_qos = QosPolicyBuilder::new()
+      .ownership(policy::Ownership::Exclusive { strength: 1 })
+      .build();
+    let mut data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher
+        .create_datawriter(&topic, Some(writer_qos))
+        .expect("Failed to create datawriter");
+
+    let deadline = policy::Deadline(duration::Duration::from_millis(200));
+    let changeable = QosPolicyBuilder::new()
+      .deadline(deadline)
+      .ownership(policy::Ownership::Exclusive { strength: 5 })
+      .build();
+    data_writer.set_qos(&changeable).unwrap();
+    assert_eq!(data_writer.qos().deadline(), Some(deadline));
+    assert_eq!(
+      data_writer.qos().ownership(),
+      Some(policy::Ownership::Exclusive { strength: 5 })
+    );
+    assert_eq!(publisher.datawriters()[0].qos.deadline(), Some(deadline));
+
+    let shared = QosPolicyBuilder::new()
+      .ownership(policy::Ownership::Shared)
+      .build();
+    assert!(matches!(
+      data_writer.set_qos(&shared),
+      Err(QosError::ImmutablePolicy {
+        policy: QosPolicyId::Ownership
+      })
+    ));
+    let reliable = QosPolicyBuilder::new()
+      .reliable(duration::Duration::from_millis(100))
+      .deadline(policy::Deadline(duration::Duration::from_millis(300)))
+      .build();
+    assert!(matches!(
+      data_writer.set_qos(&reliable),
+      Err(QosError::ImmutablePolicy {
+        policy: QosPolicyId::Reliability
+      })
+    ));
+    // Rejected change has no effect
+    assert_eq!(data_writer.qos().deadline(), Some(deadline));
+
+    data_writer
+      .write(
+        RandomData {
+          a: 1,
+          b: "after set_qos".to_string(),
+        },
+        None,
+      )
+      .expect("Unable to write data");
+  }
 }
@@ -1,6 +1,6 @@
 use std::{
   cmp::max,
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet},
   io,
   marker::PhantomData,
   pin::Pin,
@@ -8,30 +8,47 @@ use std::{
   task::{Context, Poll, Waker},
 };
 
-use futures::stream::{FusedStream, Stream};
+use futures::{
+  future,
+  stream::{FusedStream, Stream, StreamExt},
+};
 use serde::de::DeserializeOwned;
 use mio_extras::channel as mio_channel;
+use enumflags2::BitFlags;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
 use crate::{
+  read_error_internal, read_error_poisoned,
   dds::{
     adapters::with_key::*,
     ddsdata::*,
+    helpers::try_send_timeout,
     key::*,
+    listener::DataReaderListener,
     pubsub::Subscriber,
     qos::*,
     result::*,
     statusevents::*,
-    topic::{Topic, TopicDescription},
+    topic::{SampleFilter, Topic, TopicDescription},
+    waitset::StatusCondition,
     with_key::datasample::{DeserializedCacheChange, Sample},
   },
-  discovery::discovery::DiscoveryCommand,
+  discovery::{
+    discovery::DiscoveryCommand, discovery_db::discovery_db_read,
+    sedp_messages::PublicationBuiltinTopicData,
+  },
   mio_source::PollEventSource,
+  rtps::{
+    coherent_set::{CoherentSetTracker, CoherentSets},
+    command_channel_wakers::CommandChannelWakers,
+    reader::{DataAvailability, MatchedWriterGuids, WriterStrengths},
+  },
   serialization::CDRDeserializerAdapter,
   structure::{
     cache_change::CacheChange,
     dds_cache::TopicCache,
+    duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GUID},
     sequence_number::SequenceNumber,
@@ -39,10 +56,19 @@ use crate::{
   },
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub(crate) enum ReaderCommand {
   #[allow(dead_code)] // TODO: Implement this (resetting) feature
   ResetRequestedDeadlineStatus,
+  // Signal when historical data of all matched writers has been received.
+  WaitForHistoricalData {
+    all_received: StatusChannelSender<()>,
+  },
+  // Notify the DataReader of available data at the given time, when a sample
+  // held back by TIME_BASED_FILTER becomes deliverable.
+  NotifyDataAvailableAt {
+    at: Timestamp,
+  },
 }
 
 // This is helper struct.
@@ -105,13 +131,29 @@ pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializ
 
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusChannelReceiver<DataReaderStatus>,
+  // For statuses that arise above the RTPS Reader, e.g. SampleRejected
+  status_sender: StatusChannelSender<DataReaderStatus>,
 
-  #[allow(dead_code)] // TODO: This is currently unused, because we do not implement
-  // resetting deadline missed status. Remove attribute when it is supported.
   reader_command: mio_channel::SyncSender<ReaderCommand>,
+  reader_command_wakers: Arc<CommandChannelWakers>,
   data_reader_waker: Arc<Mutex<Option<Waker>>>,
 
   event_source: PollEventSource,
+
+  // Filter of the ContentFilteredTopic, if the reader was created for one.
+  sample_filter: Option<SampleFilter<D>>,
+
+  // Maintained by the RTPS Reader
+  writer_strengths: WriterStrengths,
+  matched_writer_guids: MatchedWriterGuids,
+
+  // Coherent sets that are not complete yet. Maintained by the RTPS Reader.
+  coherent_sets: Option<CoherentSets>,
+
+  // Shared with the RTPS Reader, which sends the DataAvailable events
+  data_availability: Arc<DataAvailability>,
+
+  status_condition: StatusCondition,
 }
 
 impl<D, DA> Drop for SimpleDataReader<D, DA>
@@ -156,9 +198,16 @@ where
     topic_cache: Arc<Mutex<TopicCache>>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_receiver: StatusChannelReceiver<DataReaderStatus>,
+    status_sender: StatusChannelSender<DataReaderStatus>,
     reader_command: mio_channel::SyncSender<ReaderCommand>,
+    reader_command_wakers: Arc<CommandChannelWakers>,
     data_reader_waker: Arc<Mutex<Option<Waker>>>,
     event_source: PollEventSource,
+    sample_filter: Option<SampleFilter<D>>,
+    writer_strengths: WriterStrengths,
+    matched_writer_guids: MatchedWriterGuids,
+    coherent_sets: Option<CoherentSets>,
+    data_availability: Arc<DataAvailability>,
   ) -> CreateResult<Self> {
     let dp = match subscriber.participant() {
       Some(dp) => dp,
@@ -183,6 +232,11 @@ where
       });
     }
 
+    let status_condition = StatusCondition::new(
+      Arc::clone(data_availability.status_changes()),
+      Some(Arc::clone(&data_availability)),
+    );
+
     Ok(Self {
       my_subscriber: subscriber,
       qos_policy,
@@ -194,9 +248,17 @@ where
       deserializer_type: PhantomData,
       discovery_command,
       status_receiver,
+      status_sender,
       reader_command,
+      reader_command_wakers,
       data_reader_waker,
       event_source,
+      sample_filter,
+      writer_strengths,
+      matched_writer_guids,
+      coherent_sets,
+      data_availability,
+      status_condition,
     })
   }
   pub(crate) fn set_waker(&self, w: Option<Waker>) {
@@ -204,22 +266,115 @@ where
   }
 
   pub(crate) fn drain_read_notifications(&self) {
+    // Data arriving from now on is new to us
+    self.data_availability.data_read();
     let rec = self.notification_receiver.lock().unwrap();
     while rec.try_recv().is_ok() {}
     self.event_source.drain();
   }
 
+  // Ask the RTPS Reader to notify us again at the given time.
+  pub(crate) fn notify_data_available_at(&self, at: Timestamp) {
+    self
+      .reader_command
+      .try_send(ReaderCommand::NotifyDataAvailableAt { at })
+      .unwrap_or_else(|e| warn!("Cannot reach Reader to schedule notification: {e:?}"));
+  }
+
+  pub(crate) fn data_availability(&self) -> &Arc<DataAvailability> {
+    &self.data_availability
+  }
+
+  pub(crate) fn status_condition(&self) -> &StatusCondition {
+    &self.status_condition
+  }
+
+  pub(crate) fn set_listener(
+    &self,
+    listener: Option<Arc<dyn DataReaderListener>>,
+    mask: BitFlags<StatusKind>,
+  ) {
+    self
+      .my_subscriber
+      .set_reader_listener(self.my_guid, listener, mask);
+  }
+
+  pub(crate) fn set_data_available_status(&self, enabled: bool) {
+    self.data_availability.set_status_enabled(enabled);
+  }
+
   fn try_take_undecoded<'a>(
     is_reliable: bool,
     topic_cache: &'a TopicCache,
     latest_instant: Timestamp,
     last_read_sn: &'a BTreeMap<GUID, SequenceNumber>,
+    coherent_sets: Option<&'a CoherentSetTracker>,
   ) -> Box<dyn Iterator<Item = (Timestamp, &'a CacheChange)> + 'a> {
-    if is_reliable {
+    let changes = if is_reliable {
       topic_cache.get_changes_in_range_reliable(last_read_sn)
     } else {
       topic_cache.get_changes_in_range_best_effort(latest_instant, Timestamp::now())
+    };
+
+    let Some(coherent_sets) = coherent_sets else {
+      return changes;
+    };
+    let is_withheld = |cc: &CacheChange| {
+      coherent_sets.is_withheld(
+        cc.writer_guid,
+        cc.sequence_number,
+        cc.write_options.coherent_set(),
+      )
+    };
+    // Changes are read in sequence number order from each Writer, so nothing
+    // more can be read from a Writer after a withheld change. Changes of other
+    // Writers can be read past it.
+    let mut withheld_writers = BTreeSet::new();
+    Box::new(changes.filter(move |(_, cc)| {
+      if withheld_writers.contains(&cc.writer_guid) || is_withheld(cc) {
+        withheld_writers.insert(cc.writer_guid);
+        false
+      } else {
+        // The Best Effort read pointer is held back at withheld changes, so
+        // changes after it may have been read already.
+        last_read_sn
+          .get(&cc.writer_guid)
+          .map_or(true, |last_read| *last_read < cc.sequence_number)
+      }
+    }))
+  }
+
+  // Timestamp of the first withheld change, which the Best Effort read pointer
+  // must not move past.
+  fn first_withheld_instant(
+    is_reliable: bool,
+    topic_cache: &TopicCache,
+    latest_instant: Timestamp,
+    coherent_sets: Option<&CoherentSetTracker>,
+  ) -> Option<Timestamp> {
+    let coherent_sets = coherent_sets.filter(|_| !is_reliable)?;
+    topic_cache
+      .get_changes_in_range_best_effort(latest_instant, Timestamp::now())
+      .find(|(_, cc)| {
+        coherent_sets.is_withheld(
+          cc.writer_guid,
+          cc.sequence_number,
+          cc.write_options.coherent_set(),
+        )
+      })
+      .map(|(instant, _)| instant)
+  }
+
+  fn advance_read_pointer(
+    read_state: &mut ReadState<D::K>,
+    timestamp: Timestamp,
+    (writer_guid, sequence_number): (GUID, SequenceNumber),
+    withheld_instant: Option<Timestamp>,
+  ) {
+    if withheld_instant.map_or(true, |withheld| timestamp < withheld) {
+      read_state.latest_instant = max(read_state.latest_instant, timestamp);
     }
+    read_state.last_read_sn.insert(writer_guid, sequence_number);
   }
 
   fn update_hash_to_key_map(
@@ -327,6 +482,11 @@ where
           })
         }
       }
+
+      // try_take_one() skips these
+      DDSData::CoherentSetEnd => Err(ReadError::Internal {
+        reason: "Coherent set end marker is not a sample".to_string(),
+      }),
     } // match
   }
 
@@ -341,18 +501,62 @@ where
     let topic_cache = self.acquire_the_topic_cache_guard();
 
     let mut read_state_ref = self.read_state.lock().unwrap();
-    let latest_instant = read_state_ref.latest_instant;
-    let (last_read_sn, hash_to_key_map) = read_state_ref.get_sn_map_and_hash_map();
+
+    let mut coherent_sets = self
+      .coherent_sets
+      .as_ref()
+      .map(|coherent_sets| coherent_sets.lock().unwrap());
+    if let Some(coherent_sets) = coherent_sets.as_mut().filter(|_| !is_reliable) {
+      // Best Effort sets may never complete.
+      coherent_sets.expire(Timestamp::now());
+    }
 
     // loop in case we get a sample that should be ignored, so we try next.
     loop {
-      let (timestamp, cc) =
-        match Self::try_take_undecoded(is_reliable, &topic_cache, latest_instant, last_read_sn)
-          .next()
-        {
-          None => return Ok(None), // no more data available right now
-          Some((ts, cc)) => (ts, cc),
-        };
+      let latest_instant = read_state_ref.latest_instant;
+      let withheld_instant = Self::first_withheld_instant(
+        is_reliable,
+        &topic_cache,
+        latest_instant,
+        coherent_sets.as_deref(),
+      );
+      let (last_read_sn, hash_to_key_map) = read_state_ref.get_sn_map_and_hash_map();
+
+      let (timestamp, cc) = match Self::try_take_undecoded(
+        is_reliable,
+        &topic_cache,
+        latest_instant,
+        last_read_sn,
+        coherent_sets.as_deref(),
+      )
+      .next()
+      {
+        None => return Ok(None), // no more data available right now
+        Some((ts, cc)) => (ts, cc),
+      };
+
+      if cc.data_value == DDSData::CoherentSetEnd {
+        // Nothing to deliver, just move the read pointers past it.
+        let change_id = (cc.writer_guid, cc.sequence_number);
+        Self::advance_read_pointer(&mut read_state_ref, timestamp, change_id, withheld_instant);
+        continue;
+      }
+
+      if let Some(policy::Lifespan { duration }) = self.qos_policy.lifespan() {
+        let source_timestamp = cc.write_options.source_timestamp().unwrap_or(timestamp);
+        if source_timestamp + duration < Timestamp::now() {
+          // Expired sample is not delivered, but read pointers move past it.
+          debug!(
+            "Sample {:?} from {:?} lifespan exceeded. Topic = {}",
+            cc.sequence_number,
+            cc.writer_guid,
+            self.my_topic.name()
+          );
+          let change_id = (cc.writer_guid, cc.sequence_number);
+          Self::advance_read_pointer(&mut read_state_ref, timestamp, change_id, withheld_instant);
+          continue;
+        }
+      }
 
       let result = self.deserialize(timestamp, cc, hash_to_key_map);
 
@@ -365,10 +569,12 @@ where
         let sequence_number = cc.sequence_number;
         // Advance read pointer, error or not, because otherwise
         // the SimpleDatareader is stuck.
-        read_state_ref.latest_instant = max(latest_instant, timestamp);
-        read_state_ref
-          .last_read_sn
-          .insert(writer_guid, sequence_number);
+        Self::advance_read_pointer(
+          &mut read_state_ref,
+          timestamp,
+          (writer_guid, sequence_number),
+          withheld_instant,
+        );
 
         // // Debug sanity check:
         // use crate::Duration;
@@ -379,15 +585,188 @@ where
         //      );
         // }
 
+        // Samples not passing the content filter are skipped. Disposes are
+        // always delivered, as they carry no data to filter on.
+        if let (
+          Some(filter),
+          Ok(DeserializedCacheChange {
+            sample: Sample::Value(d),
+            ..
+          }),
+        ) = (&self.sample_filter, &result)
+        {
+          if !filter.matches(d) {
+            trace!("Sample {sequence_number:?} from {writer_guid:?} filtered out");
+            continue;
+          }
+        }
+
         return result.map(Some);
       }
     }
   }
 
+  // Historical data is sent only to durable readers, and only a reliable
+  // Reader knows when it has all of it.
+  fn expects_historical_data(&self) -> bool {
+    self.qos_policy.is_reliable()
+      && !matches!(
+        self.qos_policy.durability(),
+        None | Some(policy::Durability::Volatile)
+      )
+  }
+
+  // Ask the RTPS Reader to signal to the returned receiver when the
+  // historical data of all matched writers has been received.
+  fn historical_data_channel(
+    &self,
+  ) -> ReadResult<(StatusChannelSender<()>, StatusChannelReceiver<()>)> {
+    sync_status_channel::<()>(1).or_else(|e| read_error_internal!("Cannot create channel: {e}"))
+  }
+
+  pub(crate) fn wait_for_historical_data(&self, max_wait: Duration) -> ReadResult<bool> {
+    if !self.expects_historical_data() {
+      return Ok(true);
+    }
+    let deadline = Timestamp::now() + max_wait;
+    let (all_received_sender, mut all_received_receiver) = self.historical_data_channel()?;
+    let poll = mio_06::Poll::new().or_else(|e| read_error_internal!("Cannot create poll: {e}"))?;
+    poll
+      .register(
+        all_received_receiver.as_status_evented(),
+        mio_06::Token(0),
+        mio_06::Ready::readable(),
+        mio_06::PollOpt::edge(),
+      )
+      .or_else(|e| read_error_internal!("Cannot register poll: {e}"))?;
+
+    let command = ReaderCommand::WaitForHistoricalData {
+      all_received: all_received_sender,
+    };
+    match try_send_timeout(&self.reader_command, command, Some(max_wait)) {
+      Ok(()) => (),
+      Err(mio_channel::TrySendError::Full(_)) => {
+        warn!("wait_for_historical_data: Reader is not responding. Timing out.");
+        return Ok(false);
+      }
+      Err(e) => return read_error_poisoned!("wait_for_historical_data: Cannot reach Reader: {e}"),
+    }
+
+    let mut events = mio_06::Events::with_capacity(1);
+    loop {
+      if all_received_receiver.try_recv().is_ok() {
+        return Ok(true);
+      }
+      // Poll may also return without an event, so check the time.
+      let now = Timestamp::now();
+      if now >= deadline {
+        return Ok(false);
+      }
+      poll
+        .poll(&mut events, Some(deadline.duration_since(now).to_std()))
+        .or_else(|e| read_error_internal!("Cannot poll: {e}"))?;
+    }
+  }
+
+  pub(crate) async fn async_wait_for_historical_data(&self) -> ReadResult<bool> {
+    if !self.expects_historical_data() {
+      return Ok(true);
+    }
+    let (all_received_sender, all_received_receiver) = self.historical_data_channel()?;
+
+    let mut command = Some(ReaderCommand::WaitForHistoricalData {
+      all_received: all_received_sender,
+    });
+    future::poll_fn(|cx| {
+      // Registered before trying, so that the wakeup is not missed, if the
+      // channel gets room right after a failed try.
+      self.reader_command_wakers.register(cx.waker());
+      match self.reader_command.try_send(command.take().unwrap()) {
+        Ok(()) => Poll::Ready(Ok(())),
+        Err(mio_channel::TrySendError::Full(c)) => {
+          // The Reader is busy. It wakes us up when it has taken commands.
+          command = Some(c);
+          Poll::Pending
+        }
+        Err(e) => Poll::Ready(read_error_poisoned!(
+          "async_wait_for_historical_data: Cannot reach Reader: {e}"
+        )),
+      }
+    })
+    .await?;
+
+    // None means that the Reader is gone.
+    Ok(
+      all_received_receiver
+        .as_async_status_stream()
+        .next()
+        .await
+        .is_some(),
+    )
+  }
+
   pub fn qos(&self) -> &QosPolicies {
     &self.qos_policy
   }
 
+  /// Changes the QoS of the DataReader. See
+  /// [`DataReader::set_qos`](crate::with_key::DataReader::set_qos).
+  pub fn set_qos(&mut self, new_qos: &QosPolicies) -> Result<(), QosError> {
+    let qos = self.qos_policy.modify_by(new_qos);
+    if let Some(policy) = self.qos_policy.immutable_policy_change(&qos) {
+      return Err(QosError::ImmutablePolicy { policy });
+    }
+    self.qos_policy = qos.clone();
+    self.my_subscriber.update_reader_qos(self.my_guid, &qos);
+    self
+      .discovery_command
+      .try_send(DiscoveryCommand::UpdateLocalReaderQos {
+        guid: self.my_guid,
+        qos,
+      })
+      .unwrap_or_else(|e| error!("Cannot announce new QoS of {:?}: {e:?}", self.my_guid));
+    Ok(())
+  }
+
+  pub(crate) fn writer_strengths(&self) -> WriterStrengths {
+    Arc::clone(&self.writer_strengths)
+  }
+
+  #[cfg(test)]
+  pub(crate) fn matched_writer_guids(&self) -> MatchedWriterGuids {
+    Arc::clone(&self.matched_writer_guids)
+  }
+
+  // Sends a status that the DataReader has detected itself.
+  pub(crate) fn send_status(&self, status: DataReaderStatus) {
+    if let Err(e) = self.status_sender.try_send(status) {
+      debug!("Cannot send DataReaderStatus: {e:?}");
+    }
+  }
+
+  pub(crate) fn matched_publications(&self) -> Vec<PublicationBuiltinTopicData> {
+    let matched_writers: Vec<GUID> = self
+      .matched_writer_guids
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+      .iter()
+      .copied()
+      .collect();
+    let Some(dp) = self.my_subscriber.participant() else {
+      return Vec::new(); // No participant, no discovery data
+    };
+    let discovery_db = dp.discovery_db();
+    let db = discovery_db_read(&discovery_db);
+    matched_writers
+      .iter()
+      .filter_map(|guid| {
+        db.get_topic_writer(guid)
+          .or_else(|| db.get_local_topic_writer(*guid))
+      })
+      .map(|dwd| dwd.publication_topic_data.clone())
+      .collect()
+  }
+
   pub fn guid(&self) -> GUID {
     self.my_guid
   }
@@ -558,6 +937,8 @@ where
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     debug!("poll_next");
+    // Data arriving after this is new, and causes a new DataAvailable status.
+    self.simple_datareader.drain_read_notifications();
     match self.simple_datareader.try_take_one() {
       Err(e) =>
       // DDS fails
@@ -582,6 +963,7 @@ where
         // }
         // // DEBUG
         self.simple_datareader.set_waker(Some(cx.waker().clone()));
+        self.simple_datareader.drain_read_notifications();
         match self.simple_datareader.try_take_one() {
           Err(e) => Poll::Ready(Some(Err(e))),
           Ok(Some(d)) => Poll::Ready(Some(Ok(d))),
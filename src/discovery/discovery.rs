@@ -56,7 +56,7 @@ use crate::{
 #[cfg(not(feature = "security"))]
 use crate::no_security::*;
 
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Eq, PartialEq)]
 pub enum DiscoveryCommand {
   StopDiscovery,
   AddLocalWriter {
@@ -74,7 +74,18 @@ pub enum DiscoveryCommand {
   RemoveLocalReader {
     guid: GUID,
   },
+  // Application has changed the QoS of a local DataWriter or DataReader.
+  UpdateLocalWriterQos {
+    guid: GUID,
+    qos: QosPolicies,
+  },
+  UpdateLocalReaderQos {
+    guid: GUID,
+    qos: QosPolicies,
+  },
   ManualAssertLiveliness,
+  // Application has marked some remote entities ignored.
+  RemoveIgnored,
   AssertTopicLiveliness {
     writer_guid: GUID,
     manual_assertion: bool,
@@ -760,10 +771,42 @@ impl Discovery {
                   self.send_endpoint_dispose_message(guid);
                   discovery_db_write(&self.discovery_db).remove_local_topic_reader(guid);
                 }
+                DiscoveryCommand::UpdateLocalWriterQos { guid, qos } => {
+                  discovery_db_write(&self.discovery_db).update_local_topic_writer_qos(guid, &qos);
+                  // Re-announce, so that remote matches are re-evaluated
+                  self.write_single_writer_info(guid);
+                  self.send_discovery_notification(
+                    DiscoveryNotificationType::LocalWriterQosUpdated {
+                      writer_guid: guid,
+                      qos,
+                    },
+                  );
+                }
+                DiscoveryCommand::UpdateLocalReaderQos { guid, qos } => {
+                  discovery_db_write(&self.discovery_db).update_local_topic_reader_qos(guid, &qos);
+                  self.write_single_reader_info(guid);
+                  self.send_discovery_notification(
+                    DiscoveryNotificationType::LocalReaderQosUpdated {
+                      reader_guid: guid,
+                      qos,
+                    },
+                  );
+                }
                 DiscoveryCommand::ManualAssertLiveliness => {
                   self
                     .liveliness_state
                     .manual_participant_liveness_refresh_requested = true;
+                  // Local endpoints learn of this right away. Remote ones get a
+                  // ParticipantMessage later.
+                  self.send_discovery_notification(
+                    DiscoveryNotificationType::ParticipantLivelinessAsserted {
+                      guid_prefix: self.domain_participant.guid_prefix(),
+                      kind: ParticipantMessageDataKind::MANUAL_LIVELINESS_UPDATE,
+                    },
+                  );
+                }
+                DiscoveryCommand::RemoveIgnored => {
+                  self.remove_ignored();
                 }
                 DiscoveryCommand::AssertTopicLiveliness {
                   writer_guid,
@@ -985,8 +1028,11 @@ impl Discovery {
     &mut self,
     participant_data: &SpdpDiscoveredParticipantData,
   ) {
-    let was_new = discovery_db_write(&self.discovery_db).update_participant(participant_data);
     let guid_prefix = participant_data.participant_guid.prefix;
+    if discovery_db_read(&self.discovery_db).is_ignored_participant(guid_prefix) {
+      return;
+    }
+    let was_new = discovery_db_write(&self.discovery_db).update_participant(participant_data);
     self.send_discovery_notification(DiscoveryNotificationType::ParticipantUpdated { guid_prefix });
     if was_new {
       let dpd = participant_data.into();
@@ -1002,6 +1048,22 @@ impl Discovery {
     }
   }
 
+  // Forget the ignored entities, and unmatch them from local endpoints.
+  // No status events are sent, because the application does not want to hear
+  // of them anymore.
+  fn remove_ignored(&self) {
+    let (participants, readers, writers) = discovery_db_write(&self.discovery_db).remove_ignored();
+    for guid_prefix in participants {
+      self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost { guid_prefix });
+    }
+    for reader_guid in readers {
+      self.send_discovery_notification(DiscoveryNotificationType::ReaderLost { reader_guid });
+    }
+    for writer_guid in writers {
+      self.send_discovery_notification(DiscoveryNotificationType::WriterLost { writer_guid });
+    }
+  }
+
   fn process_participant_dispose(&mut self, participant_guidp: GuidPrefix) {
     discovery_db_write(&self.discovery_db).remove_participant(participant_guidp, true); // true = actively removed
     self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost {
@@ -1105,7 +1167,9 @@ impl Discovery {
       if permission == NormalDiscoveryPermission::Allow {
         match d {
           Sample::Value(d) => {
-            let drd = discovery_db_write(&self.discovery_db).update_subscription(&d);
+            let Some(drd) = discovery_db_write(&self.discovery_db).update_subscription(&d) else {
+              continue; // ignored
+            };
             debug!(
               "handle_subscription_reader - send_discovery_notification ReaderUpdated  {:?}",
               &drd
@@ -1179,8 +1243,11 @@ impl Discovery {
         match d {
           Sample::Value(dwd) => {
             trace!("handle_publication_reader discovered {:?}", &dwd);
-            let discovered_writer_data =
-              discovery_db_write(&self.discovery_db).update_publication(&dwd);
+            let Some(discovered_writer_data) =
+              discovery_db_write(&self.discovery_db).update_publication(&dwd)
+            else {
+              continue; // ignored
+            };
             self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
               discovered_writer_data,
             });
@@ -1328,6 +1395,11 @@ impl Discovery {
     let mut db = discovery_db_write(&self.discovery_db);
     for msg in msgs {
       db.update_lease_duration(&msg);
+      // Readers track the liveliness of the remote writers
+      self.send_discovery_notification(DiscoveryNotificationType::ParticipantLivelinessAsserted {
+        guid_prefix: msg.guid,
+        kind: msg.kind,
+      });
     }
   }
 
@@ -1441,6 +1513,13 @@ impl Discovery {
           match msg_kind {
             ParticipantMessageDataKind::AUTOMATIC_LIVELINESS_UPDATE => {
               self.liveliness_state.last_auto_update = timenow;
+              // Local Readers do not receive our own ParticipantMessages
+              self.send_discovery_notification(
+                DiscoveryNotificationType::ParticipantLivelinessAsserted {
+                  guid_prefix: self.domain_participant.guid_prefix(),
+                  kind: msg_kind,
+                },
+              );
             }
             ParticipantMessageDataKind::MANUAL_LIVELINESS_UPDATE => {
               // We delivered what was requested
@@ -1579,10 +1658,13 @@ impl Discovery {
           Sample::Value(sec_sub) => {
             // Currently we use only the DiscoveredReaderData field, no DataTag
             let drd_from_topic = sec_sub.discovered_reader_data;
-            let drd = discovery_db_write(&self.discovery_db).update_subscription(&drd_from_topic);
-            self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
-              discovered_reader_data: drd,
-            });
+            if let Some(drd) =
+              discovery_db_write(&self.discovery_db).update_subscription(&drd_from_topic)
+            {
+              self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
+                discovered_reader_data: drd,
+              });
+            }
           }
           Sample::Dispose(reader_guid) => {
             info!("Secure Dispose Reader {:?}", reader_guid);
@@ -1639,10 +1721,13 @@ impl Discovery {
           Sample::Value(se_pub) => {
             // Currently we use only the DiscoveredWriterData field, no DataTag
             let dwd_from_topic = se_pub.discovered_writer_data;
-            let dwd = discovery_db_write(&self.discovery_db).update_publication(&dwd_from_topic);
-            self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
-              discovered_writer_data: dwd,
-            });
+            if let Some(dwd) =
+              discovery_db_write(&self.discovery_db).update_publication(&dwd_from_topic)
+            {
+              self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
+                discovered_writer_data: dwd,
+              });
+            }
           }
           Sample::Dispose(writer_guid) => {
             info!("Secure Dispose Writer {:?}", writer_guid);
@@ -1,5 +1,5 @@
 use std::{
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet},
   sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
   time::Instant,
 };
@@ -11,7 +11,7 @@ use log::{debug, error, info, trace, warn};
 use crate::{
   dds::{
     participant::DomainParticipant,
-    qos::HasQoSPolicy,
+    qos::{HasQoSPolicy, QosPolicies},
     statusevents::{DomainParticipantStatusEvent, LostReason, StatusChannelSender},
     topic::{Topic, TopicDescription},
   },
@@ -26,6 +26,7 @@ use crate::{
   },
 };
 use super::{
+  content_filter_property::ContentFilterProperty,
   sedp_messages::{
     topics_inconsistent, DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData,
     ParticipantMessageData, ReaderProxy, SubscriptionBuiltinTopicData, TopicBuiltinTopicData,
@@ -75,12 +76,47 @@ pub(crate) struct DiscoveryDB {
   // Inner key is topic data sender.
   topics: BTreeMap<String, BTreeMap<GUID, (DiscoveredVia, DiscoveredTopicData)>>,
 
+  // Remote topic data senders that disagree with our own definition of the
+  // topic, so that each is counted only once. Key is topic name. Senders are
+  // removed when their participant is lost.
+  inconsistent_topic_sources: BTreeMap<String, BTreeSet<GUID>>,
+  // Cumulative INCONSISTENT_TOPIC status counts. Key is topic name.
+  inconsistent_topic_counts: BTreeMap<String, i32>,
+
+  // Remote entities that the application does not want to hear of. Shared
+  // with MessageReceiver.
+  ignored: Arc<RwLock<IgnoredEntities>>,
+
   // sender for notifying (potential) waiters in participant.find_topic() call
   topic_updated_sender: mio_extras::channel::SyncSender<()>,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 }
 
+// Remote participants, endpoints, and topics set aside by the DDS ignore_*
+// operations. Ignoring cannot be reversed.
+#[derive(Debug, Default)]
+pub(crate) struct IgnoredEntities {
+  participants: BTreeSet<GuidPrefix>,
+  endpoints: BTreeSet<GUID>, // Readers and Writers
+  topics: BTreeSet<String>,
+}
+
+impl IgnoredEntities {
+  pub fn participant(&self, guid_prefix: GuidPrefix) -> bool {
+    self.participants.contains(&guid_prefix)
+  }
+
+  // Endpoints of ignored participants are also ignored.
+  pub fn endpoint(&self, guid: GUID) -> bool {
+    self.endpoints.contains(&guid) || self.participant(guid.prefix)
+  }
+
+  pub fn topic(&self, topic_name: &str) -> bool {
+    self.topics.contains(topic_name)
+  }
+}
+
 // How did we discover this topic
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub(crate) enum DiscoveredVia {
@@ -138,11 +174,102 @@ impl DiscoveryDB {
       external_topic_readers_attic: BTreeMap::new(),
       external_topic_writers_attic: BTreeMap::new(),
       topics: BTreeMap::new(),
+      inconsistent_topic_sources: BTreeMap::new(),
+      inconsistent_topic_counts: BTreeMap::new(),
+      ignored: Arc::new(RwLock::new(IgnoredEntities::default())),
       topic_updated_sender,
       participant_status_sender,
     }
   }
 
+  pub(crate) fn ignored_entities(&self) -> Arc<RwLock<IgnoredEntities>> {
+    Arc::clone(&self.ignored)
+  }
+
+  fn ignored(&self) -> RwLockReadGuard<'_, IgnoredEntities> {
+    self.ignored.read().unwrap()
+  }
+
+  // The ignore_* operations only mark entities ignored. Those already known
+  // are removed by remove_ignored().
+  pub fn ignore_participant(&mut self, guid_prefix: GuidPrefix) {
+    self
+      .ignored
+      .write()
+      .unwrap()
+      .participants
+      .insert(guid_prefix);
+  }
+
+  pub fn ignore_endpoint(&mut self, guid: GUID) {
+    self.ignored.write().unwrap().endpoints.insert(guid);
+  }
+
+  pub fn ignore_topic(&mut self, topic_name: String) {
+    self.ignored.write().unwrap().topics.insert(topic_name);
+  }
+
+  pub fn is_ignored_participant(&self, guid_prefix: GuidPrefix) -> bool {
+    self.ignored().participant(guid_prefix)
+  }
+
+  // Removes all ignored entities that we already know of. Returns the removed
+  // participants, Readers, and Writers, so that they can be unmatched.
+  pub fn remove_ignored(&mut self) -> (Vec<GuidPrefix>, Vec<GUID>, Vec<GUID>) {
+    let ignored = Arc::clone(&self.ignored);
+    let ignored = ignored.read().unwrap();
+
+    let participants: Vec<GuidPrefix> = self
+      .participant_proxies
+      .keys()
+      .filter(|p| ignored.participant(**p))
+      .copied()
+      .collect();
+    for guid_prefix in &participants {
+      self.remove_participant(*guid_prefix, true);
+    }
+
+    let readers: Vec<GUID> = self
+      .external_topic_readers
+      .iter()
+      .filter(|(guid, drd)| {
+        ignored.endpoint(**guid) || ignored.topic(drd.subscription_topic_data.topic_name())
+      })
+      .map(|(guid, _)| *guid)
+      .collect();
+    for guid in &readers {
+      self.external_topic_readers.remove(guid);
+    }
+
+    let writers: Vec<GUID> = self
+      .external_topic_writers
+      .iter()
+      .filter(|(guid, dwd)| {
+        ignored.endpoint(**guid) || ignored.topic(dwd.publication_topic_data.topic_name())
+      })
+      .map(|(guid, _)| *guid)
+      .collect();
+    for guid in &writers {
+      self.external_topic_writers.remove(guid);
+    }
+
+    self.external_topic_readers_attic.retain(|guid, drd| {
+      !ignored.endpoint(*guid) && !ignored.topic(drd.subscription_topic_data.topic_name())
+    });
+    self.external_topic_writers_attic.retain(|guid, dwd| {
+      !ignored.endpoint(*guid) && !ignored.topic(dwd.publication_topic_data.topic_name())
+    });
+
+    let my_prefix = self.my_guid.prefix;
+    self.topics.retain(|name, sources| {
+      // Our own definitions are kept, even if the topic is ignored.
+      sources.retain(|source, _| source.prefix == my_prefix || !ignored.endpoint(*source));
+      !sources.is_empty() && (!ignored.topic(name) || sources.keys().any(|s| s.prefix == my_prefix))
+    });
+
+    (participants, readers, writers)
+  }
+
   fn send_participant_status(&self, event: DomainParticipantStatusEvent) {
     self
       .participant_status_sender
@@ -175,6 +302,11 @@ impl DiscoveryDB {
     //   return
     // }
 
+    if self.is_ignored_participant(guid.prefix) {
+      trace!("Participant {:?} is ignored.", guid.prefix);
+      return false;
+    }
+
     let mut new_participant = false;
     if !self.participant_proxies.contains_key(&guid.prefix) {
       info!("New remote participant: {:?}", &data);
@@ -235,6 +367,12 @@ impl DiscoveryDB {
     self.participant_last_life_signs.remove(&guid_prefix);
     #[cfg(feature = "security")]
     self.authentication_statuses.remove(&guid_prefix);
+    self
+      .inconsistent_topic_sources
+      .retain(|_topic_name, sources| {
+        sources.retain(|guid| guid.prefix != guid_prefix);
+        !sources.is_empty()
+      });
 
     if active_disposal {
       self.remove_topic_reader_with_prefix(guid_prefix);
@@ -284,7 +422,6 @@ impl DiscoveryDB {
     self.external_topic_readers.get(guid)
   }
 
-  #[cfg(feature = "security")]
   pub fn get_topic_writer(&self, guid: &GUID) -> Option<&DiscoveredWriterData> {
     self.external_topic_writers.get(guid)
   }
@@ -414,6 +551,12 @@ impl DiscoveryDB {
     self.local_topic_writers.remove(&guid);
   }
 
+  pub fn update_local_topic_writer_qos(&mut self, guid: GUID, qos: &QosPolicies) {
+    if let Some(dwd) = self.local_topic_writers.get_mut(&guid) {
+      dwd.publication_topic_data.set_qos(qos);
+    }
+  }
+
   // TODO: This is silly. Returns one of the parameters cloned, or None
   // TODO: Why are we here checking if discovery db already has this? What about
   // reader proxies in writers?
@@ -426,8 +569,21 @@ impl DiscoveryDB {
   // them from the remote participant.
   //
   // The topic is updated to the topics table.
-  pub fn update_subscription(&mut self, data: &DiscoveredReaderData) -> DiscoveredReaderData {
+  //
+  // Returns None, if the Reader or its topic is ignored.
+  pub fn update_subscription(
+    &mut self,
+    data: &DiscoveredReaderData,
+  ) -> Option<DiscoveredReaderData> {
     let guid = data.reader_proxy.remote_reader_guid;
+    if self.ignored().endpoint(guid)
+      || self
+        .ignored()
+        .topic(data.subscription_topic_data.topic_name())
+    {
+      trace!("Reader {:?} is ignored.", guid);
+      return None;
+    }
 
     self.external_topic_readers.insert(guid, data.clone());
 
@@ -469,19 +625,32 @@ impl DiscoveryDB {
     // from that record and modify by QoS given in the DRD.
 
     // Return DiscoveredReaderData with possibly updated locators.
-    DiscoveredReaderData {
+    Some(DiscoveredReaderData {
       reader_proxy: ReaderProxy::from(RtpsReaderProxy::from_discovered_reader_data(
         data,
         &default_locator_lists.0,
         &default_locator_lists.1,
       )),
       ..data.clone()
-    }
+    })
   }
 
   // TODO: This is silly. Returns one of the parameters cloned, or None
-  pub fn update_publication(&mut self, data: &DiscoveredWriterData) -> DiscoveredWriterData {
+  //
+  // Returns None, if the Writer or its topic is ignored.
+  pub fn update_publication(
+    &mut self,
+    data: &DiscoveredWriterData,
+  ) -> Option<DiscoveredWriterData> {
     let guid = data.writer_proxy.remote_writer_guid;
+    if self.ignored().endpoint(guid)
+      || self
+        .ignored()
+        .topic(data.publication_topic_data.topic_name())
+    {
+      trace!("Writer {:?} is ignored.", guid);
+      return None;
+    }
 
     self
       .external_topic_writers
@@ -521,14 +690,14 @@ impl DiscoveryDB {
       DiscoveredVia::Publication,
     );
 
-    DiscoveredWriterData {
+    Some(DiscoveredWriterData {
       writer_proxy: WriterProxy::from(RtpsWriterProxy::from_discovered_writer_data(
         data,
         &default_locator_lists.0,
         &default_locator_lists.1,
       )),
       ..data.clone()
-    }
+    })
   }
 
   // This is for local participant updating the topic table
@@ -555,6 +724,12 @@ impl DiscoveryDB {
     discovered_via: DiscoveredVia,
   ) {
     trace!("Update topic data: {:?}", &dtd);
+    if updater.prefix != self.my_guid.prefix
+      && (self.ignored().endpoint(updater) || self.ignored().topic(&dtd.topic_data.name))
+    {
+      trace!("Topic data from {:?} is ignored.", updater);
+      return;
+    }
     let topic_name = dtd.topic_data.name.clone();
     let mut notify = false;
     let mut inconsistency_event_to_send = None;
@@ -622,6 +797,7 @@ impl DiscoveryDB {
         type_name: dtd.topic_data.type_name.clone(),
       });
     };
+    self.record_inconsistent_topic_sources(&dtd.topic_data.name, updater, &dtd.topic_data);
     if let Some(ev) = inconsistency_event_to_send {
       self.send_participant_status(ev);
     }
@@ -635,12 +811,61 @@ impl DiscoveryDB {
     }
   }
 
+  // Compares topic data from `source` to the other definitions of the topic,
+  // and records remote sources that disagree with our own definition.
+  fn record_inconsistent_topic_sources(
+    &mut self,
+    topic_name: &str,
+    source: GUID,
+    topic_data: &TopicBuiltinTopicData,
+  ) {
+    let Some(t) = self.topics.get(topic_name) else {
+      return;
+    };
+    let my_prefix = self.my_guid.prefix;
+    let is_local = |guid: &GUID| guid.prefix == my_prefix;
+    let inconsistent: Vec<GUID> = t
+      .iter()
+      // Compare remote to local, or local to remote
+      .filter(|(other, _)| is_local(other) != is_local(&source))
+      .filter(|(_, (_, other_dtd))| topics_inconsistent(&other_dtd.topic_data, topic_data))
+      .map(|(other, _)| if is_local(other) { source } else { *other })
+      .collect();
+    if inconsistent.is_empty() {
+      return;
+    }
+    let sources = self
+      .inconsistent_topic_sources
+      .entry(topic_name.to_string())
+      .or_default();
+    let new_sources = inconsistent
+      .into_iter()
+      .filter(|guid| sources.insert(*guid))
+      .count() as i32;
+    if new_sources > 0 {
+      *self
+        .inconsistent_topic_counts
+        .entry(topic_name.to_string())
+        .or_default() += new_sources;
+    }
+  }
+
+  // Total count for the INCONSISTENT_TOPIC status of the local Topic
+  pub fn inconsistent_topic_count(&self, topic_name: &str) -> i32 {
+    self
+      .inconsistent_topic_counts
+      .get(topic_name)
+      .copied()
+      .unwrap_or(0)
+  }
+
   // local topic readers
   pub fn update_local_topic_reader(
     &mut self,
     domain_participant: &DomainParticipant,
     topic: &Topic,
     reader: &ReaderIngredients,
+    content_filter: Option<ContentFilterProperty>,
     sec_info_opt: Option<EndpointSecurityInfo>,
   ) {
     let reader_guid = reader.guid;
@@ -656,9 +881,6 @@ impl DiscoveryDB {
       sec_info_opt,
     );
 
-    // TODO: possibly change content filter to dynamic value
-    let content_filter = None;
-
     let discovered_reader_data = DiscoveredReaderData {
       reader_proxy: ReaderProxy::from(reader_proxy),
       subscription_topic_data: subscription_data,
@@ -670,6 +892,32 @@ impl DiscoveryDB {
       .insert(reader_guid, discovered_reader_data);
   }
 
+  // Replaces the content filter of those local readers that use the same
+  // ContentFilteredTopic. Returns the GUIDs of the updated readers.
+  pub fn update_local_reader_content_filter(
+    &mut self,
+    content_filter: &ContentFilterProperty,
+  ) -> Vec<GUID> {
+    let mut updated = Vec::new();
+    for (guid, drd) in self.local_topic_readers.iter_mut() {
+      let same_filtered_topic = drd.content_filter.as_ref().is_some_and(|cf| {
+        cf.content_filtered_topic_name == content_filter.content_filtered_topic_name
+          && cf.related_topic_name == content_filter.related_topic_name
+      });
+      if same_filtered_topic {
+        drd.content_filter = Some(content_filter.clone());
+        updated.push(*guid);
+      }
+    }
+    updated
+  }
+
+  pub fn update_local_topic_reader_qos(&mut self, guid: GUID, qos: &QosPolicies) {
+    if let Some(drd) = self.local_topic_readers.get_mut(&guid) {
+      drd.subscription_topic_data.set_qos(qos);
+    }
+  }
+
   pub fn remove_local_topic_reader(&mut self, guid: GUID) {
     self.local_topic_readers.remove(&guid);
   }
@@ -747,6 +995,24 @@ impl DiscoveryDB {
       .collect()
   }
 
+  pub fn writers_on_topic(&self, topic_name: &str) -> Vec<DiscoveredWriterData> {
+    self
+      .external_topic_writers
+      .values()
+      .filter(|dwd| dwd.publication_topic_data.topic_name == topic_name)
+      .cloned()
+      .collect()
+  }
+
+  pub fn readers_on_topic(&self, topic_name: &str) -> Vec<DiscoveredReaderData> {
+    self
+      .external_topic_readers
+      .values()
+      .filter(|drd| drd.subscription_topic_data.topic_name() == topic_name)
+      .cloned()
+      .collect()
+  }
+
   // // TODO: return iterator somehow?
   #[cfg(test)] // used only for testing
   pub fn get_local_topic_readers<'a, T: TopicDescription>(
@@ -796,8 +1062,8 @@ mod tests {
   use super::*;
   use crate::{
     dds::{
-      qos::QosPolicies,
-      statusevents::{sync_status_channel, DataReaderStatus},
+      qos::{policy, QosPolicies, QosPolicyBuilder},
+      statusevents::{sync_status_channel, DataReaderStatus, StatusEvented},
       topic::TopicKind,
       with_key::simpledatareader::ReaderCommand,
     },
@@ -989,18 +1255,24 @@ mod tests {
       like_stateless: false,
       qos_policy: QosPolicies::qos_none(),
       data_reader_command_receiver: reader_command_receiver1,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker: data_reader_waker1,
       poll_event_sender: notification_event_sender1,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
 
     // Add the reader to the database and verify the info is updated
-    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None);
+    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None, None);
     assert_eq!(discoverydb.local_topic_readers.len(), 1);
     assert_eq!(discoverydb.get_local_topic_readers(&topic).len(), 1);
 
     // Verify that the info does not change if the reader is added a second time
-    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None);
+    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None, None);
     assert_eq!(discoverydb.local_topic_readers.len(), 1);
     assert_eq!(discoverydb.get_local_topic_readers(&topic).len(), 1);
 
@@ -1026,14 +1298,158 @@ mod tests {
       like_stateless: false,
       qos_policy: QosPolicies::qos_none(),
       data_reader_command_receiver: reader_command_receiver2,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker: data_reader_waker2,
       poll_event_sender: notification_event_sender2,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
 
     // Add the second reader to the database and verify the info is updated
-    discoverydb.update_local_topic_reader(&dp, &topic, &reader2_ing, None);
+    discoverydb.update_local_topic_reader(&dp, &topic, &reader2_ing, None, None);
     assert_eq!(discoverydb.get_local_topic_readers(&topic).len(), 2);
     assert_eq!(discoverydb.get_all_local_topic_readers().count(), 2);
   }
+
+  #[test]
+  fn discdb_counts_inconsistent_topics() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, status_receiver) = sync_status_channel(16).unwrap();
+    let my_guid = GUID::new_participant_guid();
+    let mut discovery_db = DiscoveryDB::new(my_guid, discovery_db_event_sender, status_sender);
+
+    let topic_data = |type_name: &str, qos: &QosPolicies| {
+      DiscoveredTopicData::new(
+        Utc::now(),
+        TopicBuiltinTopicData::new(None, "Foobar".to_string(), type_name.to_string(), qos),
+      )
+    };
+    let remote = |name: &[u8; 12]| {
+      let mut guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+      guid.prefix = GuidPrefix::new(name);
+      guid
+    };
+    let qos = QosPolicies::qos_none();
+
+    // Remote definition is seen before ours.
+    discovery_db.update_topic_data(
+      &topic_data("OtherType", &qos),
+      remote(b"Remote fake1"),
+      DiscoveredVia::Subscription,
+    );
+    assert_eq!(discovery_db.inconsistent_topic_count("Foobar"), 0);
+
+    discovery_db.update_topic_data(
+      &topic_data("RandomData", &qos),
+      my_guid,
+      DiscoveredVia::SelfDefined,
+    );
+    assert_eq!(discovery_db.inconsistent_topic_count("Foobar"), 1);
+
+    // Consistent remote, and the same inconsistent one again
+    discovery_db.update_topic_data(
+      &topic_data("RandomData", &qos),
+      remote(b"Remote fake2"),
+      DiscoveredVia::Publication,
+    );
+    discovery_db.update_topic_data(
+      &topic_data("OtherType", &qos),
+      remote(b"Remote fake1"),
+      DiscoveredVia::Subscription,
+    );
+    assert_eq!(discovery_db.inconsistent_topic_count("Foobar"), 1);
+
+    // Ownership kind must agree, too.
+    let exclusive = QosPolicyBuilder::new()
+      .ownership(policy::Ownership::Exclusive { strength: 1 })
+      .build();
+    discovery_db.update_topic_data(
+      &topic_data("RandomData", &exclusive),
+      remote(b"Remote fake3"),
+      DiscoveredVia::Publication,
+    );
+    assert_eq!(discovery_db.inconsistent_topic_count("Foobar"), 2);
+    assert_eq!(discovery_db.inconsistent_topic_count("Barfoo"), 0);
+
+    let mut inconsistencies = 0;
+    while let Some(event) = status_receiver.try_recv_status() {
+      if let DomainParticipantStatusEvent::InconsistentTopic { .. } = event {
+        inconsistencies += 1;
+      }
+    }
+    assert_eq!(inconsistencies, 3);
+
+    // A lost participant is forgotten, but the count stays. If it comes back,
+    // it is counted again.
+    discovery_db.remove_participant(remote(b"Remote fake1").prefix, true);
+    assert!(!discovery_db.inconsistent_topic_sources["Foobar"].contains(&remote(b"Remote fake1")));
+    assert_eq!(discovery_db.inconsistent_topic_count("Foobar"), 2);
+    discovery_db.update_topic_data(
+      &topic_data("OtherType", &qos),
+      remote(b"Remote fake1"),
+      DiscoveredVia::Subscription,
+    );
+    assert_eq!(discovery_db.inconsistent_topic_count("Foobar"), 3);
+  }
+
+  #[test]
+  fn discdb_ignores_entities() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let reader = |prefix: &[u8; 12]| {
+      let mut reader_proxy = reader_proxy_data().unwrap();
+      reader_proxy.remote_reader_guid.prefix = GuidPrefix::new(prefix);
+      DiscoveredReaderData {
+        reader_proxy,
+        subscription_topic_data: subscription_builtin_topic_data().unwrap(),
+        content_filter: None,
+      }
+    };
+    let reader1 = reader(b"Remote fake1");
+    let reader2 = reader(b"Remote fake2");
+    let guid1 = reader1.reader_proxy.remote_reader_guid;
+    let guid2 = reader2.reader_proxy.remote_reader_guid;
+    let topic_name = reader1.subscription_topic_data.topic_name().clone();
+
+    assert!(discovery_db.update_subscription(&reader1).is_some());
+    assert!(discovery_db.update_subscription(&reader2).is_some());
+
+    // Known Readers are removed when ignored.
+    discovery_db.ignore_endpoint(guid1);
+    assert_eq!(discovery_db.remove_ignored(), (vec![], vec![guid1], vec![]));
+    assert!(discovery_db.update_subscription(&reader1).is_none());
+    assert!(discovery_db.update_subscription(&reader2).is_some());
+
+    // Ignoring the participant ignores its Readers, too.
+    discovery_db.ignore_participant(guid2.prefix);
+    assert_eq!(discovery_db.remove_ignored(), (vec![], vec![guid2], vec![]));
+    assert!(discovery_db.update_subscription(&reader2).is_none());
+
+    let reader3 = reader(b"Remote fake3");
+    assert!(discovery_db.update_subscription(&reader3).is_some());
+    assert!(discovery_db.get_topic(&topic_name).is_some());
+    discovery_db.ignore_topic(topic_name.clone());
+    assert_eq!(
+      discovery_db.remove_ignored(),
+      (
+        vec![],
+        vec![reader3.reader_proxy.remote_reader_guid],
+        vec![]
+      )
+    );
+    assert!(discovery_db.get_topic(&topic_name).is_none());
+    assert!(discovery_db.update_subscription(&reader3).is_none());
+  }
 }
@@ -1073,11 +1073,16 @@ pub fn topics_inconsistent(t1: &TopicBuiltinTopicData, t2: &TopicBuiltinTopicDat
   t1.type_name != t2.type_name
 
   // Check for QoS inconsistencies:
+  //
+  // Most QoS policies that must match between Reader and Writer are
+  // request-offer, so different values do not make the Topic inconsistent.
+  // Ownership kind, however, must be the same everywhere. Missing policy means
+  // the default, which is Shared.
+  || is_exclusive(t1.ownership) != is_exclusive(t2.ownership)
+}
 
-  // TODO: Is there any combination of Topic QoS settings that would make the
-  // two definitions of the Topic definetely incompatible (inconsistent).
-  // There are several QoS policies that must match between Reader and Writer,
-  // but the spec seems to say nothing about Topics.
+fn is_exclusive(ownership: Option<Ownership>) -> bool {
+  matches!(ownership, Some(Ownership::Exclusive { .. }))
 }
 
 // =======================================================================
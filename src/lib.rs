@@ -202,16 +202,23 @@ pub use dds::{
   key::{Key, Keyed},
   participant::{DomainParticipant, DomainParticipantBuilder},
   pubsub::{Publisher, Subscriber},
+  listener::{
+    DataReaderListener, DataWriterListener, DomainParticipantListener, PublisherListener,
+    SubscriberListener,
+  },
   qos,
   qos::{policy, QosPolicies, QosPolicyBuilder},
-  readcondition::ReadCondition,
+  readcondition::{QueryCondition, ReadCondition, SampleCondition},
   sampleinfo::{InstanceState, NotAliveGenerationCounts, SampleInfo, SampleState, ViewState},
   statusevents::{
-    DataReaderStatus, DataWriterStatus, DomainParticipantStatusEvent, EndpointDescription,
-    LostReason, ParticipantDescription, StatusEvented,
+    CountWithChange, DataReaderStatus, DataWriterStatus, DomainParticipantStatusEvent,
+    EndpointDescription, InconsistentTopicStatus, LivelinessLostStatus, LostReason,
+    OfferedDeadlineMissedStatus, OfferedIncompatibleQosStatus, ParticipantDescription,
+    PublicationMatchedStatus, StatusEvented, StatusKind, SubscriberStatus,
   },
-  topic::{Topic, TopicDescription, TopicKind},
+  topic::{ContentFilteredTopic, ReaderTopicDescription, Topic, TopicDescription, TopicKind},
   typedesc::TypeDesc,
+  waitset::{Condition, DataReaderReadCondition, GuardCondition, StatusCondition, WaitSet},
   with_key::{datareader::SelectByKey, WriteOptions, WriteOptionsBuilder},
 };
 /// Needed to specify serialized data representation in case it is other than
@@ -8,9 +8,16 @@ use log::{debug, error, info, trace, warn};
 
 use crate::{
   dds::key::KeyHash,
-  messages::submessages::elements::{parameter_list::ParameterList, RepresentationIdentifier},
+  messages::{
+    submessages::elements::{parameter_list::ParameterList, RepresentationIdentifier},
+    vendor_id::VendorId,
+  },
   serialization::{pl_cdr_adapters::PlCdrDeserializeError, speedy_pl_cdr_helpers::*},
-  structure::{cache_change::ChangeKind, parameter_id::ParameterId, rpc::SampleIdentity},
+  rtps::coherent_set::CoherentSet,
+  structure::{
+    cache_change::ChangeKind, guid::GUID, parameter_id::ParameterId, rpc::SampleIdentity,
+    sequence_number::SequenceNumber,
+  },
 };
 #[cfg(test)]
 use crate::{
@@ -81,6 +88,41 @@ impl InlineQos {
       None => None,
     })
   }
+
+  // `vendor_id` is the vendor of the sending participant. Vendor-specific
+  // parameters of other vendors are ignored.
+  pub fn coherent_set(
+    params: &ParameterList,
+    rep_id: RepresentationIdentifier,
+    vendor_id: VendorId,
+  ) -> Result<Option<CoherentSet>, PlCdrDeserializeError> {
+    let find = |pid| params.parameters.iter().find(|p| p.parameter_id == pid);
+    let Some(first_sn) = find(ParameterId::PID_COHERENT_SET) else {
+      return Ok(None);
+    };
+    let ctx = pl_cdr_rep_id_to_speedy_d(rep_id)?;
+
+    let group_sequence_number = match find(ParameterId::PID_GROUP_COHERENT_SET) {
+      Some(p) => Some(SequenceNumber::read_from_buffer_with_ctx(ctx, &p.value)?),
+      None => None,
+    };
+    // A list of GUIDs, 16 bytes each. There is no length field, the parameter
+    // length tells how many there are. This is our own vendor-specific parameter.
+    let group_writers = find(ParameterId::PID_COHERENT_SET_GROUP_WRITERS)
+      .filter(|_| vendor_id == VendorId::THIS_IMPLEMENTATION)
+      .map(|p| {
+        p.value
+          .chunks_exact(16)
+          .map(|guid_bytes| GUID::from_bytes(guid_bytes.try_into().unwrap()))
+          .collect()
+      });
+
+    Ok(Some(CoherentSet {
+      first_sequence_number: SequenceNumber::read_from_buffer_with_ctx(ctx, &first_sn.value)?,
+      group_sequence_number,
+      group_writers,
+    }))
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -13,6 +13,10 @@ use local_ip_address::list_afinet_netifas;
 
 use crate::{network::util::get_local_multicast_ip_addrs, structure::locator::Locator};
 
+// Messages larger than this do not fit in a single Ethernet frame, so the IP
+// layer has to fragment them, and losing any fragment loses the message.
+pub(crate) const MAX_UNFRAGMENTED_MESSAGE_SIZE: usize = 1500;
+
 // We need one multicast sender socket per interface
 
 #[derive(Debug)]
@@ -127,7 +131,7 @@ impl UDPSender {
   }
 
   pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) {
-    if buffer.len() > 1500 {
+    if buffer.len() > MAX_UNFRAGMENTED_MESSAGE_SIZE {
       warn!("send_to_locator: Message size = {}", buffer.len());
     }
     let send = |socket_address: SocketAddr| {
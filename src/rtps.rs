@@ -1,8 +1,12 @@
 #[allow(dead_code)] // We allow this, since extra constants are not too harmful.
 pub(crate) mod constant;
 
+pub(crate) mod coherent_set;
+pub(crate) mod command_channel_wakers;
+
 pub(crate) mod dp_event_loop;
 pub(crate) mod fragment_assembler;
+pub(crate) mod history_reservations;
 pub(crate) mod message_receiver;
 pub(crate) mod reader;
 pub(crate) mod rtps_reader_proxy;
@@ -0,0 +1,709 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc, Mutex,
+  },
+};
+
+use mio_extras::channel::{self as mio_channel, TrySendError};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  dds::{
+    ddsdata::DDSData,
+    helpers::try_send_timeout,
+    result::{WriteError, WriteResult},
+    with_key::datawriter::WriteOptionsBuilder,
+  },
+  rtps::{reader::DataReaderNotifier, writer::WriterCommand},
+  structure::{duration::Duration, guid::GUID, sequence_number::SequenceNumber, time::Timestamp},
+};
+
+// Coherent sets, see DDS spec v1.4 Section 2.2.2.4.1.10 begin_coherent_changes
+// and RTPS spec v2.5 Section 8.7.5 "Coherent Sets".
+//
+// A Writer marks each sample of a coherent set with the sequence number of the
+// first sample in the set. The set is ended by a DATA submessage that has no
+// payload, only the same marking. The receiving DataReader withholds the
+// samples of the set until it has received all of them.
+//
+// If the Publisher has GROUP access scope, the sets of all its DataWriters are
+// additionally marked with a group set number, and the end markers list all
+// the Writers that took part. A Subscriber with GROUP access scope withholds
+// the sets of all participating Writers it is matched with, until each one of
+// them is complete.
+
+/// Coherent set marking of a sample. This is carried in
+/// [`WriteOptions`](crate::with_key::WriteOptions).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct CoherentSet {
+  // Sequence number of the first sample of the set from this Writer.
+  pub first_sequence_number: SequenceNumber, // PID_COHERENT_SET
+  // Group set number. With GROUP access scope only.
+  pub group_sequence_number: Option<SequenceNumber>, // PID_GROUP_COHERENT_SET
+  // The Writers taking part in a group set. In end markers only.
+  pub group_writers: Option<Vec<GUID>>, // PID_COHERENT_SET_GROUP_WRITERS
+}
+
+// Writer side: Coherent changes in progress in a Publisher. Shared between the
+// Publisher and its DataWriters.
+pub(crate) type PublisherCoherentChanges = Arc<Mutex<CoherentChanges>>;
+
+struct CoherentSetWriter {
+  first_sequence_number: SequenceNumber,
+  cc_upload: mio_channel::SyncSender<WriterCommand>,
+  available_sequence_number: Arc<AtomicI64>, // shared with DataWriter
+  max_blocking_time: Option<Duration>,       // of the DataWriter
+}
+
+pub(crate) struct CoherentChanges {
+  group_access: bool, // Presentation access scope is GROUP
+  // Nesting depth of begin_coherent_changes()
+  depth: usize,
+  group_sequence_number: Option<SequenceNumber>,
+  // Each Publisher numbers its group sets independently. Readers tell them
+  // apart by the participating Writers.
+  next_group_sequence_number: SequenceNumber,
+  // DataWriters that have written something in the current set
+  writers: BTreeMap<GUID, CoherentSetWriter>,
+}
+
+impl CoherentChanges {
+  pub fn new_shared(group_access: bool) -> PublisherCoherentChanges {
+    Arc::new(Mutex::new(Self {
+      group_access,
+      depth: 0,
+      group_sequence_number: None,
+      next_group_sequence_number: SequenceNumber::new(1),
+      writers: BTreeMap::new(),
+    }))
+  }
+
+  pub fn begin(&mut self) {
+    if self.depth == 0 && self.group_access {
+      self.group_sequence_number = Some(self.next_group_sequence_number);
+      self.next_group_sequence_number = self.next_group_sequence_number.plus_1();
+    }
+    self.depth += 1;
+  }
+
+  // A DataWriter is writing a sample. Returns the coherent set marking for it,
+  // if a set is in progress.
+  pub fn sample_written(
+    &mut self,
+    writer: GUID,
+    sequence_number: SequenceNumber,
+    cc_upload: &mio_channel::SyncSender<WriterCommand>,
+    available_sequence_number: &Arc<AtomicI64>,
+    max_blocking_time: Option<Duration>,
+  ) -> Option<CoherentSet> {
+    if self.depth == 0 {
+      return None;
+    }
+    let set_writer = self
+      .writers
+      .entry(writer)
+      .or_insert_with(|| CoherentSetWriter {
+        first_sequence_number: sequence_number,
+        cc_upload: cc_upload.clone(),
+        available_sequence_number: Arc::clone(available_sequence_number),
+        max_blocking_time,
+      });
+    Some(CoherentSet {
+      first_sequence_number: set_writer.first_sequence_number,
+      group_sequence_number: self.group_sequence_number,
+      group_writers: None,
+    })
+  }
+
+  // Ends the set, if this is the outermost end. Each DataWriter that took part
+  // sends an end marker. DataWriters need this struct to write, so a full
+  // command channel is waited for at most the DataWriter's max_blocking_time.
+  pub fn end(&mut self) -> WriteResult<(), ()> {
+    if self.depth == 0 {
+      warn!("end_coherent_changes() called without matching begin_coherent_changes()");
+      return Ok(());
+    }
+    self.depth -= 1;
+    if self.depth > 0 {
+      return Ok(());
+    }
+
+    let group_sequence_number = self.group_sequence_number.take();
+    let group_writers: Option<Vec<GUID>> =
+      group_sequence_number.map(|_| self.writers.keys().copied().collect());
+    let mut result = Ok(());
+    for (writer, set_writer) in std::mem::take(&mut self.writers) {
+      let coherent_set = CoherentSet {
+        first_sequence_number: set_writer.first_sequence_number,
+        group_sequence_number,
+        group_writers: group_writers.clone(),
+      };
+      let sequence_number = SequenceNumber::from(
+        set_writer
+          .available_sequence_number
+          .fetch_add(1, Ordering::Relaxed),
+      );
+      let command = WriterCommand::DDSData {
+        ddsdata: DDSData::CoherentSetEnd,
+        write_options: WriteOptionsBuilder::new()
+          .coherent_set(coherent_set)
+          .build(),
+        sequence_number,
+        filterable_sample: None, // end marker goes to every Reader
+        instance: None,
+      };
+      let send_result =
+        try_send_timeout(&set_writer.cc_upload, command, set_writer.max_blocking_time);
+      if send_result.is_err() {
+        set_writer
+          .available_sequence_number
+          .fetch_sub(1, Ordering::Relaxed);
+      }
+      match send_result {
+        Ok(()) => (),
+        Err(TrySendError::Full(_)) => {
+          warn!("Ending coherent set of {writer:?} timed out");
+          result = Err(WriteError::WouldBlock { data: () });
+        }
+        Err(e) => {
+          // The DataWriter may have been dropped in the middle of the set.
+          error!("Cannot end coherent set of {writer:?}: {e}");
+          result = Err(WriteError::Poisoned {
+            reason: format!("Cannot end coherent set of {writer:?}: {e}"),
+            data: (),
+          });
+        }
+      }
+    }
+    result
+  }
+}
+
+// Reader side: Shared between an RTPS Reader, which tracks the coherent sets it receives,
+// and its DataReader, which must not hand out samples of incomplete sets. With
+// GROUP access scope, this is shared by all Readers of a Subscriber.
+pub(crate) type CoherentSets = Arc<Mutex<CoherentSetTracker>>;
+
+struct ReceivedSet {
+  group_sequence_number: Option<SequenceNumber>,
+  // The Writers taking part in a group set, from the end marker.
+  group_writers: Option<BTreeSet<GUID>>,
+  // Sequence number of the end marker, once it has been received
+  end: Option<SequenceNumber>,
+  // All samples of the set, and the end marker, have been received.
+  complete: bool,
+  // When something of the set was last received
+  last_received: Timestamp,
+}
+
+impl ReceivedSet {
+  fn new(group_sequence_number: Option<SequenceNumber>) -> Self {
+    Self {
+      group_sequence_number,
+      group_writers: None,
+      end: None,
+      complete: false,
+      last_received: Timestamp::now(),
+    }
+  }
+
+  // Group sets are identified by the group set number and the participating
+  // Writers, as each Publisher numbers its group sets independently. Without
+  // the list of Writers, the set is treated as a set of a single Writer.
+  fn group_id(&self) -> Option<GroupId> {
+    self.group_sequence_number.zip(self.group_writers.clone())
+  }
+}
+
+type GroupId = (SequenceNumber, BTreeSet<GUID>);
+
+// A Best Effort Reader may lose the end marker of a set. If nothing of an
+// incomplete set has been received for this long, the set is given up.
+const BEST_EFFORT_SET_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct MatchedWriter {
+  // How many of our Readers are matched with the Writer
+  readers: usize,
+  // How many of those are Reliable
+  reliable_readers: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct CoherentSetTracker {
+  // Sets that have not been released to the application yet, by Writer and
+  // first sequence number.
+  sets: BTreeMap<GUID, BTreeMap<SequenceNumber, ReceivedSet>>,
+  // Sequence number of the latest end marker from each Writer. Sets starting
+  // before this one have been ended already.
+  ended_before: BTreeMap<GUID, SequenceNumber>,
+  // Writers that have completed their part of each group set
+  groups: BTreeMap<GroupId, BTreeSet<GUID>>,
+  // Writers our Readers are matched with. A group set needs to be complete
+  // from these Writers only.
+  matched_writers: BTreeMap<GUID, MatchedWriter>,
+  // Nesting depth of Subscriber::begin_access(). Nothing is released while
+  // access is in progress.
+  access_depth: usize,
+  // DataReaders to wake up when some set is released.
+  notifiers: BTreeMap<GUID, DataReaderNotifier>,
+}
+
+impl CoherentSetTracker {
+  pub fn new_shared() -> CoherentSets {
+    Arc::new(Mutex::new(Self::default()))
+  }
+
+  pub fn add_notifier(&mut self, reader: GUID, notifier: DataReaderNotifier) {
+    self.notifiers.insert(reader, notifier);
+  }
+
+  pub fn remove_notifier(&mut self, reader: GUID) {
+    self.notifiers.remove(&reader);
+  }
+
+  pub fn writer_matched(&mut self, writer: GUID, reliable: bool) {
+    let matched = self.matched_writers.entry(writer).or_default();
+    matched.readers += 1;
+    if reliable {
+      matched.reliable_readers += 1;
+    }
+  }
+
+  pub fn writer_unmatched(&mut self, writer: GUID, reliable: bool) {
+    if let Some(matched) = self.matched_writers.get_mut(&writer) {
+      matched.readers -= 1;
+      if reliable {
+        matched.reliable_readers -= 1;
+      }
+      if matched.readers == 0 {
+        self.matched_writers.remove(&writer);
+        // Group sets need not wait for this one anymore.
+        self.release();
+      }
+    }
+  }
+
+  /// Samples of `writer` from this sequence number on must not be delivered
+  /// yet.
+  pub fn withheld_from(&self, writer: GUID) -> Option<SequenceNumber> {
+    self
+      .sets
+      .get(&writer)
+      .and_then(|sets| sets.first_key_value())
+      .map(|(first_sn, _)| *first_sn)
+  }
+
+  /// Must the change be withheld from the application? It must, if it belongs
+  /// to a coherent set that has not been released, or it is written after such
+  /// a set.
+  pub fn is_withheld(
+    &self,
+    writer: GUID,
+    sequence_number: SequenceNumber,
+    coherent_set: Option<&CoherentSet>,
+  ) -> bool {
+    let after_withheld = self
+      .withheld_from(writer)
+      .is_some_and(|withheld_from| withheld_from <= sequence_number);
+    // The Reader may not have processed the change yet. This happens with the
+    // changes of local Writers, as they share the topic cache.
+    let unreleased = coherent_set.is_some_and(|coherent_set| {
+      let first_sn = coherent_set.first_sequence_number;
+      let ended = self
+        .ended_before
+        .get(&writer)
+        .is_some_and(|ended_before| first_sn < *ended_before);
+      !ended
+        || self
+          .sets
+          .get(&writer)
+          .is_some_and(|sets| sets.contains_key(&first_sn))
+    });
+    after_withheld || unreleased
+  }
+
+  // Samples from the Writer are received by Best Effort Readers only, so
+  // missing samples and end markers will not be repaired.
+  fn is_best_effort(&self, writer: GUID) -> bool {
+    self
+      .matched_writers
+      .get(&writer)
+      .is_some_and(|matched| matched.reliable_readers == 0)
+  }
+
+  /// A sample belonging to a coherent set has been received.
+  pub fn sample_received(&mut self, writer: GUID, coherent_set: &CoherentSet) {
+    if self
+      .ended_before
+      .get(&writer)
+      .is_some_and(|ended_before| coherent_set.first_sequence_number < *ended_before)
+    {
+      // Late arrival from a set that was ended already. This is normal if
+      // samples are repaired out of order.
+      return;
+    }
+    if self.is_best_effort(writer) {
+      // The Writer has started a new set, so the end markers of any earlier
+      // incomplete sets have been lost.
+      self.give_up_incomplete_sets_before(writer, coherent_set.first_sequence_number);
+    }
+    self
+      .sets
+      .entry(writer)
+      .or_default()
+      .entry(coherent_set.first_sequence_number)
+      .or_insert_with(|| ReceivedSet::new(coherent_set.group_sequence_number))
+      .last_received = Timestamp::now();
+  }
+
+  /// The end marker of a coherent set has been received.
+  /// `received_before` tells up to which sequence number all samples from the
+  /// Writer have been received.
+  pub fn end_received(
+    &mut self,
+    writer: GUID,
+    end_sequence_number: SequenceNumber,
+    coherent_set: &CoherentSet,
+    received_before: SequenceNumber,
+  ) {
+    let ended_before = self
+      .ended_before
+      .entry(writer)
+      .or_insert(end_sequence_number);
+    *ended_before = (*ended_before).max(end_sequence_number);
+
+    if self.is_best_effort(writer) {
+      self.give_up_incomplete_sets_before(writer, coherent_set.first_sequence_number);
+    }
+
+    // None of the samples of the set may have arrived yet, or the set is empty.
+    let set = self
+      .sets
+      .entry(writer)
+      .or_default()
+      .entry(coherent_set.first_sequence_number)
+      .or_insert_with(|| ReceivedSet::new(coherent_set.group_sequence_number));
+    set.end = Some(end_sequence_number);
+    set.group_writers = coherent_set
+      .group_writers
+      .as_ref()
+      .map(|group_writers| group_writers.iter().copied().collect());
+    set.last_received = Timestamp::now();
+
+    self.received_before(writer, received_before);
+  }
+
+  /// All samples from `writer` before `received_before` have been received.
+  pub fn received_before(&mut self, writer: GUID, received_before: SequenceNumber) {
+    let Some(sets) = self.sets.get_mut(&writer) else {
+      return;
+    };
+    let mut completed = false;
+    for set in sets.values_mut() {
+      match set.end {
+        Some(end) if !set.complete && end < received_before => {
+          set.complete = true;
+          completed = true;
+          if let Some(group_id) = set.group_id() {
+            self.groups.entry(group_id).or_default().insert(writer);
+          }
+        }
+        _ => (),
+      }
+    }
+    if completed {
+      self.release();
+    }
+  }
+
+  pub fn begin_access(&mut self) {
+    self.access_depth += 1;
+  }
+
+  pub fn end_access(&mut self) {
+    if self.access_depth == 0 {
+      warn!("end_access() called without matching begin_access()");
+      return;
+    }
+    self.access_depth -= 1;
+    self.release();
+  }
+
+  /// Sets from Best Effort Writers, which have not received anything for a
+  /// while, are given up. Their end marker, or the sets of the other Writers
+  /// of their group, have been lost.
+  pub fn expire(&mut self, now: Timestamp) {
+    let expired: Vec<(GUID, Vec<SequenceNumber>)> = self
+      .sets
+      .iter()
+      .filter(|(writer, _)| self.is_best_effort(**writer))
+      .map(|(writer, sets)| {
+        let first_sns = sets
+          .iter()
+          .filter(|(_, set)| set.last_received + BEST_EFFORT_SET_TIMEOUT < now)
+          .map(|(first_sn, _)| *first_sn)
+          .collect();
+        (*writer, first_sns)
+      })
+      .collect();
+    for (writer, first_sns) in expired {
+      self.give_up_sets(writer, &first_sns);
+    }
+  }
+
+  fn give_up_incomplete_sets_before(&mut self, writer: GUID, before: SequenceNumber) {
+    let incomplete: Vec<SequenceNumber> = self
+      .sets
+      .get(&writer)
+      .into_iter()
+      .flat_map(|sets| sets.range(..before))
+      .filter(|(_, set)| !set.complete)
+      .map(|(first_sn, _)| *first_sn)
+      .collect();
+    self.give_up_sets(writer, &incomplete);
+  }
+
+  // Stops withholding the given sets of `writer`. The samples received from
+  // them are delivered as such.
+  fn give_up_sets(&mut self, writer: GUID, first_sns: &[SequenceNumber]) {
+    let Some(sets) = self.sets.get_mut(&writer) else {
+      return;
+    };
+    let given_up: Vec<ReceivedSet> = first_sns
+      .iter()
+      .filter_map(|first_sn| sets.remove(first_sn))
+      .collect();
+    let Some(last_first_sn) = first_sns.iter().max() else {
+      return;
+    };
+    debug!(
+      "Giving up {} coherent set(s) from {writer:?}",
+      given_up.len()
+    );
+    // Any late samples of these sets are not withheld either.
+    let ended_before = self
+      .ended_before
+      .entry(writer)
+      .or_insert(last_first_sn.plus_1());
+    *ended_before = (*ended_before).max(last_first_sn.plus_1());
+    // The sets count as complete for their groups, so that the other Writers
+    // of a group are not withheld because of them.
+    for set in &given_up {
+      if let Some(group_id) = set.group_id() {
+        self.groups.entry(group_id).or_default().insert(writer);
+      }
+    }
+    self.sets.retain(|_, sets| !sets.is_empty());
+    self.notify();
+    self.release();
+  }
+
+  fn is_group_complete(&self, group_id: &GroupId, complete_writers: &BTreeSet<GUID>) -> bool {
+    let (_, writers) = group_id;
+    writers
+      .iter()
+      .filter(|w| self.matched_writers.contains_key(w))
+      .all(|w| complete_writers.contains(w))
+  }
+
+  fn notify(&self) {
+    for notifier in self.notifiers.values() {
+      notifier.notify();
+    }
+  }
+
+  // Releases the complete sets, unless access is in progress.
+  fn release(&mut self) {
+    if self.access_depth > 0 {
+      return;
+    }
+
+    let complete_groups: BTreeSet<GroupId> = self
+      .groups
+      .iter()
+      .filter(|(group_id, complete_writers)| self.is_group_complete(group_id, complete_writers))
+      .map(|(group_id, _)| group_id.clone())
+      .collect();
+
+    let mut released = false;
+    for sets in self.sets.values_mut() {
+      sets.retain(|_, set| {
+        let releasable = set.complete
+          && set
+            .group_id()
+            .map_or(true, |group_id| complete_groups.contains(&group_id));
+        released |= releasable;
+        !releasable
+      });
+    }
+    self.sets.retain(|_, sets| !sets.is_empty());
+    for group_id in complete_groups {
+      self.groups.remove(&group_id);
+    }
+
+    if released {
+      self.notify();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::{EntityId, EntityKind, GuidPrefix};
+
+  fn writer(n: u8) -> GUID {
+    GUID::new_with_prefix_and_id(
+      GuidPrefix::new(&[1; 12]),
+      EntityId::new([n, 0, 0], EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+    )
+  }
+
+  fn set(first: i64) -> CoherentSet {
+    CoherentSet {
+      first_sequence_number: SequenceNumber::new(first),
+      group_sequence_number: None,
+      group_writers: None,
+    }
+  }
+
+  fn group_set(first: i64, group_writers: Option<Vec<GUID>>) -> CoherentSet {
+    CoherentSet {
+      first_sequence_number: SequenceNumber::new(first),
+      group_sequence_number: Some(SequenceNumber::new(1)),
+      group_writers,
+    }
+  }
+
+  #[test]
+  fn coherent_set_withheld_until_all_received() {
+    let mut tracker = CoherentSetTracker::default();
+    let w = writer(1);
+
+    tracker.sample_received(w, &set(5));
+    assert_eq!(tracker.withheld_from(w), Some(SequenceNumber::new(5)));
+
+    // End marker at 7, but sample 6 is still missing
+    tracker.end_received(w, SequenceNumber::new(7), &set(5), SequenceNumber::new(6));
+    assert_eq!(tracker.withheld_from(w), Some(SequenceNumber::new(5)));
+
+    tracker.received_before(w, SequenceNumber::new(8));
+    assert_eq!(tracker.withheld_from(w), None);
+
+    // Late repair of a sample of the ended set does not withhold anything
+    tracker.sample_received(w, &set(5));
+    assert_eq!(tracker.withheld_from(w), None);
+  }
+
+  #[test]
+  fn group_coherent_set_waits_for_matched_writers() {
+    let mut tracker = CoherentSetTracker::default();
+    let (w1, w2, w3) = (writer(1), writer(2), writer(3));
+    tracker.writer_matched(w1, true);
+    tracker.writer_matched(w2, true);
+    // Writer 3 takes part in the set, but we are not matched with it.
+    let group_writers = Some(vec![w1, w2, w3]);
+
+    tracker.sample_received(w1, &group_set(1, None));
+    tracker.sample_received(w2, &group_set(1, None));
+    tracker.end_received(
+      w1,
+      SequenceNumber::new(2),
+      &group_set(1, group_writers.clone()),
+      SequenceNumber::new(3),
+    );
+    // Writer 1 is complete, but must wait for writer 2
+    assert_eq!(tracker.withheld_from(w1), Some(SequenceNumber::new(1)));
+    assert_eq!(tracker.withheld_from(w2), Some(SequenceNumber::new(1)));
+
+    tracker.end_received(
+      w2,
+      SequenceNumber::new(2),
+      &group_set(1, group_writers),
+      SequenceNumber::new(3),
+    );
+    assert_eq!(tracker.withheld_from(w1), None);
+    assert_eq!(tracker.withheld_from(w2), None);
+    assert!(tracker.groups.is_empty());
+  }
+
+  #[test]
+  fn group_sets_of_different_publishers_kept_apart() {
+    let mut tracker = CoherentSetTracker::default();
+    // Writers 1 and 2 are in different Publishers of the same participant.
+    // Both Publishers use group set number 1.
+    let (w1, w2) = (writer(1), writer(2));
+    tracker.writer_matched(w1, true);
+    tracker.writer_matched(w2, true);
+
+    tracker.sample_received(w1, &group_set(1, None));
+    tracker.sample_received(w2, &group_set(1, None));
+    tracker.end_received(
+      w1,
+      SequenceNumber::new(2),
+      &group_set(1, Some(vec![w1])),
+      SequenceNumber::new(3),
+    );
+    // Writer 1's group is complete, even though writer 2's is not.
+    assert_eq!(tracker.withheld_from(w1), None);
+    assert_eq!(tracker.withheld_from(w2), Some(SequenceNumber::new(1)));
+  }
+
+  #[test]
+  fn best_effort_set_given_up_when_next_one_starts() {
+    let mut tracker = CoherentSetTracker::default();
+    let w = writer(1);
+    tracker.writer_matched(w, false);
+
+    tracker.sample_received(w, &set(1));
+    // End marker of set 1 is lost, and the Writer starts the next set.
+    tracker.sample_received(w, &set(4));
+    assert_eq!(tracker.withheld_from(w), Some(SequenceNumber::new(4)));
+    assert!(!tracker.is_withheld(w, SequenceNumber::new(2), Some(&set(1))));
+    assert!(tracker.is_withheld(w, SequenceNumber::new(4), Some(&set(4))));
+
+    // A Reliable Reader waits for the end marker to be repaired.
+    let w2 = writer(2);
+    tracker.writer_matched(w2, true);
+    tracker.sample_received(w2, &set(1));
+    tracker.sample_received(w2, &set(4));
+    assert_eq!(tracker.withheld_from(w2), Some(SequenceNumber::new(1)));
+  }
+
+  #[test]
+  fn best_effort_set_expires() {
+    let mut tracker = CoherentSetTracker::default();
+    let (w1, w2) = (writer(1), writer(2));
+    tracker.writer_matched(w1, false);
+    tracker.writer_matched(w2, true);
+
+    tracker.sample_received(w1, &set(1));
+    tracker.sample_received(w2, &set(1));
+    tracker.expire(Timestamp::now());
+    assert_eq!(tracker.withheld_from(w1), Some(SequenceNumber::new(1)));
+
+    tracker.expire(Timestamp::now() + BEST_EFFORT_SET_TIMEOUT + Duration::from_secs(1));
+    assert_eq!(tracker.withheld_from(w1), None);
+    assert!(!tracker.is_withheld(w1, SequenceNumber::new(1), Some(&set(1))));
+    // Reliable sets do not expire.
+    assert_eq!(tracker.withheld_from(w2), Some(SequenceNumber::new(1)));
+  }
+
+  #[test]
+  fn coherent_set_not_released_during_access() {
+    let mut tracker = CoherentSetTracker::default();
+    let w = writer(1);
+
+    tracker.sample_received(w, &set(1));
+    tracker.begin_access();
+    tracker.end_received(w, SequenceNumber::new(2), &set(1), SequenceNumber::new(3));
+    assert_eq!(tracker.withheld_from(w), Some(SequenceNumber::new(1)));
+
+    tracker.end_access();
+    assert_eq!(tracker.withheld_from(w), None);
+  }
+}
@@ -0,0 +1,78 @@
+use std::{sync::Mutex, task::Waker};
+
+// Tasks waiting for room in a command channel from a DataWriter to its RTPS
+// Writer, or from a DataReader to its RTPS Reader. Several async operations may
+// be waiting at the same time, e.g. a write and a Publisher-level wait for
+// acknowledgments. The receiving end wakes them all whenever it has taken
+// commands off the channel. HistoryReservations wakes async writers waiting for
+// room in history the same way.
+#[derive(Default)]
+pub(crate) struct CommandChannelWakers {
+  wakers: Mutex<Vec<Waker>>,
+}
+
+impl CommandChannelWakers {
+  pub fn register(&self, waker: &Waker) {
+    let mut wakers = self
+      .wakers
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+      wakers.push(waker.clone());
+    }
+  }
+
+  pub fn wake_all(&self) {
+    let wakers = std::mem::take(
+      &mut *self
+        .wakers
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    for waker in wakers {
+      waker.wake();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  };
+
+  use futures::task::{waker, ArcWake};
+
+  use super::*;
+
+  #[derive(Default)]
+  struct WakeCounter(AtomicUsize);
+
+  impl ArcWake for WakeCounter {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+      arc_self.0.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  #[test]
+  fn command_channel_wakers_wake_each_task_once() {
+    let wakers = CommandChannelWakers::default();
+    let (task1, task2) = (
+      Arc::new(WakeCounter::default()),
+      Arc::new(WakeCounter::default()),
+    );
+    let waker1 = waker(Arc::clone(&task1));
+
+    wakers.register(&waker1);
+    wakers.register(&waker1); // polled again before being woken
+    wakers.register(&waker(Arc::clone(&task2)));
+    wakers.wake_all();
+    assert_eq!(task1.0.load(Ordering::Relaxed), 1);
+    assert_eq!(task2.0.load(Ordering::Relaxed), 1);
+
+    // Woken tasks must register again.
+    wakers.wake_all();
+    assert_eq!(task1.0.load(Ordering::Relaxed), 1);
+  }
+}
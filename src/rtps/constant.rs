@@ -4,9 +4,10 @@ use mio_06::Token;
 use mio_extras::channel as mio_channel;
 
 use crate::{
+  dds::qos::QosPolicies,
   discovery::{
     builtin_endpoint::BuiltinEndpointSet,
-    sedp_messages::{DiscoveredReaderData, DiscoveredWriterData},
+    sedp_messages::{DiscoveredReaderData, DiscoveredWriterData, ParticipantMessageDataKind},
   },
   structure::guid::{EntityId, EntityKind, GuidPrefix, GUID},
 };
@@ -280,6 +281,20 @@ pub(crate) enum DiscoveryNotificationType {
     writer_guid: GUID,
     manual_assertion: bool,
   },
+  // Application has changed the QoS of a local DataWriter or DataReader
+  LocalWriterQosUpdated {
+    writer_guid: GUID,
+    qos: QosPolicies,
+  },
+  LocalReaderQosUpdated {
+    reader_guid: GUID,
+    qos: QosPolicies,
+  },
+  // A participant has asserted its liveliness with a ParticipantMessage
+  ParticipantLivelinessAsserted {
+    guid_prefix: GuidPrefix,
+    kind: ParticipantMessageDataKind,
+  },
   #[cfg(feature = "security")]
   ParticipantAuthenticationStatusChanged {
     guid_prefix: GuidPrefix,
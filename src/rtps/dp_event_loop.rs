@@ -11,13 +11,13 @@ use mio_extras::channel as mio_channel;
 
 use crate::{
   dds::{
-    qos::policy,
+    qos::{policy, QosPolicies},
     statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
   },
   discovery::{
     discovery::DiscoveryCommand,
     discovery_db::{discovery_db_read, DiscoveryDB},
-    sedp_messages::{DiscoveredReaderData, DiscoveredWriterData},
+    sedp_messages::{DiscoveredReaderData, DiscoveredWriterData, ParticipantMessageDataKind},
   },
   messages::submessages::submessages::AckSubmessage,
   network::{udp_listener::UDPListener, udp_sender::UDPSender},
@@ -197,6 +197,8 @@ impl DPEventLoop {
     #[cfg(not(feature = "security"))]
     let security_plugins_opt = security_plugins_opt.and(None); // make sure it is None an consume value
 
+    let ignored_entities = discovery_db_read(&discovery_db).ignored_entities();
+
     Self {
       domain_info,
       poll,
@@ -209,6 +211,7 @@ impl DPEventLoop {
         acknack_sender,
         spdp_liveness_sender,
         security_plugins_opt.clone(),
+        ignored_entities,
       ),
       #[cfg(feature = "security")]
       security_plugins_opt,
@@ -370,6 +373,18 @@ impl DPEventLoop {
                         .map(|w| w.handle_heartbeat_tick(manual_assertion));
                     }
 
+                    LocalWriterQosUpdated { writer_guid, qos } => {
+                      ev_wrapper.local_writer_qos_updated(writer_guid, qos);
+                    }
+
+                    LocalReaderQosUpdated { reader_guid, qos } => {
+                      ev_wrapper.local_reader_qos_updated(reader_guid, qos);
+                    }
+
+                    ParticipantLivelinessAsserted { guid_prefix, kind } => {
+                      ev_wrapper.participant_liveliness_asserted(guid_prefix, kind);
+                    }
+
                     #[cfg(feature = "security")]
                     ParticipantAuthenticationStatusChanged { guid_prefix } => {
                       ev_wrapper.on_remote_participant_authentication_status_changed(guid_prefix);
@@ -692,8 +707,39 @@ impl DPEventLoop {
     }
   }
 
+  fn participant_liveliness_asserted(
+    &mut self,
+    participant_guid_prefix: GuidPrefix,
+    kind: ParticipantMessageDataKind,
+  ) {
+    if participant_guid_prefix == self.domain_info.domain_participant_guid.prefix
+      && kind == ParticipantMessageDataKind::MANUAL_LIVELINESS_UPDATE
+    {
+      for writer in self.writers.values_mut() {
+        writer.participant_liveliness_asserted();
+      }
+    }
+
+    for reader in self.message_receiver.available_readers.values_mut() {
+      reader.participant_liveliness_asserted(participant_guid_prefix, kind);
+    }
+  }
+
   fn remote_reader_discovered(&mut self, remote_reader: &DiscoveredReaderData) {
+    self.match_remote_reader(remote_reader, None);
+  }
+
+  // Matches the remote Reader to local Writers of the same topic, or only to
+  // `local_writer`, if given.
+  fn match_remote_reader(
+    &mut self,
+    remote_reader: &DiscoveredReaderData,
+    local_writer: Option<GUID>,
+  ) {
     for writer in self.writers.values_mut() {
+      if local_writer.is_some_and(|guid| guid != writer.guid()) {
+        continue;
+      }
       if remote_reader.subscription_topic_data.topic_name() == writer.topic_name() {
         #[cfg(not(feature = "security"))]
         let match_to_reader = true;
@@ -768,8 +814,21 @@ impl DPEventLoop {
   }
 
   fn remote_writer_discovered(&mut self, remote_writer: &DiscoveredWriterData) {
+    self.match_remote_writer(remote_writer, None);
+  }
+
+  // Matches the remote Writer to local Readers of the same topic, or only to
+  // `local_reader`, if given.
+  fn match_remote_writer(
+    &mut self,
+    remote_writer: &DiscoveredWriterData,
+    local_reader: Option<GUID>,
+  ) {
     // update writer proxies in local readers
     for reader in self.message_receiver.available_readers.values_mut() {
+      if local_reader.is_some_and(|guid| guid != reader.guid()) {
+        continue;
+      }
       if &remote_writer.publication_topic_data.topic_name == reader.topic_name() {
         #[cfg(not(feature = "security"))]
         let match_to_writer = true;
@@ -842,6 +901,35 @@ impl DPEventLoop {
     }
   }
 
+  // The application has changed the QoS of a local Writer. Matches to
+  // Readers are re-evaluated, because they may have become compatible or
+  // incompatible.
+  fn local_writer_qos_updated(&mut self, writer_guid: GUID, qos: QosPolicies) {
+    let Some(writer) = self.writers.get_mut(&writer_guid.entity_id) else {
+      warn!("QoS updated for unknown Writer {writer_guid:?}");
+      return;
+    };
+    writer.set_qos(qos);
+    let remote_readers =
+      discovery_db_read(&self.discovery_db).readers_on_topic(writer.topic_name());
+    for remote_reader in &remote_readers {
+      self.match_remote_reader(remote_reader, Some(writer_guid));
+    }
+  }
+
+  fn local_reader_qos_updated(&mut self, reader_guid: GUID, qos: QosPolicies) {
+    let Some(reader) = self.message_receiver.reader_mut(reader_guid.entity_id) else {
+      warn!("QoS updated for unknown Reader {reader_guid:?}");
+      return;
+    };
+    reader.set_qos(qos);
+    let remote_writers =
+      discovery_db_read(&self.discovery_db).writers_on_topic(reader.topic_name());
+    for remote_writer in &remote_writers {
+      self.match_remote_writer(remote_writer, Some(reader_guid));
+    }
+  }
+
   fn add_local_reader(&mut self, reader_ing: ReaderIngredients) {
     let timer = new_simple_timer();
     self
@@ -1191,8 +1279,14 @@ mod tests {
         like_stateless: false,
         qos_policy: QosPolicies::qos_none(),
         data_reader_command_receiver: reader_command_receiver,
+        data_reader_command_wakers: Arc::default(),
         data_reader_waker: data_reader_waker.clone(),
         poll_event_sender: notification_event_sender,
+        writer_strengths: Arc::default(),
+        matched_writer_guids: Arc::default(),
+        coherent_sets: None,
+        data_availability: Arc::default(),
+        subscriber_status_sender: Arc::default(),
         security_plugins: None,
       };
 
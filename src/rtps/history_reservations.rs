@@ -0,0 +1,308 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  sync::{Condvar, Mutex, MutexGuard},
+  task::Waker,
+  time::Duration,
+};
+
+use super::command_channel_wakers::CommandChannelWakers;
+use crate::{
+  dds::{
+    key::KeyHash,
+    qos::{policy, QosPolicies},
+  },
+  structure::sequence_number::SequenceNumber,
+};
+
+// RESOURCE_LIMITS of a reliable KEEP_ALL Writer, see DDS spec v1.4 Section
+// 2.2.3.19 and 2.2.3.18.
+//
+// Such a Writer must keep all of its changes until all matched reliable
+// readers have acknowledged them, so its history is bounded only by
+// RESOURCE_LIMITS. When the history is full, writing blocks for at most
+// max_blocking_time of the RELIABILITY QoS, waiting for acknowledgements.
+//
+// The DataWriter reserves room for each change before it gets a sequence
+// number, and passes it to the RTPS Writer. The RTPS Writer releases the
+// changes as they become acknowledged. Instances are identified by key hash.
+#[derive(Default)]
+pub(crate) struct HistoryReservations {
+  limits: Option<policy::ResourceLimits>, // None, if this Writer does not block
+  reserved: Mutex<BTreeMap<SequenceNumber, KeyHash>>,
+  released: Condvar,
+  // Async writers waiting for room
+  wakers: CommandChannelWakers,
+}
+
+impl HistoryReservations {
+  pub fn new(qos: &QosPolicies) -> Self {
+    let limits = match (qos.history(), qos.resource_limits()) {
+      (Some(policy::History::KeepAll), Some(limits)) if qos.is_reliable() => Some(limits),
+      _ => None,
+    };
+    Self {
+      limits,
+      reserved: Mutex::new(BTreeMap::new()),
+      released: Condvar::new(),
+      wakers: CommandChannelWakers::default(),
+    }
+  }
+
+  pub fn is_limited(&self) -> bool {
+    self.limits.is_some()
+  }
+
+  fn lock(&self) -> MutexGuard<'_, BTreeMap<SequenceNumber, KeyHash>> {
+    // The map is always left in a consistent state, so poisoning is harmless.
+    self
+      .reserved
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn has_room(&self, reserved: &BTreeMap<SequenceNumber, KeyHash>, instance: KeyHash) -> bool {
+    let Some(limits) = self.limits else {
+      return true;
+    };
+    if limits
+      .sample_limit()
+      .is_some_and(|max_samples| reserved.len() >= max_samples)
+    {
+      return false;
+    }
+    let instance_samples = reserved.values().filter(|&&k| k == instance).count();
+    if limits
+      .samples_per_instance_limit()
+      .is_some_and(|max_samples_per_instance| instance_samples >= max_samples_per_instance)
+    {
+      return false;
+    }
+    if instance_samples == 0 {
+      let instances: BTreeSet<&KeyHash> = reserved.values().collect();
+      if limits
+        .instance_limit()
+        .is_some_and(|max_instances| instances.len() >= max_instances)
+      {
+        return false;
+      }
+    }
+    true
+  }
+
+  // Reserves room for a change without blocking. The sequence number is
+  // assigned only once there is room, so that a writer waiting for room does
+  // not hold back the sequence numbers of others. Returns None if the history
+  // is full.
+  pub fn try_reserve(
+    &self,
+    instance: KeyHash,
+    assign_sequence_number: impl FnOnce() -> SequenceNumber,
+  ) -> Option<SequenceNumber> {
+    if !self.is_limited() {
+      return Some(assign_sequence_number());
+    }
+    let mut reserved = self.lock();
+    self
+      .has_room(&reserved, instance)
+      .then(|| Self::insert(&mut reserved, instance, assign_sequence_number))
+  }
+
+  // Like try_reserve, but an async writer is woken up when changes are
+  // released.
+  pub fn try_reserve_or_wake(
+    &self,
+    instance: KeyHash,
+    assign_sequence_number: impl FnOnce() -> SequenceNumber,
+    waker: &Waker,
+  ) -> Option<SequenceNumber> {
+    self.wakers.register(waker);
+    // Try only after registering the waker, so that a release cannot slip in
+    // between.
+    self.try_reserve(instance, assign_sequence_number)
+  }
+
+  // Reserves room for a change like try_reserve, but blocks until there is
+  // room or the timeout expires. Returns None on timeout.
+  pub fn reserve(
+    &self,
+    instance: KeyHash,
+    assign_sequence_number: impl FnOnce() -> SequenceNumber,
+    timeout: Duration,
+  ) -> Option<SequenceNumber> {
+    if !self.is_limited() {
+      return Some(assign_sequence_number());
+    }
+    let (mut reserved, _timeout_result) = self
+      .released
+      .wait_timeout_while(self.lock(), timeout, |reserved| {
+        !self.has_room(reserved, instance)
+      })
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    self
+      .has_room(&reserved, instance)
+      .then(|| Self::insert(&mut reserved, instance, assign_sequence_number))
+  }
+
+  fn insert(
+    reserved: &mut BTreeMap<SequenceNumber, KeyHash>,
+    instance: KeyHash,
+    assign_sequence_number: impl FnOnce() -> SequenceNumber,
+  ) -> SequenceNumber {
+    let sequence_number = assign_sequence_number();
+    reserved.insert(sequence_number, instance);
+    sequence_number
+  }
+
+  // The change was not written after all.
+  pub fn cancel(&self, sequence_number: SequenceNumber) {
+    if self.is_limited() {
+      self.lock().remove(&sequence_number);
+      self.notify_released();
+    }
+  }
+
+  // Releases the changes before the given sequence number, because they have
+  // been acknowledged.
+  pub fn release_before(&self, sequence_number: SequenceNumber) {
+    if !self.is_limited() {
+      return;
+    }
+    let released = {
+      let mut reserved = self.lock();
+      let kept = reserved.split_off(&sequence_number);
+      std::mem::replace(&mut *reserved, kept)
+    };
+    if !released.is_empty() {
+      self.notify_released();
+    }
+  }
+
+  fn notify_released(&self) {
+    self.released.notify_all();
+    self.wakers.wake_all();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  };
+
+  use futures::task::{waker, ArcWake};
+
+  use super::*;
+
+  fn reservations(
+    max_samples: i32,
+    max_instances: i32,
+    max_per_instance: i32,
+  ) -> HistoryReservations {
+    let qos = QosPolicies::builder()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(10),
+      })
+      .history(policy::History::KeepAll)
+      .resource_limits(policy::ResourceLimits {
+        max_samples,
+        max_instances,
+        max_samples_per_instance: max_per_instance,
+      })
+      .build();
+    HistoryReservations::new(&qos)
+  }
+
+  fn sn(n: i64) -> SequenceNumber {
+    SequenceNumber::new(n)
+  }
+
+  #[test]
+  fn reservations_follow_resource_limits() {
+    let timeout = Duration::from_millis(10);
+    let a = KeyHash::zero();
+    let b = KeyHash::from_pl_cdr_bytes(vec![1; 16]).unwrap();
+    let c = KeyHash::from_pl_cdr_bytes(vec![2; 16]).unwrap();
+
+    let limits = reservations(3, 2, 2);
+    assert_eq!(limits.reserve(a, || sn(1), timeout), Some(sn(1)));
+    assert_eq!(limits.reserve(a, || sn(2), timeout), Some(sn(2)));
+    // max_samples_per_instance
+    assert_eq!(limits.reserve(a, || sn(3), timeout), None);
+    assert_eq!(limits.reserve(b, || sn(3), timeout), Some(sn(3)));
+    // max_samples
+    assert_eq!(limits.try_reserve(b, || sn(4)), None);
+    limits.release_before(sn(2));
+    // max_instances
+    assert_eq!(limits.try_reserve(c, || sn(4)), None);
+    assert_eq!(limits.try_reserve(b, || sn(4)), Some(sn(4)));
+
+    // LENGTH_UNLIMITED does not block, and only reliable KEEP_ALL is limited
+    let unlimited = reservations(-1, -1, -1);
+    assert!(unlimited.is_limited());
+    for n in 1..100 {
+      assert_eq!(unlimited.try_reserve(a, || sn(n)), Some(sn(n)));
+    }
+    let keep_last = HistoryReservations::new(&QosPolicies::qos_none());
+    assert!(!keep_last.is_limited());
+  }
+
+  #[test]
+  fn sequence_number_is_assigned_only_with_room() {
+    let limits = reservations(1, -1, -1);
+    let next = std::cell::Cell::new(1);
+    let assign = || {
+      let n = next.replace(next.get() + 1);
+      sn(n)
+    };
+    assert_eq!(limits.try_reserve(KeyHash::zero(), assign), Some(sn(1)));
+    assert_eq!(limits.try_reserve(KeyHash::zero(), assign), None);
+    assert_eq!(
+      limits.reserve(KeyHash::zero(), assign, Duration::from_millis(10)),
+      None
+    );
+    assert_eq!(next.get(), 2);
+  }
+
+  #[test]
+  fn reservation_waits_for_release() {
+    let limits = std::sync::Arc::new(reservations(1, -1, -1));
+    assert!(limits.try_reserve(KeyHash::zero(), || sn(1)).is_some());
+
+    let releaser = std::sync::Arc::clone(&limits);
+    let handle = std::thread::spawn(move || {
+      std::thread::sleep(Duration::from_millis(50));
+      releaser.release_before(sn(2));
+    });
+    assert!(limits
+      .reserve(KeyHash::zero(), || sn(2), Duration::from_secs(5))
+      .is_some());
+    handle.join().unwrap();
+  }
+
+  #[derive(Default)]
+  struct WakeCounter(AtomicUsize);
+
+  impl ArcWake for WakeCounter {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+      arc_self.0.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  #[test]
+  fn release_wakes_all_async_writers() {
+    let limits = reservations(1, -1, -1);
+    assert!(limits.try_reserve(KeyHash::zero(), || sn(1)).is_some());
+    let counters: Vec<Arc<WakeCounter>> = (0..2).map(|_| Arc::default()).collect();
+    for (n, counter) in (2..).zip(&counters) {
+      let waker = waker(Arc::clone(counter));
+      assert!(limits
+        .try_reserve_or_wake(KeyHash::zero(), || sn(n), &waker)
+        .is_none());
+    }
+    limits.release_before(sn(2));
+    for counter in counters {
+      assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+    }
+  }
+}
@@ -19,9 +19,9 @@ use crate::{
     validity_trait::Validity,
     vendor_id::VendorId,
   },
-  rtps::{writer::Writer as RtpsWriter, Submessage, SubmessageBody},
+  rtps::{coherent_set::CoherentSet, writer::Writer as RtpsWriter, Submessage, SubmessageBody},
   structure::{
-    cache_change::CacheChange,
+    cache_change::{CacheChange, ChangeKind},
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
     parameter_id::ParameterId,
@@ -37,6 +37,11 @@ use crate::{
 #[cfg(not(feature = "security"))]
 use crate::no_security::SecurityPluginsHandle;
 
+// Serialized sizes of the RTPS Header and of a Submessage header. RTPS spec
+// Sections 9.4.4 and 9.4.5.1.
+pub(crate) const RTPS_MESSAGE_HEADER_SIZE: usize = 20;
+const SUBMESSAGE_HEADER_SIZE: usize = 4;
+
 #[derive(Debug, Clone)]
 pub struct Message {
   pub header: Header,
@@ -192,7 +197,17 @@ impl MessageBuilder {
 
     // Check if we are disposing by key hash
     match cache_change.data_value {
-      DDSData::Data { .. } | DDSData::DisposeByKey { .. } => (), // no
+      DDSData::Data { .. } | DDSData::CoherentSetEnd => (), // no
+      DDSData::DisposeByKey { change_kind, .. } => {
+        // The key is sent as payload, but the reader still needs to know
+        // if the instance was disposed or unregistered.
+        let status_info = Parameter::create_pid_status_info_parameter(
+          /* disposed */ change_kind == ChangeKind::NotAliveDisposed,
+          /* unregistered */ change_kind == ChangeKind::NotAliveUnregistered,
+          /* filtered */ false,
+        );
+        param_list.push(status_info);
+      }
       DDSData::DisposeByKeyHash { key_hash, .. } => {
         // yes, insert to inline QoS
         // insert key hash
@@ -218,12 +233,16 @@ impl MessageBuilder {
       });
     }
 
+    if let Some(coherent_set) = cache_change.write_options.coherent_set() {
+      push_coherent_set_parameters(&mut param_list, coherent_set, endianness);
+    }
+
     let serialized_payload = match cache_change.data_value {
       DDSData::Data {
         ref serialized_payload,
       } => Some(serialized_payload.clone()), // contents is Bytes
       DDSData::DisposeByKey { ref key, .. } => Some(key.clone()),
-      DDSData::DisposeByKeyHash { .. } => None,
+      DDSData::DisposeByKeyHash { .. } | DDSData::CoherentSetEnd => None,
     };
 
     #[cfg(not(feature = "security"))]
@@ -280,7 +299,7 @@ impl MessageBuilder {
       | (match cache_change.data_value {
         DDSData::Data { .. } => BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data),
         DDSData::DisposeByKey { .. } => BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Key),
-        DDSData::DisposeByKeyHash { .. } => {
+        DDSData::DisposeByKeyHash { .. } | DDSData::CoherentSetEnd => {
           BitFlags::<DATA_Flags>::from_flag(DATA_Flags::InlineQos)
         }
       })
@@ -327,9 +346,10 @@ impl MessageBuilder {
     // Check if we are disposing by key hash
     match cache_change.data_value {
       DDSData::Data { .. } | DDSData::DisposeByKey { .. } => (), // no => ok
-      DDSData::DisposeByKeyHash { .. } => {
+      DDSData::DisposeByKeyHash { .. } | DDSData::CoherentSetEnd => {
         error!(
-          "data_frag_msg: Called with DDSData::DisposeByKeyHash. This is not legit! Discarding."
+          "data_frag_msg: Called with {:?}. This is not legit! Discarding.",
+          cache_change.data_value
         );
         // DataFrag must contain either data or key payload, disposing by key hash
         // sent in inline QoS (without key or data) is not possible like in Data
@@ -348,6 +368,10 @@ impl MessageBuilder {
       });
     }
 
+    if let Some(coherent_set) = cache_change.write_options.coherent_set() {
+      push_coherent_set_parameters(&mut param_list, coherent_set, endianness);
+    }
+
     let have_inline_qos = !param_list.is_empty(); // we need this later also
 
     // fragments are numbered starting from 1, not 0.
@@ -417,7 +441,7 @@ impl MessageBuilder {
       | (match cache_change.data_value {
         DDSData::Data { .. } => BitFlags::<DATAFRAG_Flags>::empty(),
         DDSData::DisposeByKey { .. } => BitFlags::<DATAFRAG_Flags>::from_flag(DATAFRAG_Flags::Key),
-        DDSData::DisposeByKeyHash { .. } => unreachable!(),
+        DDSData::DisposeByKeyHash { .. } | DDSData::CoherentSetEnd => unreachable!(),
       })
       // inline QoS flag
       | (if have_inline_qos {
@@ -511,6 +535,27 @@ impl MessageBuilder {
     self
   }
 
+  /// Moves the submessages of `other` to the end of this builder.
+  pub fn append(mut self, mut other: Self) -> Self {
+    self.submessages.append(&mut other.submessages);
+    self
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.submessages.is_empty()
+  }
+
+  /// Serialized size of the message that would be built, including the
+  /// RTPS header and the submessage headers.
+  pub fn len_serialized(&self) -> usize {
+    RTPS_MESSAGE_HEADER_SIZE
+      + self
+        .submessages
+        .iter()
+        .map(|s| SUBMESSAGE_HEADER_SIZE + usize::from(s.header.content_length))
+        .sum::<usize>()
+  }
+
   pub fn add_header_and_build(self, guid_prefix: GuidPrefix) -> Message {
     Message {
       header: Header {
@@ -524,6 +569,33 @@ impl MessageBuilder {
   }
 }
 
+// Inline QoS parameters for a sample of a coherent set, or its end marker.
+fn push_coherent_set_parameters(
+  param_list: &mut ParameterList,
+  coherent_set: &CoherentSet,
+  endianness: Endianness,
+) {
+  param_list.push(Parameter {
+    parameter_id: ParameterId::PID_COHERENT_SET,
+    value: coherent_set
+      .first_sequence_number
+      .write_to_vec_with_ctx(endianness)
+      .unwrap(),
+  });
+  if let Some(group_sn) = coherent_set.group_sequence_number {
+    param_list.push(Parameter {
+      parameter_id: ParameterId::PID_GROUP_COHERENT_SET,
+      value: group_sn.write_to_vec_with_ctx(endianness).unwrap(),
+    });
+  }
+  if let Some(group_writers) = &coherent_set.group_writers {
+    param_list.push(Parameter {
+      parameter_id: ParameterId::PID_COHERENT_SET_GROUP_WRITERS,
+      value: group_writers.iter().flat_map(GUID::to_bytes).collect(),
+    });
+  }
+}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 mod tests {
@@ -761,4 +833,118 @@ mod tests {
     info!("read_from_buffer() --> {rtps:?}");
     // if we get here without panic, the test passes
   }
+  #[test]
+  fn coherent_set_end_marker() {
+    use crate::{
+      dds::with_key::datawriter::WriteOptionsBuilder,
+      messages::submessages::elements::{inline_qos::InlineQos, RepresentationIdentifier},
+      structure::guid::EntityKind,
+    };
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let coherent_set = CoherentSet {
+      first_sequence_number: SequenceNumber::new(3),
+      group_sequence_number: Some(SequenceNumber::new(7)),
+      group_writers: Some(vec![
+        writer_guid,
+        GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      ]),
+    };
+    let cache_change = CacheChange::new(
+      writer_guid,
+      SequenceNumber::new(5),
+      WriteOptionsBuilder::new()
+        .coherent_set(coherent_set.clone())
+        .build(),
+      DDSData::CoherentSetEnd,
+    );
+
+    let message = MessageBuilder::new()
+      .data_msg(
+        &cache_change,
+        EntityId::UNKNOWN,
+        writer_guid,
+        Endianness::BigEndian,
+        None,
+      )
+      .add_header_and_build(writer_guid.prefix);
+
+    let bytes = message
+      .write_to_vec_with_ctx(Endianness::BigEndian)
+      .unwrap();
+    let message = Message::read_from_buffer(&Bytes::from(bytes)).unwrap();
+    match &message.submessages[0].body {
+      SubmessageBody::Writer(WriterSubmessage::Data(data, flags)) => {
+        // End marker has no payload, only inline QoS
+        assert!(data.serialized_payload.is_none());
+        assert!(!flags.contains(DATA_Flags::Data) && !flags.contains(DATA_Flags::Key));
+        let inline_qos = data.inline_qos.as_ref().unwrap();
+        assert_eq!(
+          InlineQos::coherent_set(
+            inline_qos,
+            RepresentationIdentifier::PL_CDR_BE,
+            VendorId::THIS_IMPLEMENTATION
+          )
+          .unwrap(),
+          Some(coherent_set.clone())
+        );
+        // Our vendor-specific list of Writers is not trusted from others.
+        assert_eq!(
+          InlineQos::coherent_set(
+            inline_qos,
+            RepresentationIdentifier::PL_CDR_BE,
+            VendorId::VENDOR_UNKNOWN
+          )
+          .unwrap(),
+          Some(CoherentSet {
+            group_writers: None,
+            ..coherent_set
+          })
+        );
+      }
+      other => panic!("Expected DATA, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn message_builder_len_serialized() {
+    use crate::{
+      dds::with_key::datawriter::WriteOptions,
+      messages::submessages::elements::{
+        serialized_payload::SerializedPayload, RepresentationIdentifier,
+      },
+      structure::guid::EntityKind,
+    };
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let data_change = |sn: i64| {
+      let cache_change = CacheChange::new(
+        writer_guid,
+        SequenceNumber::new(sn),
+        WriteOptions::default(),
+        DDSData::new(SerializedPayload::new_from_bytes(
+          RepresentationIdentifier::CDR_LE,
+          Bytes::from_static(&[1, 2, 3, 4, 5]),
+        )),
+      );
+      MessageBuilder::new()
+        .ts_msg(Endianness::LittleEndian, Some(Timestamp::now()))
+        .data_msg(
+          &cache_change,
+          EntityId::UNKNOWN,
+          writer_guid,
+          Endianness::LittleEndian,
+          None,
+        )
+    };
+
+    let builder = data_change(1).append(data_change(2));
+    let expected_len = builder.len_serialized();
+    let message = builder.add_header_and_build(writer_guid.prefix);
+    assert_eq!(message.submessages.len(), 4);
+    let bytes = message
+      .write_to_vec_with_ctx(Endianness::LittleEndian)
+      .unwrap();
+    assert_eq!(bytes.len(), expected_len);
+  }
 }
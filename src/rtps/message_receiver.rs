@@ -1,4 +1,7 @@
-use std::collections::{btree_map::Entry, BTreeMap};
+use std::{
+  collections::{btree_map::Entry, BTreeMap},
+  sync::{Arc, RwLock},
+};
 
 use enumflags2::BitFlags;
 use mio_extras::{channel as mio_channel, channel::TrySendError};
@@ -7,8 +10,9 @@ use log::{debug, error, info, trace, warn};
 use bytes::Bytes;
 
 use crate::{
+  discovery::discovery_db::IgnoredEntities,
   messages::{protocol_version::ProtocolVersion, submessages::submessages::*, vendor_id::VendorId},
-  rtps::{reader::Reader, Message, Submessage, SubmessageBody},
+  rtps::{message::RTPS_MESSAGE_HEADER_SIZE, reader::Reader, Message, Submessage, SubmessageBody},
   structure::{
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
@@ -30,8 +34,6 @@ use crate::dds::ddsdata::DDSData;
 #[cfg(test)]
 use crate::structure::sequence_number::SequenceNumber;
 
-const RTPS_MESSAGE_HEADER_SIZE: usize = 20;
-
 // Secure submessage receiving state machine:
 //
 // [None] ---SecurePrefix--> [Prefix] ---some Submessage--> [SecureSubmessage]
@@ -67,6 +69,7 @@ pub struct SecureWrapping {
 // This is partial receiver state to be sent to Reader or Writer
 #[derive(Debug, Clone)]
 pub struct MessageReceiverState {
+  pub source_vendor_id: VendorId,
   pub source_guid_prefix: GuidPrefix,
   pub unicast_reply_locator_list: Vec<Locator>,
   pub multicast_reply_locator_list: Vec<Locator>,
@@ -78,6 +81,7 @@ pub struct MessageReceiverState {
 impl Default for MessageReceiverState {
   fn default() -> Self {
     Self {
+      source_vendor_id: VendorId::VENDOR_UNKNOWN,
       source_guid_prefix: GuidPrefix::default(),
       unicast_reply_locator_list: Vec::default(),
       multicast_reply_locator_list: Vec::default(),
@@ -105,6 +109,8 @@ pub(crate) struct MessageReceiver {
   // repeated messages with duplicate SequenceNumbers, but Discovery needs to see them.
   spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
   security_plugins: Option<SecurityPluginsHandle>,
+  // Traffic from these is dropped. Shared with DiscoveryDB.
+  ignored: Arc<RwLock<IgnoredEntities>>,
 
   own_guid_prefix: GuidPrefix,
   pub source_version: ProtocolVersion,
@@ -131,12 +137,14 @@ impl MessageReceiver {
     acknack_sender: mio_channel::SyncSender<(GuidPrefix, AckSubmessage)>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
     security_plugins: Option<SecurityPluginsHandle>,
+    ignored: Arc<RwLock<IgnoredEntities>>,
   ) -> Self {
     Self {
       available_readers: BTreeMap::new(),
       acknack_sender,
       spdp_liveness_sender,
       security_plugins,
+      ignored,
       own_guid_prefix: participant_guid_prefix,
 
       source_version: ProtocolVersion::THIS_IMPLEMENTATION,
@@ -178,6 +186,7 @@ impl MessageReceiver {
 
   fn clone_partial_message_receiver_state(&self) -> MessageReceiverState {
     MessageReceiverState {
+      source_vendor_id: self.source_vendor_id,
       source_guid_prefix: self.source_guid_prefix,
       unicast_reply_locator_list: self.unicast_reply_locator_list.clone(),
       multicast_reply_locator_list: self.multicast_reply_locator_list.clone(),
@@ -246,6 +255,18 @@ impl MessageReceiver {
     self.source_version = rtps_message.header.protocol_version;
     self.source_vendor_id = rtps_message.header.vendor_id;
 
+    if self
+      .ignored
+      .read()
+      .unwrap()
+      .participant(self.source_guid_prefix)
+    {
+      return trace!(
+        "Message from ignored participant {:?}. Dropping.",
+        self.source_guid_prefix
+      );
+    }
+
     #[cfg(not(feature = "security"))]
     let decoded_message = rtps_message;
 
@@ -514,6 +535,15 @@ impl MessageReceiver {
     } // match secure_submessage_state
   } // fn
 
+  fn sender_is_ignored(&self, sender_entity_id: EntityId) -> bool {
+    let sender = GUID::new(self.source_guid_prefix, sender_entity_id);
+    let ignored = self.ignored.read().unwrap().endpoint(sender);
+    if ignored {
+      trace!("Submessage from ignored endpoint {:?}. Dropping.", sender);
+    }
+    ignored
+  }
+
   fn handle_writer_submessage(
     &mut self,
     target_reader_entity_id: EntityId,
@@ -528,6 +558,9 @@ impl MessageReceiver {
       );
       return;
     }
+    if self.sender_is_ignored(submessage.sender_entity_id()) {
+      return;
+    }
 
     #[cfg(feature = "security")]
     if self.must_be_rtps_protection_special_case {
@@ -593,6 +626,7 @@ impl MessageReceiver {
         target_reader.handle_heartbeat_msg(
           &heartbeat,
           flags.contains(HEARTBEAT_Flags::Final),
+          flags.contains(HEARTBEAT_Flags::Liveliness),
           &mr_state,
         );
       }
@@ -787,6 +821,9 @@ impl MessageReceiver {
       );
       return;
     }
+    if self.sender_is_ignored(submessage.sender_entity_id()) {
+      return;
+    }
 
     #[cfg(feature = "security")]
     if self.must_be_rtps_protection_special_case {
@@ -1125,6 +1162,7 @@ mod tests {
       acknack_sender,
       spdp_liveness_sender,
       None,
+      Arc::default(),
     );
 
     // Create a reader to process the message
@@ -1162,8 +1200,14 @@ mod tests {
       like_stateless: false,
       qos_policy,
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
 
@@ -1250,8 +1294,13 @@ mod tests {
     let (acknack_sender, _acknack_receiver) =
       mio_channel::sync_channel::<(GuidPrefix, AckSubmessage)>(10);
     let (spdp_liveness_sender, _spdp_liveness_receiver) = mio_channel::sync_channel(8);
-    let mut message_receiver =
-      MessageReceiver::new(guid_new.prefix, acknack_sender, spdp_liveness_sender, None);
+    let mut message_receiver = MessageReceiver::new(
+      guid_new.prefix,
+      acknack_sender,
+      spdp_liveness_sender,
+      None,
+      Arc::default(),
+    );
 
     message_receiver.handle_received_packet(&udp_bits1);
     assert_eq!(message_receiver.submessage_count, 4);
@@ -1,8 +1,11 @@
 use std::{
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet},
   fmt, iter,
   rc::Rc,
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, MutexGuard,
+  },
   task::Waker,
   time::Duration as StdDuration,
 };
@@ -18,13 +21,15 @@ use crate::{
     ddsdata::DDSData,
     qos::{policy, HasQoSPolicy, QosPolicies},
     statusevents::{
-      CountWithChange, DataReaderStatus, DomainParticipantStatusEvent, StatusChannelSender,
+      CountWithChange, DataReaderStatus, DomainParticipantStatusEvent, StatusChanges,
+      StatusChannelSender, SubscriberStatus,
     },
     with_key::{
       datawriter::{WriteOptions, WriteOptionsBuilder},
       simpledatareader::ReaderCommand,
     },
   },
+  discovery::sedp_messages::ParticipantMessageDataKind,
   messages::{
     header::Header,
     protocol_id::ProtocolId,
@@ -40,8 +45,12 @@ use crate::{
   mio_source,
   network::udp_sender::UDPSender,
   rtps::{
-    fragment_assembler::FragmentAssembler, message_receiver::MessageReceiverState,
-    rtps_writer_proxy::RtpsWriterProxy, Message,
+    coherent_set::{CoherentSet, CoherentSetTracker, CoherentSets},
+    command_channel_wakers::CommandChannelWakers,
+    fragment_assembler::FragmentAssembler,
+    message_receiver::MessageReceiverState,
+    rtps_writer_proxy::RtpsWriterProxy,
+    Message,
   },
   structure::{
     cache_change::{CacheChange, ChangeKind},
@@ -50,6 +59,7 @@ use crate::{
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
     locator::Locator,
+    parameter_id::ParameterId,
     sequence_number::{FragmentNumber, FragmentNumberSet, SequenceNumber, SequenceNumberSet},
     time::Timestamp,
   },
@@ -63,7 +73,139 @@ use crate::no_security::SecurityPluginsHandle;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum TimedEvent {
-  DeadlineMissedCheck,
+  DeadlineMissedCheck { generation: u64 },
+  LivelinessCheck { at: Timestamp },
+  DataAvailableNotification { at: Timestamp },
+}
+
+// Ownership strengths of the currently matched and alive writers. Shared with
+// the DataReader, which uses these for EXCLUSIVE ownership arbitration. A
+// writer is removed when its liveliness is lost or it is no longer matched, so
+// it cannot own any instances, and restored when its liveliness is regained.
+pub(crate) type WriterStrengths = Arc<Mutex<BTreeMap<GUID, i32>>>;
+
+// The currently matched writers. Shared with the DataReader, which reports
+// them as its matched publications.
+pub(crate) type MatchedWriterGuids = Arc<Mutex<BTreeSet<GUID>>>;
+
+// Status channel of the Subscriber, if someone is listening to it. Shared by
+// the Subscriber and the Readers of its DataReaders.
+pub(crate) type SubscriberStatusSender = Arc<Mutex<Option<StatusChannelSender<SubscriberStatus>>>>;
+
+// Does the DataReader have data it has not looked at yet. Shared by the
+// DataReader and the Reader. DataAvailable and DataOnReaders events are sent
+// only when new data arrives to a DataReader that has read everything, so
+// that a burst of samples does not flood the status channels.
+#[derive(Default)]
+pub(crate) struct DataAvailability {
+  unread: AtomicBool,
+  // Does the DataReader want DataAvailable status events
+  status_enabled: AtomicBool,
+  // Status changes of the DataReader, for waking up its Conditions
+  status_changes: Arc<StatusChanges>,
+}
+
+impl DataAvailability {
+  pub fn new(status_changes: Arc<StatusChanges>) -> Self {
+    Self {
+      unread: AtomicBool::new(false),
+      status_enabled: AtomicBool::new(false),
+      status_changes,
+    }
+  }
+
+  // Returns true, if there was no unread data before.
+  fn data_arrived(&self) -> bool {
+    let newly_unread = !self.unread.swap(true, Ordering::SeqCst);
+    if newly_unread {
+      self.status_changes.wakers().wake();
+    }
+    newly_unread
+  }
+
+  pub fn is_unread(&self) -> bool {
+    self.unread.load(Ordering::SeqCst)
+  }
+
+  pub fn status_changes(&self) -> &Arc<StatusChanges> {
+    &self.status_changes
+  }
+
+  pub fn data_read(&self) {
+    self.unread.store(false, Ordering::SeqCst);
+  }
+
+  pub fn set_status_enabled(&self, enabled: bool) {
+    self.status_enabled.store(enabled, Ordering::SeqCst);
+  }
+
+  fn status_enabled(&self) -> bool {
+    self.status_enabled.load(Ordering::SeqCst)
+  }
+}
+
+// The ways to tell a DataReader that there may be something new to read.
+#[derive(Clone)]
+pub(crate) struct DataReaderNotifier {
+  notification_sender: mio_channel::SyncSender<()>, // mio-0.6
+  data_reader_waker: Arc<Mutex<Option<Waker>>>,     // async
+  poll_event_sender: mio_source::PollEventSender,   // mio-0.8
+  // status events
+  reader_guid: GUID,
+  status_sender: StatusChannelSender<DataReaderStatus>,
+  data_availability: Arc<DataAvailability>,
+  subscriber_status_sender: SubscriberStatusSender,
+}
+
+impl DataReaderNotifier {
+  pub fn notify(&self) {
+    // async notify mechanism
+    self
+      .data_reader_waker
+      .lock()
+      .unwrap() // TODO: unwrap
+      .take() // Take to nullify the reference
+      .map(|w| w.wake_by_ref()); // If Some, call wake_by_ref
+
+    // mio-0.8 notify
+    self.poll_event_sender.send();
+
+    // mio-0.6 notify
+    match self.notification_sender.try_send(()) {
+      Ok(()) => (),
+      Err(mio_channel::TrySendError::Full(_)) => (),
+      // This is harmless. There is a notification in already.
+      Err(mio_channel::TrySendError::Disconnected(_)) => {
+        // If we get here, our DataReader has died. The Reader should now
+        // dispose itself. TODO: Implement Reader disposal.
+      }
+      Err(mio_channel::TrySendError::Io(_)) => {
+        // TODO: What does this mean? Can we ever get here?
+      }
+    }
+
+    // status events, if the DataReader has read everything before
+    if self.data_availability.data_arrived() {
+      if self.data_availability.status_enabled() {
+        self
+          .status_sender
+          .try_send(DataReaderStatus::DataAvailable)
+          .unwrap_or_else(|e| debug!("Cannot send DataAvailable: {e:?}"));
+      } else {
+        // Listeners get DataAvailable even if the status is not enabled.
+        self
+          .status_sender
+          .send_to_listener(&DataReaderStatus::DataAvailable);
+      }
+      if let Some(subscriber_status_sender) = self.subscriber_status_sender.lock().unwrap().as_ref()
+      {
+        // Disconnected is normal, if the listener was dropped.
+        let _ = subscriber_status_sender.try_send(SubscriberStatus::DataOnReaders {
+          reader: self.reader_guid,
+        });
+      }
+    }
+  }
 }
 
 // Some pieces necessary to construct a reader.
@@ -78,8 +220,15 @@ pub(crate) struct ReaderIngredients {
   pub(crate) like_stateless: bool, // Usually false (see like_stateless attribute of Reader)
   pub qos_policy: QosPolicies,
   pub data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
+  pub(crate) data_reader_command_wakers: Arc<CommandChannelWakers>,
   pub(crate) data_reader_waker: Arc<Mutex<Option<Waker>>>,
   pub(crate) poll_event_sender: mio_source::PollEventSender,
+  pub(crate) writer_strengths: WriterStrengths,
+  pub(crate) matched_writer_guids: MatchedWriterGuids,
+  // Present if the Subscriber has coherent access
+  pub(crate) coherent_sets: Option<CoherentSets>,
+  pub(crate) data_availability: Arc<DataAvailability>,
+  pub(crate) subscriber_status_sender: SubscriberStatusSender,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -102,8 +251,7 @@ impl fmt::Debug for ReaderIngredients {
 }
 
 pub(crate) struct Reader {
-  // Should the instant be sent?
-  notification_sender: mio_channel::SyncSender<()>,
+  notifier: DataReaderNotifier,
   status_sender: StatusChannelSender<DataReaderStatus>,
   udp_sender: Rc<UDPSender>,
 
@@ -141,11 +289,24 @@ pub(crate) struct Reader {
 
   requested_deadline_missed_count: i32,
   offered_incompatible_qos_count: i32,
+  // Incremented whenever the deadline check is restarted. Timer events of
+  // earlier generations are outdated, and ignored.
+  deadline_check_generation: u64,
+  // When the next LivelinessCheck is due. Timer events for other times are
+  // outdated, and ignored.
+  liveliness_check_at: Option<Timestamp>,
+  // When the DataReader is next notified of data held back by
+  // TIME_BASED_FILTER. Timer events for other times are outdated, and ignored.
+  data_available_notification_at: Option<Timestamp>,
 
   pub(crate) timed_event_timer: Timer<TimedEvent>,
   pub(crate) data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
-  data_reader_waker: Arc<Mutex<Option<Waker>>>,
-  poll_event_sender: mio_source::PollEventSender,
+  data_reader_command_wakers: Arc<CommandChannelWakers>,
+  writer_strengths: WriterStrengths,
+  matched_writer_guids: MatchedWriterGuids,
+  coherent_sets: Option<CoherentSets>,
+  // DataReader waiting for historical data from the matched writers
+  historical_data_waiter: Option<StatusChannelSender<()>>,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 
@@ -180,8 +341,24 @@ impl Reader {
       panic!("Attempted to create a stateless Reader with other than BestEffort reliability");
     }
 
-    Self {
+    let notifier = DataReaderNotifier {
       notification_sender: i.notification_sender,
+      data_reader_waker: i.data_reader_waker,
+      poll_event_sender: i.poll_event_sender,
+      reader_guid: i.guid,
+      status_sender: i.status_sender.clone(),
+      data_availability: i.data_availability,
+      subscriber_status_sender: i.subscriber_status_sender,
+    };
+    if let Some(coherent_sets) = &i.coherent_sets {
+      coherent_sets
+        .lock()
+        .unwrap()
+        .add_notifier(i.guid, notifier.clone());
+    }
+
+    Self {
+      notifier,
       status_sender: i.status_sender,
       udp_sender,
       like_stateless: i.like_stateless,
@@ -206,10 +383,16 @@ impl Reader {
       writer_match_count_total: 0,
       requested_deadline_missed_count: 0,
       offered_incompatible_qos_count: 0,
+      deadline_check_generation: 0,
+      liveliness_check_at: None,
+      data_available_notification_at: None,
       timed_event_timer,
       data_reader_command_receiver: i.data_reader_command_receiver,
-      data_reader_waker: i.data_reader_waker,
-      poll_event_sender: i.poll_event_sender,
+      data_reader_command_wakers: i.data_reader_command_wakers,
+      writer_strengths: i.writer_strengths,
+      matched_writer_guids: i.matched_writer_guids,
+      coherent_sets: i.coherent_sets,
+      historical_data_waiter: None,
       participant_status_sender,
 
       security_plugins: i.security_plugins,
@@ -224,6 +407,15 @@ impl Reader {
     self.guid().entity_id.as_token()
   }
 
+  // QoS was changed by the application. Only changeable policies differ.
+  pub fn set_qos(&mut self, qos: QosPolicies) {
+    self.qos_policy = qos;
+    // Restart the deadline check with the new period. The timer of the
+    // previous check cannot be cancelled, so it is ignored when it fires.
+    self.deadline_check_generation += 1;
+    self.set_requested_deadline_check_timer();
+  }
+
   pub fn set_requested_deadline_check_timer(&mut self) {
     if let Some(deadline) = self.qos_policy.deadline {
       debug!(
@@ -231,9 +423,12 @@ impl Reader {
         self.my_guid,
         deadline.0.to_std()
       );
-      self
-        .timed_event_timer
-        .set_timeout(deadline.0.to_std(), TimedEvent::DeadlineMissedCheck);
+      self.timed_event_timer.set_timeout(
+        deadline.0.to_std(),
+        TimedEvent::DeadlineMissedCheck {
+          generation: self.deadline_check_generation,
+        },
+      );
     } else {
       trace!(
         "GUID={:?} - no deadline policy - do not set set_requested_deadline_check_timer",
@@ -319,23 +514,71 @@ impl Reader {
   pub fn handle_timed_event(&mut self) {
     while let Some(e) = self.timed_event_timer.poll() {
       match e {
-        TimedEvent::DeadlineMissedCheck => {
-          self.handle_requested_deadline_event();
-          self.set_requested_deadline_check_timer(); // re-prime timer
+        TimedEvent::DeadlineMissedCheck { generation } => {
+          if generation == self.deadline_check_generation {
+            self.handle_requested_deadline_event();
+            self.set_requested_deadline_check_timer(); // re-prime timer
+          }
+        }
+        TimedEvent::LivelinessCheck { at } => {
+          if self.liveliness_check_at == Some(at) {
+            self.liveliness_check_at = None;
+            self.handle_liveliness_check(Timestamp::now());
+          }
+        }
+        TimedEvent::DataAvailableNotification { at } => {
+          self.handle_data_available_notification(at);
         }
       }
     }
   }
 
+  // Samples held back by TIME_BASED_FILTER in the DataReader become
+  // deliverable without any new data arriving, so the DataReader must be
+  // notified separately.
+  fn schedule_data_available_notification(&mut self, at: Timestamp) {
+    if self
+      .data_available_notification_at
+      .map_or(true, |scheduled| at < scheduled)
+    {
+      self.data_available_notification_at = Some(at);
+      self.timed_event_timer.set_timeout(
+        at.duration_since(Timestamp::now()).to_std(),
+        TimedEvent::DataAvailableNotification { at },
+      );
+    }
+  }
+
+  fn handle_data_available_notification(&mut self, at: Timestamp) {
+    if self.data_available_notification_at == Some(at) {
+      self.data_available_notification_at = None;
+      self.notifier.notify();
+    }
+  }
+
   pub fn process_command(&mut self) {
     trace!("process_command {:?}", self.my_guid);
     loop {
       use std::sync::mpsc::TryRecvError;
-      match self.data_reader_command_receiver.try_recv() {
+      let command = self.data_reader_command_receiver.try_recv();
+      if command.is_ok() {
+        // Signal that there is now space in the DataReader to Reader queue
+        self.data_reader_command_wakers.wake_all();
+      }
+      match command {
         Ok(ReaderCommand::ResetRequestedDeadlineStatus) => {
           warn!("RESET_REQUESTED_DEADLINE_STATUS not implemented!");
           // TODO: This should be implemented.
         }
+        Ok(ReaderCommand::WaitForHistoricalData { all_received }) => {
+          // Only one DataReader can wait at a time. Any previous waiter has
+          // given up already.
+          self.historical_data_waiter = Some(all_received);
+          self.check_historical_data_received();
+        }
+        Ok(ReaderCommand::NotifyDataAvailableAt { at }) => {
+          self.schedule_data_available_notification(at);
+        }
         // Disconnected is normal when terminating
         Err(TryRecvError::Disconnected) => {
           trace!("DataReader disconnected");
@@ -349,6 +592,27 @@ impl Reader {
     }
   }
 
+  // Signal the waiting DataReader, if the historical data of all matched
+  // writers has been received. Best-effort and stateless Readers do not know
+  // what the writers have, so they do not wait.
+  fn check_historical_data_received(&mut self) {
+    if self.historical_data_waiter.is_none() {
+      return;
+    }
+    let all_received = self.like_stateless
+      || self.reliability == policy::Reliability::BestEffort
+      || self
+        .matched_writers
+        .values()
+        .all(RtpsWriterProxy::historical_data_received);
+    if all_received {
+      if let Some(waiter) = self.historical_data_waiter.take() {
+        // The DataReader may have timed out and stopped waiting. That is normal.
+        let _ = waiter.try_send(());
+      }
+    }
+  }
+
   fn handle_requested_deadline_event(&mut self) {
     debug!("handle_requested_deadline_event");
     for missed_deadline in self.calculate_if_requested_deadline_is_missed() {
@@ -356,6 +620,96 @@ impl Reader {
     }
   }
 
+  // Matched writers, whose lease has expired by now, are no longer alive.
+  pub(crate) fn handle_liveliness_check(&mut self, now: Timestamp) {
+    let lost: Vec<GUID> = self
+      .matched_writers
+      .iter_mut()
+      .filter_map(|(guid, writer_proxy)| writer_proxy.check_liveliness(now).then_some(*guid))
+      .collect();
+    for writer in lost {
+      info!(
+        "Liveliness of writer {:?} lost. topic={:?}",
+        writer, self.topic_name
+      );
+      self.writer_strengths().remove(&writer);
+      self.send_liveliness_changed(writer, -1, 1);
+    }
+    self.set_liveliness_check_timer();
+  }
+
+  // Make sure that a LivelinessCheck is due when the next alive writer would
+  // lose its liveliness.
+  fn set_liveliness_check_timer(&mut self) {
+    let next_expiry = self
+      .matched_writers
+      .values()
+      .filter_map(RtpsWriterProxy::liveliness_expiry)
+      .min();
+    if let Some(next) = next_expiry {
+      if self.liveliness_check_at.map_or(true, |at| next < at) {
+        self.liveliness_check_at = Some(next);
+        self.timed_event_timer.set_timeout(
+          next.duration_since(Timestamp::now()).to_std(),
+          TimedEvent::LivelinessCheck { at: next },
+        );
+      }
+    }
+  }
+
+  fn writer_liveliness_asserted(&mut self, writer_guid: GUID) {
+    let Some(writer_proxy) = self.matched_writer_mut(writer_guid) else {
+      return;
+    };
+    if writer_proxy.assert_liveliness(Timestamp::now()) {
+      let strength = writer_proxy.ownership_strength;
+      self.writer_strengths().insert(writer_guid, strength);
+      self.send_liveliness_changed(writer_guid, 1, -1);
+      self.set_liveliness_check_timer();
+    } else if self.liveliness_check_at.is_none() {
+      self.set_liveliness_check_timer();
+    }
+  }
+
+  // A ParticipantMessage asserts the liveliness of the AUTOMATIC writers of
+  // the participant. MANUAL_LIVELINESS_UPDATE asserts also the
+  // MANUAL_BY_PARTICIPANT writers.
+  pub fn participant_liveliness_asserted(
+    &mut self,
+    guid_prefix: GuidPrefix,
+    kind: ParticipantMessageDataKind,
+  ) {
+    let asserted: Vec<GUID> = self
+      .matched_writers
+      .range(guid_prefix.range())
+      .filter(|(_, writer_proxy)| match writer_proxy.liveliness {
+        policy::Liveliness::Automatic { .. } => true,
+        policy::Liveliness::ManualByParticipant { .. } => {
+          kind == ParticipantMessageDataKind::MANUAL_LIVELINESS_UPDATE
+        }
+        policy::Liveliness::ManualByTopic { .. } => false,
+      })
+      .map(|(guid, _)| *guid)
+      .collect();
+    for writer in asserted {
+      self.writer_liveliness_asserted(writer);
+    }
+  }
+
+  fn send_liveliness_changed(&self, writer: GUID, alive_change: i32, not_alive_change: i32) {
+    let alive_count = self
+      .matched_writers
+      .values()
+      .filter(|writer_proxy| writer_proxy.is_alive())
+      .count() as i32;
+    let not_alive_count = self.matched_writers.len() as i32 - alive_count;
+    self.send_status_change(DataReaderStatus::LivelinessChanged {
+      alive_total: CountWithChange::new(alive_count, alive_change),
+      not_alive_total: CountWithChange::new(not_alive_count, not_alive_change),
+      writer,
+    });
+  }
+
   // TODO Used for test/debugging purposes
   #[cfg(test)]
   pub fn history_cache_change_data(&self, sequence_number: SequenceNumber) -> Option<DDSData> {
@@ -383,7 +737,7 @@ impl Reader {
   }
 
   // updates or adds a new writer proxy, doesn't touch changes
-  pub fn update_writer_proxy(&mut self, proxy: RtpsWriterProxy, offered_qos: &QosPolicies) {
+  pub fn update_writer_proxy(&mut self, mut proxy: RtpsWriterProxy, offered_qos: &QosPolicies) {
     if self.like_stateless {
       debug!(
         "Attempted to update writer proxy for stateless reader. Ignoring. topic={:?}",
@@ -398,8 +752,29 @@ impl Reader {
     match offered_qos.compliance_failure_wrt(&self.qos_policy) {
       None => {
         // success, update or insert
+        if let Some(liveliness) = offered_qos.liveliness {
+          proxy.liveliness = liveliness;
+        }
+        if let Some(policy::Ownership::Exclusive { strength }) = offered_qos.ownership() {
+          proxy.ownership_strength = strength;
+        }
         let count_change = self.matched_writer_update(proxy);
+        if let Some(writer_proxy) = self.matched_writer(writer) {
+          // A writer that has lost its liveliness gets its strength back when
+          // it asserts liveliness again.
+          if writer_proxy.is_alive() {
+            let strength = writer_proxy.ownership_strength;
+            self.writer_strengths().insert(writer, strength);
+          }
+        }
         if count_change > 0 {
+          self.matched_writer_guids().insert(writer);
+          if let Some(mut coherent_sets) = self.coherent_sets() {
+            coherent_sets.writer_matched(
+              writer,
+              matches!(self.reliability, policy::Reliability::Reliable { .. }),
+            );
+          }
           self.writer_match_count_total += count_change;
           self.send_status_change(DataReaderStatus::SubscriptionMatched {
             total: CountWithChange::new(self.writer_match_count_total, count_change),
@@ -410,6 +785,8 @@ impl Reader {
             local_reader: self.my_guid,
             remote_writer: writer,
           });
+          self.send_liveliness_changed(writer, 1, 0);
+          self.set_liveliness_check_timer();
 
           info!(
             "Matched new remote writer on topic={:?} writer={:?}",
@@ -419,6 +796,9 @@ impl Reader {
       }
       Some(bad_policy_id) => {
         // no QoS match.
+        // Writer may have been matched before a QoS change
+        self.remove_writer_proxy(writer);
+
         self.offered_incompatible_qos_count += 1;
         self.send_status_change(DataReaderStatus::RequestedIncompatibleQos {
           count: CountWithChange::new(self.offered_incompatible_qos_count, 1),
@@ -455,8 +835,15 @@ impl Reader {
   }
 
   pub fn remove_writer_proxy(&mut self, writer_guid: GUID) {
-    if self.matched_writers.contains_key(&writer_guid) {
-      self.matched_writers.remove(&writer_guid);
+    if let Some(writer_proxy) = self.matched_writers.remove(&writer_guid) {
+      self.writer_strengths().remove(&writer_guid);
+      self.matched_writer_guids().remove(&writer_guid);
+      if let Some(mut coherent_sets) = self.coherent_sets() {
+        coherent_sets.writer_unmatched(
+          writer_guid,
+          matches!(self.reliability, policy::Reliability::Reliable { .. }),
+        );
+      }
       #[cfg(feature = "security")]
       if let Some(security_plugins_handle) = &self.security_plugins {
         security_plugins_handle
@@ -469,6 +856,13 @@ impl Reader {
         current: CountWithChange::new(self.matched_writers.len() as i32, -1),
         writer: writer_guid,
       });
+      if writer_proxy.is_alive() {
+        self.send_liveliness_changed(writer_guid, -1, 0);
+      } else {
+        self.send_liveliness_changed(writer_guid, 0, -1);
+      }
+      // We no longer wait for the data of this writer.
+      self.check_historical_data_received();
     }
   }
 
@@ -515,6 +909,36 @@ impl Reader {
     self.update_writer_proxy(proxy, qos);
   }
 
+  fn writer_strengths(&self) -> MutexGuard<'_, BTreeMap<GUID, i32>> {
+    self
+      .writer_strengths
+      .lock()
+      .unwrap_or_else(|e| panic!("Writer strengths of {:?} poisoned: {e}", self.my_guid))
+  }
+
+  fn matched_writer_guids(&self) -> MutexGuard<'_, BTreeSet<GUID>> {
+    self
+      .matched_writer_guids
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn coherent_sets(&self) -> Option<MutexGuard<'_, CoherentSetTracker>> {
+    self.coherent_sets.as_ref().map(|coherent_sets| {
+      coherent_sets
+        .lock()
+        .unwrap_or_else(|e| panic!("Coherent sets of {:?} poisoned: {e}", self.my_guid))
+    })
+  }
+
+  // Coherent sets may complete when we know that all samples up to some
+  // sequence number have been received.
+  fn coherent_sets_received_before(&self, writer_guid: GUID, received_before: SequenceNumber) {
+    if let Some(mut coherent_sets) = self.coherent_sets() {
+      coherent_sets.received_before(writer_guid, received_before);
+    }
+  }
+
   fn matched_writer(&self, remote_writer_guid: GUID) -> Option<&RtpsWriterProxy> {
     self.matched_writers.get(&remote_writer_guid)
   }
@@ -552,6 +976,20 @@ impl Reader {
     {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
+    // Check if the sample belongs to a coherent set
+    if let Some(coherent_set) = data.inline_qos.as_ref().and_then(|inline_qos_parameters| {
+      InlineQos::coherent_set(
+        inline_qos_parameters,
+        representation_identifier,
+        mr_state.source_vendor_id,
+      )
+      .unwrap_or_else(|e| {
+        error!("Deserializing coherent_set: {:?}", &e);
+        None
+      })
+    }) {
+      write_options_b = write_options_b.coherent_set(coherent_set);
+    }
 
     let writer_guid = GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, data.writer_id);
     let writer_seq_num = data.writer_sn; // for borrow checker
@@ -575,26 +1013,9 @@ impl Reader {
     mr_state: &MessageReceiverState,
   ) {
     let writer_guid = GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, datafrag.writer_id);
-    let seq_num = datafrag.writer_sn;
     let receive_timestamp = Timestamp::now();
     //trace!("DATAFRAG received topic={:?}", self.topic_name);
 
-    // check if this submessage is expired already
-    // TODO: Maybe this check is in the wrong place altogether? It should be
-    // done when Datareader fetches data for the application.
-    if let (Some(source_timestamp), Some(lifespan)) =
-      (mr_state.source_timestamp, self.qos().lifespan)
-    {
-      let elapsed = receive_timestamp.duration_since(source_timestamp);
-      if lifespan.duration < elapsed {
-        info!(
-          "DataFrag {:?} from {:?} lifespan exceeded. duration={:?} elapsed={:?}",
-          seq_num, writer_guid, lifespan.duration, elapsed
-        );
-        return;
-      }
-    }
-
     // parse write_options out of the message
     // TODO: This is almost duplicate code from DATA processing
     let mut write_options_b = WriteOptionsBuilder::new();
@@ -618,6 +1039,24 @@ impl Reader {
     {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
+    // Check if the sample belongs to a coherent set
+    if let Some(coherent_set) = datafrag
+      .inline_qos
+      .as_ref()
+      .and_then(|inline_qos_parameters| {
+        InlineQos::coherent_set(
+          inline_qos_parameters,
+          representation_identifier,
+          mr_state.source_vendor_id,
+        )
+        .unwrap_or_else(|e| {
+          error!("Deserializing coherent_set: {:?}", &e);
+          None
+        })
+      })
+    {
+      write_options_b = write_options_b.coherent_set(coherent_set);
+    }
 
     // Feed to fragment assembler ...
     let writer_seq_num = datafrag.writer_sn; // for borrow checker
@@ -741,10 +1180,17 @@ impl Reader {
           return;
         }
       }
+      // Any DATA asserts the liveliness of the writer.
+      self.writer_liveliness_asserted(writer_guid);
     } else {
       // stateless reader: nothing to do before making cache change
     }
 
+    let coherent_set = write_options.coherent_set().cloned();
+    let is_coherent_set_end = dds_data == DDSData::CoherentSetEnd;
+
+    // Coherent set end markers go to the topic cache also, so that reliable
+    // DataReaders see an unbroken sequence of changes. DataReaders skip them.
     self.make_cache_change(
       dds_data,
       receive_timestamp,
@@ -753,11 +1199,42 @@ impl Reader {
       writer_sn,
     );
 
+    if let Some(coherent_set) = coherent_set {
+      self.track_coherent_set(writer_guid, writer_sn, &coherent_set, is_coherent_set_end);
+    }
+
     // Add to own track-keeping data structure
     #[cfg(test)]
     self.seqnum_instant_map.insert(writer_sn, receive_timestamp);
 
     self.notify_cache_change();
+    self.check_historical_data_received();
+  }
+
+  fn track_coherent_set(
+    &self,
+    writer_guid: GUID,
+    writer_sn: SequenceNumber,
+    coherent_set: &CoherentSet,
+    is_end: bool,
+  ) {
+    let Some(mut coherent_sets) = self.coherent_sets() else {
+      return; // Not tracking coherent sets. Samples are delivered as they come.
+    };
+    coherent_sets.expire(Timestamp::now());
+    // A Reliable Reader must wait for any missing samples of the set. A Best
+    // Effort Reader delivers whatever it got.
+    let received_before = match (self.reliability, self.matched_writer(writer_guid)) {
+      (policy::Reliability::Reliable { .. }, Some(wp)) => wp.all_ackable_before(),
+      _ => writer_sn.plus_1(),
+    };
+    if is_end {
+      coherent_sets.end_received(writer_guid, writer_sn, coherent_set, received_before);
+    } else {
+      coherent_sets.sample_received(writer_guid, coherent_set);
+      // This may have been a missing sample of an ended set.
+      coherent_sets.received_before(writer_guid, received_before);
+    }
   }
 
   fn data_to_dds_data(
@@ -798,11 +1275,21 @@ impl Reader {
           })
         }) {
           Ok(h)
-        } else {
-          info!("Received DATA that has no payload and no key_hash inline QoS - discarding");
-          // Note: This case is normal when handling coherent sets.
+        } else if data
+          .inline_qos
+          .as_ref()
+          .is_some_and(|inline_qos_parameters| {
+            inline_qos_parameters
+              .parameters
+              .iter()
+              .any(|p| p.parameter_id == ParameterId::PID_COHERENT_SET)
+          })
+        {
           // The coherent set end marker is sent as DATA with no payload and not key, only
           // Inline QoS.
+          return Ok(DDSData::CoherentSetEnd);
+        } else {
+          info!("Received DATA that has no payload and no key_hash inline QoS - discarding");
           Err("DATA with no contents".to_string())
         }?;
         // now, let's try to determine what is the dispose reason
@@ -863,11 +1350,26 @@ impl Reader {
     &mut self,
     heartbeat: &Heartbeat,
     final_flag_set: bool,
+    liveliness_flag_set: bool,
     mr_state: &MessageReceiverState,
   ) -> bool {
     let writer_guid =
       GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, heartbeat.writer_id);
 
+    // A HEARTBEAT with the liveliness flag is a manual assertion of the writer.
+    // Any traffic asserts AUTOMATIC liveliness.
+    let automatic_liveliness = self
+      .matched_writer(writer_guid)
+      .is_some_and(|writer_proxy| {
+        matches!(
+          writer_proxy.liveliness,
+          policy::Liveliness::Automatic { .. }
+        )
+      });
+    if liveliness_flag_set || automatic_liveliness {
+      self.writer_liveliness_asserted(writer_guid);
+    }
+
     if self.reliability == policy::Reliability::BestEffort || self.like_stateless {
       debug!(
         "HEARTBEAT from {:?}, but this Reader is BestEffort or stateless. Ignoring. topic={:?} \
@@ -897,7 +1399,7 @@ impl Reader {
       );
     }
 
-    self
+    let acknack_sent = self
       .with_mutable_writer_proxy(writer_guid, |this, writer_proxy| {
         // Note: This is worker closure. Use `this` instead of `self`.
 
@@ -913,6 +1415,7 @@ impl Reader {
           return false;
         }
         writer_proxy.received_heartbeat_count = heartbeat.count;
+        writer_proxy.set_historical_data_last(heartbeat.last_sn);
 
         // remove changes until first_sn.
         writer_proxy.irrelevant_changes_up_to(heartbeat.first_sn);
@@ -921,6 +1424,7 @@ impl Reader {
           .acquire_the_topic_cache_guard()
           .mark_reliably_received_before(writer_guid, writer_proxy.all_ackable_before());
         if marker_moved {
+          this.coherent_sets_received_before(writer_guid, writer_proxy.all_ackable_before());
           this.notify_cache_change();
         }
 
@@ -1055,7 +1559,11 @@ impl Reader {
 
         false
       }) // worker fn
-      .unwrap_or(false) // default false: no writer_proxy -> no acknack
+      .unwrap_or(false); // default false: no writer_proxy -> no acknack
+
+    // The heartbeat may tell that we have all the historical data already.
+    self.check_historical_data_received();
+    acknack_sent
   } // fn
 
   pub fn handle_gap_msg(&mut self, gap: &Gap, mr_state: &MessageReceiverState) {
@@ -1128,7 +1636,9 @@ impl Reader {
     // E.g. we had #2, but were missing #1. Now GAP says that #1 does not exist.
     // Then a Reliable Datareader
     if marker_moved {
+      self.coherent_sets_received_before(writer_guid, all_ackable_before);
       self.notify_cache_change();
+      self.check_historical_data_received();
     }
     // able to move forward, i.e. hand over data to application, if
     // we now know that nothing is missng from the past.
@@ -1209,30 +1719,7 @@ impl Reader {
   // notifies DataReaders (or any listeners that history cache has changed for
   // this reader) likely use of mio channel
   pub fn notify_cache_change(&mut self) {
-    // async notify mechanism
-    self
-      .data_reader_waker
-      .lock()
-      .unwrap() // TODO: unwrap
-      .take() // Take to nullify the reference
-      .map(|w| w.wake_by_ref()); // If Some, call wake_by_ref
-
-    // mio-0.8 notify
-    self.poll_event_sender.send();
-
-    // mio-0.6 notify
-    match self.notification_sender.try_send(()) {
-      Ok(()) => (),
-      Err(mio_channel::TrySendError::Full(_)) => (),
-      // This is harmless. There is a notification in already.
-      Err(mio_channel::TrySendError::Disconnected(_)) => {
-        // If we get here, our DataReader has died. The Reader should now
-        // dispose itself. TODO: Implement Reader disposal.
-      }
-      Err(mio_channel::TrySendError::Io(_)) => {
-        // TODO: What does this mean? Can we ever get here?
-      }
-    }
+    self.notifier.notify();
   }
 
   #[cfg(not(feature = "security"))]
@@ -1437,6 +1924,14 @@ impl RTPSEntity for Reader {
   }
 }
 
+impl Drop for Reader {
+  fn drop(&mut self) {
+    if let Some(mut coherent_sets) = self.coherent_sets() {
+      coherent_sets.remove_notifier(self.my_guid);
+    }
+  }
+}
+
 impl fmt::Debug for Reader {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct("Reader")
@@ -1454,12 +1949,78 @@ mod tests {
   use std::sync::RwLock;
 
   use crate::{
-    dds::{qos::policy::Reliability, statusevents::sync_status_channel, typedesc::TypeDesc},
+    dds::{
+      qos::policy::Reliability,
+      statusevents::{sync_status_channel, StatusChannelReceiver},
+      typedesc::TypeDesc,
+    },
     structure::{dds_cache::DDSCache, guid::EntityKind},
     QosPolicyBuilder,
   };
   use super::*;
 
+  // The ends of a test Reader's channels, which a DataReader would hold.
+  struct ReaderHandles {
+    command_sender: mio_channel::SyncSender<ReaderCommand>,
+    status_receiver: StatusChannelReceiver<DataReaderStatus>,
+    data_availability: Arc<DataAvailability>,
+    subscriber_status_sender: SubscriberStatusSender,
+    _notification_receiver: mio_channel::Receiver<()>,
+    _notification_event_source: mio_source::PollEventSource,
+    _participant_status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
+  }
+
+  fn test_reader(qos_policy: QosPolicies) -> (Reader, ReaderHandles) {
+    let topic_name = "test_name";
+    let topic_cache_handle = DDSCache::new().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+    let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let (status_sender, status_receiver) = sync_status_channel::<DataReaderStatus>(16).unwrap();
+    let (participant_status_sender, participant_status_receiver) = sync_status_channel(16).unwrap();
+    let (command_sender, reader_command_receiver) = mio_channel::sync_channel::<ReaderCommand>(10);
+    let handles = ReaderHandles {
+      command_sender,
+      status_receiver,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
+      _notification_receiver: notification_receiver,
+      _notification_event_source: notification_event_source,
+      _participant_status_receiver: participant_status_receiver,
+    };
+
+    let reader_ing = ReaderIngredients {
+      guid: GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      notification_sender,
+      status_sender,
+      topic_name: topic_name.to_string(),
+      topic_cache_handle,
+      like_stateless: false,
+      qos_policy,
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::clone(&handles.data_availability),
+      subscriber_status_sender: Arc::clone(&handles.subscriber_status_sender),
+      security_plugins: None,
+    };
+    let reader = Reader::new(
+      reader_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    (reader, handles)
+  }
+
   #[test]
   fn reader_sends_notification_when_receiving_data() {
     // 1. Create a reader
@@ -1503,8 +2064,14 @@ mod tests {
       like_stateless: false,
       qos_policy,
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
     let mut reader = Reader::new(
@@ -1550,6 +2117,92 @@ mod tests {
     // checked?
   }
 
+  #[test]
+  fn reader_sends_data_available_statuses_once_until_read() {
+    // 1. Create a reader with DataAvailable events enabled and a Subscriber
+    // status listener
+    let (mut reader, handles) = test_reader(QosPolicies::qos_none());
+    let reader_guid = reader.guid();
+    let status_receiver = &handles.status_receiver;
+    let reader_command_sender = &handles.command_sender;
+    let data_availability = &handles.data_availability;
+    data_availability.set_status_enabled(true);
+    let (subscriber_status_sender, subscriber_status_receiver) =
+      sync_status_channel::<SubscriberStatus>(16).unwrap();
+    *handles.subscriber_status_sender.lock().unwrap() = Some(subscriber_status_sender);
+
+    let data_available_count = || {
+      iter::from_fn(|| status_receiver.try_recv().ok())
+        .filter(|status| matches!(status, DataReaderStatus::DataAvailable))
+        .count()
+    };
+    let data_on_readers = || {
+      iter::from_fn(|| subscriber_status_receiver.try_recv().ok())
+        .map(|SubscriberStatus::DataOnReaders { reader }| reader)
+        .collect::<Vec<_>>()
+    };
+
+    // 2. Match a writer
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &QosPolicies::qos_none(),
+    );
+
+    let data = |sn: i64| Data {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(sn),
+      ..Data::default()
+    };
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+
+    // 3. Two samples arrive, but the events are sent only once
+    reader.handle_data_msg(data(1), data_flags, &mr_state);
+    reader.handle_data_msg(data(2), data_flags, &mr_state);
+    assert_eq!(data_available_count(), 1);
+    assert_eq!(data_on_readers(), vec![reader_guid]);
+
+    // 4. After the DataReader has read, new data causes new events
+    data_availability.data_read();
+    reader.handle_data_msg(data(3), data_flags, &mr_state);
+    assert_eq!(data_available_count(), 1);
+    assert_eq!(data_on_readers(), vec![reader_guid]);
+
+    // 5. Disabled DataAvailable events are not sent
+    data_availability.set_status_enabled(false);
+    data_availability.data_read();
+    reader.handle_data_msg(data(4), data_flags, &mr_state);
+    assert_eq!(data_available_count(), 0);
+    assert_eq!(data_on_readers(), vec![reader_guid]);
+
+    // 6. A sample held back by TIME_BASED_FILTER becomes deliverable. Only the
+    // earliest scheduled notification is sent.
+    data_availability.set_status_enabled(true);
+    data_availability.data_read();
+    let now = Timestamp::now();
+    let later = now + Duration::from_secs(1);
+    reader_command_sender
+      .send(ReaderCommand::NotifyDataAvailableAt { at: later })
+      .unwrap();
+    reader_command_sender
+      .send(ReaderCommand::NotifyDataAvailableAt { at: now })
+      .unwrap();
+    reader.process_command();
+    reader.handle_data_available_notification(later);
+    assert_eq!(data_available_count(), 0);
+    reader.handle_data_available_notification(now);
+    assert_eq!(data_available_count(), 1);
+    assert_eq!(data_on_readers(), vec![reader_guid]);
+  }
+
   #[test]
   fn reader_sends_data_to_topic_cache() {
     // 1. Create a reader
@@ -1588,8 +2241,14 @@ mod tests {
       like_stateless: false,
       qos_policy,
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
     let mut reader = Reader::new(
@@ -1694,8 +2353,14 @@ mod tests {
       like_stateless: false,
       qos_policy: reliable_qos.clone(),
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
     let mut reader = Reader::new(
@@ -1730,7 +2395,7 @@ mod tests {
       last_sn: SequenceNumber::new(0),
       count: 1,
     };
-    assert!(!reader.handle_heartbeat_msg(&hb_new, true, &mr_state)); // should be false, no ack
+    assert!(!reader.handle_heartbeat_msg(&hb_new, true, false, &mr_state)); // should be false, no ack
 
     // 4. Send the first proper heartbeat, reader should respond with acknack
     let hb_one = Heartbeat {
@@ -1740,12 +2405,12 @@ mod tests {
       last_sn: SequenceNumber::new(1),
       count: 2,
     };
-    assert!(reader.handle_heartbeat_msg(&hb_one, false, &mr_state)); // Should send an ack_nack
+    assert!(reader.handle_heartbeat_msg(&hb_one, false, false, &mr_state)); // Should send an ack_nack
 
     // 5. Send a duplicate of the first heartbeat, reader should not respond with
     // acknack
     let hb_one2 = hb_one.clone();
-    assert!(!reader.handle_heartbeat_msg(&hb_one2, false, &mr_state)); // No acknack
+    assert!(!reader.handle_heartbeat_msg(&hb_one2, false, false, &mr_state)); // No acknack
 
     // 6. Send a second proper heartbeat, reader should respond with acknack
     let hb_2 = Heartbeat {
@@ -1755,7 +2420,7 @@ mod tests {
       last_sn: SequenceNumber::new(3),  // writer has written 3 samples
       count: 3,
     };
-    assert!(reader.handle_heartbeat_msg(&hb_2, false, &mr_state)); // Should send an ack_nack
+    assert!(reader.handle_heartbeat_msg(&hb_2, false, false, &mr_state)); // Should send an ack_nack
 
     // 7. Count of acknack sent should be 2
     // The count is verified from the writer proxy
@@ -1765,6 +2430,83 @@ mod tests {
     assert_eq!(writer_proxy.sent_ack_nack_count, 2);
   }
 
+  #[test]
+  fn reader_signals_when_historical_data_received() {
+    let durable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .durability(policy::Durability::TransientLocal)
+      .build();
+    let (mut reader, handles) = test_reader(durable_qos.clone());
+    let reader_command_sender = &handles.command_sender;
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &durable_qos,
+    );
+
+    let (all_received_sender, all_received_receiver) = sync_status_channel::<()>(1).unwrap();
+    reader_command_sender
+      .send(ReaderCommand::WaitForHistoricalData {
+        all_received: all_received_sender,
+      })
+      .unwrap();
+    reader.process_command();
+    // No heartbeat yet, so we do not know what the writer has.
+    assert!(all_received_receiver.try_recv().is_err());
+
+    // The writer has samples 1..3, which are its historical data.
+    let heartbeat = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(3),
+      count: 1,
+    };
+    reader.handle_heartbeat_msg(&heartbeat, false, false, &mr_state);
+    assert!(all_received_receiver.try_recv().is_err());
+
+    // Samples 1 and 2 are no longer available.
+    let gap = Gap {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      gap_start: SequenceNumber::new(1),
+      gap_list: SequenceNumberSet::new_empty(SequenceNumber::new(3)),
+    };
+    reader.handle_gap_msg(&gap, &mr_state);
+    assert!(all_received_receiver.try_recv().is_err());
+
+    // Later heartbeats announce new data, which is not historical.
+    let heartbeat = Heartbeat {
+      last_sn: SequenceNumber::new(5),
+      count: 2,
+      ..heartbeat
+    };
+    reader.handle_heartbeat_msg(&heartbeat, false, false, &mr_state);
+    assert!(all_received_receiver.try_recv().is_err());
+
+    let data = Data {
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(3),
+      ..Default::default()
+    };
+    reader.handle_data_msg(
+      data,
+      BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data),
+      &mr_state,
+    );
+    assert!(all_received_receiver.try_recv().is_ok());
+  }
+
   #[test]
   fn reader_handles_gaps() {
     // 1. Create a reader
@@ -1803,8 +2545,14 @@ mod tests {
       like_stateless: false,
       qos_policy,
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
     let mut reader = Reader::new(
@@ -1941,8 +2689,14 @@ mod tests {
       like_stateless,
       qos_policy,
       data_reader_command_receiver: reader_command_receiver,
+      data_reader_command_wakers: Arc::default(),
       data_reader_waker,
       poll_event_sender: notification_event_sender,
+      writer_strengths: Arc::default(),
+      matched_writer_guids: Arc::default(),
+      coherent_sets: None,
+      data_availability: Arc::default(),
+      subscriber_status_sender: Arc::default(),
       security_plugins: None,
     };
     let mut reader = Reader::new(
@@ -1972,4 +2726,101 @@ mod tests {
     // we attempted to add
     assert!(reader.matched_writer(writer_guid).is_none());
   }
+
+  #[test]
+  fn reader_tracks_writer_liveliness() {
+    // 1. Create a reliable reader
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .build();
+    let (mut reader, handles) = test_reader(reliable_qos);
+    let status_receiver = &handles.status_receiver;
+
+    // The latest LivelinessChanged status as (alive, not_alive)
+    let liveliness_changed = || {
+      let mut latest = None;
+      while let Ok(status) = status_receiver.try_recv() {
+        if let DataReaderStatus::LivelinessChanged {
+          alive_total,
+          not_alive_total,
+          ..
+        } = status
+        {
+          latest = Some((alive_total, not_alive_total));
+        }
+      }
+      latest
+    };
+
+    // 2. Match a writer with MANUAL_BY_PARTICIPANT liveliness
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    let offered_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .liveliness(policy::Liveliness::ManualByParticipant {
+        lease_duration: Duration::from_millis(10),
+      })
+      .build();
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &offered_qos);
+    assert_eq!(
+      liveliness_changed(),
+      Some((CountWithChange::new(1, 1), CountWithChange::new(0, 0)))
+    );
+
+    // 3. Lease expires
+    reader.handle_liveliness_check(Timestamp::now() + Duration::from_millis(20));
+    assert_eq!(
+      liveliness_changed(),
+      Some((CountWithChange::new(0, -1), CountWithChange::new(1, 1)))
+    );
+
+    // 4. Automatic participant liveliness does not assert the writer, but
+    // manual does.
+    reader.participant_liveliness_asserted(
+      writer_guid.prefix,
+      ParticipantMessageDataKind::AUTOMATIC_LIVELINESS_UPDATE,
+    );
+    assert_eq!(liveliness_changed(), None);
+    reader.participant_liveliness_asserted(
+      writer_guid.prefix,
+      ParticipantMessageDataKind::MANUAL_LIVELINESS_UPDATE,
+    );
+    assert_eq!(
+      liveliness_changed(),
+      Some((CountWithChange::new(1, 1), CountWithChange::new(0, -1)))
+    );
+
+    // 5. A heartbeat with the liveliness flag asserts the writer
+    reader.handle_liveliness_check(Timestamp::now() + Duration::from_millis(20));
+    assert_eq!(
+      liveliness_changed(),
+      Some((CountWithChange::new(0, -1), CountWithChange::new(1, 1)))
+    );
+    let heartbeat = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(0),
+      count: 1,
+    };
+    reader.handle_heartbeat_msg(&heartbeat, false, true, &mr_state);
+    assert_eq!(
+      liveliness_changed(),
+      Some((CountWithChange::new(1, 1), CountWithChange::new(0, -1)))
+    );
+
+    // 6. Removing the writer
+    reader.remove_writer_proxy(writer_guid);
+    assert_eq!(
+      liveliness_changed(),
+      Some((CountWithChange::new(0, -1), CountWithChange::new(0, 0)))
+    );
+  }
 }
@@ -5,8 +5,14 @@ use bit_vec::BitVec;
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  dds::{participant::DomainParticipant, qos::QosPolicies},
-  discovery::sedp_messages::DiscoveredReaderData,
+  dds::{
+    ddssql::{Filter, FilterableSample, DDSSQL_FILTER_CLASS_NAME},
+    participant::DomainParticipant,
+    qos::QosPolicies,
+  },
+  discovery::{
+    content_filter_property::ContentFilterProperty, sedp_messages::DiscoveredReaderData,
+  },
   messages::submessages::submessage::AckSubmessage,
   rtps::constant::*,
   structure::{
@@ -58,6 +64,9 @@ pub(crate) struct RtpsReaderProxy {
   pub repair_mode: bool,
   qos: QosPolicies,
   frags_requested: BTreeMap<SequenceNumber, BitVec>,
+  // Content filter of the remote Reader, if it has one we know how to evaluate.
+  // Samples not passing the filter are sent as GAP.
+  content_filter: Option<Filter>,
 }
 
 impl RtpsReaderProxy {
@@ -75,6 +84,7 @@ impl RtpsReaderProxy {
       repair_mode: false,
       qos,
       frags_requested: BTreeMap::new(),
+      content_filter: None,
     }
   }
 
@@ -110,6 +120,11 @@ impl RtpsReaderProxy {
       warn!("Upddate changes QoS in ReaderProxy.");
       self.qos = update.qos.clone();
     }
+
+    if self.content_filter != update.content_filter {
+      info!("Update changes content filter in ReaderProxy.");
+      self.content_filter.clone_from(&update.content_filter);
+    }
   }
 
   pub fn qos(&self) -> &QosPolicies {
@@ -161,6 +176,7 @@ impl RtpsReaderProxy {
       repair_mode: false,
       qos: reader.qos_policy.clone(),
       frags_requested: BTreeMap::new(),
+      content_filter: None,
     }
   }
 
@@ -212,6 +228,46 @@ impl RtpsReaderProxy {
       repair_mode: false,
       qos: discovered_reader_data.subscription_topic_data.qos(),
       frags_requested: BTreeMap::new(),
+      content_filter: discovered_reader_data
+        .content_filter
+        .as_ref()
+        .and_then(Self::parse_content_filter),
+    }
+  }
+
+  // We can filter only DDSSQL. If the Reader uses some other filter class, or
+  // we cannot make sense of the expression, we do not filter at all, and leave
+  // the filtering to the Reader.
+  fn parse_content_filter(content_filter: &ContentFilterProperty) -> Option<Filter> {
+    if content_filter.filter_class_name != DDSSQL_FILTER_CLASS_NAME {
+      info!(
+        "Unknown content filter class {:?}. Not filtering at writer.",
+        content_filter.filter_class_name
+      );
+      return None;
+    }
+    Filter::new(
+      &content_filter.filter_expression,
+      content_filter.expression_parameters.clone(),
+    )
+    .map_err(|e| {
+      warn!(
+        "Cannot use content filter {:?} of remote reader: {}",
+        content_filter.filter_expression, e
+      );
+    })
+    .ok()
+  }
+
+  pub fn has_content_filter(&self) -> bool {
+    self.content_filter.is_some()
+  }
+
+  // Should the sample be sent to this Reader?
+  pub fn content_filter_accepts(&self, sample: &FilterableSample) -> bool {
+    match &self.content_filter {
+      Some(filter) => filter.matches_filterable(sample),
+      None => true,
     }
   }
 
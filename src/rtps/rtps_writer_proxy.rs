@@ -5,8 +5,10 @@ use std::{cmp::max, collections::BTreeMap};
 use log::{debug, error, info, trace, warn};
 
 use crate::{
+  dds::qos::policy,
   discovery::sedp_messages::DiscoveredWriterData,
   structure::{
+    duration::Duration,
     guid::{EntityId, GUID},
     locator::Locator,
     sequence_number::SequenceNumber,
@@ -74,9 +76,28 @@ pub(crate) struct RtpsWriterProxy {
   // These are used for quick tracking of
   last_received_sequence_number: SequenceNumber,
   last_received_timestamp: Timestamp,
+
+  // The historical data of the writer are the changes before this.
+  // Set by the first heartbeat received after matching.
+  historical_data_before: Option<SequenceNumber>,
+
+  // LIVELINESS offered by the remote Writer, and our view of it. A Writer is
+  // considered alive when matched.
+  pub liveliness: policy::Liveliness,
+  last_liveliness_assertion: Timestamp,
+  alive: bool,
+
+  // OWNERSHIP strength offered by the remote Writer. Zero with SHARED
+  // ownership.
+  pub ownership_strength: i32,
 }
 
 impl RtpsWriterProxy {
+  // DDS default, used if the Writer does not tell otherwise
+  const DEFAULT_LIVELINESS: policy::Liveliness = policy::Liveliness::Automatic {
+    lease_duration: Duration::INFINITE,
+  };
+
   pub fn new(
     remote_writer_guid: GUID,
     unicast_locator_list: Vec<Locator>,
@@ -96,6 +117,11 @@ impl RtpsWriterProxy {
       ack_base: SequenceNumber::new(1),
       last_received_sequence_number: SequenceNumber::new(0),
       last_received_timestamp: Timestamp::INVALID,
+      historical_data_before: None,
+      liveliness: Self::DEFAULT_LIVELINESS,
+      last_liveliness_assertion: Timestamp::now(),
+      alive: true,
+      ownership_strength: 0,
     }
   }
 
@@ -115,6 +141,40 @@ impl RtpsWriterProxy {
     self.unicast_locator_list = other.unicast_locator_list;
     self.multicast_locator_list = other.multicast_locator_list;
     self.remote_group_entity_id = other.remote_group_entity_id;
+    self.liveliness = other.liveliness;
+    self.ownership_strength = other.ownership_strength;
+  }
+
+  pub fn is_alive(&self) -> bool {
+    self.alive
+  }
+
+  // Returns true, if the Writer was not alive before this assertion.
+  pub fn assert_liveliness(&mut self, now: Timestamp) -> bool {
+    self.last_liveliness_assertion = now;
+    let regained = !self.alive;
+    self.alive = true;
+    regained
+  }
+
+  // When the liveliness will be lost, unless asserted before that.
+  // None, if the Writer is not alive or the lease is infinite.
+  pub fn liveliness_expiry(&self) -> Option<Timestamp> {
+    let lease_duration = self.liveliness.duration();
+    if self.alive && lease_duration != Duration::INFINITE {
+      Some(self.last_liveliness_assertion + lease_duration)
+    } else {
+      None
+    }
+  }
+
+  // Returns true, if the liveliness was lost just now.
+  pub fn check_liveliness(&mut self, now: Timestamp) -> bool {
+    let expired = self.liveliness_expiry().is_some_and(|expiry| expiry <= now);
+    if expired {
+      self.alive = false;
+    }
+    expired
   }
 
   // This is used to check for DEADLINE policy
@@ -126,6 +186,20 @@ impl RtpsWriterProxy {
     }
   }
 
+  // The first heartbeat tells how much historical data the writer has.
+  pub fn set_historical_data_last(&mut self, last_sn: SequenceNumber) {
+    if self.historical_data_before.is_none() {
+      self.historical_data_before = Some(last_sn.plus_1());
+    }
+  }
+
+  // Have we received (or know to be unavailable) all the historical data?
+  pub fn historical_data_received(&self) -> bool {
+    self
+      .historical_data_before
+      .is_some_and(|before| before <= self.ack_base)
+  }
+
   // Check if we no samples in the received state.
   pub fn no_changes_received(&self) -> bool {
     self.ack_base == SequenceNumber::new(0) && self.changes.is_empty()
@@ -328,6 +402,11 @@ impl RtpsWriterProxy {
       ack_base: SequenceNumber::default(),
       last_received_sequence_number: SequenceNumber::new(0),
       last_received_timestamp: Timestamp::INVALID,
+      historical_data_before: None,
+      liveliness: Self::DEFAULT_LIVELINESS,
+      last_liveliness_assertion: Timestamp::now(),
+      alive: true,
+      ownership_strength: 0,
     }
   } // fn
 
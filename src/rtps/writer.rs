@@ -1,11 +1,13 @@
 use std::{
-  cmp::max,
+  cmp::{max, min},
   collections::{BTreeMap, BTreeSet, HashSet},
   ops::Bound::Included,
   rc::Rc,
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, MutexGuard,
+  },
 };
-use core::task::Waker;
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
@@ -19,25 +21,31 @@ use mio_06::Token;
 use crate::{
   dds::{
     ddsdata::DDSData,
+    ddssql::FilterableSample,
+    key::KeyHash,
     qos::{
       policy,
       policy::{History, Reliability},
       HasQoSPolicy, QosPolicies,
     },
     statusevents::{
-      CountWithChange, DataWriterStatus, DomainParticipantStatusEvent, StatusChannelSender,
+      CountWithChange, DataWriterStatus, DataWriterStatusRecord, DomainParticipantStatusEvent,
+      StatusChannelSender,
     },
     with_key::datawriter::WriteOptions,
   },
   messages::submessages::submessages::AckSubmessage,
-  network::udp_sender::UDPSender,
+  network::udp_sender::{UDPSender, MAX_UNFRAGMENTED_MESSAGE_SIZE},
   rtps::{
+    command_channel_wakers::CommandChannelWakers,
     constant::{NACK_RESPONSE_DELAY, NACK_SUPPRESSION_DURATION},
+    history_reservations::HistoryReservations,
+    message::RTPS_MESSAGE_HEADER_SIZE,
     rtps_reader_proxy::RtpsReaderProxy,
     Message, MessageBuilder,
   },
   structure::{
-    cache_change::CacheChange,
+    cache_change::{CacheChange, ChangeKind},
     dds_cache::TopicCache,
     duration::Duration,
     entity::RTPSEntity,
@@ -67,6 +75,8 @@ pub(crate) enum TimedEvent {
   CacheCleaning,
   SendRepairData { to_reader: GUID },
   SendRepairFrags { to_reader: GUID },
+  DeadlineMissedCheck { generation: u64 },
+  LivelinessLostCheck,
 }
 
 // This is used to construct an actual Writer.
@@ -74,13 +84,20 @@ pub(crate) enum TimedEvent {
 pub(crate) struct WriterIngredients {
   pub guid: GUID,
   pub writer_command_receiver: mio_channel::Receiver<WriterCommand>,
-  pub writer_command_receiver_waker: Arc<Mutex<Option<Waker>>>,
+  pub writer_command_receiver_wakers: Arc<CommandChannelWakers>,
   pub topic_name: String,
   pub(crate) topic_cache_handle: Arc<Mutex<TopicCache>>, /* A handle to the topic cache in DDS
                                                           * cache */
   pub(crate) like_stateless: bool, // Usually false (see like_stateless attribute of Writer)
   pub qos_policies: QosPolicies,
   pub status_sender: StatusChannelSender<DataWriterStatus>,
+  // Shared with DataWriter. Tells if any matched reader has a content filter,
+  // so that DataWriter knows to provide samples in filterable form.
+  pub(crate) has_filtering_readers: Arc<AtomicBool>,
+  // Shared with DataWriter, which reads statuses from here.
+  pub(crate) status_record: Arc<Mutex<DataWriterStatusRecord>>,
+  // Shared with DataWriter, which reserves room in history for its changes.
+  pub(crate) history_reservations: Arc<HistoryReservations>,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -180,12 +197,23 @@ pub(crate) struct Writer {
 
   my_guid: GUID,
   pub(crate) writer_command_receiver: mio_channel::Receiver<WriterCommand>,
-  writer_command_receiver_waker: Arc<Mutex<Option<Waker>>>,
+  writer_command_receiver_wakers: Arc<CommandChannelWakers>,
   /// The RTPS ReaderProxy class represents the information an RTPS
   /// StatefulWriter maintains on each matched RTPS Reader
   readers: BTreeMap<GUID, RtpsReaderProxy>,
   matched_readers_count_total: i32, // all matches, never decremented
   requested_incompatible_qos_count: i32, // how many times a Reader requested incompatible QoS
+  // Latest write time of each alive instance. Only with DEADLINE QoS.
+  instance_write_times: BTreeMap<KeyHash, Timestamp>,
+  offered_deadline_missed_count: i32,
+  // Incremented whenever the deadline check is restarted. Timer events of
+  // earlier generations are outdated, and ignored.
+  deadline_check_generation: u64,
+  // Latest liveliness assertion by the application. Only used with MANUAL
+  // liveliness, because AUTOMATIC liveliness is asserted by the participant.
+  last_liveliness_assertion: Timestamp,
+  liveliness_lost: bool,
+  liveliness_lost_count: i32,
   // message: Option<Message>,
   udp_sender: Rc<UDPSender>,
 
@@ -210,6 +238,10 @@ pub(crate) struct Writer {
   /// Useful when negative acknack is received.
   sequence_number_to_instant: BTreeMap<SequenceNumber, Timestamp>,
 
+  /// When the LIFESPAN of each change in our history started: its source
+  /// timestamp, or the time it was added to the history cache if it has none.
+  lifespan_starts: BTreeMap<SequenceNumber, Timestamp>,
+
   /// Maps this writers local sequence numbers to DDSHistoryCache instants.
   /// Useful when datawriter dispose is received.
   // key_to_instant: HashMap<u128, Timestamp>,  // unused?
@@ -234,9 +266,23 @@ pub(crate) struct Writer {
 
   // Used for sending status info about messages sent
   status_sender: StatusChannelSender<DataWriterStatus>,
-  // offered_deadline_status: OfferedDeadlineMissedStatus,
-  ack_waiter: Option<AckWaiter>,
+  status_record: Arc<Mutex<DataWriterStatusRecord>>,
+  // Each wait_for_acknowledgments call, of the DataWriter or its Publisher,
+  // waits separately.
+  ack_waiters: Vec<AckWaiter>,
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+  has_filtering_readers: Arc<AtomicBool>,
+  history_reservations: Arc<HistoryReservations>,
+
+  /// Present while publications are suspended by the Publisher. Lists the
+  /// changes written since, with the readers that filtered each change out.
+  /// They are sent in batches when publications are resumed.
+  suspended_changes: Option<Vec<(Timestamp, BTreeSet<GUID>)>>,
+
+  /// Changes in our history in filterable form, so that content filters of
+  /// late joining readers can be applied also to historical data. Only kept
+  /// if Durability is not Volatile.
+  filterable_samples: BTreeMap<SequenceNumber, FilterableSample>,
 
   security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -247,10 +293,21 @@ pub enum WriterCommand {
     ddsdata: DDSData,
     write_options: WriteOptions,
     sequence_number: SequenceNumber,
+    // Present, if some matched reader has a content filter and the
+    // serializer adapter can provide this.
+    filterable_sample: Option<FilterableSample>,
+    // Key hash of the written instance. Present only with DEADLINE QoS,
+    // which is tracked per instance.
+    instance: Option<KeyHash>,
   },
   WaitForAcknowledgments {
     all_acked: StatusChannelSender<()>,
   },
+  // Publisher::suspend_publications: keep accepting samples, but do not send
+  // them until resumed.
+  SuspendPublications,
+  // Publisher::resume_publications: send everything written while suspended.
+  ResumePublications,
   // ResetOfferedDeadlineMissedStatus { writer_guid: GUID },
 }
 
@@ -310,6 +367,17 @@ impl Writer {
       std::time::Duration::from(cache_cleaning_period),
       TimedEvent::CacheCleaning,
     );
+    // Start periodic offered deadline check
+    if let Some(policy::Deadline(deadline)) = i.qos_policies.deadline() {
+      timed_event_timer.set_timeout(
+        deadline.to_std(),
+        TimedEvent::DeadlineMissedCheck { generation: 0 },
+      );
+    }
+    // Start monitoring manual liveliness
+    if let Some(lease_duration) = Self::manual_liveliness_lease(&i.qos_policies) {
+      timed_event_timer.set_timeout(lease_duration.to_std(), TimedEvent::LivelinessLostCheck);
+    }
 
     Self {
       endianness: Endianness::LittleEndian,
@@ -328,21 +396,33 @@ impl Writer {
       // We should get the minimum over all outgoing interfaces.
       my_guid: i.guid,
       writer_command_receiver: i.writer_command_receiver,
-      writer_command_receiver_waker: i.writer_command_receiver_waker,
+      writer_command_receiver_wakers: i.writer_command_receiver_wakers,
       readers: BTreeMap::new(),
       matched_readers_count_total: 0,
       requested_incompatible_qos_count: 0,
+      instance_write_times: BTreeMap::new(),
+      offered_deadline_missed_count: 0,
+      deadline_check_generation: 0,
+      last_liveliness_assertion: Timestamp::now(),
+      liveliness_lost: false,
+      liveliness_lost_count: 0,
       udp_sender,
       topic_cache: i.topic_cache_handle,
       my_topic_name: i.topic_name,
       sequence_number_to_instant: BTreeMap::new(),
+      lifespan_starts: BTreeMap::new(),
       disposed_sequence_numbers: HashSet::new(),
       timed_event_timer,
       like_stateless: i.like_stateless,
       qos_policies: i.qos_policies,
       status_sender: i.status_sender,
+      status_record: i.status_record,
       participant_status_sender,
-      ack_waiter: None,
+      ack_waiters: Vec::new(),
+      has_filtering_readers: i.has_filtering_readers,
+      history_reservations: i.history_reservations,
+      suspended_changes: None,
+      filterable_samples: BTreeMap::new(),
 
       security_plugins: i.security_plugins,
     }
@@ -358,6 +438,26 @@ impl Writer {
     self.qos_policies.is_reliable()
   }
 
+  // QoS was changed by the application. Only changeable policies differ.
+  pub fn set_qos(&mut self, qos: QosPolicies) {
+    self.qos_policies = qos;
+    // Restart the deadline check with the new period. The timer of the
+    // previous check cannot be cancelled, so it is ignored when it fires.
+    self.deadline_check_generation += 1;
+    self.set_deadline_check_timer();
+  }
+
+  fn set_deadline_check_timer(&mut self) {
+    if let Some(policy::Deadline(deadline)) = self.qos_policies.deadline() {
+      self.timed_event_timer.set_timeout(
+        deadline.to_std(),
+        TimedEvent::DeadlineMissedCheck {
+          generation: self.deadline_check_generation,
+        },
+      );
+    }
+  }
+
   pub fn local_readers(&self) -> Vec<EntityId> {
     let min = GUID::new_with_prefix_and_id(self.my_guid.prefix, EntityId::MIN);
     let max = GUID::new_with_prefix_and_id(self.my_guid.prefix, EntityId::MAX);
@@ -435,26 +535,126 @@ impl Writer {
             } // if
           } // if let
         } // SendRepairFrags
+        TimedEvent::DeadlineMissedCheck { generation } => {
+          if generation == self.deadline_check_generation {
+            self.handle_deadline_missed_check(Timestamp::now());
+            self.set_deadline_check_timer(); // re-prime timer
+          }
+        }
+        TimedEvent::LivelinessLostCheck => {
+          self.handle_liveliness_lost_check(Timestamp::now());
+        }
       } // match
     } // while
   } // fn
 
+  // The DEADLINE offered by this Writer was missed for each instance that has
+  // not been written during the deadline period. Each period without an
+  // update counts as a new miss. Disposed and unregistered instances are not
+  // tracked.
+  fn handle_deadline_missed_check(&mut self, now: Timestamp) {
+    let Some(policy::Deadline(deadline)) = self.qos_policies.deadline() else {
+      return;
+    };
+    let missed: Vec<KeyHash> = self
+      .instance_write_times
+      .iter_mut()
+      .filter(|(_, last_write)| now.duration_since(**last_write) > deadline)
+      .map(|(instance, last_write)| {
+        *last_write = now; // start the next deadline period
+        *instance
+      })
+      .collect();
+    for instance in missed {
+      debug!(
+        "Offered deadline missed: topic={:?} instance={:?}",
+        self.my_topic_name, instance
+      );
+      self.offered_deadline_missed_count += 1;
+      self.send_status(DataWriterStatus::OfferedDeadlineMissed {
+        count: CountWithChange::new(self.offered_deadline_missed_count, 1),
+        last_instance_key: instance,
+      });
+    }
+  }
+
+  // Lease duration of MANUAL_BY_PARTICIPANT and MANUAL_BY_TOPIC liveliness,
+  // unless it is infinite.
+  fn manual_liveliness_lease(qos: &QosPolicies) -> Option<Duration> {
+    match qos.liveliness {
+      Some(
+        policy::Liveliness::ManualByParticipant { lease_duration }
+        | policy::Liveliness::ManualByTopic { lease_duration },
+      ) if lease_duration != Duration::INFINITE => Some(lease_duration),
+      _ => None,
+    }
+  }
+
+  // The application has asserted the liveliness of this Writer by writing, or
+  // by calling assert_liveliness.
+  fn liveliness_asserted(&mut self) {
+    self.last_liveliness_assertion = Timestamp::now();
+    self.liveliness_lost = false;
+  }
+
+  // Liveliness of the whole DomainParticipant was asserted. This asserts the
+  // liveliness of MANUAL_BY_PARTICIPANT Writers.
+  pub fn participant_liveliness_asserted(&mut self) {
+    if let Some(policy::Liveliness::ManualByParticipant { .. }) = self.qos_policies.liveliness {
+      self.liveliness_asserted();
+    }
+  }
+
+  // LIVELINESS_LOST is raised once, when the lease expires without an
+  // assertion. The Writer must be asserted again before it can be lost again.
+  fn handle_liveliness_lost_check(&mut self, now: Timestamp) {
+    let Some(lease_duration) = Self::manual_liveliness_lease(&self.qos_policies) else {
+      return;
+    };
+    let since_assertion = now.duration_since(self.last_liveliness_assertion);
+    let next_check = if since_assertion < lease_duration {
+      lease_duration - since_assertion
+    } else {
+      if !self.liveliness_lost {
+        info!(
+          "Liveliness lost: topic={:?} writer={:?}",
+          self.my_topic_name, self.my_guid
+        );
+        self.liveliness_lost = true;
+        self.liveliness_lost_count += 1;
+        self.send_status(DataWriterStatus::LivelinessLost {
+          count: CountWithChange::new(self.liveliness_lost_count, 1),
+        });
+      }
+      lease_duration
+    };
+    self
+      .timed_event_timer
+      .set_timeout(next_check.to_std(), TimedEvent::LivelinessLostCheck);
+  }
+
   /// This is called by dp_wrapper every time cacheCleaning message is received.
   fn handle_cache_cleaning(&mut self) {
-    let resource_limit = 32; // TODO: This limit should be obtained
-                             // from Topic and Writer QoS. There should be some reasonable default limit
-                             // in case some supplied QoS setting does not specify a larger value.
-                             // In any case, there has to be some limit to avoid memory leak.
+    // Acknowledged changes are kept for late joining readers up to
+    // RESOURCE_LIMITS max_samples. There has to be some limit to avoid a memory
+    // leak, so this is used if max_samples is unlimited.
+    let default_sample_limit = 32;
+    let sample_limit = self
+      .qos_policies
+      .resource_limits()
+      .and_then(|limits| limits.sample_limit());
 
+    self.remove_expired_changes(Timestamp::now());
     match self.qos_policies.history {
       None => {
         self.remove_all_acked_changes_but_keep_depth(1);
       }
       Some(History::KeepAll) => {
-        self.remove_all_acked_changes_but_keep_depth(resource_limit);
+        self.remove_all_acked_changes_but_keep_depth(sample_limit.unwrap_or(default_sample_limit));
       }
       Some(History::KeepLast { depth: d }) => {
-        self.remove_all_acked_changes_but_keep_depth(d as usize);
+        let depth = usize::try_from(d).unwrap_or(1);
+        self.remove_all_acked_changes_but_keep_depth(sample_limit.map_or(depth, |l| l.min(depth)));
       }
     }
   }
@@ -475,26 +675,48 @@ impl Writer {
   // Receive new data samples from the DDS DataWriter
   pub fn process_writer_command(&mut self) {
     while let Ok(cc) = self.writer_command_receiver.try_recv() {
+      // Signal that there is now space in the DataWriter to Writer queue
+      self.writer_command_receiver_wakers.wake_all();
+
       match cc {
         WriterCommand::DDSData {
           ddsdata: dds_data,
           write_options,
           sequence_number,
+          filterable_sample,
+          instance,
         } => {
-          // Signal that there is now space in the DataWriter to Writer queue
-          {
-            self
-              .writer_command_receiver_waker
-              .lock()
-              .unwrap()
-              .as_ref()
-              .map(|w| w.wake_by_ref());
+          self.liveliness_asserted();
+          if let Some(instance) = instance {
+            if dds_data.change_kind() == ChangeKind::Alive {
+              self.instance_write_times.insert(instance, Timestamp::now());
+            } else {
+              self.instance_write_times.remove(&instance);
+            }
           }
 
           // Insert data to DDS / history cache
           let timestamp =
             self.insert_to_history_cache(dds_data, write_options.clone(), sequence_number);
 
+          // Readers whose content filter does not accept the sample. These get only
+          // a GAP for this sequence number.
+          let filtered_out: BTreeSet<GUID> = match filterable_sample {
+            Some(ref sample) if !self.like_stateless => self
+              .readers
+              .values()
+              .filter(|reader| !reader.content_filter_accepts(sample))
+              .map(|reader| reader.remote_reader_guid)
+              .collect(),
+            _ => BTreeSet::new(),
+          };
+          // Keep the sample for filtering historical data.
+          if let Some(sample) =
+            filterable_sample.filter(|_| !self.like_stateless && !self.qos_policies.is_volatile())
+          {
+            self.filterable_samples.insert(sequence_number, sample);
+          }
+
           // If not acting stateless-like, notify reader proxies that there is a new
           // sample
           if !self.like_stateless {
@@ -508,20 +730,25 @@ impl Writer {
                   reader.insert_pending_gap(sequence_number);
                 }
               }
+
+              // Same for readers that filtered the sample out. Best-effort readers
+              // do not need GAPs, because they do not ask for missing samples.
+              if filtered_out.contains(&reader.remote_reader_guid) && reader.qos().is_reliable() {
+                reader.insert_pending_gap(sequence_number);
+              }
             }
           }
           self.increase_heartbeat_counter();
+          // Changes need no room in history, if there is no one to acknowledge them.
+          self.release_acked_reservations();
 
-          if self.push_mode {
+          if let Some(suspended_changes) = self.suspended_changes.as_mut() {
+            // Publications are suspended. Sending waits until they are resumed.
+            suspended_changes.push((timestamp, filtered_out));
+          } else if self.push_mode {
             // Send data (DATA or DATAFRAGs) and a Heartbeat
             if let Some(cc) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
-              let target_reader_opt = match write_options.to_single_reader() {
-                Some(guid) => self.readers.get(&guid), // Sending only to this reader
-                None => None,                          // Sending to all matched readers
-              };
-
-              let send_also_heartbeat = true;
-              self.send_cache_change(cc, send_also_heartbeat, target_reader_opt);
+              self.push_cache_change(cc, &filtered_out);
             } else {
               error!("Lost the cache change that was just added?!");
             }
@@ -541,6 +768,15 @@ impl Writer {
           }
         }
 
+        WriterCommand::SuspendPublications => {
+          if self.suspended_changes.is_none() {
+            self.suspended_changes = Some(Vec::new());
+          }
+        }
+        WriterCommand::ResumePublications => {
+          self.resume_publications();
+        }
+
         // WriterCommand::ResetOfferedDeadlineMissedStatus { writer_guid: _, } => {
         //   self.reset_offered_deadline_missed_status();
         // }
@@ -567,25 +803,161 @@ impl Writer {
               }
             })
             .collect();
-          self.ack_waiter = if readers_pending.is_empty() {
+          if readers_pending.is_empty() {
             // all acked already: try to signal app waiting at DataWriter
             let _ = all_acked.try_send(());
             // but we ignore any failure to signal, if no-one is listening
             // since that is normal. They may have timed out and stopped waiting.
-            None
           } else {
             // Someone still needs to ack. Wait for them.
-            Some(AckWaiter {
+            self.ack_waiters.push(AckWaiter {
               wait_until,
               complete_channel: all_acked,
               readers_pending,
-            })
-          };
+            });
+          }
+        }
+      }
+    }
+  }
+
+  // Send a new cache change to the matched readers, along with a Heartbeat.
+  // Readers in filtered_out get a GAP instead.
+  fn push_cache_change(&self, cc: &CacheChange, filtered_out: &BTreeSet<GUID>) {
+    let send_also_heartbeat = true;
+    match cc.write_options.to_single_reader() {
+      Some(guid) => {
+        // Sending only to this reader
+        self.send_cache_change(cc, send_also_heartbeat, self.readers.get(&guid));
+      }
+      None if filtered_out.is_empty() => {
+        // Sending to all matched readers
+        self.send_cache_change(cc, send_also_heartbeat, None);
+      }
+      None => {
+        // Content filters are in use. Readers without a filter get the change
+        // as usual. Readers with a filter get it one by one, if it passes the
+        // filter, and a GAP otherwise.
+        let (filtering, unfiltered): (Vec<&RtpsReaderProxy>, Vec<&RtpsReaderProxy>) = self
+          .readers
+          .values()
+          .partition(|reader| reader.has_content_filter());
+        if !unfiltered.is_empty() {
+          for message in self.cache_change_messages(cc, send_also_heartbeat, None) {
+            self.send_message_to_readers(
+              DeliveryMode::Multicast,
+              message,
+              &mut unfiltered.iter().copied(),
+            );
+          }
+        }
+        for reader in filtering {
+          if filtered_out.contains(&reader.remote_reader_guid) {
+            self.send_pending_gap(reader);
+          } else {
+            self.send_cache_change(cc, send_also_heartbeat, Some(reader));
+          }
         }
       }
     }
   }
 
+  // Send the changes written while publications were suspended. Changes that
+  // go to all readers unfragmented are packed into as few messages as
+  // possible, none larger than an unfragmented DATA may be. Others are sent
+  // one by one, as usual.
+  fn resume_publications(&mut self) {
+    let suspended_changes = match self.suspended_changes.take() {
+      Some(changes) if !changes.is_empty() => changes,
+      _ => return,
+    };
+
+    let final_flag = false; // false = request that readers acknowledge with ACKNACK.
+    let liveliness_flag = false;
+
+    if !self.push_mode {
+      // Announce all the new changes with a single Heartbeat.
+      let hb_message = MessageBuilder::new()
+        .heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag)
+        .add_header_and_build(self.my_guid.prefix);
+      self.send_message_to_readers(
+        DeliveryMode::Multicast,
+        hb_message,
+        &mut self.readers.values(),
+      );
+      return;
+    }
+
+    let topic_cache = self.acquire_the_topic_cache_guard();
+    let mut batch = MessageBuilder::new();
+
+    for (timestamp, filtered_out) in suspended_changes {
+      let cc = match topic_cache.get_change(&timestamp) {
+        Some(cc) => cc,
+        None => {
+          // Could have been removed already, e.g. by History depth.
+          debug!("resume_publications: change at {timestamp:?} is no longer in the cache");
+          continue;
+        }
+      };
+      if self.change_has_expired(cc.sequence_number) {
+        debug!("resume_publications: change at {timestamp:?} has exceeded its lifespan");
+        continue;
+      }
+
+      let batchable = filtered_out.is_empty()
+        && cc.write_options.to_single_reader().is_none()
+        && cc.data_value.payload_size() <= self.data_max_size_serialized;
+
+      if !batchable {
+        // Keep the order of changes: send what we have so far first.
+        if !batch.is_empty() {
+          let message = std::mem::take(&mut batch).add_header_and_build(self.my_guid.prefix);
+          self.send_message_to_readers(
+            DeliveryMode::Multicast,
+            message,
+            &mut self.readers.values(),
+          );
+        }
+        self.push_cache_change(cc, &filtered_out);
+        continue;
+      }
+
+      // Every DATA gets its own INFO_TS, so that a timestamp of the previous
+      // change does not carry over to a change without one.
+      let change = MessageBuilder::new()
+        .ts_msg(self.endianness, cc.write_options.source_timestamp())
+        .data_msg(
+          cc,
+          EntityId::UNKNOWN,
+          self.my_guid,
+          self.endianness,
+          self.security_plugins.as_ref(),
+        );
+
+      // A batch must fit in a single unfragmented UDP message. Otherwise losing
+      // any IP fragment would lose the whole batch. Both builders count the
+      // RTPS header, but the batch will have only one.
+      if !batch.is_empty()
+        && batch.len_serialized() + change.len_serialized() - RTPS_MESSAGE_HEADER_SIZE
+          > MAX_UNFRAGMENTED_MESSAGE_SIZE
+      {
+        let message = std::mem::take(&mut batch).add_header_and_build(self.my_guid.prefix);
+        self.send_message_to_readers(DeliveryMode::Multicast, message, &mut self.readers.values());
+      }
+      batch = batch.append(change);
+    }
+
+    // One Heartbeat covers all of the changes.
+    if !self.like_stateless {
+      batch = batch.heartbeat_msg(self, EntityId::UNKNOWN, final_flag, liveliness_flag);
+    }
+    if !batch.is_empty() {
+      let message = batch.add_header_and_build(self.my_guid.prefix);
+      self.send_message_to_readers(DeliveryMode::Multicast, message, &mut self.readers.values());
+    }
+  }
+
   // Returns a boolean telling if the data had to be fragmented
   fn send_cache_change(
     &self,
@@ -619,6 +991,36 @@ impl Writer {
       }
     }
 
+    // Send the messages, either to all readers or just one
+    for msg in self.cache_change_messages(cc, send_also_heartbeat, target_reader_opt) {
+      match target_reader_opt {
+        None => {
+          // To all
+          self.send_message_to_readers(DeliveryMode::Multicast, msg, &mut self.readers.values());
+        }
+        Some(reader_proxy) => {
+          // To one
+          self.send_message_to_readers(
+            DeliveryMode::Unicast,
+            msg,
+            &mut std::iter::once(reader_proxy),
+          );
+        }
+      }
+    }
+
+    // The return value tells if the data had to be fragmented
+    cc.data_value.payload_size() > self.data_max_size_serialized
+  }
+
+  // DATA or DATAFRAG messages carrying a cache change, addressed to the target
+  // reader, or to all readers if there is no target.
+  fn cache_change_messages(
+    &self,
+    cc: &CacheChange,
+    send_also_heartbeat: bool,
+    target_reader_opt: Option<&RtpsReaderProxy>,
+  ) -> Vec<Message> {
     // All the messages are pushed to a vector first before sending them.
     // If this hinders performance when many datafrag messages need to be
     // sent, optimize.
@@ -742,26 +1144,24 @@ impl Writer {
       }
     }
 
-    // Send the messages, either to all readers or just one
-    for msg in messages_to_send {
-      match target_reader_opt {
-        None => {
-          // To all
-          self.send_message_to_readers(DeliveryMode::Multicast, msg, &mut self.readers.values());
-        }
-        Some(reader_proxy) => {
-          // To one
-          self.send_message_to_readers(
-            DeliveryMode::Unicast,
-            msg,
-            &mut std::iter::once(reader_proxy),
-          );
-        }
-      }
-    }
+    messages_to_send
+  }
 
-    // The return value tells if the data had to be fragmented
-    fragmentation_needed
+  // Send GAP for all the sequence numbers the reader is pending GAP on, if any.
+  fn send_pending_gap(&self, reader: &RtpsReaderProxy) {
+    if reader.get_pending_gap().is_empty() {
+      return;
+    }
+    let gap_msg = MessageBuilder::new()
+      .dst_submessage(self.endianness, reader.remote_reader_guid.prefix)
+      .gap_msg(
+        reader.get_pending_gap(),
+        self.entity_id(),
+        self.endianness,
+        reader.remote_reader_guid,
+      )
+      .add_header_and_build(self.my_guid.prefix);
+    self.send_message_to_readers(DeliveryMode::Unicast, gap_msg, &mut std::iter::once(reader));
   }
 
   fn insert_to_history_cache(
@@ -774,6 +1174,7 @@ impl Writer {
 
     // Create a new CacheChange from DDSData & insert to topic cache
     // The timestamp taken here is used as a unique(!) key in the cache.
+    let lifespan_start = write_options.source_timestamp();
     let new_cache_change = CacheChange::new(self.guid(), new_sequence_number, write_options, data);
     let timestamp = Timestamp::now();
 
@@ -800,6 +1201,13 @@ impl Writer {
     self
       .sequence_number_to_instant
       .insert(new_sequence_number, timestamp);
+    self
+      .lifespan_starts
+      .insert(new_sequence_number, lifespan_start.unwrap_or(timestamp));
+
+    // The smallest sequence number in the topic cache may be an expired one.
+    self.remove_expired_changes(Timestamp::now());
+    self.remove_changes_over_sample_limit();
 
     timestamp
   }
@@ -810,6 +1218,9 @@ impl Writer {
 
   /// This is called periodically.
   pub fn handle_heartbeat_tick(&mut self, is_manual_assertion: bool) {
+    if is_manual_assertion {
+      self.liveliness_asserted();
+    }
     if self.like_stateless {
       info!(
         "Ignoring handling heartbeat tick in a stateless-like Writer, since it currently supports \
@@ -818,6 +1229,15 @@ impl Writer {
       );
       return;
     }
+    if self.suspended_changes.is_some() && !is_manual_assertion {
+      // Heartbeats would announce the changes that are held back while
+      // publications are suspended.
+      trace!(
+        "heartbeat tick: publications suspended. topic={:?}",
+        self.my_topic_name
+      );
+      return;
+    }
     // Reliable Stateful Writer (that tracks Readers by ReaderProxy) will not set
     // the final flag.
     let final_flag = false;
@@ -833,10 +1253,13 @@ impl Writer {
     // TODO: This produces same heartbeat count for all messages sent, but
     // then again, they represent the same writer status.
 
-    if self
-      .readers
-      .values()
-      .all(|rp| self.last_change_sequence_number < rp.all_acked_before)
+    // A manual liveliness assertion must reach the Readers even if they
+    // already have everything.
+    if !is_manual_assertion
+      && self
+        .readers
+        .values()
+        .all(|rp| self.last_change_sequence_number < rp.all_acked_before)
     {
       trace!("heartbeat tick: all readers have all available data.");
     } else {
@@ -983,6 +1406,7 @@ impl Writer {
             );
           }
         }
+        self.release_acked_reservations();
       } // AckNack
       AckSubmessage::NackFrag(ref nackfrag) => {
         // NackFrag is negative acknowledgement only, i.e. requesting missing fragments.
@@ -1002,17 +1426,32 @@ impl Writer {
   }
 
   fn update_ack_waiters(&mut self, guid: GUID, acked_before: Option<SequenceNumber>) {
-    let completed = self
-      .ack_waiter
-      .as_mut()
-      .map_or(false, |aw| aw.reader_acked_or_lost(guid, acked_before));
-    if completed {
-      self
-        .ack_waiter
-        .as_ref()
-        .map(AckWaiter::notify_wait_complete);
-      self.ack_waiter = None;
-    }
+    self.ack_waiters.retain_mut(|aw| {
+      let completed = aw.reader_acked_or_lost(guid, acked_before);
+      if completed {
+        aw.notify_wait_complete();
+      }
+      !completed
+    });
+  }
+
+  // All reliable readers have acknowledged the changes before this.
+  fn acked_by_reliable_readers_before(&self) -> SequenceNumber {
+    self
+      .readers
+      .values()
+      .filter(|reader| reader.qos().is_reliable())
+      .map(RtpsReaderProxy::acked_up_to_before)
+      .min()
+      .unwrap_or(self.last_change_sequence_number + SequenceNumber::new(1))
+  }
+
+  // Lets the DataWriter reuse the room in history taken by changes that all
+  // reliable readers have acknowledged.
+  fn release_acked_reservations(&self) {
+    self
+      .history_reservations
+      .release_before(self.acked_by_reliable_readers_before());
   }
 
   // Send out missing data
@@ -1085,11 +1524,14 @@ impl Writer {
       let pending_gaps = reader_proxy.get_pending_gap();
       if pending_gaps.contains(&unsent_sn) {
         no_longer_relevant.extend(pending_gaps);
+      } else if self.filtered_out_of_history(reader_proxy, unsent_sn) {
+        no_longer_relevant.insert(unsent_sn);
       } else {
         // Reader not pending gap on unsent_sn. Get the cache change from topic cache
         let topic_cache = self.acquire_the_topic_cache_guard();
         if let Some(cc) = self
           .sequence_number_to_instant(unsent_sn)
+          .filter(|_| !self.change_has_expired(unsent_sn))
           .and_then(|ts| topic_cache.get_change(&ts))
         {
           // The cache change was found. Send it to the reader
@@ -1124,6 +1566,11 @@ impl Writer {
               "Reader {:?} requested disposed {:?}. Topic {:?}",
               &reader_proxy, unsent_sn, &self.my_topic_name
             );
+          } else if self.change_has_expired(unsent_sn) {
+            debug!(
+              "Reader {:?} requested expired {:?}. Topic {:?}",
+              &reader_proxy, unsent_sn, &self.my_topic_name
+            );
           } else {
             // we are running out of excuses
             error!(
@@ -1160,6 +1607,20 @@ impl Writer {
     }
   } // fn
 
+  // Does the content filter of the reader reject this change in our history?
+  // Live changes are filtered when they are written, but a late joining
+  // reader asks for historical data by repair.
+  fn filtered_out_of_history(
+    &self,
+    reader_proxy: &RtpsReaderProxy,
+    sequence_number: SequenceNumber,
+  ) -> bool {
+    self
+      .filterable_samples
+      .get(&sequence_number)
+      .is_some_and(|sample| !reader_proxy.content_filter_accepts(sample))
+  }
+
   fn handle_repair_frags_send_worker(
     &mut self,
     reader_proxy: &mut RtpsReaderProxy, /* This is mutable proxy temporarily detached from the
@@ -1177,7 +1638,10 @@ impl Writer {
       // Sanity check request
       // ^^^ TODO
 
-      if let Some(timestamp) = self.sequence_number_to_instant(seq_num) {
+      if let Some(timestamp) = self
+        .sequence_number_to_instant(seq_num)
+        .filter(|_| !self.change_has_expired(seq_num))
+      {
         // Try to find the cache change from topic cache
         if let Some(cache_change) = self.acquire_the_topic_cache_guard().get_change(&timestamp) {
           // If the data is meant for a single reader only, make sure it is the one we're
@@ -1273,7 +1737,8 @@ impl Writer {
       // if we end up with SequenceNumber(1), it may be due to "max()" above,
       // and may mean that no messages have ever been received, so it is
       // normal that we did not find anything.
-      if first_keeper > SequenceNumber::new(1) {
+      // Also, everything may have exceeded its lifespan.
+      if first_keeper > SequenceNumber::new(1) && first_keeper <= self.last_change_sequence_number {
         warn!(
           "DDCache garbage collect: {:?} missing from instant map",
           first_keeper
@@ -1285,8 +1750,88 @@ impl Writer {
         );
       }
     }
+    self.forget_changes_before(first_keeper);
+  }
+
+  // Keeps our history within RESOURCE_LIMITS max_samples by dropping the
+  // oldest changes. With KEEP_ALL, only acknowledged changes are dropped. The
+  // DataWriter does not write more unacknowledged changes than fit.
+  // max_samples_per_instance is not applied here: our history is a range of
+  // sequence numbers, so changes of one instance cannot be dropped alone.
+  // max_instances is enforced by the DataWriter when instances are registered.
+  fn remove_changes_over_sample_limit(&mut self) {
+    let Some(sample_limit) = self
+      .qos_policies
+      .resource_limits()
+      .and_then(|limits| limits.sample_limit())
+    else {
+      return;
+    };
+    let over_limit = self
+      .sequence_number_to_instant
+      .len()
+      .saturating_sub(sample_limit);
+    let Some(&(mut first_keeper)) = self.sequence_number_to_instant.keys().nth(over_limit) else {
+      return;
+    };
+    if self.qos_policies.history() == Some(History::KeepAll) {
+      first_keeper = min(first_keeper, self.acked_by_reliable_readers_before());
+    }
+    if first_keeper <= self.first_change_sequence_number {
+      return;
+    }
+    self.forget_changes_before(first_keeper);
+  }
+
+  // LIFESPAN of our changes is counted from their source timestamp, or from the
+  // moment they were added to the history cache if they have none.
+  fn change_has_expired(&self, sequence_number: SequenceNumber) -> bool {
+    let Some(policy::Lifespan { duration }) = self.qos_policies.lifespan() else {
+      return false;
+    };
+    self
+      .lifespan_starts
+      .get(&sequence_number)
+      .is_some_and(|start| *start + duration < Timestamp::now())
+  }
+
+  // Drops the changes that have exceeded their lifespan from our history, so
+  // that they are no longer repaired or sent to late joining readers. Releasing
+  // them from the topic cache is left to cache cleaning.
+  // Source timestamps need not follow the order of sequence numbers, so only
+  // the expired changes before the first unexpired one can be dropped. Any
+  // others are skipped when sending.
+  fn remove_expired_changes(&mut self, now: Timestamp) {
+    let Some(policy::Lifespan { duration }) = self.qos_policies.lifespan() else {
+      return;
+    };
+    let expired_before = now - duration;
+    let first_unexpired = self
+      .lifespan_starts
+      .iter()
+      .find(|(_sn, start)| **start >= expired_before)
+      .map_or(
+        self.last_change_sequence_number + SequenceNumber::new(1),
+        |(sn, _)| *sn,
+      );
+
+    if first_unexpired <= self.first_change_sequence_number {
+      return;
+    }
+    trace!(
+      "Lifespan expired changes before {:?}. Topic {:?}",
+      first_unexpired,
+      self.my_topic_name
+    );
+    self.forget_changes_before(first_unexpired);
+  }
+
+  // Drops the changes before first_keeper from our history.
+  fn forget_changes_before(&mut self, first_keeper: SequenceNumber) {
     self.first_change_sequence_number = first_keeper;
     self.sequence_number_to_instant = self.sequence_number_to_instant.split_off(&first_keeper);
+    self.lifespan_starts = self.lifespan_starts.split_off(&first_keeper);
+    self.filterable_samples = self.filterable_samples.split_off(&first_keeper);
   }
 
   fn increase_heartbeat_counter(&mut self) {
@@ -1417,6 +1962,11 @@ impl Writer {
 
   // Send status to DataWriter or however is listening
   fn send_status(&self, status: DataWriterStatus) {
+    self
+      .status_record
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+      .record(&status);
     self
       .status_sender
       .try_send(status)
@@ -1474,6 +2024,9 @@ impl Writer {
           requested_qos, self.qos_policies
         );
 
+        // Reader may have been matched before a QoS change
+        self.reader_lost(reader_proxy.remote_reader_guid);
+
         self.requested_incompatible_qos_count += 1;
         self.send_status(DataWriterStatus::OfferedIncompatibleQos {
           count: CountWithChange::new(self.requested_incompatible_qos_count, 1),
@@ -1513,9 +2066,20 @@ impl Writer {
         }
         new_proxy
       });
+    self.update_has_filtering_readers();
     new
   }
 
+  fn update_has_filtering_readers(&self) {
+    let has_filtering_readers = self
+      .readers
+      .values()
+      .any(RtpsReaderProxy::has_content_filter);
+    self
+      .has_filtering_readers
+      .store(has_filtering_readers, Ordering::Relaxed);
+  }
+
   fn matched_reader_remove(&mut self, guid: GUID) -> Option<RtpsReaderProxy> {
     let removed = self.readers.remove(&guid);
     if let Some(ref removed_reader) = removed {
@@ -1525,6 +2089,7 @@ impl Writer {
         removed_reader.remote_reader_guid,
       );
       debug!("Removed reader proxy details: {:?}", removed_reader);
+      self.update_has_filtering_readers();
     }
     #[cfg(feature = "security")]
     if let Some(security_plugins_handle) = &self.security_plugins {
@@ -1551,8 +2116,9 @@ impl Writer {
         reader: guid,
       });
     }
-    // also remember to remove reader from ack_waiter
+    // also remember to remove reader from ack_waiters
     self.update_ack_waiters(guid, None);
+    self.release_acked_reservations();
   }
 
   // Entire remote participant was lost.
@@ -1632,12 +2198,28 @@ mod tests {
 
   use crate::{
     dds::{
-      participant::DomainParticipant, qos::QosPolicies, topic::TopicKind,
+      adapters::no_key::SerializerAdapter,
+      key::{Key, Keyed},
+      participant::DomainParticipant,
+      qos::QosPolicies,
+      statusevents::{sync_status_channel, StatusChannelReceiver},
+      topic::TopicKind,
+      typedesc::TypeDesc,
       with_key::datawriter::DataWriter,
     },
+    discovery::{
+      content_filter_property::ContentFilterProperty, sedp_messages::DiscoveredReaderData,
+    },
+    messages::submessages::elements::serialized_payload::SerializedPayload,
     serialization::cdr_serializer::CDRSerializerAdapter,
-    test::random_data::*,
+    structure::{
+      dds_cache::{DDSCache, TopicCache},
+      guid::EntityKind,
+    },
+    test::{random_data::*, test_data::*},
+    QosPolicyBuilder, RepresentationIdentifier,
   };
+  use super::*;
 
   #[test]
   fn test_writer_receives_datawriter_cache_change_notifications() {
@@ -1689,4 +2271,474 @@ mod tests {
     thread::sleep(std::time::Duration::from_millis(100));
     info!("writerResult:  {:?}", write_result);
   }
+
+  // The ends of a test Writer's channels, which a DataWriter would hold.
+  struct WriterHandles {
+    command_sender: mio_channel::SyncSender<WriterCommand>,
+    status_receiver: StatusChannelReceiver<DataWriterStatus>,
+    status_record: Arc<Mutex<DataWriterStatusRecord>>,
+    has_filtering_readers: Arc<AtomicBool>,
+    history_reservations: Arc<HistoryReservations>,
+    topic_cache: Arc<Mutex<TopicCache>>,
+    _participant_status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
+  }
+
+  impl WriterHandles {
+    fn write(&self, sn: i64, a: i64) {
+      self.send_change(sn, a, ChangeKind::Alive, WriteOptions::default());
+    }
+
+    // Sends a change of RandomData { a } to the Writer, like a DataWriter does.
+    fn send_change(&self, sn: i64, a: i64, change_kind: ChangeKind, write_options: WriteOptions) {
+      let data = RandomData {
+        a,
+        b: "Fobar".to_string(),
+      };
+      let ddsdata = if change_kind == ChangeKind::Alive {
+        DDSData::new(SerializedPayload::new_from_bytes(
+          RepresentationIdentifier::CDR_LE,
+          CDRSerializerAdapter::<RandomData>::to_bytes(&data).unwrap(),
+        ))
+      } else {
+        DDSData::new_disposed_by_key_hash(change_kind, data.key().hash_key(false))
+      };
+      self
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata,
+          write_options,
+          sequence_number: SequenceNumber::new(sn),
+          filterable_sample: CDRSerializerAdapter::<RandomData>::to_filterable(&data),
+          instance: Some(data.key().hash_key(false)),
+        })
+        .unwrap();
+    }
+  }
+
+  fn test_writer(topic_name: &str, qos: QosPolicies) -> (Writer, WriterHandles) {
+    let topic_cache = DDSCache::new().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("RandomData".to_string()),
+      &qos,
+    );
+    let (command_sender, command_receiver) = mio_channel::sync_channel::<WriterCommand>(10);
+    let (status_sender, status_receiver) = sync_status_channel(16).unwrap();
+    let (participant_status_sender, participant_status_receiver) = sync_status_channel(16).unwrap();
+    let handles = WriterHandles {
+      command_sender,
+      status_receiver,
+      status_record: Arc::default(),
+      has_filtering_readers: Arc::default(),
+      history_reservations: Arc::new(HistoryReservations::new(&qos)),
+      topic_cache: Arc::clone(&topic_cache),
+      _participant_status_receiver: participant_status_receiver,
+    };
+
+    let writer_ing = WriterIngredients {
+      guid: GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      writer_command_receiver: command_receiver,
+      writer_command_receiver_wakers: Arc::default(),
+      topic_name: topic_name.to_string(),
+      topic_cache_handle: topic_cache,
+      like_stateless: false,
+      qos_policies: qos,
+      status_sender,
+      has_filtering_readers: Arc::clone(&handles.has_filtering_readers),
+      status_record: Arc::clone(&handles.status_record),
+      history_reservations: Arc::clone(&handles.history_reservations),
+      security_plugins: None,
+    };
+    let writer = Writer::new(
+      writer_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    (writer, handles)
+  }
+
+  fn test_reader_proxy(
+    entity_key: u8,
+    content_filter: Option<ContentFilterProperty>,
+  ) -> RtpsReaderProxy {
+    let mut drd = DiscoveredReaderData {
+      reader_proxy: reader_proxy_data().unwrap(),
+      subscription_topic_data: subscription_builtin_topic_data().unwrap(),
+      content_filter,
+    };
+    drd.reader_proxy.remote_reader_guid.entity_id.entity_key = [0, 0, entity_key];
+    RtpsReaderProxy::from_discovered_reader_data(&drd, &[], &[])
+  }
+
+  fn content_filter(topic_name: &str, filter_class_name: &str) -> ContentFilterProperty {
+    ContentFilterProperty {
+      content_filtered_topic_name: "filtered".to_string(),
+      related_topic_name: topic_name.to_string(),
+      filter_class_name: filter_class_name.to_string(),
+      filter_expression: "a > %0".to_string(),
+      expression_parameters: vec!["5".to_string()],
+    }
+  }
+
+  #[test]
+  fn writer_sends_gap_to_readers_filtering_out_sample() {
+    let topic_name = "filtered_topic";
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(History::KeepAll)
+      .build();
+    let (mut writer, handles) = test_writer(topic_name, qos);
+
+    // Two reliable remote readers with the same filter expression. Only the first
+    // one uses a filter class that we know.
+    let ddssql_reader = test_reader_proxy(1, Some(content_filter(topic_name, "DDSSQL")));
+    let unknown_class_reader =
+      test_reader_proxy(2, Some(content_filter(topic_name, "SomethingElse")));
+    assert!(ddssql_reader.has_content_filter());
+    assert!(!unknown_class_reader.has_content_filter());
+
+    writer.matched_reader_update(&ddssql_reader);
+    writer.matched_reader_update(&unknown_class_reader);
+    assert!(handles.has_filtering_readers.load(Ordering::Relaxed));
+
+    handles.write(1, 3); // does not pass the filter
+    handles.write(2, 7);
+    writer.process_writer_command();
+
+    // Only the DDSSQL reader gets a GAP instead of the first sample.
+    let ddssql_guid = ddssql_reader.remote_reader_guid;
+    let unknown_class_guid = unknown_class_reader.remote_reader_guid;
+    assert_eq!(
+      writer.readers[&ddssql_guid].get_pending_gap(),
+      &BTreeSet::from([SequenceNumber::new(1)])
+    );
+    assert!(writer.readers[&unknown_class_guid]
+      .get_pending_gap()
+      .is_empty());
+
+    // DataWriter no longer needs to provide filterable samples.
+    writer.reader_lost(ddssql_guid);
+    assert!(!handles.has_filtering_readers.load(Ordering::Relaxed));
+  }
+
+  #[test]
+  fn writer_filters_historical_data_for_late_joining_reader() {
+    let topic_name = "filtered_history_topic";
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .durability(policy::Durability::TransientLocal)
+      .history(History::KeepAll)
+      .build();
+    let (mut writer, handles) = test_writer(topic_name, qos);
+
+    // Written before any reader is matched
+    handles.write(1, 3);
+    handles.write(2, 7);
+    writer.process_writer_command();
+
+    let reader_proxy = test_reader_proxy(1, Some(content_filter(topic_name, "DDSSQL")));
+    writer.matched_reader_update(&reader_proxy);
+    let reader_proxy = &writer.readers[&reader_proxy.remote_reader_guid];
+
+    // Only the change passing the filter is repaired, the other one gets a GAP.
+    assert!(writer.filtered_out_of_history(reader_proxy, SequenceNumber::new(1)));
+    assert!(!writer.filtered_out_of_history(reader_proxy, SequenceNumber::new(2)));
+
+    // Filterable samples are dropped along with the history.
+    writer.forget_changes_before(SequenceNumber::new(2));
+    assert_eq!(writer.filterable_samples.len(), 1);
+  }
+
+  #[test]
+  fn writer_holds_back_changes_while_suspended() {
+    let qos = QosPolicyBuilder::new().history(History::KeepAll).build();
+    let (mut writer, handles) = test_writer("suspended_topic", qos);
+
+    handles.write(1, 1);
+    handles
+      .command_sender
+      .send(WriterCommand::SuspendPublications)
+      .unwrap();
+    handles.write(2, 2);
+    handles.write(3, 3);
+    writer.process_writer_command();
+
+    // All changes are in the cache, but the ones written after suspending are
+    // waiting to be sent.
+    assert_eq!(writer.last_change_sequence_number, SequenceNumber::new(3));
+    assert_eq!(
+      handles
+        .topic_cache
+        .lock()
+        .unwrap()
+        .writers_smallest_sn_in_cache(writer.guid()),
+      Some(SequenceNumber::new(1))
+    );
+    assert_eq!(writer.suspended_changes.as_ref().map(Vec::len), Some(2));
+
+    handles
+      .command_sender
+      .send(WriterCommand::ResumePublications)
+      .unwrap();
+    handles.write(4, 4);
+    writer.process_writer_command();
+    assert!(writer.suspended_changes.is_none());
+    assert_eq!(writer.last_change_sequence_number, SequenceNumber::new(4));
+  }
+
+  #[test]
+  fn writer_drops_expired_changes_from_history() {
+    let qos = QosPolicyBuilder::new()
+      .history(History::KeepAll)
+      .lifespan(policy::Lifespan {
+        duration: Duration::from_millis(50),
+      })
+      .build();
+    let (mut writer, handles) = test_writer("lifespan_topic", qos);
+
+    handles.write(1, 1);
+    handles.write(2, 2);
+    writer.process_writer_command();
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(1));
+    writer.remove_expired_changes(Timestamp::now());
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(1));
+
+    // Expired changes are no longer in history, even though the topic cache
+    // still has them.
+    writer.remove_expired_changes(Timestamp::now() + Duration::from_millis(100));
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(3));
+    assert!(writer
+      .sequence_number_to_instant(SequenceNumber::new(2))
+      .is_none());
+    assert_eq!(
+      handles
+        .topic_cache
+        .lock()
+        .unwrap()
+        .writers_smallest_sn_in_cache(writer.guid()),
+      Some(SequenceNumber::new(1))
+    );
+  }
+
+  #[test]
+  fn writer_counts_lifespan_from_source_timestamp() {
+    let qos = QosPolicyBuilder::new()
+      .history(History::KeepAll)
+      .lifespan(policy::Lifespan {
+        duration: Duration::from_secs(1),
+      })
+      .build();
+    let (mut writer, handles) = test_writer("lifespan_source_timestamp_topic", qos);
+
+    // A change stamped long ago has expired as soon as it is written.
+    let long_ago = Timestamp::now() - Duration::from_secs(10);
+    handles.send_change(1, 1, ChangeKind::Alive, WriteOptions::from(Some(long_ago)));
+    writer.process_writer_command();
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(2));
+    assert!(writer
+      .sequence_number_to_instant(SequenceNumber::new(1))
+      .is_none());
+
+    // Without a source timestamp, the lifespan starts when the change is added.
+    handles.write(2, 2);
+    writer.process_writer_command();
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(2));
+    assert!(!writer.change_has_expired(SequenceNumber::new(2)));
+  }
+
+  #[test]
+  fn writer_releases_history_when_acked() {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(History::KeepAll)
+      .resource_limits(policy::ResourceLimits {
+        max_samples: 2,
+        max_instances: 1,
+        max_samples_per_instance: 2,
+      })
+      .build();
+    let (mut writer, handles) = test_writer("resource_limits_topic", qos);
+
+    let reader_proxy = test_reader_proxy(1, None);
+    let reader_guid = reader_proxy.remote_reader_guid;
+    writer.matched_reader_update(&reader_proxy);
+
+    let write = |sn: i64| {
+      assert!(handles
+        .history_reservations
+        .try_reserve(KeyHash::zero(), || SequenceNumber::new(sn))
+        .is_some());
+      handles.write(sn, 1);
+    };
+
+    write(1);
+    write(2);
+    writer.process_writer_command();
+    // History is full, until the reliable reader acknowledges.
+    assert!(handles
+      .history_reservations
+      .try_reserve(KeyHash::zero(), || SequenceNumber::new(3))
+      .is_none());
+
+    writer.reader_lost(reader_guid);
+    write(3);
+    writer.process_writer_command();
+    // Acknowledged changes are dropped to stay within max_samples.
+    assert_eq!(writer.first_change_sequence_number, SequenceNumber::new(2));
+    assert!(handles
+      .history_reservations
+      .try_reserve(KeyHash::zero(), || SequenceNumber::new(4))
+      .is_some());
+  }
+
+  #[test]
+  fn writer_completes_each_ack_waiter() {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(History::KeepAll)
+      .build();
+    let (mut writer, handles) = test_writer("ack_wait_topic", qos);
+
+    let reader_proxy = test_reader_proxy(1, None);
+    let reader_guid = reader_proxy.remote_reader_guid;
+    writer.matched_reader_update(&reader_proxy);
+    handles.write(1, 1);
+
+    // The DataWriter and its Publisher wait at the same time.
+    let (writer_acked_sender, writer_acked_receiver) = sync_status_channel(1).unwrap();
+    let (publisher_acked_sender, publisher_acked_receiver) = sync_status_channel(1).unwrap();
+    for all_acked in [writer_acked_sender, publisher_acked_sender] {
+      handles
+        .command_sender
+        .send(WriterCommand::WaitForAcknowledgments { all_acked })
+        .unwrap();
+    }
+    writer.process_writer_command();
+    assert!(writer_acked_receiver.try_recv().is_err());
+    assert!(publisher_acked_receiver.try_recv().is_err());
+
+    writer.reader_lost(reader_guid);
+    assert!(writer_acked_receiver.try_recv().is_ok());
+    assert!(publisher_acked_receiver.try_recv().is_ok());
+    assert!(writer.ack_waiters.is_empty());
+  }
+
+  #[test]
+  fn writer_records_statuses_for_data_writer() {
+    let qos = QosPolicies::qos_none();
+    let (mut writer, handles) = test_writer("status_topic", qos.clone());
+
+    let reader_1 = test_reader_proxy(1, None);
+    let reader_2 = test_reader_proxy(2, None);
+    writer.update_reader_proxy(&reader_1, &qos);
+    writer.update_reader_proxy(&reader_2, &qos);
+    writer.reader_lost(reader_1.remote_reader_guid);
+
+    let status = handles.status_record.lock().unwrap().publication_matched();
+    assert_eq!(status.total, CountWithChange::new(2, 2));
+    assert_eq!(status.current, CountWithChange::new(1, 1));
+    assert_eq!(status.last_reader, Some(reader_1.remote_reader_guid));
+
+    // Changes are relative to the previous read
+    let status = handles.status_record.lock().unwrap().publication_matched();
+    assert_eq!(status.total, CountWithChange::new(2, 0));
+    assert_eq!(status.current, CountWithChange::new(1, 0));
+
+    // The status events are still there
+    assert_eq!(
+      std::iter::from_fn(|| handles.status_receiver.try_recv().ok()).count(),
+      3
+    );
+  }
+
+  #[test]
+  fn writer_detects_offered_deadline_missed() {
+    let qos = QosPolicyBuilder::new()
+      .deadline(policy::Deadline(Duration::from_millis(10)))
+      .build();
+    let (mut writer, handles) = test_writer("deadline_topic", qos);
+
+    handles.write(1, 1);
+    handles.write(2, 2);
+    handles.send_change(3, 2, ChangeKind::NotAliveDisposed, WriteOptions::default());
+    writer.process_writer_command();
+    let start = Timestamp::now();
+
+    // Nothing is missed before the deadline
+    writer.handle_deadline_missed_check(start);
+    assert_eq!(
+      handles
+        .status_record
+        .lock()
+        .unwrap()
+        .offered_deadline_missed()
+        .count,
+      CountWithChange::new(0, 0)
+    );
+
+    // Instance 1 is missed, but disposed instance 2 is not tracked anymore.
+    writer.handle_deadline_missed_check(start + Duration::from_millis(20));
+    let status = handles
+      .status_record
+      .lock()
+      .unwrap()
+      .offered_deadline_missed();
+    assert_eq!(status.count, CountWithChange::new(1, 1));
+    assert_eq!(status.last_instance_key, Some(1_i64.hash_key(false)));
+
+    // Each period without an update is a new miss
+    writer.handle_deadline_missed_check(start + Duration::from_millis(40));
+    let status = handles
+      .status_record
+      .lock()
+      .unwrap()
+      .offered_deadline_missed();
+    assert_eq!(status.count, CountWithChange::new(2, 1));
+  }
+
+  #[test]
+  fn writer_detects_liveliness_lost() {
+    let qos = QosPolicyBuilder::new()
+      .liveliness(policy::Liveliness::ManualByTopic {
+        lease_duration: Duration::from_millis(10),
+      })
+      .build();
+    let (mut writer, handles) = test_writer("liveliness_topic", qos);
+    let liveliness_lost_count = || {
+      handles
+        .status_record
+        .lock()
+        .unwrap()
+        .liveliness_lost()
+        .count
+    };
+    let later = |millis| Timestamp::now() + Duration::from_millis(millis);
+
+    // Not lost within the lease duration
+    writer.handle_liveliness_lost_check(Timestamp::now());
+    assert_eq!(liveliness_lost_count(), CountWithChange::new(0, 0));
+
+    // Lease expires
+    writer.handle_liveliness_lost_check(later(20));
+    assert_eq!(liveliness_lost_count(), CountWithChange::new(1, 1));
+
+    // Liveliness is lost only once without new assertions
+    writer.handle_liveliness_lost_check(later(40));
+    assert_eq!(liveliness_lost_count(), CountWithChange::new(1, 0));
+
+    // Manual assertion makes the writer alive again, so it can be lost again.
+    writer.handle_heartbeat_tick(true);
+    writer.handle_liveliness_lost_check(Timestamp::now());
+    assert_eq!(liveliness_lost_count(), CountWithChange::new(1, 0));
+    writer.handle_liveliness_lost_check(later(20));
+    assert_eq!(liveliness_lost_count(), CountWithChange::new(2, 1));
+  }
 }
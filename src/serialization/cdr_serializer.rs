@@ -7,6 +7,7 @@ use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 use crate::{
   dds::{
     adapters::{no_key, with_key},
+    ddssql::FilterableSample,
     key::Keyed,
   },
   RepresentationIdentifier,
@@ -117,6 +118,10 @@ where
     to_writer::<D, BO, &mut Vec<u8>>(&mut buffer, value)?;
     Ok(Bytes::from(buffer))
   }
+
+  fn to_filterable(value: &D) -> Option<FilterableSample> {
+    FilterableSample::new(value)
+  }
 }
 
 impl<D, BO> with_key::SerializerAdapter<D> for CDRSerializerAdapter<D, BO>
@@ -129,6 +129,13 @@ impl std::ops::Add for Duration {
   }
 }
 
+impl std::ops::Sub for Duration {
+  type Output = Self;
+  fn sub(self, other: Self) -> Self {
+    Self::from_ticks(self.to_ticks() - other.to_ticks())
+  }
+}
+
 impl std::ops::Mul<Duration> for f64 {
   type Output = Duration;
   fn mul(self, rhs: Duration) -> Duration {
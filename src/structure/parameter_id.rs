@@ -64,6 +64,13 @@ impl ParameterId {
   pub const PID_KEY_HASH: Self = Self { value: 0x0070 };
   pub const PID_STATUS_INFO: Self = Self { value: 0x0071 };
 
+  // Inline QoS for coherent sets. RTPS spec v2.5 Table 9.19
+  pub const PID_COHERENT_SET: Self = Self { value: 0x0056 };
+  pub const PID_GROUP_COHERENT_SET: Self = Self { value: 0x0063 };
+  // Vendor-specific: list of Writer GUIDs taking part in a group coherent set.
+  // This is sent only in end markers of group coherent sets.
+  pub const PID_COHERENT_SET_GROUP_WRITERS: Self = Self { value: 0x8065 };
+
   // From Specification "Remote Procedure Calls over DDS v1.0"
   // Section 7.6.2.1.1 Extended PublicationBuiltin TopicData and
   // 7.6.2.1.2 Extended SubscriptionBuiltinTopicData